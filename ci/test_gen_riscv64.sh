@@ -0,0 +1,22 @@
+#!/usr/bin/env bash
+
+# Runs the LLVM gen tests on riscv64gc. Meant to run inside a linux/riscv64 container, which on
+# an x86_64 machine is emulated with qemu-user (see .github/workflows/nightly_linux_riscv64.yml).
+
+# https://vaneyckt.io/posts/safer_bash_scripts_with_set_euxo_pipefail/
+set -euxo pipefail
+
+ZIG_VERSION=0.11.0
+
+apt-get update
+apt-get install -y curl build-essential git libz-dev libzstd-dev llvm-16-dev clang-16 lld-16 xz-utils
+
+curl -fsSL "https://ziglang.org/download/$ZIG_VERSION/zig-linux-riscv64-$ZIG_VERSION.tar.xz" | tar -xJ -C /opt
+export PATH="/opt/zig-linux-riscv64-$ZIG_VERSION:$PATH"
+
+curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y --profile minimal
+export PATH="$HOME/.cargo/bin:$PATH"
+
+export LLVM_SYS_160_PREFIX=/usr/lib/llvm-16
+
+cargo test --locked --release --package test_gen
@@ -8,25 +8,30 @@ use clap::{
     builder::PossibleValuesParser, parser::ValueSource, value_parser, Arg, ArgAction, ArgMatches,
     Command,
 };
-use roc_build::link::{LinkType, LinkingStrategy};
+use roc_build::call_graph::CallGraphFormat;
+use roc_build::link::{split_debug_info, LinkType, LinkingStrategy};
 use roc_build::program::{
     handle_error_module, handle_loading_problem, standard_load_config, BuildFileError,
-    BuildOrdering, BuiltFile, CodeGenBackend, CodeGenOptions, DEFAULT_ROC_FILENAME,
+    BuildOrdering, BuiltFile, CodeGenBackend, CodeGenOptions, Pgo, DEFAULT_ROC_FILENAME,
 };
 #[cfg(not(windows))]
 use roc_collections::MutMap;
 use roc_error_macros::{internal_error, user_error};
 use roc_gen_dev::AssemblyBackendMode;
-use roc_gen_llvm::llvm::build::LlvmBackendMode;
+use roc_gen_llvm::llvm::build::{has_builtins_for, LlvmBackendMode};
+use roc_gen_llvm::llvm::crash_dump::supports_crash_dumps;
+use roc_gen_llvm::llvm::memory::MemoryStrategyKind;
+use roc_gen_llvm::llvm::refcounting::RcOutlining;
 use roc_load::{ExpectMetadata, Threading};
 #[cfg(not(windows))]
 use roc_module::symbol::ModuleId;
 use roc_mono::ir::OptLevel;
+use roc_mono::stack_size::DEFAULT_STACK_VALUE_LIMIT;
 use roc_packaging::cache::RocCacheDir;
 use roc_packaging::tarball::Compression;
 #[cfg(not(windows))]
 use roc_reporting::report::ANSI_STYLE_CODES;
-use roc_target::{Architecture, Target};
+use roc_target::{Architecture, OperatingSystem, Target};
 use std::env;
 use std::ffi::{CString, OsStr, OsString};
 use std::io;
@@ -42,7 +47,9 @@ use strum::IntoEnumIterator;
 use tempfile::TempDir;
 
 mod format;
+mod watch;
 pub use format::{format_files, format_src, FormatMode};
+pub use watch::watch;
 
 pub const CMD_BUILD: &str = "build";
 pub const CMD_RUN: &str = "run";
@@ -79,7 +86,28 @@ pub const FLAG_STDOUT: &str = "stdout";
 pub const FLAG_WASM_STACK_SIZE_KB: &str = "wasm-stack-size-kb";
 pub const FLAG_OUTPUT: &str = "output";
 pub const FLAG_FUZZ: &str = "fuzz";
+pub const FLAG_CACHE_HELPERS: &str = "cache-helpers";
+pub const FLAG_CACHE_MONO: &str = "cache-mono";
+pub const FLAG_RC_OUTLINING: &str = "rc-outlining";
+pub const FLAG_MEMORY: &str = "memory";
+pub const FLAG_ATOMIC_RC: &str = "atomic-rc";
+pub const FLAG_SHUFFLE_PARALLEL: &str = "shuffle-parallel";
+pub const FLAG_FREESTANDING: &str = "freestanding";
+pub const FLAG_STATIC_HEAP: &str = "static-heap";
+pub const FLAG_CRASH_DUMPS: &str = "crash-dumps";
+pub const FLAG_MEMORY_API: &str = "memory-api";
+pub const FLAG_EMIT_SYMBOL_MAP: &str = "emit-symbol-map";
+pub const FLAG_EMIT_CALL_GRAPH: &str = "emit-call-graph";
+pub const FLAG_SIZE_REPORT: &str = "size-report";
+pub const FLAG_EMIT_ENTRY_MANIFEST: &str = "emit-entry-manifest";
+pub const FLAG_STACK_VALUE_LIMIT: &str = "stack-value-limit";
+pub const FLAG_PROFILE_GENERATE: &str = "profile-generate";
+pub const FLAG_PROFILE_USE: &str = "profile-use";
 pub const FLAG_MAIN: &str = "main";
+pub const FLAG_FX_MOCKS: &str = "fx-mocks";
+pub const FLAG_WATCH: &str = "watch";
+pub const FLAG_REPRODUCIBLE: &str = "reproducible";
+pub const FLAG_SPLIT_DEBUG_INFO: &str = "split-debuginfo";
 pub const ROC_FILE: &str = "ROC_FILE";
 pub const ROC_DIR: &str = "ROC_DIR";
 pub const GLUE_DIR: &str = "GLUE_DIR";
@@ -108,7 +136,7 @@ pub fn build_app() -> Command {
 
     let flag_opt_size = Arg::new(FLAG_OPT_SIZE)
         .long(FLAG_OPT_SIZE)
-        .help("Optimize the compiled program to have a small binary size\n(Optimization takes time to complete.)")
+        .help("Optimize the compiled program to have a small binary size\n(Like -Oz: refcounting helpers are not inlined, loops are not unrolled, and identical functions are merged. Optimization takes time to complete.)")
         .action(ArgAction::SetTrue)
         .required(false);
 
@@ -126,7 +154,7 @@ pub fn build_app() -> Command {
 
     let flag_profiling = Arg::new(FLAG_PROFILING)
         .long(FLAG_PROFILING)
-        .help("Keep debug info in the final generated program even in optimized builds")
+        .help("Make the program easier to profile with tools like perf and Instruments\n(Keeps debug info even in optimized builds, and keeps frame pointers and the call frames of refcounting helpers.)")
         .action(ArgAction::SetTrue)
         .required(false);
 
@@ -138,8 +166,8 @@ pub fn build_app() -> Command {
 
     let flag_linker = Arg::new(FLAG_LINKER)
         .long(FLAG_LINKER)
-        .help("Set which linker to use\n(The surgical linker is enabled by default only when building for wasm32 or x86_64 Linux, because those are the only targets it currently supports. Otherwise the legacy linker is used by default.)")
-        .value_parser(["surgical", "legacy"])
+        .help("Set which linker to use\n(The surgical linker is enabled by default only when building for wasm32 or x86_64 Linux, because those are the only targets it currently supports. Otherwise the legacy linker is used by default. `static` uses the legacy linker to produce a fully static Linux executable that links musl instead of glibc.)")
+        .value_parser(["surgical", "legacy", "static"])
         .required(false);
 
     let flag_build_host = Arg::new(FLAG_BUILD_HOST)
@@ -166,6 +194,131 @@ pub fn build_app() -> Command {
         .action(ArgAction::SetTrue)
         .required(false);
 
+    let flag_cache_helpers = Arg::new(FLAG_CACHE_HELPERS)
+        .long(FLAG_CACHE_HELPERS)
+        .help("Reuse refcounting helpers generated by earlier builds, and cache new ones\n(Helpers are stored in the roc cache directory.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_cache_mono = Arg::new(FLAG_CACHE_MONO)
+        .long(FLAG_CACHE_MONO)
+        .help("Reuse the mono IR of earlier builds when no module changed, skipping parsing, type checking and specialization\n(The mono IR is stored in the roc cache directory.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_rc_outlining = Arg::new(FLAG_RC_OUTLINING)
+        .long(FLAG_RC_OUTLINING)
+        .help("Choose when refcounting helpers are inlined at their call sites\n(`inline` and `outline` force either mode; a number inlines helpers with fewer LLVM instructions than that.)")
+        .value_parser(value_parser!(String))
+        .required(false);
+
+    let flag_memory = Arg::new(FLAG_MEMORY)
+        .long(FLAG_MEMORY)
+        .help("Choose how the program manages heap memory\n(`refcount` frees memory as soon as it is no longer used. `arena` skips the frees of compiled code and only lets builtins free memory, which can be faster for short-lived programs. `poison` refcounts, and fills new memory with 0xAA bytes to expose reads of uninitialized memory.)")
+        .value_parser(["refcount", "arena", "poison"])
+        .required(false);
+
+    let flag_atomic_rc = Arg::new(FLAG_ATOMIC_RC)
+        .long(FLAG_ATOMIC_RC)
+        .help("Use atomic refcounts for values the host can share between threads\n(Compiled code updates only the refcounts of values passed to or from the host atomically, and keeps using cheaper non-atomic refcounts for all others. The builtins update every refcount atomically.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_shuffle_parallel = Arg::new(FLAG_SHUFFLE_PARALLEL)
+        .long(FLAG_SHUFFLE_PARALLEL)
+        .value_name("SEED")
+        .help("Run the tasks of List.mapParallel one at a time, in an order shuffled with this seed, instead of on the platform's thread pool\n(The same seed gives the same order on every run, so code that depends on the order fails reproducibly.)")
+        .value_parser(value_parser!(u64))
+        .required(false);
+
+    let flag_profile_generate = Arg::new(FLAG_PROFILE_GENERATE)
+        .long(FLAG_PROFILE_GENERATE)
+        .help("Instrument the program to record how often its code runs, for use with --profile-use\n(The host must link LLVM's profile runtime, e.g. by linking with `clang -fprofile-instr-generate`. Merge the .profraw files the program writes with `llvm-profdata merge`.)")
+        .action(ArgAction::SetTrue)
+        .conflicts_with(FLAG_PROFILE_USE)
+        .required(false);
+
+    let flag_profile_use = Arg::new(FLAG_PROFILE_USE)
+        .long(FLAG_PROFILE_USE)
+        .help("Optimize using a .profdata file recorded by a program built with --profile-generate")
+        .value_parser(value_parser!(PathBuf))
+        .required(false);
+
+    let flag_freestanding = Arg::new(FLAG_FREESTANDING)
+        .long(FLAG_FREESTANDING)
+        .help("Build an object file for a platform without an OS\n(The program only calls the `roc_` functions of the host, and nothing from libc except memcpy, memmove, memset, and memcmp. Requires --no-link.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_static_heap = Arg::new(FLAG_STATIC_HEAP)
+        .long(FLAG_STATIC_HEAP)
+        .help("Allocate from a static buffer of this many bytes instead of calling the host's roc_alloc\n(Memory is never freed; the program panics when the buffer is full.)")
+        .value_parser(value_parser!(u32))
+        .required(false);
+
+    let flag_emit_symbol_map = Arg::new(FLAG_EMIT_SYMBOL_MAP)
+        .long(FLAG_EMIT_SYMBOL_MAP)
+        .help("Write a `.symbols.json` file next to the .roc file, describing the Roc definition and layouts that each function in the program comes from")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_emit_call_graph = Arg::new(FLAG_EMIT_CALL_GRAPH)
+        .long(FLAG_EMIT_CALL_GRAPH)
+        .value_name("FORMAT")
+        .help("Write the call graph of the functions in the program next to the .roc file, as `.callgraph.json` or as `.callgraph.dot` for Graphviz\n(Every function is marked as a Roc definition, a compiler-generated helper, a builtin, or a function of the host.)")
+        .value_parser(["json", "dot"])
+        .required(false);
+
+    let flag_size_report = Arg::new(FLAG_SIZE_REPORT)
+        .long(FLAG_SIZE_REPORT)
+        .help("Print how many bytes of machine code each Roc definition and each kind of compiler-generated helper (refcounting, equality, copying) turns into, and an estimate of the stack each entry point uses")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_crash_dumps = Arg::new(FLAG_CRASH_DUMPS)
+        .long(FLAG_CRASH_DUMPS)
+        .help("Before a crash, hand the host the call stack and a table of Roc function names\n(The host must provide roc_crash_report. Keeps frame pointers, like --profiling.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_memory_api = Arg::new(FLAG_MEMORY_API)
+        .long(FLAG_MEMORY_API)
+        .help("Expose roc__retain_*, roc__release_*, roc__deepcopy_*, roc__sizeof_* and roc__alignof_* functions for every value that crosses the host boundary, for hosts that keep Roc values across calls")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_emit_entry_manifest = Arg::new(FLAG_EMIT_ENTRY_MANIFEST)
+        .long(FLAG_EMIT_ENTRY_MANIFEST)
+        .help("Write a `.entry-points.json` file next to the output, describing the symbols and memory layouts of everything the app exposes to its host")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_stack_value_limit = Arg::new(FLAG_STACK_VALUE_LIMIT)
+        .long(FLAG_STACK_VALUE_LIMIT)
+        .help("Warn about values that take more than this many bytes on the stack\n(Use 0 to turn the warning off.)")
+        .value_parser(value_parser!(u32))
+        .required(false);
+
+    let flag_watch = Arg::new(FLAG_WATCH)
+        .long(FLAG_WATCH)
+        .help("Build again whenever a .roc file in the app's directory changes\n(A running app is stopped first. Stop watching with ctrl-c.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_reproducible = Arg::new(FLAG_REPRODUCIBLE)
+        .long(FLAG_REPRODUCIBLE)
+        .help("Produce the same binary for the same inputs on every machine\n(Compiles on a single thread, so that modules and their symbols are numbered in the same order on every build, and keeps the time of the build out of the output.)")
+        .action(ArgAction::SetTrue)
+        .conflicts_with(FLAG_MAX_THREADS)
+        .conflicts_with(FLAG_CACHE_HELPERS)
+        .required(false);
+
+    let flag_split_debug_info = Arg::new(FLAG_SPLIT_DEBUG_INFO)
+        .long(FLAG_SPLIT_DEBUG_INFO)
+        .help("Move the debug info out of the executable, into a .debug file on Linux or a .dSYM bundle on macOS\n(The executable keeps its symbols, so stack traces still show function names.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
     let flag_main = Arg::new(FLAG_MAIN)
         .long(FLAG_MAIN)
         .help("The .roc file of the main app/package module to resolve dependencies from")
@@ -210,11 +363,31 @@ pub fn build_app() -> Command {
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_cache_helpers.clone())
+            .arg(flag_cache_mono.clone())
+            .arg(flag_rc_outlining.clone())
+            .arg(flag_memory.clone())
+            .arg(flag_atomic_rc.clone())
+            .arg(flag_shuffle_parallel.clone())
+            .arg(flag_profile_generate.clone())
+            .arg(flag_profile_use.clone())
+            .arg(flag_watch.clone())
+            .arg(flag_freestanding)
+            .arg(flag_static_heap)
+            .arg(flag_crash_dumps)
+            .arg(flag_memory_api)
+            .arg(flag_emit_symbol_map)
+            .arg(flag_emit_call_graph)
+            .arg(flag_size_report)
+            .arg(flag_emit_entry_manifest)
+            .arg(flag_stack_value_limit)
+            .arg(flag_reproducible)
+            .arg(flag_split_debug_info)
             .arg(flag_wasm_stack_size_kb)
             .arg(
                 Arg::new(FLAG_TARGET)
                     .long(FLAG_TARGET)
-                    .help("Choose a different target")
+                    .help("Choose a different target\n(You can cross-compile to linux-x32, linux-x64, linux-arm64, linux-riscv64, windows-x64, and wasm32. Other targets can only be built on a machine of that target.)")
                     .default_value(Into::<&'static str>::into(Target::default()))
                     .value_parser(build_target_values_parser.clone())
                     .required(false),
@@ -263,6 +436,14 @@ pub fn build_app() -> Command {
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_cache_helpers.clone())
+            .arg(flag_cache_mono.clone())
+            .arg(flag_rc_outlining.clone())
+            .arg(flag_memory.clone())
+            .arg(flag_atomic_rc.clone())
+            .arg(flag_shuffle_parallel.clone())
+            .arg(flag_profile_generate.clone())
+            .arg(flag_profile_use.clone())
             .arg(
                 Arg::new(FLAG_VERBOSE)
                     .long(FLAG_VERBOSE)
@@ -270,6 +451,13 @@ pub fn build_app() -> Command {
                     .action(ArgAction::SetTrue)
                     .required(false)
             )
+            .arg(
+                Arg::new(FLAG_FX_MOCKS)
+                    .long(FLAG_FX_MOCKS)
+                    .help("A shared library whose `roc_fx_mocks` table replaces effects of the platform in effectful expects\n(Each entry names a host function, like roc_fx_putLine, and points to a function with the same C signature.)")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+            )
             .arg(
                 Arg::new(ROC_FILE)
                     .help("The .roc file to test")
@@ -310,6 +498,15 @@ pub fn build_app() -> Command {
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_cache_helpers.clone())
+            .arg(flag_cache_mono.clone())
+            .arg(flag_rc_outlining.clone())
+            .arg(flag_memory.clone())
+            .arg(flag_atomic_rc.clone())
+            .arg(flag_shuffle_parallel.clone())
+            .arg(flag_profile_generate.clone())
+            .arg(flag_profile_use.clone())
+            .arg(flag_watch.clone())
             .arg(roc_file_to_run.clone())
             .arg(args_for_app.clone().last(true))
         )
@@ -326,6 +523,15 @@ pub fn build_app() -> Command {
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_cache_helpers.clone())
+            .arg(flag_cache_mono.clone())
+            .arg(flag_rc_outlining.clone())
+            .arg(flag_memory.clone())
+            .arg(flag_atomic_rc.clone())
+            .arg(flag_shuffle_parallel.clone())
+            .arg(flag_profile_generate.clone())
+            .arg(flag_profile_use.clone())
+            .arg(flag_watch)
             .arg(roc_file_to_run.clone())
             .arg(args_for_app.clone().last(true))
         )
@@ -462,6 +668,14 @@ pub fn build_app() -> Command {
         .arg(flag_build_host)
         .arg(flag_suppress_build_host_warning)
         .arg(flag_fuzz)
+        .arg(flag_cache_helpers)
+        .arg(flag_cache_mono)
+        .arg(flag_rc_outlining)
+        .arg(flag_memory)
+        .arg(flag_atomic_rc)
+        .arg(flag_shuffle_parallel)
+        .arg(flag_profile_generate)
+        .arg(flag_profile_use)
         .arg(roc_file_to_run)
         .arg(args_for_app.trailing_var_arg(true))
 }
@@ -503,12 +717,29 @@ struct ModuleTestResults {
 #[cfg(not(windows))]
 pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
     use roc_build::program::report_problems_monomorphized;
-    use roc_load::{ExecutionMode, FunctionKind, LoadConfig, LoadMonomorphizedError};
+    use roc_load::{ExecutionMode, FunctionKind, LoadConfig, LoadMonomorphizedError, MonoCache};
     use roc_packaging::cache;
+    use roc_repl_expect::effect_mocks::EffectMocks;
 
     let start_time = Instant::now();
     let arena = Bump::new();
     let opt_level = opt_level_from_flags(matches);
+    let shuffle_parallel_tasks = matches.get_one::<u64>(FLAG_SHUFFLE_PARALLEL).copied();
+
+    let effect_mocks = matches.get_one::<PathBuf>(FLAG_FX_MOCKS).map(|path| {
+        EffectMocks::load(path).unwrap_or_else(|error| {
+            user_error!(
+                "Could not load the effect mocks in {}: {error}",
+                path.display()
+            )
+        })
+    });
+
+    let mono_cache = if matches.get_flag(FLAG_CACHE_MONO) {
+        MonoCache::new(cache::roc_cache_mono_dir())
+    } else {
+        None
+    };
 
     let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
         None => Threading::AllAvailable,
@@ -568,13 +799,24 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
             threading,
             exec_mode: ExecutionMode::Test,
         };
-        let load_result = roc_load::load_and_monomorphize(
-            arena,
-            path.to_path_buf(),
-            opt_main_path.cloned(),
-            RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
-            load_config,
-        );
+        let roc_cache_dir = RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path());
+        let load_result = match &mono_cache {
+            Some(mono_cache) => roc_load::load_and_monomorphize_cached(
+                arena,
+                path.to_path_buf(),
+                opt_main_path.cloned(),
+                roc_cache_dir,
+                load_config,
+                mono_cache,
+            ),
+            None => roc_load::load_and_monomorphize(
+                arena,
+                path.to_path_buf(),
+                opt_main_path.cloned(),
+                roc_cache_dir,
+                load_config,
+            ),
+        };
 
         let mut loaded = match load_result {
             Ok(loaded) => loaded,
@@ -599,9 +841,19 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
                 loaded,
                 opt_level,
                 LlvmBackendMode::CliTest,
+                shuffle_parallel_tasks,
             )
             .unwrap();
 
+        if let Some(effect_mocks) = &effect_mocks {
+            for name in effect_mocks.install(&dyn_lib) {
+                eprintln!(
+                    "Warning: {name} is mocked, but {} never calls it.",
+                    path.display()
+                );
+            }
+        }
+
         // Print warnings before running tests.
         {
             debug_assert_eq!(
@@ -887,16 +1139,49 @@ pub fn build(
         CodeGenBackend::Llvm(backend_mode)
     };
 
+    if matches!(code_gen_backend, CodeGenBackend::Llvm(_)) && !has_builtins_for(target) {
+        let supported: Vec<&str> = Target::iter()
+            .filter(|target| has_builtins_for(*target))
+            .map(Into::into)
+            .collect();
+
+        user_error!(
+            "Roc cannot build for {target} from this machine yet.\n\nThese targets are supported here: {}",
+            supported.join(", ")
+        );
+    }
+
     let emit_llvm_ir = matches.get_flag(FLAG_EMIT_LLVM_IR);
     if emit_llvm_ir && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
         user_error!("Cannot emit llvm ir while using a dev backend.");
     }
 
-    let emit_debug_info = matches.get_flag(FLAG_PROFILING)
-        || matches!(opt_level, OptLevel::Development | OptLevel::Normal);
+    let profiling = matches.get_flag(FLAG_PROFILING);
+    let emit_debug_info =
+        profiling || matches!(opt_level, OptLevel::Development | OptLevel::Normal);
     let emit_timings = matches.get_flag(FLAG_TIME);
 
+    // only defined for `roc build`
+    let reproducible = matches
+        .try_get_one::<bool>(FLAG_REPRODUCIBLE)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+
+    if reproducible {
+        // zig and the linkers read these, and then leave the time of the build out of what they
+        // write, e.g. ld64 stores the modification times of the object files it links otherwise
+        env::set_var("SOURCE_DATE_EPOCH", "0");
+        env::set_var("ZERO_AR_DATE", "1");
+    }
+
+    // modules get their ids in the order in which they are loaded, which depends on how threads
+    // are scheduled. Those ids decide the order of symbols and procedures in the output. The
+    // hash maps of the compiler use a hasher without a random seed, so a single thread is enough
+    // to iterate them in the same order on every build.
     let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
+        None if reproducible => Threading::Single,
         None => Threading::AllAvailable,
         Some(0) => user_error!("cannot build with at most 0 threads"),
         Some(1) => Threading::Single,
@@ -905,11 +1190,20 @@ pub fn build(
 
     let wasm_dev_backend = matches!(code_gen_backend, CodeGenBackend::Wasm);
 
-    let linking_strategy = if wasm_dev_backend {
+    let linker = matches.get_one::<String>(FLAG_LINKER).map(|s| s.as_str());
+
+    let linking_strategy = if linker == Some("static") {
+        if !matches!(target.operating_system(), OperatingSystem::Linux) {
+            user_error!("Static linking is only supported when building for Linux.");
+        }
+        if link_type != LinkType::Executable {
+            user_error!("Static linking is only supported when building an executable.");
+        }
+
+        LinkingStrategy::Static
+    } else if wasm_dev_backend {
         LinkingStrategy::Additive
-    } else if !roc_linker::supported(link_type, target)
-        || matches.get_one::<String>(FLAG_LINKER).map(|s| s.as_str()) == Some("legacy")
-    {
+    } else if !roc_linker::supported(link_type, target) || linker == Some("legacy") {
         LinkingStrategy::Legacy
     } else {
         LinkingStrategy::Surgical
@@ -925,6 +1219,174 @@ pub fn build(
         user_error!("Cannot instrument binary for fuzzing while using a dev backend.");
     }
 
+    let cache_helpers = matches.get_flag(FLAG_CACHE_HELPERS);
+    if cache_helpers && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot cache refcounting helpers while using a dev backend.");
+    }
+
+    let cache_mono = matches.get_flag(FLAG_CACHE_MONO);
+
+    let rc_outlining = match matches.get_one::<String>(FLAG_RC_OUTLINING).map(|s| s.as_str()) {
+        // every inlined copy of a helper costs code size
+        None if matches!(opt_level, OptLevel::Size) => RcOutlining::Outline,
+        None => RcOutlining::default(),
+        Some("inline") => RcOutlining::Inline,
+        Some("outline") => RcOutlining::Outline,
+        Some(threshold) => match threshold.parse() {
+            Ok(threshold) => RcOutlining::Threshold(threshold),
+            Err(_) => user_error!(
+                "Invalid value for --{FLAG_RC_OUTLINING}: {threshold:?}. Expected `inline`, `outline`, or a number."
+            ),
+        },
+    };
+
+    // the value parser of the flag only accepts these values
+    let memory_strategy = match matches.get_one::<String>(FLAG_MEMORY).map(|s| s.as_str()) {
+        Some("arena") => MemoryStrategyKind::Arena,
+        Some("poison") => MemoryStrategyKind::Poison,
+        _ => MemoryStrategyKind::Refcount,
+    };
+    if memory_strategy != MemoryStrategyKind::default()
+        && !matches!(code_gen_backend, CodeGenBackend::Llvm(_))
+    {
+        user_error!("Cannot change the memory management strategy while using a dev backend.");
+    }
+
+    let atomic_refcounts = matches.get_flag(FLAG_ATOMIC_RC);
+    if atomic_refcounts && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot use atomic refcounts while using a dev backend.");
+    }
+
+    let shuffle_parallel_tasks = matches.get_one::<u64>(FLAG_SHUFFLE_PARALLEL).copied();
+    if shuffle_parallel_tasks.is_some() && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot shuffle parallel tasks while using a dev backend.");
+    }
+
+    let pgo = if matches.get_flag(FLAG_PROFILE_GENERATE) {
+        Some(Pgo::Generate)
+    } else {
+        matches
+            .get_one::<PathBuf>(FLAG_PROFILE_USE)
+            .map(|path| Pgo::Use(path.clone()))
+    };
+    if pgo.is_some() && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot use profile-guided optimization while using a dev backend.");
+    }
+    if let Some(Pgo::Use(path)) = &pgo {
+        if !path.exists() {
+            user_error!("The profile {} does not exist.", path.display());
+        }
+    }
+
+    // these flags are only defined for `roc build`
+    let freestanding = matches
+        .try_get_one::<bool>(FLAG_FREESTANDING)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+    if freestanding {
+        if !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+            user_error!("Cannot build freestanding while using a dev backend.");
+        }
+        if link_type != LinkType::None {
+            user_error!("A freestanding build cannot be linked by roc; use --{FLAG_NO_LINK} and let the platform link the object file.");
+        }
+    }
+
+    let static_heap_bytes = matches
+        .try_get_one::<u32>(FLAG_STATIC_HEAP)
+        .ok()
+        .flatten()
+        .copied();
+    if static_heap_bytes.is_some() && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot use a static heap while using a dev backend.");
+    }
+
+    let crash_dumps = matches
+        .try_get_one::<bool>(FLAG_CRASH_DUMPS)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+    if crash_dumps {
+        if !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+            user_error!("Cannot write crash dumps while using a dev backend.");
+        }
+        if !supports_crash_dumps(target) {
+            user_error!(
+                "Crash dumps are not supported on {target}, because we can't walk its stack."
+            );
+        }
+    }
+
+    let memory_api = matches
+        .try_get_one::<bool>(FLAG_MEMORY_API)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+    if memory_api && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot expose the memory API while using a dev backend.");
+    }
+
+    let emit_entry_manifest = matches
+        .try_get_one::<bool>(FLAG_EMIT_ENTRY_MANIFEST)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+
+    let emit_symbol_map = matches
+        .try_get_one::<bool>(FLAG_EMIT_SYMBOL_MAP)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+    if emit_symbol_map && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot emit a symbol map while using a dev backend.");
+    }
+
+    let emit_call_graph = match matches.try_get_one::<String>(FLAG_EMIT_CALL_GRAPH) {
+        Ok(Some(format)) if format == "dot" => Some(CallGraphFormat::Dot),
+        Ok(Some(_)) => Some(CallGraphFormat::Json),
+        Ok(None) | Err(_) => None,
+    };
+    if emit_call_graph.is_some() && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot emit a call graph while using a dev backend.");
+    }
+
+    let size_report = matches
+        .try_get_one::<bool>(FLAG_SIZE_REPORT)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+    if size_report && !matches!(code_gen_backend, CodeGenBackend::Llvm(_)) {
+        user_error!("Cannot make a size report while using a dev backend.");
+    }
+
+    let split_debug = matches
+        .try_get_one::<bool>(FLAG_SPLIT_DEBUG_INFO)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+    if split_debug {
+        if !emit_debug_info {
+            user_error!("An optimized build has no debug info to split off; also pass --{FLAG_PROFILING} to keep it.");
+        }
+        if link_type != LinkType::Executable {
+            user_error!("Debug info can only be split off of an executable.");
+        }
+    }
+
+    let stack_value_limit = match matches.try_get_one::<u32>(FLAG_STACK_VALUE_LIMIT) {
+        Ok(Some(0)) => None,
+        Ok(Some(limit)) => Some(*limit),
+        Ok(None) | Err(_) => Some(DEFAULT_STACK_VALUE_LIMIT),
+    };
+
     let wasm_dev_stack_bytes: Option<u32> = matches
         .try_get_one::<u32>(FLAG_WASM_STACK_SIZE_KB)
         .ok()
@@ -942,6 +1404,23 @@ pub fn build(
         emit_debug_info,
         emit_llvm_ir,
         fuzz,
+        cache_helpers,
+        cache_mono,
+        rc_outlining,
+        memory_strategy,
+        atomic_refcounts,
+        freestanding,
+        static_heap_bytes,
+        profiling,
+        crash_dumps,
+        memory_api,
+        shuffle_parallel_tasks,
+        emit_symbol_map,
+        emit_call_graph,
+        size_report,
+        pgo,
+        emit_entry_manifest,
+        stack_value_limit,
     };
 
     let load_config = standard_load_config(target, build_ordering, threading);
@@ -985,6 +1464,20 @@ pub fn build(
                     problems.print_error_warning_count(total_time);
                     println!(" while successfully building:\n\n    {generated_filename}");
 
+                    if split_debug {
+                        match split_debug_info(&binary_path, target) {
+                            Ok(debug_path) => {
+                                println!(
+                                    "\nwith its debug info in:\n\n    {}",
+                                    debug_path.display()
+                                )
+                            }
+                            Err(error) => {
+                                user_error!("I could not split off the debug info: {error}")
+                            }
+                        }
+                    }
+
                     // Return a nonzero exit code if there were problems
                     Ok(problems.exit_code())
                 }
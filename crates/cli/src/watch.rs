@@ -0,0 +1,121 @@
+//! `--watch`: build (and run) an app again whenever one of its .roc files changes.
+//!
+//! Running an app replaces the process that built it (see `roc_run_native`), so the watching
+//! process can't do the builds itself. Instead, every build runs in a child `roc` process with
+//! the same arguments, minus `--watch`.
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::FLAG_WATCH;
+
+/// How often we look for changed files
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The modification times of the .roc files in a directory and its subdirectories
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+/// Build `roc_file_path` with the arguments this process was started with, and build it again
+/// whenever a .roc file next to it (or in a subdirectory) changes. Only returns on errors; the
+/// user stops watching with ctrl-c.
+pub fn watch(roc_file_path: &Path) -> io::Result<i32> {
+    let dir = match roc_file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let roc_binary = std::env::current_exe()?;
+    let args = args_without_watch(std::env::args_os().skip(1));
+
+    loop {
+        let snapshot = snapshot(dir)?;
+        let mut child = Command::new(&roc_binary).args(&args).spawn()?;
+
+        let changed = wait_for_change(dir, &snapshot)?;
+
+        stop(&mut child)?;
+
+        println!(
+            "\n{} changed, building {} again...\n",
+            changed.display(),
+            roc_file_path.display()
+        );
+    }
+}
+
+/// Everything after `--` goes to the app, so only the flags before it can be our `--watch`
+fn args_without_watch(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let watch_flag = OsString::from(format!("--{FLAG_WATCH}"));
+    let mut for_app = false;
+
+    args.filter(|arg| {
+        for_app = for_app || arg == "--";
+
+        for_app || *arg != watch_flag
+    })
+    .collect()
+}
+
+/// Returns the first file that was added, removed or modified compared to `snapshot`
+fn wait_for_change(dir: &Path, snapshot: &Snapshot) -> io::Result<PathBuf> {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let current = self::snapshot(dir)?;
+
+        let modified = current
+            .iter()
+            .find(|(path, time)| snapshot.get(*path) != Some(*time))
+            .map(|(path, _)| path);
+        let removed = snapshot.keys().find(|path| !current.contains_key(*path));
+
+        if let Some(path) = modified.or(removed) {
+            return Ok(path.clone());
+        }
+    }
+}
+
+/// The app may still be running (e.g. a server), or waiting for input
+fn stop(child: &mut Child) -> io::Result<()> {
+    if child.try_wait()?.is_none() {
+        child.kill()?;
+    }
+
+    child.wait().map(|_| ())
+}
+
+fn snapshot(dir: &Path) -> io::Result<Snapshot> {
+    let mut snapshot = Snapshot::new();
+
+    add_roc_files(dir, &mut snapshot)?;
+
+    Ok(snapshot)
+}
+
+fn add_roc_files(dir: &Path, snapshot: &mut Snapshot) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        // skip .git and friends, they can be big and never contain modules
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            add_roc_files(&path, snapshot)?;
+        } else if path.extension() == Some(OsStr::new("roc")) {
+            // the file may have been removed since we listed the directory
+            if let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) {
+                snapshot.insert(path, modified);
+            }
+        }
+    }
+
+    Ok(())
+}
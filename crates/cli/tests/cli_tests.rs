@@ -107,6 +107,63 @@ mod cli_tests {
         cli_check_out.assert_clean_success();
     }
 
+    #[test]
+    fn wasi_platform() {
+        let cli_check = ExecCli::new(CMD_CHECK, file_from_root("examples/wasi", "main.roc"));
+
+        let cli_check_out = cli_check.run();
+        cli_check_out.assert_clean_success();
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn wasi_platform_runs_in_wasmtime() {
+        // the example needs a WASI runtime with file system access, which our wasm interpreter
+        // does not have
+        if std::process::Command::new("wasmtime")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!(
+                "WARNING: skipping running the WASI example because wasmtime is not installed."
+            );
+            return;
+        }
+
+        let roc_file_path = file_from_root("examples/wasi", "main.roc");
+
+        ExecCli::new(CMD_BUILD, roc_file_path.clone())
+            .arg(BUILD_HOST_FLAG)
+            .arg(SUPPRESS_BUILD_HOST_WARNING_FLAG)
+            .arg(concatcp!(TARGET_FLAG, "=wasm32"))
+            .run()
+            .assert_clean_success();
+
+        // the app writes a file to the directory that it is given
+        let work_dir = tempfile::tempdir().unwrap();
+        let wasmtime_out = std::process::Command::new("wasmtime")
+            .args(["--dir", "."])
+            .arg(roc_file_path.with_extension("wasm"))
+            .current_dir(work_dir.path())
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&wasmtime_out.stdout);
+        assert!(
+            wasmtime_out.status.success(),
+            "wasmtime failed:\n{stdout}\n{}",
+            String::from_utf8_lossy(&wasmtime_out.stderr)
+        );
+        assert!(
+            stdout.starts_with(
+                "Roc <3 WASI!\nRead back: Written by a Roc program running in a WASI runtime.\n"
+            ),
+            "{stdout}"
+        );
+        assert!(work_dir.path().join("roc-wasi.txt").exists());
+    }
+
     #[test]
     #[cfg_attr(
         windows,
@@ -287,6 +344,150 @@ mod cli_tests {
         );
     }
 
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn entry_manifest() {
+        let roc_file = file_from_root(
+            "crates/cli/tests/test-projects/multiple_exposed",
+            "main.roc",
+        );
+
+        let cli_build = ExecCli::new(CMD_BUILD, roc_file.clone())
+            .arg(BUILD_HOST_FLAG)
+            .arg(SUPPRESS_BUILD_HOST_WARNING_FLAG)
+            .arg(concatcp!("--", roc_cli::FLAG_EMIT_ENTRY_MANIFEST))
+            .arg(concatcp!("--", roc_cli::FLAG_MEMORY_API));
+
+        cli_build.run().assert_clean_success();
+
+        let manifest =
+            std::fs::read_to_string(roc_file.with_extension("entry-points.json")).unwrap();
+
+        for expected in [
+            r#""generic_symbol": "roc__exposedForHost1_1_exposed_generic""#,
+            r#""generic_symbol": "roc__exposedForHost2_1_exposed_generic""#,
+            r#""memory_api": "exposedForHost1_1_exposed_result""#,
+            r#""ownership": "consumed""#,
+        ] {
+            assert!(
+                manifest.contains(expected),
+                "{expected} is missing from the manifest:\n\n{manifest}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn symbol_map() {
+        let roc_file = file_from_root(
+            "crates/cli/tests/test-projects/multiple_exposed",
+            "main.roc",
+        );
+
+        let cli_build = ExecCli::new(CMD_BUILD, roc_file.clone())
+            .arg(BUILD_HOST_FLAG)
+            .arg(SUPPRESS_BUILD_HOST_WARNING_FLAG)
+            .arg(concatcp!("--", roc_cli::FLAG_EMIT_SYMBOL_MAP));
+
+        cli_build.run().assert_clean_success();
+
+        let symbol_map = std::fs::read_to_string(roc_file.with_extension("symbols.json")).unwrap();
+
+        for expected in [
+            r#""kind": "proc""#,
+            r##""definition": "#UserApp.exposed1""##,
+            r#""arguments": ["#,
+        ] {
+            assert!(
+                symbol_map.contains(expected),
+                "{expected} is missing from the symbol map:\n\n{symbol_map}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn call_graph() {
+        let roc_file = file_from_root(
+            "crates/cli/tests/test-projects/multiple_exposed",
+            "main.roc",
+        );
+
+        for (format, expected) in [
+            (
+                "json",
+                [
+                    r#""symbol": "roc__exposedForHost1_1_exposed""#,
+                    r#""kind": "entry_point""#,
+                    r##""origin": "#UserApp.exposed1""##,
+                ],
+            ),
+            (
+                "dot",
+                [
+                    "digraph ",
+                    r#""roc__exposedForHost1_1_exposed" [label="#,
+                    r#""roc__exposedForHost1_1_exposed" -> "#,
+                ],
+            ),
+        ] {
+            let cli_build = ExecCli::new(CMD_BUILD, roc_file.clone())
+                .arg(BUILD_HOST_FLAG)
+                .arg(SUPPRESS_BUILD_HOST_WARNING_FLAG)
+                .add_args([concatcp!("--", roc_cli::FLAG_EMIT_CALL_GRAPH), format]);
+
+            cli_build.run().assert_clean_success();
+
+            let extension = format!("callgraph.{format}");
+            let call_graph = std::fs::read_to_string(roc_file.with_extension(extension)).unwrap();
+
+            for expected in expected {
+                assert!(
+                    call_graph.contains(expected),
+                    "{expected} is missing from the call graph:\n\n{call_graph}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn size_report() {
+        let roc_file = file_from_root(
+            "crates/cli/tests/test-projects/multiple_exposed",
+            "main.roc",
+        );
+
+        let cli_build = ExecCli::new(CMD_BUILD, roc_file)
+            .arg(BUILD_HOST_FLAG)
+            .arg(SUPPRESS_BUILD_HOST_WARNING_FLAG)
+            .arg(concatcp!("--", roc_cli::FLAG_SIZE_REPORT));
+
+        let output = cli_build.run();
+        output.assert_clean_success();
+
+        for expected in [
+            "Machine code in the app: ",
+            "Roc definitions\n",
+            "host entry points\n",
+            "Stack used by each entry point, estimated:\n",
+            "roc__exposedForHost1_1_exposed",
+        ] {
+            assert!(
+                output.stdout.contains(expected),
+                "{expected} is missing from the size report:\n\n{}",
+                output.stdout
+            );
+        }
+
+        // fib and fact are tail recursive, so they are loops
+        assert!(
+            !output.stdout.contains("no bound"),
+            "the stack depth should be bounded:\n\n{}",
+            output.stdout
+        );
+    }
+
     #[test]
     #[cfg_attr(windows, ignore)]
     fn test_module_imports_pkg_w_flag() {
@@ -905,6 +1106,38 @@ mod cli_tests {
             cli_build.run().assert_clean_stdout(expected_out);
         }
 
+        #[test]
+        #[cfg_attr(windows, ignore)]
+        fn reproducible_builds_are_identical() {
+            build_platform_host();
+
+            let out_dir = tempfile::tempdir().unwrap();
+
+            let build_to = |name: &str| {
+                let output = out_dir.path().join(name);
+
+                ExecCli::new(
+                    CMD_BUILD,
+                    file_from_root("crates/cli/tests/test-projects/effectful/", "hello.roc"),
+                )
+                .arg(concatcp!("--", roc_cli::FLAG_REPRODUCIBLE))
+                .arg(format!("--{}={}", roc_cli::FLAG_OUTPUT, output.display()))
+                .run()
+                .assert_clean_success();
+
+                std::fs::read(output).unwrap()
+            };
+
+            let first = build_to("hello1");
+            let second = build_to("hello2");
+
+            assert!(
+                first == second,
+                "building hello.roc twice with --{} gave different executables",
+                roc_cli::FLAG_REPRODUCIBLE
+            );
+        }
+
         #[test]
         #[cfg_attr(windows, ignore)]
         fn effectful_loops() {
@@ -1011,6 +1244,87 @@ mod cli_tests {
 
             cli_build.check_build_and_run(expected_output, ALLOW_VALGRIND, None, None);
         }
+
+        static BUILD_FX_MOCKS: std::sync::Once = std::sync::Once::new();
+
+        /// Build the mock library of fx_mocks.zig once, and return its path
+        fn build_fx_mocks() -> std::path::PathBuf {
+            let library = std::env::temp_dir()
+                .join(format!("roc_fx_mocks.{}", std::env::consts::DLL_EXTENSION));
+
+            BUILD_FX_MOCKS.call_once(|| {
+                let source =
+                    file_from_root("crates/cli/tests/test-projects/effectful", "fx_mocks.zig");
+
+                let output = roc_command_utils::zig()
+                    .arg("build-lib")
+                    .arg(source)
+                    .args([
+                        "-lc",
+                        &format!("-femit-bin={}", library.display()),
+                        "-dynamic",
+                    ])
+                    .output()
+                    .unwrap();
+
+                assert!(
+                    output.status.success(),
+                    "building the mock library failed:\n{}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            });
+
+            library
+        }
+
+        fn test_with_fx_mocks() -> cli_test_utils::command::CmdOut {
+            build_platform_host();
+            let fx_mocks = build_fx_mocks();
+
+            ExecCli::new(
+                CMD_TEST,
+                file_from_root("crates/cli/tests/test-projects/effectful", "fx_mocks.roc"),
+            )
+            .arg(format!(
+                "--{}={}",
+                roc_cli::FLAG_FX_MOCKS,
+                fx_mocks.display()
+            ))
+            .run()
+        }
+
+        #[test]
+        #[cfg_attr(windows, ignore = "running tests does not work on windows right now")]
+        fn fx_mocks_replace_effects() {
+            let cli_test_out = test_with_fx_mocks();
+
+            assert!(cli_test_out.status.success(), "{cli_test_out}");
+            assert!(
+                cli_test_out.stdout.contains("putLine was mocked"),
+                "{cli_test_out}"
+            );
+            assert!(
+                !cli_test_out.stdout.contains("Hello from an expect"),
+                "{cli_test_out}"
+            );
+        }
+
+        #[test]
+        #[cfg_attr(windows, ignore = "running tests does not work on windows right now")]
+        fn fx_mocks_warn_about_unused_mocks() {
+            let cli_test_out = test_with_fx_mocks();
+
+            assert!(
+                cli_test_out
+                    .stderr
+                    .contains("Warning: roc_fx_putLien is mocked, but"),
+                "{cli_test_out}"
+            );
+            assert!(
+                !cli_test_out.stderr.contains("roc_fx_putLine is mocked"),
+                "{cli_test_out}"
+            );
+        }
     }
 
     // this is for testing the benchmarks (on small inputs), to perform proper benchmarks see crates/cli/benches/README.md
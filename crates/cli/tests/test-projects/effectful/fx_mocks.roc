@@ -0,0 +1,11 @@
+app [main!] { pf: platform "../test-platform-effects-zig/main.roc" }
+
+import pf.Effect
+
+main! : {} => {}
+main! = \{} ->
+    Effect.putLine! "Hello from the platform"
+
+expect-fx
+    Effect.putLine! "Hello from an expect"
+    Bool.true
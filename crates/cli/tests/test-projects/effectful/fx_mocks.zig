@@ -0,0 +1,23 @@
+// A mock library for `roc test --fx-mocks`, used by the cli tests with fx_mocks.roc
+const std = @import("std");
+
+const RocFxMock = extern struct {
+    name: ?[*:0]const u8,
+    function: ?*const anyopaque,
+};
+
+fn mockPutLine(line: *const anyopaque) callconv(.C) i64 {
+    _ = line;
+
+    const stdout = std.io.getStdOut().writer();
+    stdout.print("putLine was mocked\n", .{}) catch unreachable;
+
+    return 0;
+}
+
+export const roc_fx_mocks = [_]RocFxMock{
+    .{ .name = "roc_fx_putLine", .function = @ptrCast(&mockPutLine) },
+    // misspelled on purpose, to test the warning about mocks that are never called
+    .{ .name = "roc_fx_putLien", .function = @ptrCast(&mockPutLine) },
+    .{ .name = null, .function = null },
+};
@@ -909,6 +909,34 @@ fn call_spec<'a>(
                     let state_type = layout_spec(env, builder, interner, state_layout)?;
                     let init_state = list;
 
+                    add_loop(builder, block, state_type, init_state, loop_body)
+                }
+                ListMapParallel { xs } => {
+                    let list = env.symbols[xs];
+
+                    let loop_body = |builder: &mut FuncDefBuilder, block, state| {
+                        let input_bag = builder.add_get_tuple_field(block, list, LIST_BAG_INDEX)?;
+
+                        let element = builder.add_bag_get(block, input_bag)?;
+
+                        let new_element = call_function!(builder, block, [element]);
+
+                        list_append(builder, block, update_mode_var, state, new_element)
+                    };
+
+                    let output_element_layout = passed_function.return_layout;
+                    let output_element_type = layout_spec(
+                        env,
+                        builder,
+                        interner,
+                        interner.get_repr(output_element_layout),
+                    )?;
+
+                    let state_layout = LayoutRepr::Builtin(Builtin::List(output_element_layout));
+                    let state_type = layout_spec(env, builder, interner, state_layout)?;
+
+                    let init_state = new_list(builder, block, output_element_type)?;
+
                     add_loop(builder, block, state_type, init_state, loop_body)
                 }
             }
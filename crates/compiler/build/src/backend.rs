@@ -0,0 +1,223 @@
+//! The contract between the build pipeline and the code generators.
+//!
+//! Every backend takes the monomorphized procedures of an app and turns them into something the
+//! linker can use: an object file for the LLVM and assembly backends, or a finished module for
+//! the wasm backend. The pipeline only talks to backends through [`Backend`], so a new backend
+//! (or a test harness that wants to run the same program through all of them) only needs to
+//! implement that trait.
+use crate::program::{
+    gen_from_mono_module_llvm, BuiltHostOpt, CodeGenBackend, CodeGenOptions, GenFromMono,
+};
+use roc_error_macros::internal_error;
+use roc_gen_dev::AssemblyBackendMode;
+use roc_gen_llvm::llvm::build::LlvmBackendMode;
+use roc_load::MonomorphizedModule;
+use roc_target::{Architecture, SurgicalHostArtifacts, Target};
+use std::path::Path;
+
+/// Where a backend gets the refcounting and equality helpers for the layouts it encounters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelperProcs {
+    /// `roc_mono::code_gen_help` generates the helpers as mono procedures, which the backend
+    /// then lowers like any other procedure
+    Mono,
+    /// The backend generates the helpers itself while it lowers the procedures
+    Backend,
+}
+
+pub trait Backend<'a> {
+    /// A short name for error messages and timings
+    fn name(&self) -> &'static str;
+
+    fn helper_procs(&self) -> HelperProcs;
+
+    /// Lower all procedures of `loaded` for `target`
+    fn gen_from_mono_module(
+        &self,
+        arena: &'a bumpalo::Bump,
+        loaded: MonomorphizedModule<'a>,
+        roc_file_path: &Path,
+        target: Target,
+    ) -> GenFromMono<'a>;
+}
+
+/// Pick the backend that `code_gen_options` asks for
+pub fn backend_for<'a, 'o>(
+    code_gen_options: &'o CodeGenOptions,
+    built_host_opt: &'o BuiltHostOpt,
+    wasm_dev_stack_bytes: Option<u32>,
+) -> Box<dyn Backend<'a> + 'o> {
+    match code_gen_options.backend {
+        CodeGenBackend::Llvm(mode) => Box::new(LlvmBackend {
+            mode,
+            code_gen_options,
+        }),
+        CodeGenBackend::Assembly(mode) => Box::new(AssemblyBackend { mode }),
+        CodeGenBackend::Wasm => Box::new(WasmBackend {
+            built_host_opt,
+            stack_bytes: wasm_dev_stack_bytes,
+        }),
+    }
+}
+
+pub struct LlvmBackend<'o> {
+    pub mode: LlvmBackendMode,
+    pub code_gen_options: &'o CodeGenOptions,
+}
+
+impl<'a, 'o> Backend<'a> for LlvmBackend<'o> {
+    fn name(&self) -> &'static str {
+        "llvm"
+    }
+
+    fn helper_procs(&self) -> HelperProcs {
+        HelperProcs::Backend
+    }
+
+    fn gen_from_mono_module(
+        &self,
+        arena: &'a bumpalo::Bump,
+        loaded: MonomorphizedModule<'a>,
+        roc_file_path: &Path,
+        target: Target,
+    ) -> GenFromMono<'a> {
+        let options = self.code_gen_options;
+
+        gen_from_mono_module_llvm(
+            arena,
+            loaded,
+            roc_file_path,
+            target,
+            options.opt_level,
+            self.mode,
+            options.emit_debug_info,
+            options.emit_llvm_ir,
+            options.fuzz,
+            options.cache_helpers,
+            options.rc_outlining,
+            options.memory_strategy,
+            options.atomic_refcounts,
+            options.freestanding,
+            options.static_heap_bytes,
+            options.profiling,
+            options.crash_dumps,
+            options.memory_api,
+            options.shuffle_parallel_tasks,
+            options.emit_symbol_map,
+            options.emit_call_graph,
+            options.size_report,
+            options.pgo.clone(),
+        )
+    }
+}
+
+pub struct AssemblyBackend {
+    pub mode: AssemblyBackendMode,
+}
+
+impl<'a> Backend<'a> for AssemblyBackend {
+    fn name(&self) -> &'static str {
+        "assembly"
+    }
+
+    fn helper_procs(&self) -> HelperProcs {
+        HelperProcs::Mono
+    }
+
+    #[allow(unused_variables)]
+    fn gen_from_mono_module(
+        &self,
+        arena: &'a bumpalo::Bump,
+        loaded: MonomorphizedModule<'a>,
+        _roc_file_path: &Path,
+        target: Target,
+    ) -> GenFromMono<'a> {
+        match target.architecture() {
+            Architecture::X86_64 | Architecture::Aarch64 => {
+                #[cfg(not(feature = "target-wasm32"))]
+                {
+                    crate::program::gen_from_mono_module_dev_assembly(
+                        arena, loaded, target, self.mode,
+                    )
+                }
+
+                #[cfg(feature = "target-wasm32")]
+                {
+                    internal_error!("Compiler was not built with feature 'target-wasm32'.")
+                }
+            }
+            Architecture::Wasm32 => {
+                internal_error!("The assembly backend cannot target wasm32, use the wasm backend.")
+            }
+            Architecture::Aarch32 => {
+                internal_error!("Dev compiler backend does not support 32 bit ARM architectures")
+            }
+            Architecture::X86_32 => {
+                internal_error!("Dev compiler backend does not support 32 bit x86 architectures")
+            }
+            Architecture::Riscv64 => {
+                internal_error!("Dev compiler backend does not support RISC-V architectures")
+            }
+        }
+    }
+}
+
+pub struct WasmBackend<'h> {
+    pub built_host_opt: &'h BuiltHostOpt,
+    pub stack_bytes: Option<u32>,
+}
+
+impl<'a, 'h> Backend<'a> for WasmBackend<'h> {
+    fn name(&self) -> &'static str {
+        "wasm"
+    }
+
+    fn helper_procs(&self) -> HelperProcs {
+        HelperProcs::Mono
+    }
+
+    #[allow(unused_variables)]
+    fn gen_from_mono_module(
+        &self,
+        arena: &'a bumpalo::Bump,
+        loaded: MonomorphizedModule<'a>,
+        _roc_file_path: &Path,
+        target: Target,
+    ) -> GenFromMono<'a> {
+        if target.architecture() != Architecture::Wasm32 {
+            internal_error!("The wasm backend can only target wasm32, not {target}.");
+        }
+
+        match self.built_host_opt {
+            BuiltHostOpt::Additive(host_path) => {
+                #[cfg(feature = "target-wasm32")]
+                {
+                    crate::program::gen_from_mono_module_dev_wasm32(
+                        arena,
+                        loaded,
+                        host_path,
+                        self.stack_bytes,
+                    )
+                }
+
+                #[cfg(not(feature = "target-wasm32"))]
+                {
+                    internal_error!("Compiler was not built with feature 'target-wasm32'.");
+                }
+            }
+            BuiltHostOpt::None => {
+                internal_error!("Cannot compile wasm32 without a host on the dev compiler backend.")
+            }
+            BuiltHostOpt::Legacy(host_path) => internal_error!(
+                "Unsupported host files found for use with wasm32 dev compiler backend:\n    {}",
+                host_path.display()
+            ),
+            BuiltHostOpt::Surgical(SurgicalHostArtifacts {
+                preprocessed_host, ..
+            }) => internal_error!(
+                "Unsupported host files found for use with wasm32 dev compiler backend:\n    {}",
+                preprocessed_host.display()
+            ),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! The call graph of the generated code, to find out why a function is in the program at all: which
+//! Roc definitions use a refcounting helper, which builtins a helper needs, and what nothing calls.
+//!
+//! Nodes are the functions of the final LLVM module, so inlined functions are gone, and the host
+//! functions they call. An edge means that a function calls another, or passes it to a function
+//! that calls it, like the decrement helper that `roc_builtins.list.decref` calls on each element.
+use inkwell::module::Module;
+use roc_collections::all::{MutMap, MutSet};
+use roc_gen_llvm::llvm::symbol_map::{HelperKind, SymbolOrigin};
+use roc_gen_llvm::llvm::totality::used_functions;
+use roc_target::Target;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallGraphFormat {
+    Json,
+    Dot,
+}
+
+impl CallGraphFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            CallGraphFormat::Json => "callgraph.json",
+            CallGraphFormat::Dot => "callgraph.dot",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CallGraph {
+    pub target: String,
+    pub functions: Vec<CallGraphNode>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CallGraphNode {
+    pub symbol: String,
+    pub kind: NodeKind,
+    /// The Roc definition of a proc, e.g. `List.map`, or the helper and layout of a helper
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    pub calls: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// A wrapper that the host calls, like `roc__mainForHost_1_exposed`
+    EntryPoint,
+    /// A specialization of a Roc definition
+    Proc,
+    RefcountingHelper,
+    EqualityHelper,
+    CopyingHelper,
+    /// Part of the zig builtins
+    Builtin,
+    /// Defined outside of the app, by the host or its C library, like `roc_alloc`
+    Host,
+    /// Other generated code, like the wrappers that builtins call Roc functions through
+    Other,
+}
+
+impl CallGraph {
+    /// `builtins` are the functions that the module defined before code gen, and `origins` the
+    /// entries of the symbol map that was built along with it
+    pub fn new(
+        target: Target,
+        module: &Module<'_>,
+        builtins: &MutSet<String>,
+        origins: &[(String, SymbolOrigin)],
+    ) -> Self {
+        let origins: MutMap<&str, &SymbolOrigin> = origins
+            .iter()
+            .map(|(fn_name, origin)| (fn_name.as_str(), origin))
+            .collect();
+
+        let mut functions = Vec::new();
+        let mut host_functions = MutSet::default();
+
+        for function in module.get_functions() {
+            if function.count_basic_blocks() == 0 {
+                continue;
+            }
+
+            let symbol = function.get_name().to_string_lossy().into_owned();
+
+            let mut calls: Vec<String> = used_functions(module, function)
+                .iter()
+                .map(|used| used.get_name().to_string_lossy().into_owned())
+                .filter(|name| !name.starts_with("llvm."))
+                .collect();
+
+            calls.sort();
+            calls.dedup();
+
+            for call in calls.iter() {
+                let is_declaration = module
+                    .get_function(call)
+                    .map_or(false, |used| used.count_basic_blocks() == 0);
+
+                if is_declaration {
+                    host_functions.insert(call.clone());
+                }
+            }
+
+            let (kind, origin) = match origins.get(symbol.as_str()) {
+                Some(SymbolOrigin::Proc { definition, .. }) => {
+                    (NodeKind::Proc, Some(definition.clone()))
+                }
+                Some(SymbolOrigin::Helper {
+                    helper,
+                    kind,
+                    layout,
+                }) => {
+                    let kind = match kind {
+                        HelperKind::Refcounting => NodeKind::RefcountingHelper,
+                        HelperKind::Equality => NodeKind::EqualityHelper,
+                        HelperKind::Copying => NodeKind::CopyingHelper,
+                    };
+
+                    (kind, Some(format!("{helper} for {layout}")))
+                }
+                None if symbol.starts_with("roc__") => (NodeKind::EntryPoint, None),
+                None if builtins.contains(&symbol) => (NodeKind::Builtin, None),
+                None => (NodeKind::Other, None),
+            };
+
+            functions.push(CallGraphNode {
+                symbol,
+                kind,
+                origin,
+                calls,
+            });
+        }
+
+        functions.extend(host_functions.into_iter().map(|symbol| CallGraphNode {
+            symbol,
+            kind: NodeKind::Host,
+            origin: None,
+            calls: Vec::new(),
+        }));
+
+        functions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        Self {
+            target: target.to_string(),
+            functions,
+        }
+    }
+
+    pub fn write(&self, path: &Path, format: CallGraphFormat) -> std::io::Result<()> {
+        match format {
+            CallGraphFormat::Json => {
+                let file = std::fs::File::create(path)?;
+
+                serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+
+                Ok(())
+            }
+            CallGraphFormat::Dot => std::fs::write(path, self.to_dot()),
+        }
+    }
+
+    /// For Graphviz, e.g. `dot -Tsvg main.callgraph.dot`
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        writeln!(dot, "digraph \"{}\" {{", self.target).unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+
+        for node in &self.functions {
+            let label = match &node.origin {
+                Some(origin) => format!("{}\\n{}", escape(&node.symbol), escape(origin)),
+                None => escape(&node.symbol),
+            };
+
+            let style = match node.kind {
+                NodeKind::EntryPoint => "style=bold",
+                NodeKind::Proc => "color=black",
+                NodeKind::RefcountingHelper => "color=blue",
+                NodeKind::EqualityHelper => "color=darkgreen",
+                NodeKind::CopyingHelper => "color=purple",
+                NodeKind::Builtin => "color=gray",
+                NodeKind::Host => "style=dashed",
+                NodeKind::Other => "color=brown",
+            };
+
+            writeln!(
+                dot,
+                "    \"{}\" [label=\"{label}\", {style}];",
+                escape(&node.symbol)
+            )
+            .unwrap();
+        }
+
+        for node in &self.functions {
+            for call in &node.calls {
+                writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\";",
+                    escape(&node.symbol),
+                    escape(call)
+                )
+                .unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Written next to the app's .roc file, like the symbol map
+pub fn call_graph_path(roc_file_path: &Path, format: CallGraphFormat) -> PathBuf {
+    roc_file_path.with_extension(format.extension())
+}
@@ -2,6 +2,12 @@
 #![warn(clippy::dbg_macro)]
 // See github.com/roc-lang/roc/issues/800 for discussion of the large_enum_variant check.
 #![allow(clippy::large_enum_variant)]
+pub mod backend;
+pub mod call_graph;
 pub mod link;
+pub mod manifest;
 pub mod program;
+pub mod size_report;
+pub mod stack_depth;
+pub mod symbol_map;
 pub mod target;
@@ -24,6 +24,8 @@ pub enum LinkingStrategy {
     Surgical,
     /// Initialise the backend from a host object file, then add the app to it. No linker needed.
     Additive,
+    /// Like `Legacy`, but link against musl to produce a fully static executable. Linux only.
+    Static,
 }
 
 /// input_paths can include the host as well as the app. e.g. &["host.o", "roc_app.o"]
@@ -42,6 +44,40 @@ pub fn link(
     }
 }
 
+/// Link a fully static Linux executable, which does not depend on glibc (or any other shared
+/// library) at runtime.
+///
+/// zig provides musl and its crt files for every target it supports, so nothing needs to be
+/// installed on the build machine. The host may be a static archive (`.a`); its members are
+/// pulled in as needed.
+pub fn link_static(
+    target: Target,
+    output_path: PathBuf,
+    input_paths: &[&str],
+) -> io::Result<(Child, PathBuf)> {
+    let musl_target = match target.arch_os() {
+        (Architecture::X86_64, OperatingSystem::Linux) => "x86_64-linux-musl",
+        (Architecture::Aarch64, OperatingSystem::Linux) => "aarch64-linux-musl",
+        (Architecture::X86_32, OperatingSystem::Linux) => "i386-linux-musl",
+        (Architecture::Riscv64, OperatingSystem::Linux) => "riscv64-linux-musl",
+        _ => internal_error!("static linking is only supported for linux targets, not {target:?}"),
+    };
+
+    let mut zig_command = zig();
+
+    zig_command.args(["build-exe"]).args(input_paths).args([
+        "-target",
+        musl_target,
+        "-static",
+        "-lc",
+        &format!("-femit-bin={}", output_path.to_str().unwrap()),
+    ]);
+
+    debug_print_command(&zig_command);
+
+    Ok((zig_command.spawn()?, output_path))
+}
+
 // Attempts to find a file that is stored relative to the roc executable.
 // Since roc is built in target/debug/roc, we may need to drop that path to find the file.
 // This is used to avoid depending on the current working directory.
@@ -506,6 +542,16 @@ pub fn rebuild_host(
                 shared_lib_path,
                 builtins_host_tempfile.path(),
             ),
+            Architecture::Riscv64 => build_zig_host_native(
+                &env_path,
+                &env_home,
+                host_dest.to_str().unwrap(),
+                zig_host_src.to_str().unwrap(),
+                "riscv64-linux-gnu",
+                opt_level,
+                shared_lib_path,
+                builtins_host_tempfile.path(),
+            ),
             _ => internal_error!("Unsupported architecture {:?}", target.architecture()),
         };
 
@@ -954,6 +1000,7 @@ fn link_linux(
             }
         }
         Architecture::Aarch64 => build_path_or_panic(["/lib", "ld-linux-aarch64.so.1"]),
+        Architecture::Riscv64 => build_path_or_panic(["/lib", "ld-linux-riscv64-lp64d.so.1"]),
         _ => internal_error!(
             "TODO gracefully handle unsupported linux architecture: {:?}",
             target.architecture()
@@ -1269,6 +1316,75 @@ fn link_windows(
     }
 }
 
+/// Move the debug info of a linked executable into a separate file, where the debuggers of the
+/// target look for it: `<binary>.debug` (found through a `.gnu_debuglink` section) on Linux, and a
+/// `<binary>.dSYM` bundle on macOS. The executable keeps its symbol table, so stack traces and
+/// profiles still show function names. Returns the path of the debug info.
+pub fn split_debug_info(binary_path: &Path, target: Target) -> io::Result<PathBuf> {
+    match target.operating_system() {
+        OperatingSystem::Linux => {
+            let debug_path = binary_path.with_extension("debug");
+
+            run_debug_info_tool(
+                Command::new("objcopy")
+                    .arg("--only-keep-debug")
+                    .arg(binary_path)
+                    .arg(&debug_path),
+            )?;
+
+            let mut debuglink = OsString::from("--add-gnu-debuglink=");
+            debuglink.push(&debug_path);
+
+            run_debug_info_tool(
+                Command::new("objcopy")
+                    .arg("--strip-debug")
+                    .arg(debuglink)
+                    .arg(binary_path),
+            )?;
+
+            Ok(debug_path)
+        }
+        OperatingSystem::Mac => {
+            let dsym_path = binary_path.with_extension("dSYM");
+
+            run_debug_info_tool(
+                Command::new("dsymutil")
+                    .arg(binary_path)
+                    .arg("-o")
+                    .arg(&dsym_path),
+            )?;
+
+            // -S only removes the debug info, not the symbol table
+            run_debug_info_tool(Command::new("strip").arg("-S").arg(binary_path))?;
+
+            Ok(dsym_path)
+        }
+        OperatingSystem::Windows | OperatingSystem::Freestanding => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("splitting debug info is not supported for {target}"),
+        )),
+    }
+}
+
+fn run_debug_info_tool(command: &mut Command) -> io::Result<()> {
+    debug_print_command(command);
+
+    let output = command.output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "`{}` failed:\n{}",
+                stringify_command(command, false),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        ))
+    }
+}
+
 pub fn llvm_module_to_dylib(
     module: &inkwell::module::Module,
     target: Target,
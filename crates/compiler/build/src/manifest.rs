@@ -0,0 +1,464 @@
+//! A JSON description of everything an app exposes to its host, for tools outside the compiler
+//! (glue generators, FFI binding generators) that need to call into a compiled Roc app.
+//!
+//! Layouts are stored once in the `layouts` table, and referred to by their index in it, so
+//! recursive layouts can refer to themselves. Struct fields and tag payloads are listed in the
+//! order they have in memory, which is not the order they have in the source.
+use roc_collections::all::MutMap;
+use roc_load::{EntryPoint, MonomorphizedModule};
+use roc_mono::ir::ProcLayout;
+use roc_mono::layout::{
+    round_up_to_alignment, Builtin, InLayout, LayoutInterner, LayoutRepr, RawFunctionLayout,
+    STLayoutInterner, UnionLayout,
+};
+use roc_target::Target;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Serialize, Debug)]
+pub struct EntryManifest {
+    pub target: String,
+    pub entry_points: Vec<EntryPointInfo>,
+    pub lambda_sets: Vec<LambdaSetInfo>,
+    pub layouts: Vec<LayoutInfo>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EntryPointInfo {
+    pub name: String,
+    /// Takes its arguments and returns its result following the C calling convention of the
+    /// target
+    pub symbol: String,
+    /// Takes a pointer to write the result to, followed by the arguments
+    pub generic_symbol: String,
+    /// Returns the size of the result
+    pub size_symbol: String,
+    pub arguments: Vec<ValueInfo>,
+    pub result: ValueInfo,
+}
+
+/// A function value that the host receives from the app, and can call through `caller_symbol`
+#[derive(Serialize, Debug)]
+pub struct LambdaSetInfo {
+    pub name: String,
+    pub caller_symbol: Option<String>,
+    /// Returns the size of the captured environment
+    pub size_symbol: Option<String>,
+    pub result_size_symbol: String,
+    pub arguments: Vec<ValueInfo>,
+    pub closure: Option<ValueInfo>,
+    pub result: ValueInfo,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ValueInfo {
+    /// Index into `layouts`
+    pub layout: usize,
+    pub ownership: Ownership,
+    /// `roc__retain_<name>`, `roc__release_<name>` and `roc__deepcopy_<name>`, when the build
+    /// generates them (`--memory-api` with the LLVM backend)
+    pub memory_api: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Ownership {
+    /// The host hands the value over to Roc, and must not release it afterwards
+    Consumed,
+    /// Roc hands the value over to the host, which must release it when it is done with it
+    Returned,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LayoutInfo {
+    pub size: u32,
+    pub alignment: u32,
+    /// Whether the value (or anything it contains) has a refcount
+    pub refcounted: bool,
+    #[serde(flatten)]
+    pub shape: ShapeInfo,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ShapeInfo {
+    Bool,
+    Int {
+        signed: bool,
+        bits: u32,
+    },
+    Float {
+        bits: u32,
+    },
+    Dec,
+    Str,
+    List {
+        element: usize,
+    },
+    Struct {
+        fields: Vec<FieldInfo>,
+    },
+    /// A pointer to a value of layout `pointee`, e.g. a `Box`
+    Pointer {
+        pointee: usize,
+    },
+    TagUnion {
+        /// `non_recursive` unions store their payload inline; all others are a pointer to a heap
+        /// allocation that holds the payload
+        representation: &'static str,
+        discriminant: DiscriminantInfo,
+        tags: Vec<TagInfo>,
+    },
+    /// A pointer to a value of the recursive union `union`
+    RecursivePointer {
+        union: usize,
+    },
+    /// The captured environment of a function; its memory is laid out like `representation`
+    LambdaSet {
+        representation: usize,
+    },
+    FunctionPointer,
+    Erased,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FieldInfo {
+    pub offset: u32,
+    pub layout: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TagInfo {
+    /// Tags are numbered in alphabetical order of their names
+    pub id: u16,
+    /// Whether this tag is represented by a null pointer
+    pub null: bool,
+    pub fields: Vec<FieldInfo>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "storage", rename_all = "snake_case")]
+pub enum DiscriminantInfo {
+    /// There is only one tag with a payload, so the tag is implied (or the pointer is null)
+    None,
+    /// Stored in the payload, `offset` bytes from its start
+    Data { offset: u32, size: u32 },
+    /// Stored in the low bits of the pointer to the payload
+    PointerBits { mask: usize },
+}
+
+impl EntryManifest {
+    pub fn new(loaded: &MonomorphizedModule<'_>, target: Target, memory_api: bool) -> Self {
+        let mut layouts = LayoutTable {
+            interner: &loaded.layout_interner,
+            target,
+            indices: MutMap::default(),
+            layouts: Vec::new(),
+        };
+
+        let api = |name: String| memory_api.then_some(name);
+
+        let mut entry_points = Vec::new();
+
+        if let EntryPoint::Executable {
+            exposed_to_host, ..
+        } = &loaded.entry_point
+        {
+            for (name, _, proc_layout) in exposed_to_host.iter() {
+                let exposed = format!("{name}_1_exposed");
+
+                let ProcLayout {
+                    arguments, result, ..
+                } = proc_layout;
+
+                let arguments = arguments
+                    .iter()
+                    .enumerate()
+                    .map(|(i, argument)| {
+                        let name = format!("{exposed}_arg{i}");
+                        layouts.value(*argument, Ownership::Consumed, api(name))
+                    })
+                    .collect();
+                let result_name = format!("{exposed}_result");
+                let result = layouts.value(*result, Ownership::Returned, api(result_name));
+
+                entry_points.push(EntryPointInfo {
+                    name: name.to_string(),
+                    symbol: format!("roc__{exposed}"),
+                    generic_symbol: format!("roc__{exposed}_generic"),
+                    size_symbol: format!("roc__{exposed}_size"),
+                    arguments,
+                    result,
+                });
+            }
+        }
+
+        let mut lambda_sets = Vec::new();
+
+        for (proc_name, _, hels) in loaded.host_exposed_lambda_sets.iter() {
+            let name = format!(
+                "{}_{}",
+                proc_name.name().as_unsuffixed_str(&loaded.interns),
+                hels.id.0
+            );
+
+            match hels.raw_function_layout {
+                RawFunctionLayout::Function(arguments, closure, result) => {
+                    let arguments = arguments
+                        .iter()
+                        .enumerate()
+                        .map(|(i, argument)| {
+                            let arg_name = format!("{name}_arg{i}");
+                            layouts.value(*argument, Ownership::Consumed, api(arg_name))
+                        })
+                        .collect();
+                    let closure_name = format!("{name}_closure");
+                    let closure = layouts.value(
+                        closure.runtime_representation(),
+                        Ownership::Consumed,
+                        api(closure_name),
+                    );
+                    let result_name = format!("{name}_result");
+                    let result = layouts.value(result, Ownership::Returned, api(result_name));
+
+                    lambda_sets.push(LambdaSetInfo {
+                        caller_symbol: Some(format!("roc__{name}_caller")),
+                        size_symbol: Some(format!("roc__{name}_size")),
+                        result_size_symbol: format!("roc__{name}_result_size"),
+                        name,
+                        arguments,
+                        closure: Some(closure),
+                        result,
+                    });
+                }
+                RawFunctionLayout::ZeroArgumentThunk(result) => {
+                    let result_name = format!("{name}_result");
+                    let result = layouts.value(result, Ownership::Returned, api(result_name));
+
+                    lambda_sets.push(LambdaSetInfo {
+                        caller_symbol: None,
+                        size_symbol: None,
+                        result_size_symbol: format!("roc__{name}_result_size"),
+                        name,
+                        arguments: Vec::new(),
+                        closure: None,
+                        result,
+                    });
+                }
+                RawFunctionLayout::ErasedFunction(..) => {
+                    // erased functions are not exposed to the host yet
+                }
+            }
+        }
+
+        EntryManifest {
+            target: target.to_string(),
+            entry_points,
+            lambda_sets,
+            layouts: layouts.finish(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+}
+
+/// The path of the manifest that belongs with the binary or object file at `output_path`
+pub fn manifest_path(output_path: &Path) -> std::path::PathBuf {
+    output_path.with_extension("entry-points.json")
+}
+
+struct LayoutTable<'r, 'a> {
+    interner: &'r STLayoutInterner<'a>,
+    target: Target,
+    indices: MutMap<InLayout<'a>, usize>,
+    /// `None` while the layout is being described, so that recursive layouts can refer to it
+    layouts: Vec<Option<LayoutInfo>>,
+}
+
+impl<'r, 'a> LayoutTable<'r, 'a> {
+    fn value(
+        &mut self,
+        layout: InLayout<'a>,
+        ownership: Ownership,
+        memory_api: Option<String>,
+    ) -> ValueInfo {
+        ValueInfo {
+            layout: self.index(layout),
+            ownership,
+            memory_api,
+        }
+    }
+
+    fn index(&mut self, layout: InLayout<'a>) -> usize {
+        if let Some(index) = self.indices.get(&layout) {
+            return *index;
+        }
+
+        let index = self.layouts.len();
+        self.layouts.push(None);
+        self.indices.insert(layout, index);
+
+        let (size, alignment) = self.interner.stack_size_and_alignment(layout);
+        let shape = self.shape(self.interner.get_repr(layout));
+
+        self.layouts[index] = Some(LayoutInfo {
+            size,
+            alignment,
+            refcounted: self.interner.contains_refcounted(layout),
+            shape,
+        });
+
+        index
+    }
+
+    fn shape(&mut self, repr: LayoutRepr<'a>) -> ShapeInfo {
+        match repr {
+            LayoutRepr::Builtin(builtin) => match builtin {
+                Builtin::Int(int_width) => ShapeInfo::Int {
+                    signed: int_width.is_signed(),
+                    bits: int_width.stack_size() * 8,
+                },
+                Builtin::Float(float_width) => ShapeInfo::Float {
+                    bits: float_width.stack_size() * 8,
+                },
+                Builtin::Bool => ShapeInfo::Bool,
+                Builtin::Decimal => ShapeInfo::Dec,
+                Builtin::Str => ShapeInfo::Str,
+                Builtin::List(element) => ShapeInfo::List {
+                    element: self.index(element),
+                },
+            },
+            LayoutRepr::Struct(field_layouts) => ShapeInfo::Struct {
+                fields: self.fields(field_layouts),
+            },
+            LayoutRepr::Ptr(pointee) => ShapeInfo::Pointer {
+                pointee: self.index(pointee),
+            },
+            LayoutRepr::Union(union_layout) => self.union(union_layout),
+            LayoutRepr::LambdaSet(lambda_set) => ShapeInfo::LambdaSet {
+                representation: self.index(lambda_set.runtime_representation()),
+            },
+            LayoutRepr::RecursivePointer(union) => ShapeInfo::RecursivePointer {
+                union: self.index(union),
+            },
+            LayoutRepr::FunctionPointer(_) => ShapeInfo::FunctionPointer,
+            LayoutRepr::Erased(_) => ShapeInfo::Erased,
+        }
+    }
+
+    fn union(&mut self, union_layout: UnionLayout<'a>) -> ShapeInfo {
+        let discriminant = if union_layout.stores_tag_id_in_pointer(self.target) {
+            let (_, mask) = UnionLayout::tag_id_pointer_bits_and_mask(self.target);
+
+            DiscriminantInfo::PointerBits { mask }
+        } else {
+            match union_layout.tag_id_offset(self.interner) {
+                Some(offset) => DiscriminantInfo::Data {
+                    offset,
+                    size: union_layout.discriminant().stack_size(),
+                },
+                None => DiscriminantInfo::None,
+            }
+        };
+
+        let (representation, tags) = match union_layout {
+            UnionLayout::NonRecursive(tags) => ("non_recursive", self.tags(tags, None)),
+            UnionLayout::Recursive(tags) => ("recursive", self.tags(tags, None)),
+            UnionLayout::NonNullableUnwrapped(fields) => {
+                ("non_nullable_unwrapped", self.tags(&[fields], None))
+            }
+            UnionLayout::NullableWrapped {
+                nullable_id,
+                other_tags,
+            } => ("nullable_wrapped", self.tags(other_tags, Some(nullable_id))),
+            UnionLayout::NullableUnwrapped {
+                nullable_id,
+                other_fields,
+            } => {
+                let nullable_id = nullable_id as u16;
+
+                (
+                    "nullable_unwrapped",
+                    self.tags(&[other_fields], Some(nullable_id)),
+                )
+            }
+        };
+
+        ShapeInfo::TagUnion {
+            representation,
+            discriminant,
+            tags,
+        }
+    }
+
+    /// Describe the tags of a union; the null tag (if any) has no entry in `tags`
+    fn tags(&mut self, tags: &[&'a [InLayout<'a>]], null_id: Option<u16>) -> Vec<TagInfo> {
+        let mut infos = Vec::with_capacity(tags.len() + 1);
+        let mut id = 0;
+
+        for fields in tags {
+            if Some(id) == null_id {
+                infos.push(TagInfo {
+                    id,
+                    null: true,
+                    fields: Vec::new(),
+                });
+
+                id += 1;
+            }
+
+            infos.push(TagInfo {
+                id,
+                null: false,
+                fields: self.fields(fields),
+            });
+
+            id += 1;
+        }
+
+        if Some(id) == null_id {
+            infos.push(TagInfo {
+                id,
+                null: true,
+                fields: Vec::new(),
+            });
+        }
+
+        infos
+    }
+
+    fn fields(&mut self, field_layouts: &[InLayout<'a>]) -> Vec<FieldInfo> {
+        let mut offset = 0;
+
+        field_layouts
+            .iter()
+            .map(|field_layout| {
+                let (size, alignment) = self.interner.stack_size_and_alignment(*field_layout);
+
+                offset = round_up_to_alignment(offset, alignment);
+
+                let field = FieldInfo {
+                    offset,
+                    layout: self.index(*field_layout),
+                };
+
+                offset += size;
+
+                field
+            })
+            .collect()
+    }
+
+    fn finish(self) -> Vec<LayoutInfo> {
+        self.layouts
+            .into_iter()
+            .map(|layout| layout.expect("every layout is described before we finish"))
+            .collect()
+    }
+}
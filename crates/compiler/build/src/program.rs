@@ -1,16 +1,34 @@
-use crate::link::{link, preprocess_host_wasm32, rebuild_host, LinkType, LinkingStrategy};
+use crate::backend::backend_for;
+use crate::call_graph::{call_graph_path, CallGraph, CallGraphFormat};
+use crate::link::{
+    link, link_static, preprocess_host_wasm32, rebuild_host, LinkType, LinkingStrategy,
+};
+use crate::manifest::{manifest_path, EntryManifest};
+use crate::size_report::SizeReport;
+use crate::stack_depth::entry_point_depths;
+use crate::symbol_map::{symbol_map_path, SymbolMapFile};
 use bumpalo::collections::CollectIn;
 use bumpalo::Bump;
 use inkwell::memory_buffer::MemoryBuffer;
 use roc_error_macros::internal_error;
 use roc_gen_dev::AssemblyBackendMode;
 use roc_gen_llvm::llvm::build::{module_from_builtins, LlvmBackendMode};
-use roc_gen_llvm::llvm::externs::add_default_roc_externs;
+use roc_gen_llvm::llvm::externs::{
+    add_default_roc_externs, add_static_heap_externs, libc_dependencies,
+};
+use roc_gen_llvm::llvm::helper_cache::HelperCache;
+use roc_gen_llvm::llvm::memory::MemoryStrategyKind;
+use roc_gen_llvm::llvm::refcounting::{remove_dead_helpers, RcOutlining};
+use roc_gen_llvm::llvm::sendable::SendableLayouts;
+use roc_gen_llvm::llvm::symbol_map::SymbolMap;
+use roc_gen_llvm::llvm::totality::crashing_helpers;
 use roc_load::{
     EntryPoint, ExecutionMode, ExpectMetadata, FunctionKind, LoadConfig, LoadMonomorphizedError,
-    LoadedModule, LoadingProblem, MonomorphizedModule, Threading,
+    LoadedModule, LoadingProblem, MonoCache, MonomorphizedModule, Threading,
 };
 use roc_mono::ir::{OptLevel, SingleEntryPoint};
+use roc_mono::layout::LayoutInterner;
+use roc_mono::stack_size::{find_large_stack_values, StackValuePosition};
 use roc_packaging::cache::RocCacheDir;
 use roc_reporting::{
     cli::{report_problems, Problems},
@@ -24,7 +42,6 @@ use std::{
     time::{Duration, Instant},
 };
 
-#[cfg(feature = "target-wasm32")]
 use roc_collections::all::MutSet;
 use roc_target::SurgicalHostArtifacts;
 
@@ -55,6 +72,83 @@ pub fn report_problems_typechecked(loaded: &mut LoadedModule) -> Problems {
     )
 }
 
+/// Warn about every value of the program that takes more than `limit` bytes on the stack, and
+/// return how many warnings were printed
+pub fn report_large_stack_values(loaded: &MonomorphizedModule, limit: u32) -> usize {
+    use roc_problem::Severity;
+    use roc_reporting::report::{Report, RocDocAllocator};
+    use ven_pretty::DocAllocator;
+
+    let interns = &loaded.interns;
+    let palette = DEFAULT_PALETTE;
+    let large_values = find_large_stack_values(&loaded.layout_interner, &loaded.procedures, limit);
+
+    for value in large_values.iter() {
+        let home = value.proc.module_id();
+        let filename = match loaded.sources.get(&home) {
+            Some((path, _)) => path.clone(),
+            None => PathBuf::new(),
+        };
+
+        let alloc = RocDocAllocator::new(&[], home, interns);
+
+        let proc_name = alloc.symbol_qualified(value.proc);
+        let position = match value.position {
+            StackValuePosition::Argument(index) => alloc.concat([
+                alloc.reflow("Argument "),
+                alloc.string((index + 1).to_string()),
+                alloc.reflow(" of "),
+                proc_name,
+            ]),
+            StackValuePosition::Return => {
+                alloc.concat([alloc.reflow("The return value of "), proc_name])
+            }
+            StackValuePosition::Local(symbol) => alloc.concat([
+                alloc.symbol_unqualified(symbol),
+                alloc.reflow(" in "),
+                proc_name,
+            ]),
+        };
+
+        let doc = alloc.stack([
+            alloc.concat([
+                position,
+                alloc.reflow(" takes "),
+                alloc.string(value.size.to_string()),
+                alloc.reflow(" bytes on the stack, more than the limit of "),
+                alloc.string(limit.to_string()),
+                alloc.reflow(" bytes. Its layout is:"),
+            ]),
+            alloc
+                .string(loaded.layout_interner.dbg(value.layout))
+                .indent(4),
+            alloc.reflow("Every procedure that passes this value around copies it, and a few of them can overflow the stack."),
+            alloc.concat([
+                alloc.tip(),
+                alloc.reflow("Consider putting it in a "),
+                alloc.type_str("Box"),
+                alloc.reflow(", or raise the limit with "),
+                alloc.keyword("--stack-value-limit"),
+                alloc.reflow("."),
+            ]),
+        ]);
+
+        let report = Report {
+            title: "LARGE VALUE ON THE STACK".to_string(),
+            filename,
+            doc,
+            severity: Severity::Warning,
+        };
+
+        let mut buf = String::new();
+        report.render_color_terminal(&mut buf, &alloc, &palette);
+
+        eprintln!("\n{buf}\n");
+    }
+
+    large_values.len()
+}
+
 pub enum CodeObject {
     MemoryBuffer(MemoryBuffer),
     Vector(Vec<u8>),
@@ -78,77 +172,87 @@ pub enum CodeGenBackend {
     Wasm,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Profile-guided optimization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pgo {
+    /// Instrument the program to count how often its branches and functions run. The host must
+    /// link LLVM's profile runtime, which writes the counts to a `.profraw` file on exit.
+    Generate,
+    /// Use a profile, merged from `.profraw` files with `llvm-profdata merge`, for branch weights
+    /// and hot/cold decisions
+    Use(PathBuf),
+}
+
+#[derive(Debug, Clone)]
 pub struct CodeGenOptions {
     pub backend: CodeGenBackend,
     pub opt_level: OptLevel,
     pub emit_debug_info: bool,
     pub emit_llvm_ir: bool,
     pub fuzz: bool,
+    /// Reuse refcounting helpers generated by earlier builds (LLVM backend only)
+    pub cache_helpers: bool,
+    /// Reuse the mono IR of earlier builds of the same, unchanged program
+    pub cache_mono: bool,
+    /// When to inline refcounting helpers at their call sites (LLVM backend only)
+    pub rc_outlining: RcOutlining,
+    /// How the generated program manages heap memory (LLVM backend only)
+    pub memory_strategy: MemoryStrategyKind,
+    /// Use atomic refcounts for values the host can share between threads (LLVM backend only)
+    pub atomic_refcounts: bool,
+    /// Only depend on the `roc_` functions of the host, and nothing from libc (LLVM backend only)
+    pub freestanding: bool,
+    /// Allocate from a static buffer of this many bytes instead of calling the host's
+    /// `roc_alloc` (LLVM backend only)
+    pub static_heap_bytes: Option<u32>,
+    /// Keep frame pointers, and the frames of refcounting helpers, for profilers (LLVM backend
+    /// only). The caller should also set `emit_debug_info`.
+    pub profiling: bool,
+    /// Hand the host a report with the call stack before `roc_panic`, through
+    /// `roc_crash_report` (LLVM backend only)
+    pub crash_dumps: bool,
+    /// Expose functions to retain, release and copy the values that cross the host boundary
+    /// (LLVM backend only)
+    pub memory_api: bool,
+    /// Run the tasks of List.mapParallel one after the other, in an order shuffled with this
+    /// seed, instead of on the host's thread pool (LLVM backend only)
+    pub shuffle_parallel_tasks: Option<u64>,
+    /// Write where each generated function comes from next to the app's .roc file (LLVM backend
+    /// only)
+    pub emit_symbol_map: bool,
+    /// Write the call graph of the generated functions next to the app's .roc file (LLVM backend
+    /// only)
+    pub emit_call_graph: Option<CallGraphFormat>,
+    /// Print how many bytes of machine code each Roc definition turns into (LLVM backend only)
+    pub size_report: bool,
+    /// Profile-guided optimization (LLVM backend only)
+    pub pgo: Option<Pgo>,
+    /// Write a JSON description of everything the app exposes to its host next to the output
+    pub emit_entry_manifest: bool,
+    /// Warn about values that take more than this many bytes on the stack
+    pub stack_value_limit: Option<u32>,
 }
 
-type GenFromMono<'a> = (CodeObject, CodeGenTiming, ExpectMetadata<'a>);
+pub type GenFromMono<'a> = (CodeObject, CodeGenTiming, ExpectMetadata<'a>);
 
-#[allow(clippy::too_many_arguments)]
 pub fn gen_from_mono_module<'a>(
     arena: &'a bumpalo::Bump,
     loaded: MonomorphizedModule<'a>,
     roc_file_path: &Path,
     target: Target,
-    code_gen_options: CodeGenOptions,
+    code_gen_options: &CodeGenOptions,
     built_host_opt: &BuiltHostOpt,
     wasm_dev_stack_bytes: Option<u32>,
 ) -> GenFromMono<'a> {
-    let path = roc_file_path;
-    let debug = code_gen_options.emit_debug_info;
-    let emit_llvm_ir = code_gen_options.emit_llvm_ir;
-    let fuzz = code_gen_options.fuzz;
-    let opt = code_gen_options.opt_level;
-
-    match code_gen_options.backend {
-        CodeGenBackend::Wasm => {
-            assert_ne!(
-                *built_host_opt,
-                BuiltHostOpt::None,
-                "Wasm backend needs a built host."
-            );
+    let backend = backend_for(code_gen_options, built_host_opt, wasm_dev_stack_bytes);
 
-            gen_from_mono_module_dev(
-                arena,
-                loaded,
-                target,
-                built_host_opt,
-                wasm_dev_stack_bytes,
-                AssemblyBackendMode::Binary, // dummy value, unused in practice
-            )
-        }
-        CodeGenBackend::Assembly(backend_mode) => gen_from_mono_module_dev(
-            arena,
-            loaded,
-            target,
-            built_host_opt,
-            wasm_dev_stack_bytes,
-            backend_mode,
-        ),
-        CodeGenBackend::Llvm(backend_mode) => gen_from_mono_module_llvm(
-            arena,
-            loaded,
-            path,
-            target,
-            opt,
-            backend_mode,
-            debug,
-            emit_llvm_ir,
-            fuzz,
-        ),
-    }
+    backend.gen_from_mono_module(arena, loaded, roc_file_path, target)
 }
 
 // TODO how should imported modules factor into this? What if those use builtins too?
 // TODO this should probably use more helper functions
-// TODO make this polymorphic in the llvm functions so it can be reused for another backend.
 #[allow(clippy::too_many_arguments)]
-fn gen_from_mono_module_llvm<'a>(
+pub(crate) fn gen_from_mono_module_llvm<'a>(
     arena: &'a bumpalo::Bump,
     loaded: MonomorphizedModule<'a>,
     roc_file_path: &Path,
@@ -158,6 +262,20 @@ fn gen_from_mono_module_llvm<'a>(
     emit_debug_info: bool,
     emit_llvm_ir: bool,
     fuzz: bool,
+    cache_helpers: bool,
+    rc_outlining: RcOutlining,
+    memory_strategy: MemoryStrategyKind,
+    atomic_refcounts: bool,
+    freestanding: bool,
+    static_heap_bytes: Option<u32>,
+    profiling: bool,
+    crash_dumps: bool,
+    memory_api: bool,
+    shuffle_parallel_tasks: Option<u64>,
+    emit_symbol_map: bool,
+    emit_call_graph: Option<CallGraphFormat>,
+    size_report: bool,
+    pgo: Option<Pgo>,
 ) -> GenFromMono<'a> {
     use crate::target::{self, convert_opt_level};
     use inkwell::attributes::{Attribute, AttributeLoc};
@@ -202,10 +320,52 @@ fn gen_from_mono_module_llvm<'a>(
         }
     }
 
+    // the call graph tells the builtins apart from the code we generate
+    let builtins: MutSet<String> = match emit_call_graph {
+        Some(_) => module
+            .get_functions()
+            .filter(|function| function.count_basic_blocks() > 0)
+            .map(|function| function.get_name().to_string_lossy().into_owned())
+            .collect(),
+        None => MutSet::default(),
+    };
+
     let builder = context.create_builder();
     let (dibuilder, compile_unit) = roc_gen_llvm::llvm::build::Env::new_debug_info(module);
     let (mpm, _fpm) = roc_gen_llvm::llvm::build::construct_optimization_passes(module, opt_level);
 
+    let helper_cache = if cache_helpers {
+        HelperCache::new(
+            roc_packaging::cache::roc_cache_helpers_dir(),
+            target,
+            opt_level,
+        )
+    } else {
+        None
+    };
+
+    let verify_total_helpers = roc_debug_flags::dbg_set!(roc_debug_flags::ROC_VERIFY_TOTAL_HELPERS);
+
+    // the size report, the call graph and the totality check find out where functions come from
+    // with the symbol map as well
+    let symbol_map =
+        (emit_symbol_map || emit_call_graph.is_some() || size_report || verify_total_helpers)
+            .then(SymbolMap::default);
+
+    let mut sendable_layouts = match &loaded.entry_point {
+        EntryPoint::Executable {
+            exposed_to_host, ..
+        } if atomic_refcounts => SendableLayouts::from_host_interface(
+            &loaded.layout_interner,
+            exposed_to_host.iter().map(|(_, _, layout)| *layout),
+            loaded.procedures.values(),
+        ),
+        _ => SendableLayouts::none(),
+    };
+
+    // List.mapParallel shares values with the host's threads even without --atomic-rc
+    sendable_layouts.insert_parallel_maps(&loaded.layout_interner, loaded.procedures.values());
+
     // Compile and add all the Procs before adding main
     let env = roc_gen_llvm::llvm::build::Env {
         arena,
@@ -224,12 +384,33 @@ fn gen_from_mono_module_llvm<'a>(
             .keys()
             .copied()
             .collect(),
+        helper_cache: helper_cache.as_ref(),
+        rc_outlining,
+        memory_strategy: memory_strategy.strategy(),
+        sendable_layouts,
+        profiling,
+        crash_dumps,
+        memory_api,
+        shuffle_parallel_tasks,
+        symbol_map: symbol_map.as_ref(),
+        basic_types: Default::default(),
     };
 
     // does not add any externs for this mode (we have a host) but cleans up some functions around
     // expects that would confuse the surgical linker
     add_default_roc_externs(&env);
 
+    if let Some(heap_bytes) = static_heap_bytes {
+        add_static_heap_externs(&env, heap_bytes);
+    }
+
+    let platform_intrinsics = match &loaded.entry_point {
+        EntryPoint::Executable { platform_path, .. } => {
+            target.find_platform_intrinsics(platform_path)
+        }
+        EntryPoint::Test => None,
+    };
+
     let entry_point = match loaded.entry_point {
         EntryPoint::Executable {
             exposed_to_host,
@@ -264,12 +445,31 @@ fn gen_from_mono_module_llvm<'a>(
     let generate_final_ir = all_code_gen_start.elapsed();
     let code_gen_object_start = Instant::now();
 
-    env.dibuilder.finalize();
+    if let Some(helper_cache) = env.helper_cache {
+        helper_cache.finish(&env);
+    }
+
+    if let Some(intrinsics_path) = platform_intrinsics {
+        link_platform_intrinsics(&context, module, &intrinsics_path);
+    }
 
     if !emit_debug_info {
         module.strip_debug_info();
     }
 
+    remove_dead_helpers(module);
+
+    if verify_total_helpers {
+        let crashing = crashing_helpers(module, symbol_map.as_ref().unwrap());
+
+        if !crashing.is_empty() {
+            internal_error!(
+                "Refcounting and equality helpers must not crash, but:\n\n{}",
+                Vec::from_iter(crashing.iter().map(|helper| helper.to_string())).join("\n")
+            );
+        }
+    }
+
     // Uncomment this to see the module's optimized LLVM instruction output:
     // env.module.print_to_stderr();
 
@@ -287,11 +487,60 @@ fn gen_from_mono_module_llvm<'a>(
         );
     }
 
+    let symbol_origins = match &symbol_map {
+        Some(symbol_map) => symbol_map.entries(module),
+        None => Vec::new(),
+    };
+
+    if emit_symbol_map {
+        let symbol_map_file = SymbolMapFile::new(target, symbol_origins.clone());
+        let symbol_map_path = symbol_map_path(roc_file_path);
+
+        if let Err(error) = symbol_map_file.write(&symbol_map_path) {
+            eprintln!(
+                "I could not write the symbol map to {}: {error}",
+                symbol_map_path.display()
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(format) = emit_call_graph {
+        let call_graph = CallGraph::new(target, module, &builtins, &symbol_origins);
+        let call_graph_path = call_graph_path(roc_file_path, format);
+
+        if let Err(error) = call_graph.write(&call_graph_path, format) {
+            eprintln!(
+                "I could not write the call graph to {}: {error}",
+                call_graph_path.display()
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if freestanding {
+        let dependencies = libc_dependencies(module);
+
+        if !dependencies.is_empty() {
+            eprintln!(
+                "This program cannot be built freestanding, because it depends on these functions from libc:\n\n    {}",
+                dependencies.join("\n    ")
+            );
+            std::process::exit(1);
+        }
+    }
+
     // Uncomment this to see the module's optimized LLVM instruction output:
     // env.module.print_to_stderr();
 
-    let gen_sanitizers = cfg!(feature = "sanitizers") && std::env::var("ROC_SANITIZERS").is_ok();
-    let memory_buffer = if fuzz || gen_sanitizers {
+    let sanitizers: Vec<String> = match std::env::var("ROC_SANITIZERS") {
+        Ok(names) if cfg!(feature = "sanitizers") => {
+            names.split(',').map(|x| x.trim().to_owned()).collect()
+        }
+        _ => Vec::new(),
+    };
+    let gen_sanitizers = !sanitizers.is_empty();
+    let memory_buffer = if fuzz || gen_sanitizers || pgo.is_some() {
         let dir = tempfile::tempdir().unwrap();
         let dir = dir.into_path();
 
@@ -300,6 +549,21 @@ fn gen_from_mono_module_llvm<'a>(
         let temp_app_processed_file_str = temp_app_processed_file.to_str().unwrap().to_owned();
         let temp_app_o_file = dir.join("app.o");
 
+        // Like clang, the tsan pass only instruments functions that ask for it. Mark all of them,
+        // including the zig builtins, so that the atomic refcount updates in there are seen too.
+        if sanitizers.iter().any(|x| x == "thread") {
+            let kind_id = Attribute::get_named_enum_kind_id("sanitize_thread");
+            let sanitize_thread = context.create_enum_attribute(kind_id, 0);
+
+            for function in module.get_functions() {
+                function.add_attribute(AttributeLoc::Function, sanitize_thread);
+            }
+
+            if !atomic_refcounts {
+                eprintln!("Note: without --atomic-rc, ThreadSanitizer reports refcount updates of values that the host shares between threads as data races.");
+            }
+        }
+
         // write the ll code to a file, so we can modify it
         module.print_to_file(&temp_app_ll_file).unwrap();
 
@@ -309,6 +573,19 @@ fn gen_from_mono_module_llvm<'a>(
         let mut passes = vec![];
         let mut extra_args = vec![];
         let mut unrecognized = vec![];
+        let profile_file_arg;
+        match &pgo {
+            Some(Pgo::Generate) => {
+                passes.extend_from_slice(&["pgo-instr-gen", "instrprof"]);
+            }
+            Some(Pgo::Use(profile_path)) => {
+                profile_file_arg = format!("-pgo-test-profile-file={}", profile_path.display());
+
+                passes.push("pgo-instr-use");
+                extra_args.push(profile_file_arg.as_str());
+            }
+            None => {}
+        }
         if fuzz {
             passes.push("sancov-module");
             extra_args.extend_from_slice(&[
@@ -318,18 +595,12 @@ fn gen_from_mono_module_llvm<'a>(
                 "-sanitizer-coverage-trace-compares",
             ]);
         }
-        if gen_sanitizers {
-            for sanitizer in std::env::var("ROC_SANITIZERS")
-                .unwrap()
-                .split(',')
-                .map(|x| x.trim())
-            {
-                match sanitizer {
-                    "address" => passes.push("asan-module"),
-                    "memory" => passes.push("msan-module"),
-                    "thread" => passes.push("tsan-module"),
-                    x => unrecognized.push(x.to_owned()),
-                }
+        for sanitizer in &sanitizers {
+            match sanitizer.as_str() {
+                "address" => passes.push("asan-module"),
+                "memory" => passes.push("msan-module"),
+                "thread" => passes.extend_from_slice(&["tsan-module", "function(tsan)"]),
+                x => unrecognized.push(x.to_owned()),
             }
         }
         if !unrecognized.is_empty() {
@@ -398,7 +669,10 @@ fn gen_from_mono_module_llvm<'a>(
 
         // Emit the .o file
         match target.architecture() {
-            Architecture::X86_64 | Architecture::X86_32 | Architecture::Aarch64 => {
+            Architecture::X86_64
+            | Architecture::X86_32
+            | Architecture::Aarch64
+            | Architecture::Riscv64 => {
                 let reloc = RelocMode::PIC;
                 let target_machine =
                     target::target_machine(target, convert_opt_level(opt_level), reloc).unwrap();
@@ -422,6 +696,36 @@ fn gen_from_mono_module_llvm<'a>(
     let code_gen_object = code_gen_object_start.elapsed();
     let total = all_code_gen_start.elapsed();
 
+    if size_report {
+        let target_machine =
+            target::target_machine(target, convert_opt_level(opt_level), RelocMode::PIC).unwrap();
+
+        // for wasm, we hand zig bitcode rather than an object, so we make one just for the report
+        let wasm_object;
+        let object = match target.architecture() {
+            Architecture::Wasm32 => {
+                wasm_object = target_machine
+                    .write_to_memory_buffer(env.module, FileType::Object)
+                    .expect("Writing .o file failed");
+
+                wasm_object.as_slice()
+            }
+            _ => memory_buffer.as_slice(),
+        };
+
+        let stack_depths = entry_point_depths(
+            env.module,
+            &target_machine.get_target_data(),
+            target,
+            &symbol_origins,
+        );
+
+        match SizeReport::new(target, object, &symbol_origins, stack_depths) {
+            Ok(report) => println!("{report}"),
+            Err(error) => eprintln!("I could not make a size report: {error}"),
+        }
+    }
+
     (
         CodeObject::MemoryBuffer(memory_buffer),
         CodeGenTiming {
@@ -437,64 +741,8 @@ fn gen_from_mono_module_llvm<'a>(
     )
 }
 
-fn gen_from_mono_module_dev<'a>(
-    arena: &'a bumpalo::Bump,
-    loaded: MonomorphizedModule<'a>,
-    target: Target,
-    built_host_opt: &BuiltHostOpt,
-    wasm_dev_stack_bytes: Option<u32>,
-    #[allow(unused_variables)] backend_mode: AssemblyBackendMode,
-) -> GenFromMono<'a> {
-    match (built_host_opt, target.architecture()) {
-        (BuiltHostOpt::Additive(host_path), Architecture::Wasm32) => {
-            #[cfg(feature = "target-wasm32")]
-            {
-                gen_from_mono_module_dev_wasm32(arena, loaded, host_path, wasm_dev_stack_bytes)
-            }
-
-            #[cfg(not(feature = "target-wasm32"))]
-            {
-                internal_error!("Compiler was not built with feature 'target-wasm32'.");
-            }
-        }
-        (BuiltHostOpt::None, Architecture::Wasm32) => {
-            internal_error!("Cannot compile wasm32 without a host on the dev compiler backend.")
-        }
-        (BuiltHostOpt::Legacy(host_path), Architecture::Wasm32) => internal_error!(
-            "Unsupported host files found for use with wasm32 dev compiler backend:\n    {}",
-            host_path.display()
-        ),
-        (
-            BuiltHostOpt::Surgical(SurgicalHostArtifacts {
-                preprocessed_host, ..
-            }),
-            Architecture::Wasm32,
-        ) => internal_error!(
-            "Unsupported host files found for use with wasm32 dev compiler backend:\n    {}",
-            preprocessed_host.display()
-        ),
-        (_, Architecture::X86_64 | Architecture::Aarch64) => {
-            #[cfg(not(feature = "target-wasm32"))]
-            {
-                gen_from_mono_module_dev_assembly(arena, loaded, target, backend_mode)
-            }
-
-            #[cfg(feature = "target-wasm32")]
-            {
-                internal_error!("Compiler was not built with feature 'target-wasm32'.")
-            }
-        }
-        (_, Architecture::Aarch32) => {
-            internal_error!("Dev compiler backend does not support 32 bit ARM architectures")
-        }
-        (_, Architecture::X86_32) => {
-            internal_error!("Dev compiler backend does not support 32 bit x86 architectures")
-        }
-    }
-}
-
 #[cfg(feature = "target-wasm32")]
-fn gen_from_mono_module_dev_wasm32<'a>(
+pub(crate) fn gen_from_mono_module_dev_wasm32<'a>(
     arena: &'a bumpalo::Bump,
     loaded: MonomorphizedModule<'a>,
     built_host_path: &Path,
@@ -568,7 +816,7 @@ fn gen_from_mono_module_dev_wasm32<'a>(
 }
 
 #[allow(dead_code)]
-fn gen_from_mono_module_dev_assembly<'a>(
+pub(crate) fn gen_from_mono_module_dev_assembly<'a>(
     arena: &'a bumpalo::Bump,
     loaded: MonomorphizedModule<'a>,
     target: Target,
@@ -623,6 +871,58 @@ fn gen_from_mono_module_dev_assembly<'a>(
     )
 }
 
+/// Link the functions in a platform's `intrinsics_<target>.bc` into the app, so that LLVM can
+/// inline them where Roc code calls them. This is meant for small, latency-critical effects like
+/// reading a clock, which would otherwise be a call into the host.
+///
+/// The host usually defines the same functions, so the copies in the app are made internal: the
+/// app calls its own copy, and the host's copy does not clash with it when linking.
+fn link_platform_intrinsics<'ctx>(
+    context: &'ctx inkwell::context::Context,
+    module: &inkwell::module::Module<'ctx>,
+    intrinsics_path: &Path,
+) {
+    use inkwell::attributes::{Attribute, AttributeLoc};
+    use inkwell::module::{Linkage, Module};
+
+    let intrinsics = match Module::parse_bitcode_from_path(intrinsics_path, context) {
+        Ok(intrinsics) => intrinsics,
+        Err(error) => {
+            eprintln!(
+                "I could not read the platform intrinsics in {}:\n\n    {}",
+                intrinsics_path.display(),
+                error
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let defined: Vec<String> = intrinsics
+        .get_functions()
+        .filter(|function| function.count_basic_blocks() > 0)
+        .map(|function| function.get_name().to_string_lossy().into_owned())
+        .collect();
+
+    if let Err(error) = module.link_in_module(intrinsics) {
+        eprintln!(
+            "I could not link the platform intrinsics in {} into the app:\n\n    {}",
+            intrinsics_path.display(),
+            error
+        );
+        std::process::exit(1);
+    }
+
+    let kind_id = Attribute::get_named_enum_kind_id("alwaysinline");
+    let always_inline = context.create_enum_attribute(kind_id, 0);
+
+    for name in defined {
+        if let Some(function) = module.get_function(&name) {
+            function.set_linkage(Linkage::Internal);
+            function.add_attribute(AttributeLoc::Function, always_inline);
+        }
+    }
+}
+
 fn report_timing(buf: &mut String, label: &str, duration: Duration) {
     use std::fmt::Write;
 
@@ -753,13 +1053,29 @@ pub fn build_file<'a>(
 ) -> Result<BuiltFile<'a>, BuildFileError<'a>> {
     let compilation_start = Instant::now();
 
-    let loaded = roc_load::load_and_monomorphize(
-        arena,
-        app_module_path.clone(),
-        None,
-        roc_cache_dir,
-        load_config,
-    )
+    let mono_cache = if code_gen_options.cache_mono {
+        MonoCache::new(roc_packaging::cache::roc_cache_mono_dir())
+    } else {
+        None
+    };
+
+    let loaded = match &mono_cache {
+        Some(mono_cache) => roc_load::load_and_monomorphize_cached(
+            arena,
+            app_module_path.clone(),
+            None,
+            roc_cache_dir,
+            load_config,
+            mono_cache,
+        ),
+        None => roc_load::load_and_monomorphize(
+            arena,
+            app_module_path.clone(),
+            None,
+            roc_cache_dir,
+            load_config,
+        ),
+    }
     .map_err(|e| BuildFileError::from_mono_error(e, compilation_start))?;
 
     build_loaded_file(
@@ -791,7 +1107,7 @@ pub enum BuiltHostOpt {
 }
 
 fn build_and_preprocess_host(
-    code_gen_options: CodeGenOptions,
+    code_gen_options: &CodeGenOptions,
     dll_stub_symbols: Vec<String>,
     emit_timings: bool,
     linking_strategy: LinkingStrategy,
@@ -821,7 +1137,7 @@ fn build_and_preprocess_host(
                 metadata_path,
             )
         }
-        LinkingStrategy::Legacy => spawn_legacy_host_build_thread(
+        LinkingStrategy::Legacy | LinkingStrategy::Static => spawn_legacy_host_build_thread(
             code_gen_options.opt_level,
             target,
             platform_main_roc.to_owned(),
@@ -880,7 +1196,7 @@ fn build_loaded_file<'a>(
             match prebuilt_host {
                 BuiltHostOpt::None => {
                     build_and_preprocess_host(
-                        code_gen_options,
+                        &code_gen_options,
                         dll_stub_symbols,
                         emit_timings,
                         linking_strategy,
@@ -927,15 +1243,42 @@ fn build_loaded_file<'a>(
     // This only needs to be mutable for report_problems. This can't be done
     // inside a nested scope without causing a borrow error!
     let mut loaded = loaded;
-    let problems = report_problems_monomorphized(&mut loaded);
+    let mut problems = report_problems_monomorphized(&mut loaded);
     let loaded = loaded;
 
+    if let (Some(limit), 0) = (code_gen_options.stack_value_limit, problems.errors) {
+        problems.warnings += report_large_stack_values(&loaded, limit);
+    }
+
+    // the dev backends link the builtins as they are, and cannot make their refcounts atomic
+    if code_gen_options.atomic_refcounts
+        && !matches!(code_gen_options.backend, CodeGenBackend::Llvm(_))
+    {
+        eprintln!("Note: --atomic-rc only works with the LLVM backend, so refcounts stay non-atomic in this build.");
+    }
+
+    if code_gen_options.emit_entry_manifest {
+        // only the LLVM backend generates the retain/release/deepcopy functions
+        let memory_api = code_gen_options.memory_api
+            && matches!(code_gen_options.backend, CodeGenBackend::Llvm(_));
+        let manifest = EntryManifest::new(&loaded, target, memory_api);
+        let manifest_path = manifest_path(&output_exe_path);
+
+        if let Err(error) = manifest.write(&manifest_path) {
+            eprintln!(
+                "I could not write the entry point manifest to {}: {error}",
+                manifest_path.display()
+            );
+            std::process::exit(1);
+        }
+    }
+
     let (roc_app_bytes, code_gen_timing, expect_metadata) = gen_from_mono_module(
         arena,
         loaded,
         &app_module_path,
         target,
-        code_gen_options,
+        &code_gen_options,
         &built_host_opt,
         wasm_dev_stack_bytes,
     );
@@ -987,7 +1330,7 @@ fn build_loaded_file<'a>(
             // Just copy the object file to the output folder.
             std::fs::write(&output_exe_path, &*roc_app_bytes).unwrap();
         }
-        (LinkingStrategy::Legacy, _) => {
+        (LinkingStrategy::Legacy | LinkingStrategy::Static, _) => {
             let extension = if target == Target::Wasm32 {
                 // Legacy linker is only by used llvm wasm backend, not dev.
                 // llvm wasm backend directly emits a bitcode file when targeting wasi, not a `.o` or `.wasm` file.
@@ -1043,8 +1386,13 @@ fn build_loaded_file<'a>(
                 inputs.push(builtins_host_tempfile.path().to_str().unwrap());
             }
 
-            let (mut child, _) = link(target, output_exe_path.clone(), &inputs, link_type)
-                .map_err(|_| todo!("linker failed to spawn."))?;
+            let spawned = if let LinkingStrategy::Static = linking_strategy {
+                link_static(target, output_exe_path.clone(), &inputs)
+            } else {
+                link(target, output_exe_path.clone(), &inputs, link_type)
+            };
+
+            let (mut child, _) = spawned.map_err(|_| todo!("linker failed to spawn."))?;
 
             let exit_status = child
                 .wait()
@@ -1104,7 +1452,7 @@ fn determine_built_host_path(
         }
     } else {
         match linking_strategy {
-            LinkingStrategy::Legacy => {
+            LinkingStrategy::Legacy | LinkingStrategy::Static => {
                 let legacy_host_path_res = target.find_legacy_host(platform_main_roc_path);
 
                 match legacy_host_path_res {
@@ -1375,11 +1723,10 @@ pub fn check_file<'a>(
     let compilation_start = Instant::now();
 
     // only used for generating errors. We don't do code generation, so hardcoding should be fine
-    // we need monomorphization for when exhaustiveness checking
     let target = Target::LinuxX64;
 
-    // Step 1: compile the app and generate the .o file
-
+    // ExecutionMode::Check stops after solving types (exhaustiveness is checked while solving),
+    // so no layouts or backend state are ever built
     let load_config = LoadConfig {
         target,
         function_kind: FunctionKind::from_env(),
@@ -1456,6 +1803,23 @@ pub fn build_str_test<'a>(
         emit_debug_info: false,
         emit_llvm_ir: false,
         fuzz: false,
+        cache_helpers: false,
+        cache_mono: false,
+        rc_outlining: RcOutlining::default(),
+        memory_strategy: MemoryStrategyKind::default(),
+        atomic_refcounts: false,
+        freestanding: false,
+        static_heap_bytes: None,
+        profiling: false,
+        crash_dumps: false,
+        memory_api: false,
+        shuffle_parallel_tasks: None,
+        emit_symbol_map: false,
+        emit_call_graph: None,
+        size_report: false,
+        pgo: None,
+        emit_entry_manifest: false,
+        stack_value_limit: None,
     };
 
     let emit_timings = false;
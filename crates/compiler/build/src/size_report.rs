@@ -0,0 +1,267 @@
+//! A report of how many bytes of machine code each Roc definition and each kind of helper turns
+//! into, to find out what makes a program big.
+//!
+//! We measure the app's object file before it is linked, so the host is not part of the report,
+//! and neither are the dead functions the linker will drop. The size of a function is the distance
+//! to the next function in the same section, so alignment padding is included.
+//!
+//! The report ends with how much stack each entry point can use, see [crate::stack_depth].
+use std::fmt;
+
+use bumpalo::Bump;
+use object::{BinaryFormat, Object, ObjectSection, ObjectSymbol, SectionKind, SymbolKind};
+use roc_collections::all::MutMap;
+use roc_gen_llvm::llvm::symbol_map::{HelperKind, SymbolOrigin};
+use roc_target::{Architecture, Target};
+use roc_wasm_module::linking::{WasmObjectSymbol, WASM_SYM_UNDEFINED};
+use roc_wasm_module::{SymInfo, WasmModule};
+
+use crate::stack_depth::StackDepth;
+
+/// We only list this many of the largest definitions
+const MAX_DEFINITIONS: usize = 25;
+
+#[derive(Debug)]
+pub struct SizeReport {
+    total: u64,
+    kinds: Vec<(&'static str, u64)>,
+    modules: Vec<(String, u64)>,
+    definitions: Vec<DefinitionSize>,
+    stack_depths: Vec<StackDepth>,
+}
+
+#[derive(Debug)]
+struct DefinitionSize {
+    definition: String,
+    bytes: u64,
+    specializations: usize,
+}
+
+impl SizeReport {
+    /// `origins` are the entries of the symbol map that was built along with `object`
+    pub fn new(
+        target: Target,
+        object: &[u8],
+        origins: &[(String, SymbolOrigin)],
+        stack_depths: Vec<StackDepth>,
+    ) -> Result<Self, String> {
+        let origins: MutMap<&str, &SymbolOrigin> = origins
+            .iter()
+            .map(|(fn_name, origin)| (fn_name.as_str(), origin))
+            .collect();
+
+        let mut total = 0;
+        let mut kinds: MutMap<&'static str, u64> = MutMap::default();
+        let mut modules: MutMap<String, u64> = MutMap::default();
+        let mut definitions: MutMap<String, DefinitionSize> = MutMap::default();
+
+        for (fn_name, bytes) in function_sizes(target, object)? {
+            total += bytes;
+
+            let kind = match origins.get(fn_name.as_str()) {
+                Some(SymbolOrigin::Proc { definition, .. }) => {
+                    // module names can have dots in them, but definition names can't
+                    let module = match definition.rsplit_once('.') {
+                        Some((module, _)) => module,
+                        None => definition,
+                    };
+
+                    *modules.entry(module.to_string()).or_default() += bytes;
+
+                    let size =
+                        definitions
+                            .entry(definition.clone())
+                            .or_insert_with(|| DefinitionSize {
+                                definition: definition.clone(),
+                                bytes: 0,
+                                specializations: 0,
+                            });
+                    size.bytes += bytes;
+                    size.specializations += 1;
+
+                    "Roc definitions"
+                }
+                Some(SymbolOrigin::Helper { kind, .. }) => match kind {
+                    HelperKind::Refcounting => "refcounting helpers",
+                    HelperKind::Equality => "equality helpers",
+                    HelperKind::Copying => "copying helpers",
+                },
+                // the wrappers that the host calls, like `roc__mainForHost_1_exposed`
+                None if fn_name.starts_with("roc__") => "host entry points",
+                None => "zig builtins and other code",
+            };
+
+            *kinds.entry(kind).or_default() += bytes;
+        }
+
+        let mut definitions = Vec::from_iter(definitions.into_values());
+        definitions.sort_by(|a, b| (b.bytes, &a.definition).cmp(&(a.bytes, &b.definition)));
+        definitions.truncate(MAX_DEFINITIONS);
+
+        Ok(Self {
+            total,
+            kinds: largest_first(kinds),
+            modules: largest_first(modules),
+            definitions,
+            stack_depths,
+        })
+    }
+}
+
+fn largest_first<K: Ord>(sizes: MutMap<K, u64>) -> Vec<(K, u64)> {
+    let mut sizes = Vec::from_iter(sizes);
+
+    sizes.sort_by(|(a_key, a_bytes), (b_key, b_bytes)| (b_bytes, a_key).cmp(&(a_bytes, b_key)));
+
+    sizes
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.total.to_string().len();
+
+        writeln!(f, "Machine code in the app: {} bytes", self.total)?;
+
+        writeln!(f, "\nBy kind:")?;
+        for (kind, bytes) in &self.kinds {
+            writeln!(f, "    {bytes:>width$}  {kind}")?;
+        }
+
+        writeln!(f, "\nRoc definitions by module:")?;
+        for (module, bytes) in &self.modules {
+            writeln!(f, "    {bytes:>width$}  {module}")?;
+        }
+
+        writeln!(
+            f,
+            "\nLargest Roc definitions, all specializations combined:"
+        )?;
+        for size in &self.definitions {
+            let plural = if size.specializations == 1 { "" } else { "s" };
+
+            writeln!(
+                f,
+                "    {:>width$}  {} ({} specialization{plural})",
+                size.bytes, size.definition, size.specializations
+            )?;
+        }
+
+        let width = self
+            .stack_depths
+            .iter()
+            .map(|depth| depth.bytes.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        writeln!(f, "\nStack used by each entry point, estimated:")?;
+        for depth in &self.stack_depths {
+            let name = &depth.entry_point;
+
+            match &depth.unbounded {
+                Some(reason) => writeln!(f, "    {:>width$}  {name}, no bound: {reason}", "?")?,
+                None if depth.bytes_per_level > 0 => writeln!(
+                    f,
+                    "    {:>width$}  {name}, plus {} bytes per level of the recursive values it frees",
+                    depth.bytes, depth.bytes_per_level
+                )?,
+                None => writeln!(f, "    {:>width$}  {name}", depth.bytes)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The name and size in bytes of every function that `object` defines
+fn function_sizes(target: Target, object: &[u8]) -> Result<Vec<(String, u64)>, String> {
+    match target.architecture() {
+        Architecture::Wasm32 => wasm_function_sizes(object),
+        _ => native_function_sizes(object),
+    }
+}
+
+fn native_function_sizes(object: &[u8]) -> Result<Vec<(String, u64)>, String> {
+    let file = object::File::parse(object).map_err(|error| error.to_string())?;
+
+    // Mach-O puts an underscore in front of every C symbol
+    let prefix = match file.format() {
+        BinaryFormat::MachO => "_",
+        _ => "",
+    };
+
+    let mut functions_by_section = MutMap::default();
+
+    for symbol in file.symbols() {
+        if symbol.kind() != SymbolKind::Text || !symbol.is_definition() {
+            continue;
+        }
+
+        if let Some(section_index) = symbol.section_index() {
+            functions_by_section
+                .entry(section_index)
+                .or_insert_with(Vec::new)
+                .push(symbol);
+        }
+    }
+
+    let mut sizes = Vec::new();
+
+    for section in file.sections() {
+        if section.kind() != SectionKind::Text {
+            continue;
+        }
+
+        let Some(mut functions) = functions_by_section.remove(&section.index()) else {
+            continue;
+        };
+
+        functions.sort_by_key(|symbol| symbol.address());
+
+        let section_end = section.address() + section.size();
+        let ends = functions
+            .iter()
+            .skip(1)
+            .map(|symbol| symbol.address())
+            .chain(std::iter::once(section_end));
+
+        for (function, end) in functions.iter().zip(ends) {
+            let name = function.name().map_err(|error| error.to_string())?;
+            let name = name.strip_prefix(prefix).unwrap_or(name);
+
+            sizes.push((name.to_string(), end - function.address()));
+        }
+    }
+
+    Ok(sizes)
+}
+
+fn wasm_function_sizes(object: &[u8]) -> Result<Vec<(String, u64)>, String> {
+    let arena = Bump::new();
+    let module = WasmModule::preload(&arena, object, false).map_err(|error| error.message)?;
+
+    // function indices count the imported functions first
+    let import_count = module.import.function_count() as u32;
+    let offsets = &module.code.function_offsets;
+    let code_end = module.code.bytes.len() as u32;
+
+    let mut sizes = Vec::new();
+
+    for symbol in module.linking.symbol_table.iter() {
+        let SymInfo::Function(WasmObjectSymbol::ExplicitlyNamed { flags, index, name }) = symbol
+        else {
+            continue;
+        };
+
+        if flags & WASM_SYM_UNDEFINED != 0 {
+            continue;
+        }
+
+        let code_index = (index - import_count) as usize;
+        let start = offsets[code_index];
+        let end = offsets.get(code_index + 1).copied().unwrap_or(code_end);
+
+        sizes.push((name.to_string(), (end - start) as u64));
+    }
+
+    Ok(sizes)
+}
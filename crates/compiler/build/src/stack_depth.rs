@@ -0,0 +1,327 @@
+//! An estimate of how much stack each function that the host calls can use, so platforms for
+//! small devices can size their stacks.
+//!
+//! We look at the final LLVM module. The frame of a function is its allocas, plus room for the
+//! return address and the callee-saved registers. Spilled registers are not counted, so this is an
+//! estimate, not a bound. The depth of a function is its frame plus the deepest of the functions
+//! it calls, or passes to a builtin that calls them. The host's own functions, like `roc_alloc`,
+//! are not part of it.
+//!
+//! Freeing a recursive value, like a tree, goes through its decrement helper once per level of
+//! nesting, so those depths have a part per level. Other recursion has no bound that we know of.
+use inkwell::module::Module;
+use inkwell::targets::TargetData;
+use inkwell::values::{FunctionValue, InstructionOpcode, InstructionValue};
+use roc_collections::all::MutMap;
+use roc_gen_llvm::llvm::symbol_map::{HelperKind, SymbolOrigin};
+use roc_gen_llvm::llvm::totality::used_functions;
+use roc_target::{Architecture, Target};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackDepth {
+    /// e.g. `roc__mainForHost_1_exposed`
+    pub entry_point: String,
+    pub bytes: u64,
+    /// For every level of nesting of the recursive values that it frees
+    pub bytes_per_level: u64,
+    /// Why there is no bound, if there is none
+    pub unbounded: Option<String>,
+}
+
+/// The stack depth of every `roc__` function of `module`. `origins` are the entries of the symbol
+/// map that was built along with it.
+pub fn entry_point_depths(
+    module: &Module<'_>,
+    target_data: &TargetData,
+    target: Target,
+    origins: &[(String, SymbolOrigin)],
+) -> Vec<StackDepth> {
+    let origins: MutMap<&str, &SymbolOrigin> = origins
+        .iter()
+        .map(|(fn_name, origin)| (fn_name.as_str(), origin))
+        .collect();
+
+    let functions: Vec<FunctionValue> = module
+        .get_functions()
+        .filter(|function| function.count_basic_blocks() > 0)
+        .collect();
+
+    let names: Vec<String> = functions
+        .iter()
+        .map(|function| function.get_name().to_string_lossy().into_owned())
+        .collect();
+
+    let indices: MutMap<&str, usize> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.as_str(), index))
+        .collect();
+
+    let callees: Vec<Vec<usize>> = functions
+        .iter()
+        .map(|function| {
+            let mut callees: Vec<usize> = used_functions(module, *function)
+                .iter()
+                .filter_map(|used| indices.get(used.get_name().to_str().ok()?).copied())
+                .collect();
+
+            callees.sort_unstable();
+            callees.dedup();
+
+            callees
+        })
+        .collect();
+
+    let frames: Vec<Frame> = functions
+        .iter()
+        .map(|function| frame(target_data, target, *function))
+        .collect();
+
+    let components = strongly_connected_components(&callees);
+
+    let mut component_of = vec![0; functions.len()];
+    for (component, members) in components.iter().enumerate() {
+        for member in members {
+            component_of[*member] = component;
+        }
+    }
+
+    // callees come first, so the depths of the components a component calls are known
+    let mut depths: Vec<Depth> = Vec::with_capacity(components.len());
+
+    for (component, members) in components.iter().enumerate() {
+        let mut deepest_callee = Depth::default();
+
+        for member in members {
+            for callee in &callees[*member] {
+                if component_of[*callee] != component {
+                    deepest_callee = deepest_callee.max(&depths[component_of[*callee]]);
+                }
+            }
+        }
+
+        let is_recursive = members.len() > 1 || callees[members[0]].contains(&members[0]);
+        let frame_bytes: u64 = members.iter().map(|member| frames[*member].bytes).sum();
+        let dynamic = members.iter().find(|member| frames[**member].dynamic);
+
+        let mut depth = deepest_callee;
+
+        if let Some(member) = dynamic {
+            depth
+                .unbounded
+                .get_or_insert_with(|| format!("{} allocates a dynamic amount", names[*member]));
+        }
+
+        let frees_recursive_value = members.iter().any(|member| {
+            matches!(
+                origins.get(names[*member].as_str()),
+                Some(SymbolOrigin::Helper {
+                    kind: HelperKind::Refcounting,
+                    ..
+                })
+            )
+        });
+
+        if !is_recursive {
+            depth.bytes += frame_bytes;
+        } else if frees_recursive_value {
+            depth.bytes_per_level = depth.bytes_per_level.max(frame_bytes);
+        } else {
+            depth.unbounded.get_or_insert_with(|| {
+                let mut cycle: Vec<String> = members
+                    .iter()
+                    .map(|member| match origins.get(names[*member].as_str()) {
+                        Some(SymbolOrigin::Proc { definition, .. }) => definition.clone(),
+                        _ => names[*member].clone(),
+                    })
+                    .collect();
+
+                cycle.sort();
+                cycle.dedup();
+
+                format!("recursion through {}", cycle.join(", "))
+            });
+        }
+
+        depths.push(depth);
+    }
+
+    let mut entry_points: Vec<StackDepth> = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.starts_with("roc__"))
+        .map(|(index, name)| {
+            let depth = &depths[component_of[index]];
+
+            StackDepth {
+                entry_point: name.clone(),
+                bytes: depth.bytes,
+                bytes_per_level: depth.bytes_per_level,
+                unbounded: depth.unbounded.clone(),
+            }
+        })
+        .collect();
+
+    entry_points.sort_by(|a, b| a.entry_point.cmp(&b.entry_point));
+
+    entry_points
+}
+
+#[derive(Debug, Clone, Default)]
+struct Depth {
+    bytes: u64,
+    bytes_per_level: u64,
+    unbounded: Option<String>,
+}
+
+impl Depth {
+    fn max(self, other: &Depth) -> Depth {
+        Depth {
+            bytes: self.bytes.max(other.bytes),
+            bytes_per_level: self.bytes_per_level.max(other.bytes_per_level),
+            unbounded: self.unbounded.or_else(|| other.unbounded.clone()),
+        }
+    }
+}
+
+struct Frame {
+    bytes: u64,
+    /// An alloca of a size only known at runtime
+    dynamic: bool,
+}
+
+/// Every frame is aligned to this many bytes
+const STACK_ALIGNMENT: u64 = 16;
+
+/// Room for the return address and the callee-saved registers that a function may push
+fn frame_overhead(target: Target) -> u64 {
+    match target.architecture() {
+        // return address, rbx, rbp, r12 to r15
+        Architecture::X86_64 => 8 + 6 * 8,
+        // return address, ebx, esi, edi, ebp
+        Architecture::X86_32 => 4 + 4 * 4,
+        // fp and lr, x19 to x28, d8 to d15
+        Architecture::Aarch64 => 16 + 10 * 8 + 8 * 8,
+        // r4 to r11 and lr, d8 to d15
+        Architecture::Aarch32 => 9 * 4 + 8 * 8,
+        // ra, s0 to s11, fs0 to fs11
+        Architecture::Riscv64 => 8 + 12 * 8 + 12 * 8,
+        // the wasm stack is the engine's business; only allocas go on the stack in memory
+        Architecture::Wasm32 => 0,
+    }
+}
+
+fn frame(target_data: &TargetData, target: Target, function: FunctionValue<'_>) -> Frame {
+    let mut bytes = frame_overhead(target);
+    let mut dynamic = false;
+
+    for block in function.get_basic_blocks() {
+        let mut instruction = block.get_first_instruction();
+
+        while let Some(current) = instruction {
+            if current.get_opcode() == InstructionOpcode::Alloca {
+                match alloca_size(target_data, current) {
+                    Some((size, alignment)) => bytes = bytes.next_multiple_of(alignment) + size,
+                    None => dynamic = true,
+                }
+            }
+
+            instruction = current.get_next_instruction();
+        }
+    }
+
+    Frame {
+        bytes: bytes.next_multiple_of(STACK_ALIGNMENT),
+        dynamic,
+    }
+}
+
+/// The size and alignment of an alloca, unless the number of elements is only known at runtime
+fn alloca_size(target_data: &TargetData, alloca: InstructionValue<'_>) -> Option<(u64, u64)> {
+    let allocated_type = alloca.get_allocated_type().ok()?;
+    let size = target_data.get_abi_size(&allocated_type);
+    let alignment = alloca.get_alignment().unwrap_or(1).max(1) as u64;
+
+    let count = alloca
+        .get_operand(0)
+        .and_then(|operand| operand.left())
+        .map(|count| count.into_int_value().get_zero_extended_constant())
+        .unwrap_or(Some(1))?;
+
+    Some((size * count, alignment))
+}
+
+/// Tarjan's algorithm, without recursion: the call graph of a big program is deep. A component
+/// comes after every component it has an edge to.
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+
+    let mut index = vec![UNVISITED; edges.len()];
+    let mut lowlink = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut components = Vec::new();
+
+    for root in 0..edges.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+
+        // the nodes we are visiting, and the next edge to follow from each
+        let mut visiting = vec![(root, 0)];
+        index[root] = next_index;
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((node, next_edge)) = visiting.last_mut() {
+            let node = *node;
+            let target = edges[node].get(*next_edge).copied();
+            *next_edge += 1;
+
+            match target {
+                Some(target) if index[target] == UNVISITED => {
+                    index[target] = next_index;
+                    lowlink[target] = next_index;
+                    next_index += 1;
+                    stack.push(target);
+                    on_stack[target] = true;
+
+                    visiting.push((target, 0));
+                }
+                Some(target) => {
+                    if on_stack[target] {
+                        lowlink[node] = lowlink[node].min(index[target]);
+                    }
+                }
+                None => {
+                    visiting.pop();
+
+                    if let Some((parent, _)) = visiting.last() {
+                        lowlink[*parent] = lowlink[*parent].min(lowlink[node]);
+                    }
+
+                    if lowlink[node] == index[node] {
+                        let mut component = Vec::new();
+
+                        loop {
+                            let member = stack.pop().unwrap();
+                            on_stack[member] = false;
+                            component.push(member);
+
+                            if member == node {
+                                break;
+                            }
+                        }
+
+                        components.push(component);
+                    }
+                }
+            }
+        }
+    }
+
+    components
+}
@@ -0,0 +1,76 @@
+//! A JSON description of where each function in the generated code comes from, for tools that
+//! only see symbol names: demanglers, profilers, and binary size analysis.
+//!
+//! Only functions that survived optimization are listed. Layouts are printed the way the compiler
+//! prints them in its debug output.
+use roc_gen_llvm::llvm::symbol_map::SymbolOrigin;
+use roc_target::Target;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug)]
+pub struct SymbolMapFile {
+    pub target: String,
+    pub symbols: Vec<SymbolEntry>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SymbolEntry {
+    /// A specialization of a Roc definition, for these argument and result layouts
+    Proc {
+        symbol: String,
+        definition: String,
+        arguments: Vec<String>,
+        result: String,
+    },
+    /// A refcounting, equality or copying helper for one layout
+    Helper {
+        symbol: String,
+        helper: String,
+        layout: String,
+    },
+}
+
+impl SymbolMapFile {
+    pub fn new(target: Target, entries: Vec<(String, SymbolOrigin)>) -> Self {
+        let symbols = entries
+            .into_iter()
+            .map(|(symbol, origin)| match origin {
+                SymbolOrigin::Proc {
+                    definition,
+                    arguments,
+                    result,
+                } => SymbolEntry::Proc {
+                    symbol,
+                    definition,
+                    arguments,
+                    result,
+                },
+                SymbolOrigin::Helper { helper, layout, .. } => SymbolEntry::Helper {
+                    symbol,
+                    helper,
+                    layout,
+                },
+            })
+            .collect();
+
+        Self {
+            target: target.to_string(),
+            symbols,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+}
+
+/// Written next to the app's .roc file, like the LLVM IR of `--emit-llvm-ir`
+pub fn symbol_map_path(roc_file_path: &Path) -> PathBuf {
+    roc_file_path.with_extension("symbols.json")
+}
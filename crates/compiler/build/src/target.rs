@@ -18,6 +18,7 @@ pub fn target_triple_str(target: Target) -> &'static str {
         Target::LinuxX32 => "i386-unknown-linux-gnu",
         Target::LinuxX64 => "x86_64-unknown-linux-gnu",
         Target::MacArm64 => "aarch64-apple-darwin",
+        Target::LinuxRiscv64 => "riscv64-unknown-linux-gnu",
         Target::MacX64 => "x86_64-unknown-darwin10",
         Target::Wasm32 => "wasm32-unknown-unknown",
         Target::WinX64 => "x86_64-pc-windows-gnu",
@@ -35,8 +36,10 @@ pub fn target_zig_str(target: Target) -> &'static str {
         Target::LinuxArm64 => "aarch64-linux-gnu",
         Target::LinuxX32 => "i386-linux-gnu",
         Target::LinuxX64 => "x86_64-linux-gnu",
+        Target::LinuxRiscv64 => "riscv64-linux-gnu",
         Target::MacArm64 => "aarch64-macos-none",
         Target::MacX64 => "x86_64-macos-none",
+        Target::WinX64 => "x86_64-windows-gnu",
         _ => internal_error!("TODO gracefully handle unsupported target: {:?}", target),
     }
 }
@@ -54,6 +57,9 @@ pub fn init_arch(target: Target) {
         Architecture::Aarch32 if cfg!(feature = "target-arm") => {
             LlvmTarget::initialize_arm(&InitializationConfig::default());
         }
+        Architecture::Riscv64 if cfg!(feature = "target-riscv64") => {
+            LlvmTarget::initialize_riscv(&InitializationConfig::default());
+        }
         Architecture::Wasm32 if cfg!(feature = "target-wasm32") => {
             LlvmTarget::initialize_webassembly(&InitializationConfig::default());
         }
@@ -76,7 +82,8 @@ pub fn arch_str(target: Target) -> &'static str {
         roc_target::Architecture::X86_32 if cfg!(feature = "target-x86") => "x86",
         roc_target::Architecture::Aarch64 if cfg!(feature = "target-aarch64") => "aarch64",
         roc_target::Architecture::Aarch32 if cfg!(feature = "target-arm") => "arm",
-        roc_target::Architecture::Wasm32 if cfg!(feature = "target-webassembly") => "wasm32",
+        roc_target::Architecture::Riscv64 if cfg!(feature = "target-riscv64") => "riscv64",
+        roc_target::Architecture::Wasm32 if cfg!(feature = "target-wasm32") => "wasm32",
         _ => internal_error!(
             "TODO gracefully handle unsupported target architecture: {:?}",
             target.architecture()
@@ -108,10 +115,16 @@ pub fn target_machine(
         _ => CodeModel::Default,
     };
 
+    let features = match target.architecture() {
+        // riscv64gc, the baseline that Linux distributions build for
+        Architecture::Riscv64 => "+m,+a,+f,+d,+c",
+        _ => "",
+    };
+
     LlvmTarget::from_name(arch).unwrap().create_target_machine(
         &TargetTriple::create(target_triple_str(target)),
         "generic",
-        "",
+        features,
         opt,
         reloc,
         code_model,
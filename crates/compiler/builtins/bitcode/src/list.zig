@@ -1,4 +1,5 @@
 const std = @import("std");
+const builtin = @import("builtin");
 const utils = @import("utils.zig");
 const str = @import("str.zig");
 const sort = @import("sort.zig");
@@ -17,8 +18,13 @@ const CopyFn = *const fn (Opaque, Opaque) callconv(.C) void;
 const Inc = *const fn (?[*]u8) callconv(.C) void;
 const IncN = *const fn (?[*]u8, usize) callconv(.C) void;
 const Dec = *const fn (?[*]u8) callconv(.C) void;
+const Caller1 = *const fn (?[*]u8, ?[*]u8, ?[*]u8) callconv(.C) void;
 const HasTagId = *const fn (u16, ?[*]u8) callconv(.C) extern struct { matched: bool, data: ?[*]u8 };
 
+// Print whenever an update to a shared list (e.g. `List.set`) has to copy the whole list first.
+// In a hot loop that usually means a reference is kept alive longer than intended.
+const DEBUG_CLONE = false;
+
 const SEAMLESS_SLICE_BIT: usize =
     @as(usize, @bitCast(@as(isize, std.math.minInt(isize))));
 
@@ -144,6 +150,10 @@ pub const RocList = extern struct {
     }
 
     pub fn incref(self: RocList, amount: isize, elements_refcounted: bool) void {
+        self.increfWithType(amount, elements_refcounted, utils.RC_TYPE);
+    }
+
+    pub inline fn increfWithType(self: RocList, amount: isize, elements_refcounted: bool, comptime rc_type: utils.Refcount) void {
         // If the list is unique and not a seamless slice, the length needs to be store on the heap if the elements are refcounted.
         if (elements_refcounted and self.isUnique() and !self.isSeamlessSlice()) {
             if (self.getAllocationDataPtr()) |source| {
@@ -153,10 +163,14 @@ pub const RocList = extern struct {
                 ptr[0] = self.length;
             }
         }
-        utils.increfDataPtrC(self.getAllocationDataPtr(), amount);
+        utils.increfDataPtr(self.getAllocationDataPtr(), amount, rc_type);
     }
 
     pub fn decref(self: RocList, alignment: u32, element_width: usize, elements_refcounted: bool, dec: Dec) void {
+        self.decrefWithType(alignment, element_width, elements_refcounted, dec, utils.RC_TYPE);
+    }
+
+    pub inline fn decrefWithType(self: RocList, alignment: u32, element_width: usize, elements_refcounted: bool, dec: Dec, comptime rc_type: utils.Refcount) void {
         // If unique, decref will free the list. Before that happens, all elements must be decremented.
         if (elements_refcounted and self.isUnique()) {
             if (self.getAllocationDataPtr()) |source| {
@@ -171,7 +185,7 @@ pub const RocList = extern struct {
         }
 
         // We use the raw capacity to ensure we always decrement the refcount of seamless slices.
-        utils.decref(self.getAllocationDataPtr(), self.capacity_or_alloc_ptr, alignment, elements_refcounted);
+        utils.decrefWithType(self.getAllocationDataPtr(), self.capacity_or_alloc_ptr, alignment, elements_refcounted, rc_type);
     }
 
     pub fn elements(self: RocList, comptime T: type) ?[*]T {
@@ -182,6 +196,10 @@ pub const RocList = extern struct {
         return self.refcountMachine() == utils.REFCOUNT_ONE;
     }
 
+    pub fn isStatic(self: RocList) bool {
+        return self.refcountMachine() == utils.REFCOUNT_STATIC;
+    }
+
     fn refcountMachine(self: RocList) usize {
         if (self.getCapacity() == 0 and !self.isSeamlessSlice()) {
             // the zero-capacity is Clone, copying it will not leak memory
@@ -224,6 +242,10 @@ pub const RocList = extern struct {
         }
 
         // unfortunately, we have to clone
+        if (DEBUG_CLONE and builtin.target.cpu.arch != .wasm32) {
+            std.debug.print("📋 cloned shared list {*} ({} elements of {} bytes)\n", .{ self.bytes, self.len(), element_width });
+        }
+
         const new_list = RocList.allocate(alignment, self.length, element_width, elements_refcounted);
 
         var old_bytes: [*]u8 = @as([*]u8, @ptrCast(self.bytes));
@@ -350,6 +372,14 @@ pub fn listDecref(list: RocList, alignment: u32, element_width: usize, elements_
     list.decref(alignment, element_width, elements_refcounted, dec);
 }
 
+pub fn listIncrefAtomic(list: RocList, amount: isize, elements_refcounted: bool) callconv(.C) void {
+    list.increfWithType(amount, elements_refcounted, .atomic);
+}
+
+pub fn listDecrefAtomic(list: RocList, alignment: u32, element_width: usize, elements_refcounted: bool, dec: Dec) callconv(.C) void {
+    list.decrefWithType(alignment, element_width, elements_refcounted, dec, .atomic);
+}
+
 pub fn listWithCapacity(
     capacity: u64,
     alignment: u32,
@@ -717,6 +747,157 @@ pub fn listSortWith(
     return list;
 }
 
+// MAP PARALLEL
+
+// We hand the host at most this many tasks, however long the list
+const MAP_PARALLEL_MAX_TASKS: usize = 64;
+
+// Lists shorter than this are mapped in a single task, without involving the host
+const MAP_PARALLEL_MIN_CHUNK: usize = 256;
+
+const MapParallelTasks = struct {
+    caller: Caller1,
+    data: Opaque,
+    source: [*]u8,
+    target: [*]u8,
+    length: usize,
+    chunk_length: usize,
+    old_element_width: usize,
+    new_element_width: usize,
+};
+
+// Every task maps its own chunk of the list, and writes each result to the same index of the
+// output as the element it came from. No two tasks touch the same bytes, so the output is the
+// same whichever order the tasks run in.
+fn mapParallelTask(context: *anyopaque, task_index: usize) callconv(.C) void {
+    const tasks = @as(*const MapParallelTasks, @ptrCast(@alignCast(context)));
+
+    const start = task_index * tasks.chunk_length;
+    const end = @min(start + tasks.chunk_length, tasks.length);
+
+    var i = start;
+    while (i < end) : (i += 1) {
+        const element = tasks.source + i * tasks.old_element_width;
+        const result = tasks.target + i * tasks.new_element_width;
+
+        tasks.caller(tasks.data, element, result);
+    }
+}
+
+pub fn listMapParallel(
+    input: RocList,
+    caller: Caller1,
+    data: Opaque,
+    inc_n_data: IncN,
+    data_is_owned: bool,
+    old_alignment: u32,
+    old_element_width: usize,
+    old_elements_refcounted: bool,
+    inc: Inc,
+    dec: Dec,
+    new_alignment: u32,
+    new_element_width: usize,
+    new_elements_refcounted: bool,
+) callconv(.C) RocList {
+    return listMapHelp(true, input, caller, data, inc_n_data, data_is_owned, old_alignment, old_element_width, old_elements_refcounted, inc, dec, new_alignment, new_element_width, new_elements_refcounted);
+}
+
+// `List.mapParallel` for the backends that don't hand tasks to the host: the same chunks, run one
+// after the other. The output doesn't depend on the order of the tasks, so it is the same list.
+pub fn listMapSequential(
+    input: RocList,
+    caller: Caller1,
+    data: Opaque,
+    inc_n_data: IncN,
+    data_is_owned: bool,
+    old_alignment: u32,
+    old_element_width: usize,
+    old_elements_refcounted: bool,
+    inc: Inc,
+    dec: Dec,
+    new_alignment: u32,
+    new_element_width: usize,
+    new_elements_refcounted: bool,
+) callconv(.C) RocList {
+    return listMapHelp(false, input, caller, data, inc_n_data, data_is_owned, old_alignment, old_element_width, old_elements_refcounted, inc, dec, new_alignment, new_element_width, new_elements_refcounted);
+}
+
+inline fn listMapHelp(
+    comptime parallel: bool,
+    input: RocList,
+    caller: Caller1,
+    data: Opaque,
+    inc_n_data: IncN,
+    data_is_owned: bool,
+    old_alignment: u32,
+    old_element_width: usize,
+    old_elements_refcounted: bool,
+    inc: Inc,
+    dec: Dec,
+    new_alignment: u32,
+    new_element_width: usize,
+    new_elements_refcounted: bool,
+) RocList {
+    const length = input.len();
+
+    if (length == 0) {
+        input.decref(old_alignment, old_element_width, old_elements_refcounted, dec);
+        return RocList.empty();
+    }
+
+    const source = input.bytes orelse unreachable;
+
+    // The mapper owns the element it is given. A unique list can hand over its elements and then
+    // free just the allocation. Otherwise, each element gets a reference of its own, which the
+    // list gives up again when we are done with it.
+    const moves_elements = input.isUnique() and !input.isSeamlessSlice();
+
+    if (old_elements_refcounted and !moves_elements) {
+        var i: usize = 0;
+        while (i < length) : (i += 1) {
+            inc(source + i * old_element_width);
+        }
+    }
+
+    // every call consumes a reference to the captured environment
+    if (data_is_owned) {
+        inc_n_data(data, length);
+    }
+
+    const output = RocList.allocateExact(new_alignment, length, new_element_width, new_elements_refcounted);
+
+    const chunk_length = @max(MAP_PARALLEL_MIN_CHUNK, (length + MAP_PARALLEL_MAX_TASKS - 1) / MAP_PARALLEL_MAX_TASKS);
+    const task_count = (length + chunk_length - 1) / chunk_length;
+
+    var tasks = MapParallelTasks{
+        .caller = caller,
+        .data = data,
+        .source = source,
+        .target = output.bytes orelse unreachable,
+        .length = length,
+        .chunk_length = chunk_length,
+        .old_element_width = old_element_width,
+        .new_element_width = new_element_width,
+    };
+
+    if (task_count == 1) {
+        mapParallelTask(&tasks, 0);
+    } else if (!parallel) {
+        utils.parallelForSequential(&tasks, task_count, &mapParallelTask);
+    } else {
+        // all tasks are done once this returns, so the output is complete
+        utils.parallelFor(&tasks, task_count, &mapParallelTask);
+    }
+
+    if (moves_elements) {
+        utils.decref(input.bytes, input.getCapacity() * old_element_width, old_alignment, old_elements_refcounted);
+    } else {
+        input.decref(old_alignment, old_element_width, old_elements_refcounted, dec);
+    }
+
+    return output;
+}
+
 // SWAP ELEMENTS
 
 fn swap(
@@ -959,6 +1140,35 @@ pub fn listClone(
     return list.makeUnique(alignment, element_width, elements_refcounted, inc, dec);
 }
 
+/// Copy a list into a fresh allocation that shares nothing with the original. `copy` is called
+/// with a pointer to each new element and its old counterpart, and must deep-copy it too.
+pub fn listDeepCopy(
+    list: RocList,
+    alignment: u32,
+    element_width: usize,
+    elements_refcounted: bool,
+    copy: CopyFn,
+) callconv(.C) RocList {
+    // constants are never freed or updated in place, so the copy can share them
+    if (list.isStatic()) {
+        return list;
+    }
+
+    const size = list.len();
+    const output = RocList.allocateExact(alignment, size, element_width, elements_refcounted);
+
+    if (list.bytes) |source| {
+        if (output.bytes) |target| {
+            var i: usize = 0;
+            while (i < size) : (i += 1) {
+                copy(target + i * element_width, source + i * element_width);
+            }
+        }
+    }
+
+    return output;
+}
+
 pub fn listCapacity(
     list: RocList,
 ) callconv(.C) usize {
@@ -991,6 +1201,61 @@ test "listConcat: non-unique with unique overlapping" {
     try expect(concatted.eql(wanted));
 }
 
+test "listDeepCopy: copies into a unique allocation" {
+    var original = RocList.fromSlice(u8, ([_]u8{ 1, 2, 3 })[0..], false);
+    defer original.decref(@alignOf(u8), @sizeOf(u8), false, rcNone);
+
+    var copied = listDeepCopy(original, @alignOf(u8), @sizeOf(u8), false, &copyU8);
+    defer copied.decref(@alignOf(u8), @sizeOf(u8), false, rcNone);
+
+    try expect(copied.eql(original));
+    try expect(copied.bytes != original.bytes);
+    try expect(copied.isUnique());
+}
+
+test "listDeepCopy: shares static data" {
+    var original = RocList.fromSlice(u8, ([_]u8{ 1, 2, 3 })[0..], false);
+    defer original.decref(@alignOf(u8), @sizeOf(u8), false, rcNone);
+
+    const refcount_ptr = @as([*]usize, @ptrCast(@alignCast(original.bytes))) - 1;
+    refcount_ptr[0] = utils.REFCOUNT_STATIC;
+
+    const copied = listDeepCopy(original, @alignOf(u8), @sizeOf(u8), false, &copyU8);
+
+    // make the list freeable again for the deferred decref
+    refcount_ptr[0] = utils.REFCOUNT_ONE;
+
+    try expect(copied.bytes == original.bytes);
+}
+
+fn copyU8(dst: Opaque, src: Opaque) callconv(.C) void {
+    dst.?[0] = src.?[0];
+}
+
+fn doubleU64(_: Opaque, element: Opaque, result: Opaque) callconv(.C) void {
+    const value = @as(*const u64, @ptrCast(@alignCast(element))).*;
+    @as(*u64, @ptrCast(@alignCast(result))).* = 2 * value;
+}
+
+fn incNNone(_: ?[*]u8, _: usize) callconv(.C) void {}
+
+test "listMapParallel: results keep the order of the elements" {
+    // long enough to be split over several tasks
+    var values: [1000]u64 = undefined;
+    for (&values, 0..) |*value, i| {
+        value.* = i;
+    }
+
+    const list = RocList.fromSlice(u64, values[0..], false);
+    const mapped = listMapParallel(list, &doubleU64, null, &incNNone, false, @alignOf(u64), @sizeOf(u64), false, rcNone, rcNone, @alignOf(u64), @sizeOf(u64), false);
+    defer mapped.decref(@alignOf(u64), @sizeOf(u64), false, rcNone);
+
+    const results = mapped.elements(u64) orelse unreachable;
+    for (values, 0..) |value, i| {
+        try expectEqual(2 * value, results[i]);
+    }
+}
+
 pub fn listConcatUtf8(
     list: RocList,
     string: str.RocStr,
@@ -20,6 +20,21 @@ comptime {
     }
 }
 
+// compiler-rt has no `pow`, so we provide the libm names that `llvm.pow` lowers to.
+// They are weak, so that a libm linked into the host takes precedence.
+fn powC(base: f64, exp: f64) callconv(.C) f64 {
+    return math.pow(f64, base, exp);
+}
+
+fn powfC(base: f32, exp: f32) callconv(.C) f32 {
+    return math.pow(f32, base, exp);
+}
+
+comptime {
+    @export(powC, .{ .name = "pow", .linkage = .Weak });
+    @export(powfC, .{ .name = "powf", .linkage = .Weak });
+}
+
 comptime {
     exportDecFn(dec.absC, "abs");
     exportDecFn(dec.acosC, "acos");
@@ -70,6 +85,8 @@ comptime {
     exportListFn(list.listPrepend, "prepend");
     exportListFn(list.listWithCapacity, "with_capacity");
     exportListFn(list.listSortWith, "sort_with");
+    exportListFn(list.listMapSequential, "map");
+    exportListFn(list.listMapParallel, "map_parallel");
     exportListFn(list.listConcat, "concat");
     exportListFn(list.listSublist, "sublist");
     exportListFn(list.listDropAt, "drop_at");
@@ -78,12 +95,15 @@ comptime {
     exportListFn(list.listSwap, "swap");
     exportListFn(list.listIsUnique, "is_unique");
     exportListFn(list.listClone, "clone");
+    exportListFn(list.listDeepCopy, "deep_copy");
     exportListFn(list.listCapacity, "capacity");
     exportListFn(list.listAllocationPtr, "allocation_ptr");
     exportListFn(list.listReleaseExcessCapacity, "release_excess_capacity");
     exportListFn(list.listConcatUtf8, "concat_utf8");
     exportListFn(list.listIncref, "incref");
     exportListFn(list.listDecref, "decref");
+    exportListFn(list.listIncrefAtomic, "incref_atomic");
+    exportListFn(list.listDecrefAtomic, "decref_atomic");
 }
 
 // Num Module
@@ -206,7 +226,9 @@ comptime {
     exportStrFn(str.strTrimStart, "trim_start");
     exportStrFn(str.strTrimEnd, "trim_end");
     exportStrFn(str.strCloneTo, "clone_to");
+    exportStrFn(str.strDeepCopy, "deep_copy");
     exportStrFn(str.withCapacityC, "with_capacity");
+    exportStrFn(str.strAppendUncheckedC, "append_unchecked");
     exportStrFn(str.strAllocationPtr, "allocation_ptr");
     exportStrFn(str.strReleaseExcessCapacity, "release_excess_capacity");
 
@@ -227,14 +249,21 @@ comptime {
     exportUtilsFn(utils.test_panic, "test_panic");
     exportUtilsFn(utils.increfRcPtrC, "incref_rc_ptr");
     exportUtilsFn(utils.decrefRcPtrC, "decref_rc_ptr");
+    exportUtilsFn(utils.increfRcPtrAtomicC, "incref_rc_ptr_atomic");
+    exportUtilsFn(utils.decrefRcPtrAtomicC, "decref_rc_ptr_atomic");
     exportUtilsFn(utils.freeRcPtrC, "free_rc_ptr");
     exportUtilsFn(utils.increfDataPtrC, "incref_data_ptr");
     exportUtilsFn(utils.decrefDataPtrC, "decref_data_ptr");
     exportUtilsFn(utils.freeDataPtrC, "free_data_ptr");
     exportUtilsFn(utils.isUnique, "is_unique");
     exportUtilsFn(utils.decrefCheckNullC, "decref_check_null");
+    exportUtilsFn(utils.decrefCheckNullAtomicC, "decref_check_null_atomic");
     exportUtilsFn(utils.allocateWithRefcountC, "allocate_with_refcount");
     exportUtilsFn(utils.dictPseudoSeed, "dict_pseudo_seed");
+    exportUtilsFn(utils.parallelForSequential, "parallel_for_sequential");
+    exportUtilsFn(utils.parallelForShuffled, "parallel_for_shuffled");
+
+    @export(utils.atomic_refcounts, .{ .name = "roc_builtins.utils." ++ "atomic_refcounts", .linkage = .Strong });
 
     @export(panic_utils.panic, .{ .name = "roc_builtins.utils." ++ "panic", .linkage = .Weak });
     @export(dbg_utils.dbg_impl, .{ .name = "roc_builtins.utils." ++ "dbg_impl", .linkage = .Weak });
@@ -224,10 +224,25 @@ pub fn exportSqrt(comptime T: type, comptime name: []const u8) void {
     @export(f, .{ .name = name ++ @typeName(T), .linkage = .Strong });
 }
 
+// Like `llvm.fptosi.sat` and `llvm.fptoui.sat`, which the LLVM backend uses: NaN becomes 0, and
+// values out of the range of T become its minimum or maximum.
+fn intFromFloatSaturated(comptime F: type, comptime T: type, input: F) T {
+    if (math.isNan(input)) {
+        return 0;
+    } else if (input <= @as(F, @floatFromInt(math.minInt(T)))) {
+        return math.minInt(T);
+    } else if (input >= @as(F, @floatFromInt(math.maxInt(T)))) {
+        // the maximum is rounded up to a power of 2 when F cannot represent it exactly
+        return math.maxInt(T);
+    } else {
+        return @as(T, @intFromFloat(input));
+    }
+}
+
 pub fn exportRound(comptime F: type, comptime T: type, comptime name: []const u8) void {
     comptime var f = struct {
         fn func(input: F) callconv(.C) T {
-            return @as(T, @intFromFloat((math.round(input))));
+            return intFromFloatSaturated(F, T, math.round(input));
         }
     }.func;
     @export(f, .{ .name = name ++ @typeName(T), .linkage = .Strong });
@@ -236,7 +251,7 @@ pub fn exportRound(comptime F: type, comptime T: type, comptime name: []const u8
 pub fn exportFloor(comptime F: type, comptime T: type, comptime name: []const u8) void {
     comptime var f = struct {
         fn func(input: F) callconv(.C) T {
-            return @as(T, @intFromFloat((math.floor(input))));
+            return intFromFloatSaturated(F, T, math.floor(input));
         }
     }.func;
     @export(f, .{ .name = name ++ @typeName(T), .linkage = .Strong });
@@ -245,7 +260,7 @@ pub fn exportFloor(comptime F: type, comptime T: type, comptime name: []const u8
 pub fn exportCeiling(comptime F: type, comptime T: type, comptime name: []const u8) void {
     comptime var f = struct {
         fn func(input: F) callconv(.C) T {
-            return @as(T, @intFromFloat((math.ceil(input))));
+            return intFromFloatSaturated(F, T, math.ceil(input));
         }
     }.func;
     @export(f, .{ .name = name ++ @typeName(T), .linkage = .Strong });
@@ -365,6 +365,10 @@ pub const RocStr = extern struct {
         return @call(.always_inline, RocStr.isRefcountOne, .{self});
     }
 
+    pub fn isStatic(self: RocStr) bool {
+        return self.refcountMachine() == utils.REFCOUNT_STATIC;
+    }
+
     fn isRefcountOne(self: RocStr) bool {
         return self.refcountMachine() == utils.REFCOUNT_ONE;
     }
@@ -2335,6 +2339,65 @@ pub fn withCapacityC(capacity: u64) callconv(.C) RocStr {
     return str;
 }
 
+// Str.appendUnchecked
+//
+// Like Str.concat, but the caller promises that the string is unique, so we never look at its
+// refcount. Running out of capacity still falls back to Str.concat, which reallocates.
+pub fn strAppendUncheckedC(string: RocStr, suffix: RocStr) callconv(.C) RocStr {
+    const old_length = string.len();
+    const new_length = old_length + suffix.len();
+
+    if (string.getCapacity() < new_length) {
+        return strConcat(string, suffix);
+    }
+
+    var result = string;
+    @memcpy(result.asU8ptrMut()[old_length..new_length], suffix.asU8ptr()[0..suffix.len()]);
+    result.setLen(new_length);
+
+    return result;
+}
+
+test "strAppendUnchecked: within capacity" {
+    const prefix = RocStr.fromSlice("a string so large that it must be ");
+    defer prefix.decref();
+    const suffix = RocStr.fromSlice("heap-allocated");
+    defer suffix.decref();
+    const expected = RocStr.fromSlice("a string so large that it must be heap-allocated");
+    defer expected.decref();
+
+    var result = withCapacityC(64);
+    const bytes = result.bytes;
+    result = strAppendUncheckedC(result, prefix);
+    result = strAppendUncheckedC(result, suffix);
+    defer result.decref();
+
+    try expect(result.eq(expected));
+    try expectEqual(bytes, result.bytes);
+}
+
+test "strAppendUnchecked: out of capacity" {
+    const suffix = RocStr.fromSlice(" that it must be heap-allocated");
+    defer suffix.decref();
+    const expected = RocStr.fromSlice("a string so large that it must be heap-allocated");
+    defer expected.decref();
+
+    const result = strAppendUncheckedC(RocStr.fromSlice("a string so large"), suffix);
+    defer result.decref();
+
+    try expect(result.eq(expected));
+}
+
+/// Copy a string into a fresh allocation that shares nothing with the original. String literals
+/// are never freed or updated in place, so those are shared instead.
+pub fn strDeepCopy(string: RocStr) callconv(.C) RocStr {
+    if (string.isStatic()) {
+        return string;
+    }
+
+    return string.clone();
+}
+
 pub fn strCloneTo(
     string: RocStr,
     ptr: [*]u8,
@@ -1,11 +1,19 @@
 const std = @import("std");
 const builtin = @import("builtin");
 const Monotonic = std.builtin.AtomicOrder.Monotonic;
+const AcqRel = std.builtin.AtomicOrder.AcqRel;
 
 const DEBUG_INCDEC = false;
 const DEBUG_TESTING_ALLOC = false;
 const DEBUG_ALLOC = false;
 
+// Describe every allocation to valgrind as a block that starts at the data, instead of at the
+// refcount in front of it. Roc values only point at their data, so otherwise memcheck sees only
+// interior pointers and reports leaked blocks as "possibly lost", with no hint of what they held.
+// Client requests are only emitted when zig builds with valgrind support, e.g. in Debug mode (see
+// build.zig).
+const DEBUG_VALGRIND = false;
+
 pub fn WithOverflow(comptime T: type) type {
     return extern struct { value: T, has_overflowed: bool };
 }
@@ -22,6 +30,51 @@ extern fn roc_dealloc(c_ptr: *anyopaque, alignment: u32) callconv(.C) void;
 
 extern fn roc_dbg(loc: *anyopaque, message: *anyopaque, src: *anyopaque) callconv(.C) void;
 
+// Runs `task(context, i)` for every `i` below `task_count`, and returns once all of them are done.
+// The host decides how the tasks are spread over its threads. Only `List.mapParallel` calls this,
+// so hosts of apps that don't use it need not provide it.
+extern fn roc_parallel_for(context: *anyopaque, task_count: usize, task: ParallelTask) callconv(.C) void;
+
+pub const ParallelTask = *const fn (*anyopaque, usize) callconv(.C) void;
+
+pub fn parallelFor(context: *anyopaque, task_count: usize, task: ParallelTask) void {
+    roc_parallel_for(context, task_count, task);
+}
+
+// Runs the tasks one after the other. This is `roc_parallel_for` when there is no host to provide
+// it, like in tests and the repl.
+pub fn parallelForSequential(context: *anyopaque, task_count: usize, task: ParallelTask) callconv(.C) void {
+    var i: usize = 0;
+    while (i < task_count) : (i += 1) {
+        task(context, i);
+    }
+}
+
+// Runs the tasks one after the other, in an order that is shuffled with `seed`. This is
+// `roc_parallel_for` with `--shuffle-parallel`, so that code which wrongly depends on the order of
+// the tasks fails the same way on every run, instead of only now and then on a real thread pool.
+pub fn parallelForShuffled(context: *anyopaque, task_count: usize, task: ParallelTask, seed: u64) callconv(.C) void {
+    if (task_count < 2) {
+        return parallelForSequential(context, task_count, task);
+    }
+
+    const order_ptr = alloc(task_count * @sizeOf(usize), @alignOf(usize)) orelse unreachable;
+    const order = @as([*]usize, @ptrCast(@alignCast(order_ptr)))[0..task_count];
+
+    for (order, 0..) |*task_index, i| {
+        task_index.* = i;
+    }
+
+    var rng = std.rand.DefaultPrng.init(seed);
+    rng.random().shuffle(usize, order);
+
+    for (order) |task_index| {
+        task(context, task_index);
+    }
+
+    dealloc(order_ptr, @alignOf(usize));
+}
+
 // Since roc_dbg is never used by the builtins, we need at export a function that uses it to stop DCE.
 pub fn test_dbg(loc: *anyopaque, src: *anyopaque, message: *anyopaque) callconv(.C) void {
     roc_dbg(loc, message, src);
@@ -61,6 +114,7 @@ comptime {
         @export(testing_roc_dealloc, .{ .name = "roc_dealloc", .linkage = .Strong });
         @export(testing_roc_panic, .{ .name = "roc_panic", .linkage = .Strong });
         @export(testing_roc_dbg, .{ .name = "roc_dbg", .linkage = .Strong });
+        @export(parallelForSequential, .{ .name = "roc_parallel_for", .linkage = .Strong });
 
         if (builtin.os.tag == .macos or builtin.os.tag == .linux) {
             @export(testing_roc_getppid, .{ .name = "roc_getppid", .linkage = .Strong });
@@ -140,6 +194,37 @@ pub fn dealloc(c_ptr: [*]u8, alignment: u32) void {
     return roc_dealloc(c_ptr, alignment);
 }
 
+// Identifies our blocks to valgrind; only its address matters
+var valgrind_pool: u8 = 0;
+
+fn valgrindPool() [*]u8 {
+    const pool = @as([*]u8, @ptrCast(&valgrind_pool));
+
+    if (!std.valgrind.mempoolExists(pool)) {
+        std.valgrind.createMempool(pool, 0, false);
+    }
+
+    return pool;
+}
+
+inline fn valgrindAlloc(data_ptr: [*]u8, data_bytes: usize) void {
+    if (DEBUG_VALGRIND and builtin.target.cpu.arch != .wasm32) {
+        std.valgrind.mempoolAlloc(valgrindPool(), data_ptr[0..data_bytes]);
+    }
+}
+
+inline fn valgrindMove(old_data_ptr: [*]u8, new_data_ptr: [*]u8, data_bytes: usize) void {
+    if (DEBUG_VALGRIND and builtin.target.cpu.arch != .wasm32) {
+        std.valgrind.mempoolChange(valgrindPool(), old_data_ptr, new_data_ptr[0..data_bytes]);
+    }
+}
+
+inline fn valgrindFree(data_ptr: [*]u8) void {
+    if (DEBUG_VALGRIND and builtin.target.cpu.arch != .wasm32) {
+        std.valgrind.mempoolFree(valgrindPool(), data_ptr);
+    }
+}
+
 // indirection because otherwise zig creates an alias to the panic function which our LLVM code
 // does not know how to deal with
 pub fn test_panic(c_ptr: *anyopaque, crash_tag: u32) callconv(.C) void {
@@ -161,6 +246,8 @@ pub const Dec = fn (?[*]u8) callconv(.C) void;
 const REFCOUNT_MAX_ISIZE: isize = 0;
 pub const REFCOUNT_ONE_ISIZE: isize = std.math.minInt(isize);
 pub const REFCOUNT_ONE: usize = @as(usize, @bitCast(REFCOUNT_ONE_ISIZE));
+/// The refcount of constants (e.g. string and list literals), which are never freed
+pub const REFCOUNT_STATIC: usize = @as(usize, @bitCast(REFCOUNT_MAX_ISIZE));
 
 pub const IntWidth = enum(u8) {
     U8 = 0,
@@ -175,16 +262,44 @@ pub const IntWidth = enum(u8) {
     I128 = 9,
 };
 
-const Refcount = enum {
+pub const Refcount = enum {
     none,
     normal,
     atomic,
 };
 
-const RC_TYPE = Refcount.normal;
+pub const RC_TYPE = Refcount.normal;
+
+// The compiler sets this to 1 when the program shares values between threads, through
+// `--atomic-rc` or `List.mapParallel`. The refcount updates made by the builtins themselves (e.g.
+// when `List.concat` frees the list it consumes) are then atomic too, because the builtins cannot
+// tell which values are shared. The compiler also turns it into a constant, so that the check is
+// optimized away.
+//
+// Only the LLVM backend sets it. The dev backends always leave it at 0: wasm has no threads, and
+// the assembly backend does not support `--atomic-rc`, so `List.mapParallel` is not thread safe
+// there for values with refcounts.
+pub var atomic_refcounts: u8 = 0;
 
 pub fn increfRcPtrC(ptr_to_refcount: *isize, amount: isize) callconv(.C) void {
-    if (RC_TYPE == Refcount.none) return;
+    return @call(.always_inline, increfRcPtr, .{ ptr_to_refcount, amount, RC_TYPE });
+}
+
+// Used by the compiler for values that can be shared with other threads by the host.
+pub fn increfRcPtrAtomicC(ptr_to_refcount: *isize, amount: isize) callconv(.C) void {
+    return @call(.always_inline, increfRcPtr, .{ ptr_to_refcount, amount, Refcount.atomic });
+}
+
+inline fn increfRcPtr(ptr_to_refcount: *isize, amount: isize, comptime rc_type: Refcount) void {
+    if (rc_type == Refcount.normal and atomic_refcounts != 0) {
+        return increfRcPtrHelp(ptr_to_refcount, amount, Refcount.atomic);
+    }
+
+    return increfRcPtrHelp(ptr_to_refcount, amount, rc_type);
+}
+
+inline fn increfRcPtrHelp(ptr_to_refcount: *isize, amount: isize, comptime rc_type: Refcount) void {
+    if (rc_type == Refcount.none) return;
 
     if (DEBUG_INCDEC and builtin.target.cpu.arch != .wasm32) {
         std.debug.print("| increment {*}: ", .{ptr_to_refcount});
@@ -194,7 +309,7 @@ pub fn increfRcPtrC(ptr_to_refcount: *isize, amount: isize) callconv(.C) void {
     if (ptr_to_refcount.* != REFCOUNT_MAX_ISIZE) {
         // Note: we assume that a refcount will never overflow.
         // As such, we do not need to cap incrementing.
-        switch (RC_TYPE) {
+        switch (rc_type) {
             Refcount.normal => {
                 if (DEBUG_INCDEC and builtin.target.cpu.arch != .wasm32) {
                     const old = @as(usize, @bitCast(ptr_to_refcount.*));
@@ -227,7 +342,17 @@ pub fn decrefRcPtrC(
     // this is of course unsafe, but we trust what we get from the llvm side
     var bytes = @as([*]isize, @ptrCast(bytes_or_null));
 
-    return @call(.always_inline, decref_ptr_to_refcount, .{ bytes, alignment, elements_refcounted });
+    return @call(.always_inline, decref_ptr_to_refcount, .{ bytes, alignment, elements_refcounted, RC_TYPE });
+}
+
+pub fn decrefRcPtrAtomicC(
+    bytes_or_null: ?[*]isize,
+    alignment: u32,
+    elements_refcounted: bool,
+) callconv(.C) void {
+    var bytes = @as([*]isize, @ptrCast(bytes_or_null));
+
+    return @call(.always_inline, decref_ptr_to_refcount, .{ bytes, alignment, elements_refcounted, Refcount.atomic });
 }
 
 pub fn decrefCheckNullC(
@@ -237,7 +362,18 @@ pub fn decrefCheckNullC(
 ) callconv(.C) void {
     if (bytes_or_null) |bytes| {
         const isizes: [*]isize = @as([*]isize, @ptrCast(@alignCast(bytes)));
-        return @call(.always_inline, decref_ptr_to_refcount, .{ isizes - 1, alignment, elements_refcounted });
+        return @call(.always_inline, decref_ptr_to_refcount, .{ isizes - 1, alignment, elements_refcounted, RC_TYPE });
+    }
+}
+
+pub fn decrefCheckNullAtomicC(
+    bytes_or_null: ?[*]u8,
+    alignment: u32,
+    elements_refcounted: bool,
+) callconv(.C) void {
+    if (bytes_or_null) |bytes| {
+        const isizes: [*]isize = @as([*]isize, @ptrCast(@alignCast(bytes)));
+        return @call(.always_inline, decref_ptr_to_refcount, .{ isizes - 1, alignment, elements_refcounted, Refcount.atomic });
     }
 }
 
@@ -262,6 +398,14 @@ pub fn increfDataPtrC(
     bytes_or_null: ?[*]u8,
     inc_amount: isize,
 ) callconv(.C) void {
+    return @call(.always_inline, increfDataPtr, .{ bytes_or_null, inc_amount, RC_TYPE });
+}
+
+pub inline fn increfDataPtr(
+    bytes_or_null: ?[*]u8,
+    inc_amount: isize,
+    comptime rc_type: Refcount,
+) void {
     var bytes = bytes_or_null orelse return;
 
     const ptr = @intFromPtr(bytes);
@@ -270,7 +414,7 @@ pub fn increfDataPtrC(
 
     const isizes: *isize = @as(*isize, @ptrFromInt(masked_ptr - @sizeOf(usize)));
 
-    return increfRcPtrC(isizes, inc_amount);
+    return increfRcPtr(isizes, inc_amount, rc_type);
 }
 
 pub fn freeDataPtrC(
@@ -304,6 +448,16 @@ pub fn decref(
     data_bytes: usize,
     alignment: u32,
     elements_refcounted: bool,
+) void {
+    decrefWithType(bytes_or_null, data_bytes, alignment, elements_refcounted, RC_TYPE);
+}
+
+pub inline fn decrefWithType(
+    bytes_or_null: ?[*]u8,
+    data_bytes: usize,
+    alignment: u32,
+    elements_refcounted: bool,
+    comptime rc_type: Refcount,
 ) void {
     if (data_bytes == 0) {
         return;
@@ -313,21 +467,24 @@ pub fn decref(
 
     const isizes: [*]isize = @as([*]isize, @ptrCast(@alignCast(bytes)));
 
-    decref_ptr_to_refcount(isizes - 1, alignment, elements_refcounted);
+    decref_ptr_to_refcount(isizes - 1, alignment, elements_refcounted, rc_type);
 }
 
-inline fn free_ptr_to_refcount(
+// Freeing is the slow path of a decrement. Keeping it out of line (and cold) keeps the fast path
+// small, so it can be inlined everywhere without bloating the hot code.
+noinline fn free_ptr_to_refcount(
     refcount_ptr: [*]isize,
     alignment: u32,
     elements_refcounted: bool,
 ) void {
+    @setCold(true);
+
     if (RC_TYPE == Refcount.none) return;
-    const ptr_width = @sizeOf(usize);
-    const required_space: usize = if (elements_refcounted) (2 * ptr_width) else ptr_width;
-    const extra_bytes = @max(required_space, alignment);
-    const allocation_ptr = @as([*]u8, @ptrCast(refcount_ptr)) - (extra_bytes - @sizeOf(usize));
+    const data_ptr = @as([*]u8, @ptrCast(refcount_ptr)) + @sizeOf(usize);
+    const allocation_ptr = allocationPtrFromDataPtr(data_ptr, alignment, elements_refcounted);
 
     // NOTE: we don't even check whether the refcount is "infinity" here!
+    valgrindFree(data_ptr);
     dealloc(allocation_ptr, alignment);
 
     if (DEBUG_ALLOC and builtin.target.cpu.arch != .wasm32) {
@@ -339,8 +496,22 @@ inline fn decref_ptr_to_refcount(
     refcount_ptr: [*]isize,
     element_alignment: u32,
     elements_refcounted: bool,
+    comptime rc_type: Refcount,
 ) void {
-    if (RC_TYPE == Refcount.none) return;
+    if (rc_type == Refcount.normal and atomic_refcounts != 0) {
+        return decref_ptr_to_refcount_help(refcount_ptr, element_alignment, elements_refcounted, Refcount.atomic);
+    }
+
+    return decref_ptr_to_refcount_help(refcount_ptr, element_alignment, elements_refcounted, rc_type);
+}
+
+inline fn decref_ptr_to_refcount_help(
+    refcount_ptr: [*]isize,
+    element_alignment: u32,
+    elements_refcounted: bool,
+    comptime rc_type: Refcount,
+) void {
+    if (rc_type == Refcount.none) return;
 
     if (DEBUG_INCDEC and builtin.target.cpu.arch != .wasm32) {
         std.debug.print("| decrement {*}: ", .{refcount_ptr});
@@ -353,7 +524,7 @@ inline fn decref_ptr_to_refcount(
     // Ensure that the refcount is not whole program lifetime.
     const refcount: isize = refcount_ptr[0];
     if (refcount != REFCOUNT_MAX_ISIZE) {
-        switch (RC_TYPE) {
+        switch (rc_type) {
             Refcount.normal => {
                 const old = @as(usize, @bitCast(refcount));
                 refcount_ptr[0] = refcount -% 1;
@@ -371,7 +542,8 @@ inline fn decref_ptr_to_refcount(
                 }
             },
             Refcount.atomic => {
-                var last = @atomicRmw(isize, &refcount_ptr[0], std.builtin.AtomicRmwOp.Sub, 1, Monotonic);
+                // AcqRel, so that all uses of the value by other threads happen before it is freed
+                var last = @atomicRmw(isize, &refcount_ptr[0], std.builtin.AtomicRmwOp.Sub, 1, AcqRel);
                 if (last == REFCOUNT_ONE_ISIZE) {
                     free_ptr_to_refcount(refcount_ptr, alignment, elements_refcounted);
                 }
@@ -457,18 +629,80 @@ pub fn allocateWithRefcountC(
     return allocateWithRefcount(data_bytes, element_alignment, elements_refcounted);
 }
 
+// Hosts only have to honor alignments up to twice the pointer width (what malloc guarantees).
+// For larger alignments we pad the allocation ourselves: the data pointer is aligned by hand, and
+// the pointer that roc_alloc returned is stored right in front of the refcount (and element count),
+// so that it can be found again when the allocation is freed or reallocated.
+//
+//     [ padding ][ original pointer ][ element count ][ refcount ][ data ... ]
+//
+// Allocations that are not over-aligned have no padding and no original pointer; the data simply
+// starts `@max(required_space, alignment)` bytes into the allocation.
+const MAX_HOST_ALIGNMENT: u32 = 2 * @sizeOf(usize);
+
+inline fn isOverAligned(alignment: u32) bool {
+    return alignment > MAX_HOST_ALIGNMENT;
+}
+
+inline fn requiredSpace(elements_refcounted: bool) usize {
+    // If the element type is refcounted, we need to also allocate space to store the element count on the heap.
+    // This is used so that a seamless slice can de-allocate the underlying list type.
+    const ptr_width = @sizeOf(usize);
+    return if (elements_refcounted) (2 * ptr_width) else ptr_width;
+}
+
+/// The number of bytes to allocate for `data_bytes` of data, including the header.
+inline fn allocationBytes(data_bytes: usize, alignment: u32, elements_refcounted: bool) usize {
+    const required_space = requiredSpace(elements_refcounted);
+
+    if (isOverAligned(alignment)) {
+        // The host gives us at least pointer alignment, so aligning the data after the original
+        // pointer slot skips at most `alignment - ptr_width` bytes.
+        return required_space + alignment + data_bytes;
+    } else {
+        return @max(required_space, alignment) + data_bytes;
+    }
+}
+
+/// Place the data in an allocation returned by roc_alloc.
+inline fn dataPtrFromAllocationPtr(allocation_ptr: [*]u8, alignment: u32, elements_refcounted: bool) [*]u8 {
+    const ptr_width = @sizeOf(usize);
+    const required_space = requiredSpace(elements_refcounted);
+
+    if (isOverAligned(alignment)) {
+        const header_end = @intFromPtr(allocation_ptr) + ptr_width + required_space;
+        const data_ptr = @as([*]u8, @ptrFromInt(std.mem.alignForward(usize, header_end, alignment)));
+
+        const original_ptr_slot = @as([*]usize, @ptrCast(@alignCast(data_ptr - required_space - ptr_width)));
+        original_ptr_slot[0] = @intFromPtr(allocation_ptr);
+
+        return data_ptr;
+    } else {
+        return allocation_ptr + @max(required_space, alignment);
+    }
+}
+
+/// Find the pointer that roc_alloc returned for an allocation.
+inline fn allocationPtrFromDataPtr(data_ptr: [*]u8, alignment: u32, elements_refcounted: bool) [*]u8 {
+    const ptr_width = @sizeOf(usize);
+    const required_space = requiredSpace(elements_refcounted);
+
+    if (isOverAligned(alignment)) {
+        const original_ptr_slot = @as([*]usize, @ptrCast(@alignCast(data_ptr - required_space - ptr_width)));
+        return @as([*]u8, @ptrFromInt(original_ptr_slot[0]));
+    } else {
+        return data_ptr - @max(required_space, alignment);
+    }
+}
+
 pub fn allocateWithRefcount(
     data_bytes: usize,
     element_alignment: u32,
     elements_refcounted: bool,
 ) [*]u8 {
-    // If the element type is refcounted, we need to also allocate space to store the element count on the heap.
-    // This is used so that a seamless slice can de-allocate the underlying list type.
     const ptr_width = @sizeOf(usize);
     const alignment = @max(ptr_width, element_alignment);
-    const required_space: usize = if (elements_refcounted) (2 * ptr_width) else ptr_width;
-    const extra_bytes = @max(required_space, element_alignment);
-    const length = extra_bytes + data_bytes;
+    const length = allocationBytes(data_bytes, alignment, elements_refcounted);
 
     var new_bytes: [*]u8 = alloc(length, alignment) orelse unreachable;
 
@@ -476,10 +710,12 @@ pub fn allocateWithRefcount(
         std.debug.print("+ allocated {*} ({} bytes with alignment {})\n", .{ new_bytes, data_bytes, alignment });
     }
 
-    const data_ptr = new_bytes + extra_bytes;
+    const data_ptr = dataPtrFromAllocationPtr(new_bytes, alignment, elements_refcounted);
     const refcount_ptr = @as([*]usize, @ptrCast(@as([*]align(ptr_width) u8, @alignCast(data_ptr)) - ptr_width));
     refcount_ptr[0] = if (RC_TYPE == Refcount.none) REFCOUNT_MAX_ISIZE else REFCOUNT_ONE;
 
+    valgrindAlloc(data_ptr, data_bytes);
+
     return data_ptr;
 }
 
@@ -496,23 +732,40 @@ pub fn unsafeReallocate(
     element_width: usize,
     elements_refcounted: bool,
 ) [*]u8 {
-    const ptr_width: usize = @sizeOf(usize);
-    const required_space: usize = if (elements_refcounted) (2 * ptr_width) else ptr_width;
-    const extra_bytes = @max(required_space, alignment);
-
-    const old_width = extra_bytes + old_length * element_width;
-    const new_width = extra_bytes + new_length * element_width;
+    const old_width = allocationBytes(old_length * element_width, alignment, elements_refcounted);
+    const new_width = allocationBytes(new_length * element_width, alignment, elements_refcounted);
 
     if (old_width >= new_width) {
         return source_ptr;
     }
 
+    const old_allocation = allocationPtrFromDataPtr(source_ptr, alignment, elements_refcounted);
+
+    if (isOverAligned(alignment)) {
+        // realloc may move the data to an address with a different alignment, so the padding can
+        // change; allocate and copy instead.
+        const required_space = requiredSpace(elements_refcounted);
+        const new_allocation: [*]u8 = alloc(new_width, alignment) orelse unreachable;
+        const new_source = dataPtrFromAllocationPtr(new_allocation, alignment, elements_refcounted);
+        valgrindAlloc(new_source, new_length * element_width);
+
+        // copy the refcount (and element count) along with the data
+        const old_bytes = required_space + old_length * element_width;
+        @memcpy((new_source - required_space)[0..old_bytes], (source_ptr - required_space)[0..old_bytes]);
+
+        valgrindFree(source_ptr);
+        dealloc(old_allocation, alignment);
+
+        return new_source;
+    }
+
     // TODO handle out of memory
     // NOTE realloc will dealloc the original allocation
-    const old_allocation = source_ptr - extra_bytes;
     const new_allocation = realloc(old_allocation, new_width, old_width, alignment);
+    const new_source = dataPtrFromAllocationPtr(@as([*]u8, @ptrCast(new_allocation)), alignment, elements_refcounted);
+
+    valgrindMove(source_ptr, new_source, new_length * element_width);
 
-    const new_source = @as([*]u8, @ptrCast(new_allocation)) + extra_bytes;
     return new_source;
 }
 
@@ -541,6 +794,117 @@ test "increfC, static data" {
     try std.testing.expectEqual(mock_rc, REFCOUNT_MAX_ISIZE);
 }
 
+test "allocateWithRefcount, over-aligned data" {
+    inline for ([_]u32{ 32, 64 }) |alignment| {
+        inline for ([_]bool{ false, true }) |elements_refcounted| {
+            const data_ptr = allocateWithRefcount(100, alignment, elements_refcounted);
+            try std.testing.expectEqual(@intFromPtr(data_ptr) % alignment, 0);
+
+            const refcount_ptr = @as([*]isize, @ptrCast(@alignCast(data_ptr))) - 1;
+            try std.testing.expectEqual(refcount_ptr[0], REFCOUNT_ONE_ISIZE);
+
+            // the testing allocator reports a leak or an invalid free if we get this wrong
+            decref_ptr_to_refcount(refcount_ptr, alignment, elements_refcounted, RC_TYPE);
+        }
+    }
+}
+
+test "unsafeReallocate, over-aligned data" {
+    const alignment = 32;
+    const data_ptr = allocateWithRefcount(4, alignment, false);
+    @memcpy(data_ptr[0..4], "roc!");
+
+    const new_ptr = unsafeReallocate(data_ptr, alignment, 4, 1000, 1, false);
+    try std.testing.expectEqual(@intFromPtr(new_ptr) % alignment, 0);
+    try std.testing.expectEqualSlices(u8, "roc!", new_ptr[0..4]);
+
+    const refcount_ptr = @as([*]isize, @ptrCast(@alignCast(new_ptr))) - 1;
+    try std.testing.expectEqual(refcount_ptr[0], REFCOUNT_ONE_ISIZE);
+
+    decref_ptr_to_refcount(refcount_ptr, alignment, false, RC_TYPE);
+}
+
+test "increfRcPtrAtomicC and decrefRcPtrAtomicC" {
+    const data_ptr = allocateWithRefcount(8, @sizeOf(usize), false);
+    const refcount_ptr = @as([*]isize, @ptrCast(@alignCast(data_ptr))) - 1;
+
+    increfRcPtrAtomicC(&refcount_ptr[0], 2);
+    try std.testing.expectEqual(refcount_ptr[0], REFCOUNT_ONE_ISIZE + 2);
+
+    decrefRcPtrAtomicC(refcount_ptr, @sizeOf(usize), false);
+    decrefRcPtrAtomicC(refcount_ptr, @sizeOf(usize), false);
+    try std.testing.expectEqual(refcount_ptr[0], REFCOUNT_ONE_ISIZE);
+
+    // the testing allocator reports a leak if the last decrement does not free
+    decrefRcPtrAtomicC(refcount_ptr, @sizeOf(usize), false);
+}
+
+const TestTaskOrder = struct {
+    order: [16]usize,
+    count: usize,
+};
+
+fn recordTaskOrder(context: *anyopaque, task_index: usize) callconv(.C) void {
+    const tasks = @as(*TestTaskOrder, @ptrCast(@alignCast(context)));
+    tasks.order[tasks.count] = task_index;
+    tasks.count += 1;
+}
+
+test "parallelForShuffled: runs every task once, in the same order for the same seed" {
+    var first = TestTaskOrder{ .order = undefined, .count = 0 };
+    parallelForShuffled(&first, 16, recordTaskOrder, 42);
+    try std.testing.expectEqual(first.count, 16);
+
+    var seen = [_]bool{false} ** 16;
+    for (first.order) |task_index| {
+        try std.testing.expect(!seen[task_index]);
+        seen[task_index] = true;
+    }
+
+    var second = TestTaskOrder{ .order = undefined, .count = 0 };
+    parallelForShuffled(&second, 16, recordTaskOrder, 42);
+    try std.testing.expectEqualSlices(usize, &first.order, &second.order);
+
+    var in_order = true;
+    for (first.order, 0..) |task_index, i| {
+        in_order = in_order and task_index == i;
+    }
+    try std.testing.expect(!in_order);
+}
+
+test "increfRcPtrC is atomic when atomic_refcounts is set" {
+    atomic_refcounts = 1;
+    defer atomic_refcounts = 0;
+
+    const data_ptr = allocateWithRefcount(8, @sizeOf(usize), false);
+    const refcount_ptr = @as([*]isize, @ptrCast(@alignCast(data_ptr))) - 1;
+
+    const Incref = struct {
+        const increments = 10_000;
+
+        fn run(ptr: *isize) void {
+            var i: usize = 0;
+            while (i < increments) : (i += 1) {
+                increfRcPtrC(ptr, 1);
+            }
+        }
+    };
+
+    var threads: [4]std.Thread = undefined;
+    for (&threads) |*thread| {
+        thread.* = try std.Thread.spawn(.{}, Incref.run, .{&refcount_ptr[0]});
+    }
+    for (threads) |thread| {
+        thread.join();
+    }
+
+    // a lost update would leave the count short
+    try std.testing.expectEqual(refcount_ptr[0], REFCOUNT_ONE_ISIZE + threads.len * Incref.increments);
+
+    refcount_ptr[0] = REFCOUNT_ONE_ISIZE;
+    decrefRcPtrC(refcount_ptr, @sizeOf(usize), false);
+}
+
 // This returns a compilation dependent pseudo random seed for dictionaries.
 // The seed is the address of this function.
 // This avoids all roc Dicts using a known seed and being trivial to DOS.
@@ -2,7 +2,9 @@
 ## - Holding unknown Roc types when developing [platforms](https://github.com/roc-lang/roc/wiki/Roc-concepts-explained#platform).
 ## - To improve performance in rare cases.
 ##
-module [box, unbox]
+module [box, unbox, sizeOf, alignOf]
+
+import Num exposing [U64]
 
 ## Allocates a value on the heap. Boxing is an expensive process as it copies
 ## the value from the stack to the heap. This may provide a performance
@@ -19,6 +21,25 @@ box : a -> Box a
 ## ```
 unbox : Box a -> a
 
+## The number of bytes that a value of this type takes up in memory, not
+## counting the heap allocations it refers to. It is worked out at compile
+## time for the target, so the value passed in is only there for its type.
+##
+## Platform code can use this to read and write the host's memory without
+## hardcoding sizes that drift away from the actual layouts.
+## ```roc
+## expect Box.sizeOf (Num.toU32 0) == 4
+## expect Box.sizeOf { a: 0u8, b: 0u64 } == 16
+## ```
+sizeOf : a -> U64
+
+## The alignment in bytes of values of this type. Like [sizeOf], it is worked
+## out at compile time for the target.
+## ```roc
+## expect Box.alignOf { a: 0u8, b: 0u64 } == 8
+## ```
+alignOf : a -> U64
+
 # # we'd need reset/reuse for box for this to be efficient
 # # that is currently not implemented
 # map : Box a, (a -> b) -> Box b
@@ -9,6 +9,7 @@ module [
     prepend,
     prependIfOk,
     map,
+    mapParallel,
     len,
     withCapacity,
     walkBackwards,
@@ -758,6 +759,23 @@ map = \list, mapper ->
         \state, elem ->
             List.appendUnsafe state (mapper elem)
 
+## Like [List.map], but splits the list into chunks and asks the platform to
+## convert the chunks on its own threads, through the `roc_parallel_for` hook.
+## The converted values are in the same order as the original elements.
+##
+## Only use this with platforms that provide `roc_parallel_for`; apps built for
+## other platforms will fail to link. The conversion function and everything it
+## captures are shared between threads, so their reference counts are updated
+## atomically. For short lists or cheap conversions, [List.map] is faster.
+##
+## Pass `--shuffle-parallel` with a seed to `roc test` or `roc build` to run the
+## chunks one at a time in a shuffled order, which makes code that depends on
+## the order of the chunks fail the same way on every run.
+## ```roc
+## expect List.mapParallel [1, 2, 3] (\num -> num + 1) == [2, 3, 4]
+## ```
+mapParallel : List a, (a -> b) -> List b
+
 ## Run a transformation function on the first element of each list,
 ## and use that as the first element in the returned list.
 ## Repeat until a list runs out of elements.
@@ -908,8 +926,7 @@ range = \{ start, end, step ? 0 } ->
                 else
                     \i -> i >= at
 
-            # TODO: switch to List.withCapacity
-            rangeHelp [] inclusiveStart calcNext isValid
+            rangeWithLength inclusiveStart calcNext isValid
 
         Before before ->
             isValid =
@@ -918,25 +935,28 @@ range = \{ start, end, step ? 0 } ->
                 else
                     \i -> i > before
 
-            # TODO: switch to List.withCapacity
-            rangeHelp [] inclusiveStart calcNext isValid
+            rangeWithLength inclusiveStart calcNext isValid
 
         Length l ->
             rangeLengthHelp (List.withCapacity l) inclusiveStart l calcNext
 
-rangeHelp = \accum, i, calcNext, isValid ->
+# Counting the elements first is cheap compared to growing the list, and lets us allocate it once
+rangeWithLength = \inclusiveStart, calcNext, isValid ->
+    length = rangeLength inclusiveStart calcNext isValid 0
+
+    rangeLengthHelp (List.withCapacity length) inclusiveStart length calcNext
+
+rangeLength = \i, calcNext, isValid, length ->
     when i is
         Ok val ->
             if isValid val then
-                # TODO: change this to List.appendUnsafe once capacity is set correctly
-                rangeHelp (List.append accum val) (calcNext val) calcNext isValid
+                rangeLength (calcNext val) calcNext isValid (Num.addWrap length 1)
             else
-                accum
+                length
 
         Err _ ->
             # We went past the end of the numeric range and there is no next.
-            # return the generated list.
-            accum
+            length
 
 rangeLengthHelp = \accum, i, remaining, calcNext ->
     if remaining == 0 then
@@ -159,6 +159,11 @@ module [
     nanF64,
     infinityF32,
     infinityF64,
+    RandomState,
+    splitMix64,
+    randomSeed,
+    randomU64,
+    randomF64,
 ]
 
 import Bool exposing [Bool]
@@ -1473,3 +1478,73 @@ infinityF32 = 1.0f32 / 0.0
 ## The value for infinity for a [F64] according to the IEEE 754 standard.
 infinityF64 : F64
 infinityF64 = 1.0f64 / 0.0
+
+## The state of a seedable pseudorandom number generator, which uses the
+## [xoshiro256**](https://prng.di.unimi.it/) algorithm.
+##
+## There is no hidden global generator. Every function that produces a random
+## value takes a [RandomState] and returns the next state alongside the value,
+## so the same seed always produces the same sequence of values:
+## ```roc
+## state0 = Num.randomSeed 42
+## { value: a, state: state1 } = Num.randomU64 state0
+## { value: b } = Num.randomU64 state1
+## ```
+## These generators are fast and statistically strong, but they are not
+## suitable for cryptographic use.
+RandomState := { s0 : U64, s1 : U64, s2 : U64, s3 : U64 }
+
+## Advances a [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator by one step.
+##
+## SplitMix64 keeps its entire state in a single [U64], which makes it convenient
+## for deriving seeds. [Num.randomSeed] uses it to expand one [U64] into a [RandomState].
+splitMix64 : U64 -> { state : U64, value : U64 }
+splitMix64 = \state ->
+    next = Num.addWrap state 0x9e3779b97f4a7c15u64
+    z1 = Num.mulWrap (Num.bitwiseXor next (Num.shiftRightZfBy next 30)) 0xbf58476d1ce4e5b9u64
+    z2 = Num.mulWrap (Num.bitwiseXor z1 (Num.shiftRightZfBy z1 27)) 0x94d049bb133111ebu64
+
+    { state: next, value: Num.bitwiseXor z2 (Num.shiftRightZfBy z2 31) }
+
+## Creates a [RandomState] from a seed. Equal seeds produce equal sequences.
+randomSeed : U64 -> RandomState
+randomSeed = \seed ->
+    a = splitMix64 seed
+    b = splitMix64 a.state
+    c = splitMix64 b.state
+    d = splitMix64 c.state
+
+    @RandomState { s0: a.value, s1: b.value, s2: c.value, s3: d.value }
+
+## Generates a [U64] where every value is equally likely.
+randomU64 : RandomState -> { state : RandomState, value : U64 }
+randomU64 = \@RandomState { s0, s1, s2, s3 } ->
+    value = Num.mulWrap (rotateLeft (Num.mulWrap s1 5) 7) 9
+    t = Num.shiftLeftBy s1 17
+
+    s2a = Num.bitwiseXor s2 s0
+    s3a = Num.bitwiseXor s3 s1
+    s1a = Num.bitwiseXor s1 s2a
+    s0a = Num.bitwiseXor s0 s3a
+
+    state = @RandomState {
+        s0: s0a,
+        s1: s1a,
+        s2: Num.bitwiseXor s2a t,
+        s3: rotateLeft s3a 45,
+    }
+
+    { state, value }
+
+## Generates a [F64] in the range `[0, 1)`, where every representable multiple
+## of 2⁻⁵³ in that range is equally likely.
+randomF64 : RandomState -> { state : RandomState, value : F64 }
+randomF64 = \state ->
+    { state: next, value } = randomU64 state
+
+    # the top 53 bits fit exactly in the mantissa of an F64
+    { state: next, value: Num.toF64 (Num.shiftRightZfBy value 11) / 9007199254740992.0f64 }
+
+rotateLeft : U64, U8 -> U64
+rotateLeft = \x, k ->
+    Num.bitwiseOr (Num.shiftLeftBy x k) (Num.shiftRightZfBy x (64 - k))
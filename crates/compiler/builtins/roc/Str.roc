@@ -365,6 +365,7 @@ module [
     reserve,
     releaseExcessCapacity,
     withCapacity,
+    appendUnchecked,
     withPrefix,
     contains,
     dropPrefix,
@@ -486,6 +487,24 @@ withCapacity : U64 -> Str
 ## If you plan to use [Str.reserve] on an empty string, it's generally better to use [Str.withCapacity] instead.
 reserve : Str, U64 -> Str
 
+## Appends the second string to the first, like [Str.concat], but trusts that nothing else refers to
+## the first string instead of checking its reference count.
+##
+## This is for building up a long string piece by piece, for example in a pretty-printer. Start
+## from [Str.withCapacity] and pass each result straight to the next [Str.appendUnchecked]:
+## ```roc
+## expect
+##     Str.withCapacity 16
+##     |> Str.appendUnchecked "Hello, "
+##     |> Str.appendUnchecked "World!"
+##     == "Hello, World!"
+## ```
+##
+## When the string runs out of capacity, this reallocates just like [Str.concat] does. If the
+## string _is_ shared, appending to one copy can change what another copy contains, so use
+## [Str.concat] unless you know the string is unique.
+appendUnchecked : Str, Str -> Str
+
 ## Combines a [List] of strings into a single string, with a separator
 ## string in between each.
 ## ```roc
@@ -48,7 +48,7 @@ impl FloatWidth {
         match self {
             F32 => 4,
             F64 => match target.architecture() {
-                X86_64 | Aarch64 | Wasm32 => 8,
+                X86_64 | Aarch64 | Riscv64 | Wasm32 => 8,
                 X86_32 | Aarch32 => 4,
             },
         }
@@ -122,6 +122,7 @@ impl IntWidth {
                 Architecture::X86_64
                 | Architecture::Aarch64
                 | Architecture::Aarch32
+                | Architecture::Riscv64
                 | Architecture::Wasm32 => 8,
                 Architecture::X86_32 => 4,
             },
@@ -132,7 +133,10 @@ impl IntWidth {
                 // however, rust does not always think that this is true
                 // Our alignmets here are correct, but they will not match rust/zig/llvm until they update to llvm version 18.
                 match target.architecture() {
-                    Architecture::X86_64 | Architecture::Aarch64 | Architecture::X86_32 => 16,
+                    Architecture::X86_64
+                    | Architecture::Aarch64
+                    | Architecture::Riscv64
+                    | Architecture::X86_32 => 16,
                     Architecture::Aarch32 | Architecture::Wasm32 => 8,
                 }
             }
@@ -366,9 +370,11 @@ pub const STR_TRIM_END: &str = "roc_builtins.str.trim_end";
 pub const STR_GET_UNSAFE: &str = "roc_builtins.str.get_unsafe";
 pub const STR_RESERVE: &str = "roc_builtins.str.reserve";
 pub const STR_CLONE_TO: &str = "roc_builtins.str.clone_to";
+pub const STR_DEEP_COPY: &str = "roc_builtins.str.deep_copy";
 pub const STR_WITH_CAPACITY: &str = "roc_builtins.str.with_capacity";
 pub const STR_ALLOCATION_PTR: &str = "roc_builtins.str.allocation_ptr";
 pub const STR_RELEASE_EXCESS_CAPACITY: &str = "roc_builtins.str.release_excess_capacity";
+pub const STR_APPEND_UNCHECKED: &str = "roc_builtins.str.append_unchecked";
 
 pub const LIST_MAP: &str = "roc_builtins.list.map";
 pub const LIST_MAP2: &str = "roc_builtins.list.map2";
@@ -379,11 +385,13 @@ pub const LIST_DROP_AT: &str = "roc_builtins.list.drop_at";
 pub const LIST_SWAP: &str = "roc_builtins.list.swap";
 pub const LIST_WITH_CAPACITY: &str = "roc_builtins.list.with_capacity";
 pub const LIST_SORT_WITH: &str = "roc_builtins.list.sort_with";
+pub const LIST_MAP_PARALLEL: &str = "roc_builtins.list.map_parallel";
 pub const LIST_CONCAT: &str = "roc_builtins.list.concat";
 pub const LIST_REPLACE: &str = "roc_builtins.list.replace";
 pub const LIST_REPLACE_IN_PLACE: &str = "roc_builtins.list.replace_in_place";
 pub const LIST_IS_UNIQUE: &str = "roc_builtins.list.is_unique";
 pub const LIST_CLONE: &str = "roc_builtins.list.clone";
+pub const LIST_DEEP_COPY: &str = "roc_builtins.list.deep_copy";
 pub const LIST_PREPEND: &str = "roc_builtins.list.prepend";
 pub const LIST_APPEND_UNSAFE: &str = "roc_builtins.list.append_unsafe";
 pub const LIST_RESERVE: &str = "roc_builtins.list.reserve";
@@ -393,6 +401,8 @@ pub const LIST_RELEASE_EXCESS_CAPACITY: &str = "roc_builtins.list.release_excess
 pub const LIST_CONCAT_UTF8: &str = "roc_builtins.list.concat_utf8";
 pub const LIST_INCREF: &str = "roc_builtins.list.incref";
 pub const LIST_DECREF: &str = "roc_builtins.list.decref";
+pub const LIST_INCREF_ATOMIC: &str = "roc_builtins.list.incref_atomic";
+pub const LIST_DECREF_ATOMIC: &str = "roc_builtins.list.decref_atomic";
 
 pub const DEC_ABS: &str = "roc_builtins.dec.abs";
 pub const DEC_ACOS: &str = "roc_builtins.dec.acos";
@@ -433,13 +443,19 @@ pub const UTILS_TEST_PANIC: &str = "roc_builtins.utils.test_panic";
 pub const UTILS_ALLOCATE_WITH_REFCOUNT: &str = "roc_builtins.utils.allocate_with_refcount";
 pub const UTILS_INCREF_RC_PTR: &str = "roc_builtins.utils.incref_rc_ptr";
 pub const UTILS_DECREF_RC_PTR: &str = "roc_builtins.utils.decref_rc_ptr";
+pub const UTILS_INCREF_RC_PTR_ATOMIC: &str = "roc_builtins.utils.incref_rc_ptr_atomic";
+pub const UTILS_DECREF_RC_PTR_ATOMIC: &str = "roc_builtins.utils.decref_rc_ptr_atomic";
 pub const UTILS_FREE_RC_PTR: &str = "roc_builtins.utils.free_rc_ptr";
 pub const UTILS_INCREF_DATA_PTR: &str = "roc_builtins.utils.incref_data_ptr";
 pub const UTILS_DECREF_DATA_PTR: &str = "roc_builtins.utils.decref_data_ptr";
 pub const UTILS_FREE_DATA_PTR: &str = "roc_builtins.utils.free_data_ptr";
 pub const UTILS_IS_UNIQUE: &str = "roc_builtins.utils.is_unique";
 pub const UTILS_DECREF_CHECK_NULL: &str = "roc_builtins.utils.decref_check_null";
+pub const UTILS_DECREF_CHECK_NULL_ATOMIC: &str = "roc_builtins.utils.decref_check_null_atomic";
+pub const UTILS_ATOMIC_REFCOUNTS: &str = "roc_builtins.utils.atomic_refcounts";
 pub const UTILS_DICT_PSEUDO_SEED: &str = "roc_builtins.utils.dict_pseudo_seed";
+pub const UTILS_PARALLEL_FOR_SEQUENTIAL: &str = "roc_builtins.utils.parallel_for_sequential";
+pub const UTILS_PARALLEL_FOR_SHUFFLED: &str = "roc_builtins.utils.parallel_for_shuffled";
 
 pub const UTILS_EXPECT_FAILED_START_SHARED_BUFFER: &str =
     "roc_builtins.utils.expect_failed_start_shared_buffer";
@@ -113,6 +113,17 @@ pub struct SpecializationId(NonZeroU32);
 
 static_assertions::assert_eq_size!(SpecializationId, Option<SpecializationId>);
 
+impl SpecializationId {
+    pub fn to_u32(self) -> u32 {
+        self.0.get()
+    }
+
+    /// The id that [SpecializationId::to_u32] returned `raw` for
+    pub fn from_u32(raw: u32) -> Option<Self> {
+        NonZeroU32::new(raw).map(Self)
+    }
+}
+
 pub enum SpecializationLambdaSetError {}
 
 /// A key into a particular implementation of an ability member for an opaque type.
@@ -130,6 +130,7 @@ map_symbol_to_lowlevel_and_arity! {
     StrToNum; STR_TO_NUM; 1,
     StrWithCapacity; STR_WITH_CAPACITY; 1,
     StrReleaseExcessCapacity; STR_RELEASE_EXCESS_CAPACITY; 1,
+    StrAppendUnchecked; STR_APPEND_UNCHECKED; 2,
 
     ListLenUsize; LIST_LEN_USIZE; 1,
     ListLenU64; LIST_LEN_U64; 1,
@@ -143,6 +144,7 @@ map_symbol_to_lowlevel_and_arity! {
     ListReplaceUnsafe; LIST_REPLACE_UNSAFE; 3,
     ListConcat; LIST_CONCAT; 2,
     ListSortWith; LIST_SORT_WITH; 2,
+    ListMapParallel; LIST_MAP_PARALLEL; 2,
     ListSublist; LIST_SUBLIST_LOWLEVEL; 3,
     ListDropAt; LIST_DROP_AT; 2,
     ListSwap; LIST_SWAP; 3,
@@ -217,6 +219,8 @@ map_symbol_to_lowlevel_and_arity! {
     Not; BOOL_NOT; 1,
     BoxExpr; BOX_BOX_FUNCTION; 1,
     UnboxExpr; BOX_UNBOX; 1,
+    SizeOf; BOX_SIZE_OF; 1,
+    AlignOf; BOX_ALIGN_OF; 1,
     Unreachable; LIST_UNREACHABLE; 1,
     DictPseudoSeed; DICT_PSEUDO_SEED; 1,
 }
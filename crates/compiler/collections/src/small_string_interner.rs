@@ -107,6 +107,27 @@ impl SmallStringInterner {
         }
     }
 
+    /// The parts that [SmallStringInterner::from_raw_parts] puts back together. Unlike with
+    /// [SmallStringInterner::from_parts], the length of a generated name is negative.
+    pub fn raw_parts(&self) -> (&[u8], &[i16], &[u32]) {
+        use std::slice::from_raw_parts;
+
+        let lengths = unsafe { from_raw_parts(self.lengths.as_ptr().cast(), self.lengths.len()) };
+
+        (&self.buffer, lengths, &self.offsets)
+    }
+
+    /// # Safety
+    ///
+    /// the parts must come from [SmallStringInterner::raw_parts]
+    pub unsafe fn from_raw_parts(buffer: Vec<u8>, lengths: Vec<i16>, offsets: Vec<u32>) -> Self {
+        Self {
+            buffer,
+            lengths: lengths.into_iter().map(Length).collect(),
+            offsets,
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &str> {
         (0..self.offsets.len()).map(move |index| self.get(index))
     }
@@ -158,6 +158,14 @@ flags! {
     /// Prints LLVM function verification output.
     ROC_PRINT_LLVM_FN_VERIFICATION
 
+    /// Checks that no refcounting or equality helper of the LLVM module can reach `roc_panic` or
+    /// a trap. The LLVM gen tests always check this.
+    ROC_VERIFY_TOTAL_HELPERS
+
+    /// Makes the LLVM gen tests also build their module for the pointer widths they don't run
+    /// with, to find code gen that only works for the width of the host.
+    ROC_CHECK_ALL_PTR_WIDTHS
+
     // ===WASM Gen===
 
     /// Writes a `final.wasm` file to /tmp
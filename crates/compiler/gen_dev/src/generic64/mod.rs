@@ -2355,6 +2355,15 @@ impl<
                 &higher_order.passed_function,
                 higher_order.closure_env_layout,
             ),
+            // the host's thread pool is not available here, so this maps the list sequentially
+            HigherOrder::ListMapParallel { .. } => CallerProc::new_list_map(
+                self.env.arena,
+                self.env.module_id,
+                ident_ids,
+                self.layout_interner,
+                &higher_order.passed_function,
+                higher_order.closure_env_layout,
+            ),
         };
 
         let caller = self.debug_symbol("caller");
@@ -2485,6 +2494,101 @@ impl<
                 self.free_symbol(&Symbol::DEV_TMP2);
                 self.free_symbol(&Symbol::DEV_TMP3);
             }
+            HigherOrder::ListMapParallel { xs } => {
+                let old_element_layout = argument_layouts[0];
+                let new_element_layout = higher_order.passed_function.return_layout;
+
+                let input_list_layout = LayoutRepr::Builtin(Builtin::List(old_element_layout));
+                let input_list_in_layout = self
+                    .layout_interner
+                    .insert_direct_no_semantic(input_list_layout);
+
+                let old_alignment = self.debug_symbol("old_alignment");
+                let old_element_width = self.debug_symbol("old_element_width");
+                let old_element_refcounted = self.debug_symbol("old_element_refcounted");
+                let new_alignment = self.debug_symbol("new_alignment");
+                let new_element_width = self.debug_symbol("new_element_width");
+                let new_element_refcounted = self.debug_symbol("new_element_refcounted");
+
+                self.load_layout_alignment(old_element_layout, old_alignment);
+                self.load_layout_stack_size(old_element_layout, old_element_width);
+                self.load_layout_refcounted(old_element_layout, old_element_refcounted);
+                self.load_layout_alignment(new_element_layout, new_alignment);
+                self.load_layout_stack_size(new_element_layout, new_element_width);
+                self.load_layout_refcounted(new_element_layout, new_element_refcounted);
+
+                self.build_fn_pointer(&caller, caller_string);
+
+                // we pass a null pointer when the data is not owned. the zig code must not call this!
+                let data_is_owned = higher_order.closure_env_layout.is_some()
+                    && higher_order.passed_function.owns_captured_environment;
+
+                self.load_literal(
+                    &Symbol::DEV_TMP2,
+                    &Layout::BOOL,
+                    &Literal::Bool(data_is_owned),
+                );
+
+                let inc_fn_ptr = self.increment_fn_pointer(old_element_layout);
+                let dec_fn_ptr = self.decrement_fn_pointer(old_element_layout);
+
+                //    input: RocList,
+                //    caller: Caller1,
+                //    data: Opaque,
+                //    inc_n_data: IncN,
+                //    data_is_owned: bool,
+                //    old_alignment: u32,
+                //    old_element_width: usize,
+                //    old_elements_refcounted: bool,
+                //    inc: Inc,
+                //    dec: Dec,
+                //    new_alignment: u32,
+                //    new_element_width: usize,
+                //    new_elements_refcounted: bool,
+
+                let arguments = [
+                    xs,
+                    caller,
+                    data,
+                    inc_n_data,
+                    Symbol::DEV_TMP2,
+                    old_alignment,
+                    old_element_width,
+                    old_element_refcounted,
+                    inc_fn_ptr,
+                    dec_fn_ptr,
+                    new_alignment,
+                    new_element_width,
+                    new_element_refcounted,
+                ];
+
+                let layouts = [
+                    input_list_in_layout,
+                    ptr,
+                    ptr,
+                    ptr,
+                    Layout::BOOL,
+                    Layout::U32,
+                    usize_,
+                    Layout::BOOL,
+                    usize_,
+                    usize_,
+                    Layout::U32,
+                    usize_,
+                    Layout::BOOL,
+                ];
+
+                self.build_fn_call_stack_return(
+                    bitcode::LIST_MAP.to_string(),
+                    &arguments,
+                    &layouts,
+                    ret_layout,
+                    *dst,
+                );
+
+                self.free_symbol(&Symbol::DEV_TMP);
+                self.free_symbol(&Symbol::DEV_TMP2);
+            }
         }
     }
 
@@ -1634,6 +1634,13 @@ trait Backend<'a> {
                 arg_layouts,
                 ret_layout,
             ),
+            LowLevel::StrAppendUnchecked => self.build_fn_call(
+                sym,
+                bitcode::STR_APPEND_UNCHECKED.to_string(),
+                args,
+                arg_layouts,
+                ret_layout,
+            ),
             LowLevel::StrJoinWith => self.build_fn_call(
                 sym,
                 bitcode::STR_JOIN_WITH.to_string(),
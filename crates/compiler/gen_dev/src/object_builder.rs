@@ -405,6 +405,7 @@ fn create_relocation(target: Target, symbol: SymbolId, offset: u64) -> write::Re
                 )
             }
         }
+        roc_target::Architecture::Riscv64 => todo!(),
         roc_target::Architecture::Wasm32 => todo!(),
         roc_target::Architecture::X86_32 => todo!(),
         roc_target::Architecture::X86_64 => (
@@ -4,11 +4,8 @@ use crate::llvm::build::{
     complex_bitcast_check_size, load_roc_value, to_cc_return, CCReturn, Env, C_CALL_CONV,
     FAST_CALL_CONV,
 };
-use crate::llvm::convert::basic_type_from_layout;
+use crate::llvm::convert::{basic_type_from_in_layout, basic_type_from_layout};
 use crate::llvm::memcpy::build_memcpy;
-use crate::llvm::refcounting::{
-    decrement_refcount_layout, increment_n_refcount_layout, increment_refcount_layout,
-};
 use inkwell::attributes::{Attribute, AttributeLoc};
 use inkwell::types::{BasicType, BasicTypeEnum, StructType};
 use inkwell::values::{
@@ -49,6 +46,19 @@ pub fn call_bitcode_fn<'ctx>(
         }
     }
 
+    if env.target.architecture() == roc_target::Architecture::Riscv64 {
+        // On riscv64 zig returns a i128 in two integer registers, as a [2 x i64]
+        let array_type = env.context.i64_type().array_type(2);
+        if ret.get_type() == array_type.into() {
+            return complex_bitcast_check_size(
+                env,
+                ret,
+                env.context.i128_type().into(),
+                "return_i128",
+            );
+        }
+    }
+
     ret
 }
 
@@ -144,11 +154,7 @@ pub fn call_bitcode_fn_fixing_for_convention<'a, 'ctx, 'env>(
         }
         CCReturn::ByPointer => {
             // We need to pass the return value by pointer.
-            let roc_return_type = basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(return_layout),
-            );
+            let roc_return_type = basic_type_from_in_layout(env, layout_interner, return_layout);
 
             let cc_return_type: BasicTypeEnum<'ctx> = bitcode_return_type.into();
 
@@ -280,9 +286,8 @@ fn build_transform_caller_help<'a, 'ctx>(
         bumpalo::collections::Vec::with_capacity_in(arguments.len(), env.arena);
 
     for (argument_ptr, layout) in arguments.iter().zip(argument_layouts) {
-        let basic_type =
-            basic_type_from_layout(env, layout_interner, layout_interner.get_repr(*layout))
-                .ptr_type(AddressSpace::default());
+        let basic_type = basic_type_from_in_layout(env, layout_interner, *layout)
+            .ptr_type(AddressSpace::default());
 
         let cast_ptr = env.builder.new_build_pointer_cast(
             argument_ptr.into_pointer_value(),
@@ -311,9 +316,8 @@ fn build_transform_caller_help<'a, 'ctx>(
             // the function doesn't expect a closure argument, nothing to add
         }
         (true, layout) => {
-            let closure_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout))
-                    .ptr_type(AddressSpace::default());
+            let closure_type = basic_type_from_in_layout(env, layout_interner, layout)
+                .ptr_type(AddressSpace::default());
 
             let closure_cast = env.builder.new_build_pointer_cast(
                 closure_ptr,
@@ -416,6 +420,12 @@ fn build_rc_wrapper<'a, 'ctx>(
         Mode::Inc => format!("{fn_name}_inc"),
         Mode::Dec => format!("{fn_name}_dec"),
     };
+    env.record_helper(
+        layout_interner,
+        &fn_name,
+        symbol,
+        layout_interner.get_repr(layout),
+    );
 
     let function_value = match env.module.get_function(fn_name.as_str()) {
         Some(function_value) => function_value,
@@ -455,8 +465,7 @@ fn build_rc_wrapper<'a, 'ctx>(
 
             generic_value_ptr.set_name(Symbol::ARG_1.as_str(&env.interns));
 
-            let value_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
+            let value_type = basic_type_from_in_layout(env, layout_interner, layout);
             let value_ptr_type = value_type.ptr_type(AddressSpace::default());
             let value_ptr = env.builder.new_build_pointer_cast(
                 generic_value_ptr,
@@ -477,16 +486,31 @@ fn build_rc_wrapper<'a, 'ctx>(
             match rc_operation {
                 Mode::Inc => {
                     let n = 1;
-                    increment_refcount_layout(env, layout_interner, layout_ids, n, value, layout);
+                    env.memory_strategy.increment(
+                        env,
+                        layout_interner,
+                        layout_ids,
+                        n,
+                        value,
+                        layout,
+                    );
                 }
                 Mode::IncN => {
                     let n = it.next().unwrap().into_int_value();
                     n.set_name(Symbol::ARG_2.as_str(&env.interns));
 
-                    increment_n_refcount_layout(env, layout_interner, layout_ids, n, value, layout);
+                    env.memory_strategy.increment_n(
+                        env,
+                        layout_interner,
+                        layout_ids,
+                        n,
+                        value,
+                        layout,
+                    );
                 }
                 Mode::Dec => {
-                    decrement_refcount_layout(env, layout_interner, layout_ids, value, layout);
+                    env.memory_strategy
+                        .decrement(env, layout_interner, layout_ids, value, layout);
                 }
             }
 
@@ -515,6 +539,12 @@ pub fn build_eq_wrapper<'a, 'ctx>(
     let fn_name = layout_ids
         .get(symbol, &layout_interner.get_repr(layout))
         .to_symbol_string(symbol, &env.interns);
+    env.record_helper(
+        layout_interner,
+        &fn_name,
+        symbol,
+        layout_interner.get_repr(layout),
+    );
 
     let function_value = match env.module.get_function(fn_name.as_str()) {
         Some(function_value) => function_value,
@@ -548,9 +578,8 @@ pub fn build_eq_wrapper<'a, 'ctx>(
             value_ptr1.set_name(Symbol::ARG_1.as_str(&env.interns));
             value_ptr2.set_name(Symbol::ARG_2.as_str(&env.interns));
 
-            let value_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout))
-                    .ptr_type(AddressSpace::default());
+            let value_type = basic_type_from_in_layout(env, layout_interner, layout)
+                .ptr_type(AddressSpace::default());
 
             let value_cast1 =
                 env.builder
@@ -651,8 +680,7 @@ pub fn build_compare_wrapper<'a, 'ctx>(
             value_ptr1.set_name(Symbol::ARG_2.as_str(&env.interns));
             value_ptr2.set_name(Symbol::ARG_3.as_str(&env.interns));
 
-            let value_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
+            let value_type = basic_type_from_in_layout(env, layout_interner, layout);
             let value_ptr_type = value_type.ptr_type(AddressSpace::default());
 
             let value_cast1 =
@@ -678,8 +706,10 @@ pub fn build_compare_wrapper<'a, 'ctx>(
                 "load_opaque",
             );
 
-            increment_refcount_layout(env, layout_interner, layout_ids, 1, value1, layout);
-            increment_refcount_layout(env, layout_interner, layout_ids, 1, value2, layout);
+            env.memory_strategy
+                .increment(env, layout_interner, layout_ids, 1, value1, layout);
+            env.memory_strategy
+                .increment(env, layout_interner, layout_ids, 1, value2, layout);
 
             let default = [value1.into(), value2.into()];
 
@@ -691,11 +721,8 @@ pub fn build_compare_wrapper<'a, 'ctx>(
                     &default
                 }
                 _ => {
-                    let closure_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(closure_data_repr),
-                    );
+                    let closure_type =
+                        basic_type_from_in_layout(env, layout_interner, closure_data_repr);
                     let closure_ptr_type = closure_type.ptr_type(AddressSpace::default());
 
                     let closure_cast = env.builder.new_build_pointer_cast(
@@ -750,6 +777,12 @@ pub fn build_copy_wrapper<'a, 'ctx>(
     let fn_name = layout_ids
         .get(symbol, &layout_interner.get_repr(layout))
         .to_symbol_string(symbol, &env.interns);
+    env.record_helper(
+        layout_interner,
+        &fn_name,
+        symbol,
+        layout_interner.get_repr(layout),
+    );
 
     let function_value = match env.module.get_function(fn_name.as_str()) {
         Some(function_value) => function_value,
@@ -1115,7 +1148,7 @@ pub(crate) fn call_str_bitcode_fn<'ctx>(
 
             returns.call_and_load_32bit(env, &arguments, fn_name)
         }
-        X86_64 | Aarch64 => {
+        X86_64 | Aarch64 | Riscv64 => {
             let capacity = other_arguments.len() + strings.len() + returns.additional_arguments();
             let mut arguments: Vec<BasicValueEnum> = Vec::with_capacity_in(capacity, env.arena);
 
@@ -1170,7 +1203,7 @@ pub(crate) fn call_void_list_bitcode_fn<'ctx>(
 
             call_void_bitcode_fn(env, &arguments, fn_name);
         }
-        X86_64 | Aarch64 => {
+        X86_64 | Aarch64 | Riscv64 => {
             let capacity = other_arguments.len() + lists.len();
             let mut arguments: Vec<BasicValueEnum> = Vec::with_capacity_in(capacity, env.arena);
 
@@ -1222,7 +1255,7 @@ pub(crate) fn call_list_bitcode_fn<'ctx>(
 
             returns.call_and_load_32bit(env, &arguments, fn_name)
         }
-        X86_64 | Aarch64 => {
+        X86_64 | Aarch64 | Riscv64 => {
             let capacity = other_arguments.len() + lists.len() + returns.additional_arguments();
             let mut arguments: Vec<BasicValueEnum> = Vec::with_capacity_in(capacity, env.arena);
 
@@ -1,17 +1,22 @@
-use crate::llvm::bitcode::{build_dec_wrapper, call_bitcode_fn, call_void_list_bitcode_fn};
-use crate::llvm::build_list::{
-    allocate_list, empty_polymorphic_list, layout_refcounted, layout_width,
-};
+use crate::llvm::bitcode::call_bitcode_fn;
+use crate::llvm::build_list::{allocate_list, empty_polymorphic_list, list_allocation_ptr};
+use crate::llvm::build_str::str_allocation_ptr;
 use crate::llvm::convert::{
-    argument_type_from_layout, basic_type_from_builtin, basic_type_from_layout, zig_str_type,
+    argument_type_from_layout, basic_type_from_builtin, basic_type_from_in_layout,
+    basic_type_from_layout, zig_str_type,
 };
+use crate::llvm::effect_mocks::call_effect_or_mock;
 use crate::llvm::expect::{clone_to_shared_memory, SharedMemoryPointer};
+use crate::llvm::heap_header::HeapHeader;
+use crate::llvm::helper_cache::HelperCache;
 use crate::llvm::memcpy::build_memcpy;
-use crate::llvm::refcounting::{
-    build_reset, decrement_refcount_layout, increment_refcount_layout, PointerToRefcount,
-};
+use crate::llvm::memory::MemoryStrategy;
+use crate::llvm::memory_api::MemoryApi;
+use crate::llvm::refcounting::{build_reset, PointerToRefcount, RcOutlining};
+use crate::llvm::sendable::SendableLayouts;
 use crate::llvm::struct_::{struct_from_fields, RocStruct};
-use crate::llvm::{erased, fn_ptr};
+use crate::llvm::symbol_map::{roc_name, HelperKind, SymbolMap, SymbolOrigin};
+use crate::llvm::{crash_dump, erased, fn_ptr};
 use bumpalo::collections::Vec;
 use bumpalo::Bump;
 use inkwell::attributes::{Attribute, AttributeLoc};
@@ -33,7 +38,7 @@ use inkwell::values::{
     FunctionValue, InstructionOpcode, InstructionValue, IntMathValue, IntValue, PhiValue,
     PointerMathValue, PointerValue, StructValue,
 };
-use inkwell::{AddressSpace, IntPredicate};
+use inkwell::{AddressSpace, AtomicOrdering, IntPredicate};
 use inkwell::{FloatPredicate, OptimizationLevel};
 use morphic_lib::{
     CalleeSpecVar, FuncName, FuncSpec, FuncSpecSolutions, ModSolutions, UpdateMode, UpdateModeVar,
@@ -741,9 +746,45 @@ pub struct Env<'a, 'ctx, 'env> {
     pub target: Target,
     pub mode: LlvmBackendMode,
     pub exposed_to_host: MutSet<Symbol>,
+    pub helper_cache: Option<&'env HelperCache>,
+    pub rc_outlining: RcOutlining,
+    pub memory_strategy: &'env dyn MemoryStrategy,
+    /// Layouts whose refcounts are updated atomically, because the host can share them
+    /// between threads
+    pub sendable_layouts: SendableLayouts<'a>,
+    /// Keep call stacks intact for sampling profilers like `perf` and Instruments
+    pub profiling: bool,
+    /// Report the call stack to the host before `roc_panic`; see [crash_dump]
+    pub crash_dumps: bool,
+    /// Expose functions to retain, release and copy the values that cross the host boundary;
+    /// see [memory_api](crate::llvm::memory_api)
+    pub memory_api: bool,
+    /// Run the tasks of List.mapParallel one after the other, in an order shuffled with this
+    /// seed, instead of on the host's thread pool
+    pub shuffle_parallel_tasks: Option<u64>,
+    /// Where the generated functions come from, when we write a symbol map
+    pub symbol_map: Option<&'env SymbolMap>,
+    pub basic_types: BasicTypeCache<'ctx>,
 }
 
 impl<'a, 'ctx, 'env> Env<'a, 'ctx, 'env> {
+    /// Remember that the helper `fn_name` works on `layout`, when we write a [SymbolMap]
+    pub fn record_helper(
+        &self,
+        layout_interner: &STLayoutInterner<'a>,
+        fn_name: &str,
+        helper: Symbol,
+        layout: LayoutRepr<'a>,
+    ) {
+        if let Some(symbol_map) = self.symbol_map {
+            symbol_map.record(fn_name, || SymbolOrigin::Helper {
+                helper: helper.as_str(&self.interns).to_string(),
+                kind: HelperKind::of(helper),
+                layout: layout_interner.dbg_repr(layout),
+            });
+        }
+    }
+
     /// The integer type representing a pointer
     ///
     /// on 64-bit systems, this is i64
@@ -905,6 +946,12 @@ impl<'a, 'ctx, 'env> Env<'a, 'ctx, 'env> {
             .new_build_call(function, &[msg.into(), tag_id.into()], "roc_panic");
 
         call.set_call_convention(C_CALL_CONV);
+
+        // blocks that panic are cold, so code generation moves them out of the hot path
+        let kind_id = Attribute::get_named_enum_kind_id("cold");
+        debug_assert!(kind_id > 0);
+        let enum_attr = self.context.create_enum_attribute(kind_id, 0);
+        call.add_attribute(AttributeLoc::Function, enum_attr);
     }
 
     pub fn call_dbg(
@@ -1009,6 +1056,21 @@ impl<'a, 'ctx, 'env> Env<'a, 'ctx, 'env> {
     }
 }
 
+/// Whether the zig builtins are available as bitcode for `target`. They are always built for the
+/// host, and prebuilt for a few other targets that we can cross-compile to.
+pub fn has_builtins_for(target: Target) -> bool {
+    target == target_lexicon::Triple::host().into()
+        || matches!(
+            target,
+            Target::Wasm32
+                | Target::LinuxX32
+                | Target::LinuxX64
+                | Target::LinuxArm64
+                | Target::LinuxRiscv64
+                | Target::WinX64
+        )
+}
+
 pub fn module_from_builtins<'ctx>(
     target: Target,
     ctx: &'ctx Context,
@@ -1032,6 +1094,9 @@ pub fn module_from_builtins<'ctx>(
             Target::LinuxArm64 => {
                 include_bytes!("../../../builtins/bitcode/zig-out/builtins-aarch64.bc")
             }
+            Target::LinuxRiscv64 => {
+                include_bytes!("../../../builtins/bitcode/zig-out/builtins-riscv64.bc")
+            }
             Target::WinX64 => {
                 include_bytes!("../../../builtins/bitcode/zig-out/builtins-windows-x86_64.bc")
             }
@@ -1060,6 +1125,23 @@ pub fn module_from_builtins<'ctx>(
         "floorf",
         "memcpy",
         "memset",
+        // From compiler-rt, needed when LLVM lowers a float math intrinsic to a call
+        "ceil",
+        "ceilf",
+        "cos",
+        "cosf",
+        "floor",
+        "log",
+        "logf",
+        "round",
+        "roundf",
+        "sin",
+        "sinf",
+        "sqrt",
+        "sqrtf",
+        // From the builtins, because compiler-rt has no `pow`
+        "pow",
+        "powf",
         // From compiler-rt
         "__divti3",
         "__modti3",
@@ -1113,6 +1195,9 @@ pub fn construct_optimization_passes<'a>(
 
     mpm.add_always_inliner_pass();
 
+    // inlining may leave helpers without callers; remove them before they are optimized
+    mpm.add_global_dce_pass();
+
     // tail-call elimination is always on
     fpm.add_instruction_combining_pass();
     fpm.add_tail_call_elimination_pass();
@@ -1130,6 +1215,9 @@ pub fn construct_optimization_passes<'a>(
             // TODO: For some usecase, like embedded, it is useful to expose this and tune it.
             // This really depends on if inlining causes enough simplifications to reduce code size.
             pmb.set_inliner_with_threshold(50);
+
+            // the zig builtins are part of this module, so this also applies to their loops
+            pmb.set_disable_unroll_loops(true);
         }
         OptLevel::Optimize => {
             pmb.set_optimization_level(OptimizationLevel::Aggressive);
@@ -1169,6 +1257,13 @@ pub fn construct_optimization_passes<'a>(
     pmb.populate_module_pass_manager(&mpm);
     pmb.populate_function_pass_manager(&fpm);
 
+    if matches!(opt_level, OptLevel::Size) {
+        // Many refcounting and equality helpers, and specializations of the same builtin, are
+        // identical after optimization. Keep only one copy of each.
+        mpm.add_merge_functions_pass();
+        mpm.add_global_dce_pass();
+    }
+
     fpm.initialize();
 
     // For now, we have just one of each
@@ -1358,7 +1453,10 @@ pub fn build_exp_literal<'a, 'ctx>(
             LayoutRepr::Builtin(Builtin::Int(int_width)) => {
                 int_with_precision(env, i128::from_ne_bytes(*bytes), int_width).into()
             }
-            _ => panic!("Invalid layout for int literal = {layout:?}"),
+            _ => panic!(
+                "Invalid layout for int literal = {}",
+                layout_interner.dbg(layout)
+            ),
         },
 
         U128(bytes) => const_u128(env, u128::from_ne_bytes(*bytes)).into(),
@@ -1367,7 +1465,10 @@ pub fn build_exp_literal<'a, 'ctx>(
             LayoutRepr::Builtin(Builtin::Float(float_width)) => {
                 float_with_precision(env, *float, float_width)
             }
-            _ => panic!("Invalid layout for float literal = {layout:?}"),
+            _ => panic!(
+                "Invalid layout for float literal = {}",
+                layout_interner.dbg(layout)
+            ),
         },
 
         Decimal(bytes) => {
@@ -1643,8 +1744,7 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
     match expr {
         Literal(literal) => build_exp_literal(env, layout_interner, layout, literal),
         NullPointer => {
-            let basic_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
+            let basic_type = basic_type_from_in_layout(env, layout_interner, layout);
 
             debug_assert!(basic_type.is_pointer_type());
             basic_type.into_pointer_type().const_zero().into()
@@ -1702,8 +1802,7 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
         ErasedLoad { symbol, field } => {
             let value = scope.load_symbol(symbol).into_struct_value();
             let wanted_llvm_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout))
-                    .into_pointer_type();
+                basic_type_from_in_layout(env, layout_interner, layout).into_pointer_type();
 
             erased::load(env, value, *field, wanted_llvm_type).into()
         }
@@ -1789,7 +1888,12 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
                 // If reset is used on a shared, non-reusable reference, it behaves
                 // like dec and returns NULL, which instructs reuse to behave like ctor
                 env.builder.position_at_end(else_block);
-                refcount_ptr.decrement(env, layout_interner, layout_interner.get_repr(layout));
+                env.memory_strategy.decrement_shared(
+                    env,
+                    layout_interner,
+                    refcount_ptr,
+                    layout_interner.get_repr(layout),
+                );
                 env.builder.new_build_unconditional_branch(cont_block);
             }
             {
@@ -1872,7 +1976,12 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
                 // If reset is used on a shared, non-reusable reference, it behaves
                 // like dec and returns NULL, which instructs reuse to behave like ctor
                 env.builder.position_at_end(not_unique_block);
-                refcount_ptr.decrement(env, layout_interner, layout_interner.get_repr(layout));
+                env.memory_strategy.decrement_shared(
+                    env,
+                    layout_interner,
+                    refcount_ptr,
+                    layout_interner.get_repr(layout),
+                );
                 env.builder.new_build_unconditional_branch(cont_block);
             }
             {
@@ -1929,12 +2038,8 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
                     let struct_type = basic_type_from_layout(env, layout_interner, struct_layout);
 
                     let opaque_data_ptr = env.builder.new_build_struct_gep(
-                        basic_type_from_layout(
-                            env,
-                            layout_interner,
-                            layout_interner.get_repr(structure_layout),
-                        )
-                        .into_struct_type(),
+                        basic_type_from_in_layout(env, layout_interner, structure_layout)
+                            .into_struct_type(),
                         argument.into_pointer_value(),
                         RocUnion::TAG_DATA_INDEX,
                         "get_opaque_data_ptr",
@@ -1967,11 +2072,8 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
                     let field_layouts = tag_layouts[*tag_id as usize];
 
                     let ptr = tag_pointer_clear_tag_id(env, argument.into_pointer_value());
-                    let target_loaded_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    );
+                    let target_loaded_type =
+                        basic_type_from_in_layout(env, layout_interner, layout);
 
                     lookup_at_index_ptr(
                         env,
@@ -1987,11 +2089,8 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
                     let struct_layout = LayoutRepr::struct_(field_layouts);
 
                     let struct_type = basic_type_from_layout(env, layout_interner, struct_layout);
-                    let target_loaded_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    );
+                    let target_loaded_type =
+                        basic_type_from_in_layout(env, layout_interner, layout);
 
                     lookup_at_index_ptr(
                         env,
@@ -2019,11 +2118,8 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
                     let field_layouts = other_tags[tag_index as usize];
 
                     let ptr = tag_pointer_clear_tag_id(env, argument.into_pointer_value());
-                    let target_loaded_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    );
+                    let target_loaded_type =
+                        basic_type_from_in_layout(env, layout_interner, layout);
 
                     lookup_at_index_ptr(
                         env,
@@ -2046,11 +2142,8 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
                     let struct_layout = LayoutRepr::struct_(field_layouts);
 
                     let struct_type = basic_type_from_layout(env, layout_interner, struct_layout);
-                    let target_loaded_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    );
+                    let target_loaded_type =
+                        basic_type_from_in_layout(env, layout_interner, layout);
 
                     lookup_at_index_ptr(
                         env,
@@ -2187,11 +2280,7 @@ pub(crate) fn build_exp_expr<'a, 'ctx>(
             initializer,
             element_layout,
         } => {
-            let element_type = basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(*element_layout),
-            );
+            let element_type = basic_type_from_in_layout(env, layout_interner, *element_layout);
             let ptr = create_entry_block_alloca(env, element_type, "stack_value");
 
             if let Some(initializer) = initializer {
@@ -2244,12 +2333,8 @@ fn build_wrapped_tag<'a, 'ctx>(
             "tag_id_index",
         );
 
-        let tag_id_type = basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(tag_id_layout),
-        )
-        .into_int_type();
+        let tag_id_type =
+            basic_type_from_in_layout(env, layout_interner, tag_id_layout).into_int_type();
 
         env.builder
             .new_build_store(tag_id_ptr, tag_id_type.const_int(tag_id as u64, false));
@@ -2331,11 +2416,7 @@ fn build_tag_fields<'a, 'r, 'ctx, 'env>(
     let mut field_values = std::vec::Vec::with_capacity(capacity);
 
     for (field_symbol, tag_field_layout) in arguments.iter().zip(fields.iter()) {
-        let field_type = basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(*tag_field_layout),
-        );
+        let field_type = basic_type_from_in_layout(env, layout_interner, *tag_field_layout);
         field_types.push(field_type);
 
         let raw_value: BasicValueEnum<'ctx> = scope.load_symbol(field_symbol);
@@ -2663,12 +2744,8 @@ pub fn get_tag_id<'a, 'ctx>(
     argument: BasicValueEnum<'ctx>,
 ) -> IntValue<'ctx> {
     let tag_id_layout = union_layout.tag_id_layout();
-    let tag_id_int_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(tag_id_layout),
-    )
-    .into_int_type();
+    let tag_id_int_type =
+        basic_type_from_in_layout(env, layout_interner, tag_id_layout).into_int_type();
 
     match union_layout {
         UnionLayout::NonRecursive(_) => {
@@ -2829,7 +2906,7 @@ fn union_field_ptr_at_index<'a, 'ctx>(
     cast_if_necessary_for_opaque_recursive_pointers(env, from_value, to_type).into_pointer_value()
 }
 
-fn reserve_with_refcount_union_as_block_of_memory<'a, 'ctx>(
+pub(crate) fn reserve_with_refcount_union_as_block_of_memory<'a, 'ctx>(
     env: &Env<'a, 'ctx, '_>,
     layout_interner: &STLayoutInterner<'a>,
     union_layout: UnionLayout<'a>,
@@ -2843,57 +2920,45 @@ fn reserve_with_refcount_union_as_block_of_memory<'a, 'ctx>(
         RocUnion::untagged_from_slices(layout_interner, env.context, fields)
     };
 
-    reserve_union_with_refcount_help(
-        env,
-        roc_union.struct_type(),
-        roc_union.tag_width(),
-        roc_union.tag_alignment(),
-    )
-}
+    let number_of_data_bytes = env.ptr_int().const_int(roc_union.tag_width() as u64, false);
 
-fn reserve_union_with_refcount_help<'a, 'ctx, 'env>(
-    env: &Env<'a, 'ctx, 'env>,
-    basic_type: impl BasicType<'ctx>,
-    stack_size: u32,
-    alignment_bytes: u32,
-) -> PointerValue<'ctx> {
-    let len_type = env.ptr_int();
+    let header = HeapHeader::refcount_only(env.target);
+    let ptr = allocate_with_refcount(env, number_of_data_bytes, roc_union.tag_alignment(), header);
 
-    let value_bytes_intvalue = len_type.const_int(stack_size as u64, false);
+    let ptr_type = roc_union.struct_type().ptr_type(AddressSpace::default());
 
-    // elem_refcounted does not apply to unions, only lists.
-    let elem_refcounted = env.context.bool_type().const_zero().into();
-    allocate_with_refcount_help(
-        env,
-        basic_type,
-        alignment_bytes,
-        value_bytes_intvalue,
-        elem_refcounted,
-    )
+    env.builder
+        .new_build_pointer_cast(ptr, ptr_type, "alloc_cast_to_desired")
 }
 
-pub fn allocate_with_refcount_help<'a, 'ctx, 'env>(
-    env: &Env<'a, 'ctx, 'env>,
-    value_type: impl BasicType<'ctx>,
-    alignment_bytes: u32,
+/// Allocate refcounted memory for `number_of_data_bytes` of data, and return a pointer to the
+/// data. The refcount starts at one.
+///
+/// All refcounted memory that the generated code allocates comes from here: lists, boxes and
+/// recursive tag unions (strings and dicts are allocated by the builtins, which use the same zig
+/// function). `allocateWithRefcount` in `utils.zig` writes the `header` in front of the data, and
+/// pads over-aligned data by hand.
+pub fn allocate_with_refcount<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
     number_of_data_bytes: IntValue<'ctx>,
-    elem_refcounted: BasicValueEnum<'ctx>,
+    alignment_bytes: u32,
+    header: HeapHeader,
 ) -> PointerValue<'ctx> {
-    let ptr = call_bitcode_fn(
+    let elements_refcounted = env
+        .context
+        .bool_type()
+        .const_int(header.elements_refcounted() as u64, false);
+
+    call_bitcode_fn(
         env,
         &[
             number_of_data_bytes.into(),
             env.alignment_const(alignment_bytes).into(),
-            elem_refcounted,
+            elements_refcounted.into(),
         ],
         roc_builtins::bitcode::UTILS_ALLOCATE_WITH_REFCOUNT,
     )
-    .into_pointer_value();
-
-    let ptr_type = value_type.ptr_type(AddressSpace::default());
-
-    env.builder
-        .new_build_pointer_cast(ptr, ptr_type, "alloc_cast_to_desired")
+    .into_pointer_value()
 }
 
 fn list_literal<'a, 'ctx>(
@@ -2906,11 +2971,7 @@ fn list_literal<'a, 'ctx>(
     let ctx = env.context;
     let builder = env.builder;
 
-    let element_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(element_layout),
-    );
+    let element_type = basic_type_from_in_layout(env, layout_interner, element_layout);
 
     let list_length = elems.len();
     let list_length_intval = env.ptr_int().const_int(list_length as _, false);
@@ -2931,23 +2992,9 @@ fn list_literal<'a, 'ctx>(
             .alignment_bytes(element_layout)
             .max(env.target.ptr_width() as u32);
 
-        let refcount_slot_bytes = alignment as usize;
-
-        let refcount_slot_elements =
-            (refcount_slot_bytes as f64 / element_width as f64).ceil() as usize;
-
-        let data_bytes = list_length * element_width as usize;
-
-        assert!(refcount_slot_elements > 0);
-
         let global = if element_type.is_int_type() {
             let element_type = element_type.into_int_type();
-            let mut bytes = Vec::with_capacity_in(refcount_slot_elements + data_bytes, env.arena);
-
-            // Fill the refcount slot with nulls
-            for _ in 0..(refcount_slot_elements) {
-                bytes.push(element_type.const_zero());
-            }
+            let mut bytes = Vec::with_capacity_in(list_length, env.arena);
 
             // Copy the elements from the list literal into the array
             for element in elems.iter() {
@@ -2963,12 +3010,7 @@ fn list_literal<'a, 'ctx>(
             global
         } else if element_type.is_float_type() {
             let element_type = element_type.into_float_type();
-            let mut bytes = Vec::with_capacity_in(refcount_slot_elements + data_bytes, env.arena);
-
-            // Fill the refcount slot with nulls
-            for _ in 0..(refcount_slot_elements) {
-                bytes.push(element_type.const_zero());
-            }
+            let mut bytes = Vec::with_capacity_in(list_length, env.arena);
 
             // Copy the elements from the list literal into the array
             for element in elems.iter() {
@@ -2991,21 +3033,10 @@ fn list_literal<'a, 'ctx>(
         global.set_unnamed_addr(true);
         global.set_linkage(inkwell::module::Linkage::Private);
 
-        // Refcount the global itself in a new allocation.
-        // Necessary because morphic MAY attempt to update the global in-place, which is
-        // illegal if the global is in the read-only section.
-        let with_rc_ptr = global.as_pointer_value();
-
-        let const_data_ptr = unsafe {
-            env.builder.new_build_in_bounds_gep(
-                element_type,
-                with_rc_ptr,
-                &[env
-                    .ptr_int()
-                    .const_int(refcount_slot_elements as u64, false)],
-                "get_data_ptr",
-            )
-        };
+        // Copy the global into a new allocation, which has the refcount. Necessary because morphic
+        // MAY attempt to update the list in-place, which is illegal if the global is in the
+        // read-only section. The global itself is never refcounted, so it needs no header.
+        let const_data_ptr = global.as_pointer_value();
 
         let data_ptr = allocate_list(env, layout_interner, element_layout, list_length_intval);
 
@@ -3096,8 +3127,7 @@ pub fn store_roc_value_opaque<'a, 'ctx>(
     value: BasicValueEnum<'ctx>,
 ) {
     let target_type =
-        basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout))
-            .ptr_type(AddressSpace::default());
+        basic_type_from_in_layout(env, layout_interner, layout).ptr_type(AddressSpace::default());
     let destination = env.builder.new_build_pointer_cast(
         opaque_destination,
         target_type,
@@ -3225,8 +3255,7 @@ pub(crate) fn build_exp_stmt<'a, 'ctx>(
             cond_layout,
             cond_symbol,
         } => {
-            let ret_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(*ret_layout));
+            let ret_type = basic_type_from_in_layout(env, layout_interner, *ret_layout);
 
             let switch_args = SwitchArgsIr {
                 cond_layout: *cond_layout,
@@ -3264,11 +3293,7 @@ pub(crate) fn build_exp_stmt<'a, 'ctx>(
                 builder.position_at_end(cont_block);
 
                 for param in parameters.iter() {
-                    let basic_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(param.layout),
-                    );
+                    let basic_type = basic_type_from_in_layout(env, layout_interner, param.layout);
 
                     use crate::llvm::scope::JoinPointArg::*;
                     let joinpoint_arg = if layout_interner.is_passed_by_reference(param.layout) {
@@ -3352,11 +3377,7 @@ pub(crate) fn build_exp_stmt<'a, 'ctx>(
 
                         // Instead we copy to a tmp alloca here.
                         // After we have all tmp allocas, we copy those to the final output.
-                        let basic_type = basic_type_from_layout(
-                            env,
-                            layout_interner,
-                            layout_interner.get_repr(layout),
-                        );
+                        let basic_type = basic_type_from_in_layout(env, layout_interner, layout);
                         let tmp = create_entry_block_alloca(env, basic_type, "tmp_output_for_jmp");
                         builder
                             .build_memcpy(
@@ -3395,11 +3416,13 @@ pub(crate) fn build_exp_stmt<'a, 'ctx>(
         Refcounting(modify, cont) => {
             use ModifyRc::*;
 
+            let strategy = env.memory_strategy;
+
             match modify {
                 Inc(symbol, inc_amount) => {
                     let (value, layout) = scope.load_symbol_and_layout(symbol);
                     if layout_interner.contains_refcounted(layout) {
-                        increment_refcount_layout(
+                        strategy.increment(
                             env,
                             layout_interner,
                             layout_ids,
@@ -3408,149 +3431,36 @@ pub(crate) fn build_exp_stmt<'a, 'ctx>(
                             layout,
                         );
                     }
-
-                    build_exp_stmt(
-                        env,
-                        layout_interner,
-                        layout_ids,
-                        func_spec_solutions,
-                        scope,
-                        parent,
-                        cont,
-                    )
                 }
                 Dec(symbol) => {
                     let (value, layout) = scope.load_symbol_and_layout(symbol);
 
                     if layout_interner.contains_refcounted(layout) {
-                        decrement_refcount_layout(env, layout_interner, layout_ids, value, layout);
+                        strategy.decrement(env, layout_interner, layout_ids, value, layout);
                     }
-
-                    build_exp_stmt(
-                        env,
-                        layout_interner,
-                        layout_ids,
-                        func_spec_solutions,
-                        scope,
-                        parent,
-                        cont,
-                    )
                 }
                 DecRef(symbol) => {
                     let (value, layout) = scope.load_symbol_and_layout(symbol);
 
-                    match layout_interner.runtime_representation(layout) {
-                        LayoutRepr::Builtin(Builtin::Str) => todo!(),
-                        LayoutRepr::Builtin(Builtin::List(element_layout)) => {
-                            debug_assert!(value.is_struct_value());
-                            let dec_element_fn =
-                                build_dec_wrapper(env, layout_interner, layout_ids, element_layout);
-                            call_void_list_bitcode_fn(
-                                env,
-                                &[value.into_struct_value()],
-                                &[
-                                    env.alignment_intvalue(layout_interner, element_layout),
-                                    layout_width(env, layout_interner, element_layout),
-                                    layout_refcounted(env, layout_interner, element_layout),
-                                    dec_element_fn.as_global_value().as_pointer_value().into(),
-                                ],
-                                bitcode::LIST_DECREF,
-                            )
-                        }
-
-                        other_layout if other_layout.is_refcounted(layout_interner) => {
-                            if value.is_pointer_value() {
-                                let clear_tag_id = match other_layout {
-                                    LayoutRepr::Union(union_layout) => {
-                                        union_layout.stores_tag_id_in_pointer(env.target)
-                                    }
-                                    _ => false,
-                                };
-
-                                let value_ptr = if clear_tag_id {
-                                    tag_pointer_clear_tag_id(env, value.into_pointer_value())
-                                } else {
-                                    value.into_pointer_value()
-                                };
-
-                                let then_block = env.context.append_basic_block(parent, "then");
-                                let done_block = env.context.append_basic_block(parent, "done");
-
-                                let condition = env
-                                    .builder
-                                    .new_build_is_not_null(value_ptr, "box_is_not_null");
-                                env.builder.new_build_conditional_branch(
-                                    condition, then_block, done_block,
-                                );
-
-                                {
-                                    env.builder.position_at_end(then_block);
-                                    let refcount_ptr =
-                                        PointerToRefcount::from_ptr_to_data(env, value_ptr);
-                                    refcount_ptr.decrement(
-                                        env,
-                                        layout_interner,
-                                        layout_interner.get_repr(layout),
-                                    );
-
-                                    env.builder.new_build_unconditional_branch(done_block);
-                                }
-
-                                env.builder.position_at_end(done_block);
-                            } else {
-                                eprint!("we're likely leaking memory; see issue #985 for details");
-                            }
-                        }
-                        _ => {
-                            // nothing to do
-                        }
-                    }
-
-                    build_exp_stmt(
-                        env,
-                        layout_interner,
-                        layout_ids,
-                        func_spec_solutions,
-                        scope,
-                        parent,
-                        cont,
-                    )
+                    strategy.decref(env, layout_interner, layout_ids, parent, value, layout);
                 }
-
                 Free(symbol) => {
                     // unconditionally deallocate the symbol
                     let (value, layout) = scope.load_symbol_and_layout(symbol);
-                    let alignment = layout_interner.allocation_alignment_bytes(layout);
-
-                    debug_assert!(value.is_pointer_value());
-                    let value = value.into_pointer_value();
-
-                    let runtime_layout = layout_interner.runtime_representation(layout);
-                    let clear_tag_id = match runtime_layout {
-                        LayoutRepr::Union(union) => union.stores_tag_id_in_pointer(env.target),
-                        _ => false,
-                    };
-
-                    let ptr = if clear_tag_id {
-                        tag_pointer_clear_tag_id(env, value)
-                    } else {
-                        value
-                    };
 
-                    let rc_ptr = PointerToRefcount::from_ptr_to_data(env, ptr);
-                    rc_ptr.deallocate(env, alignment, runtime_layout);
-
-                    build_exp_stmt(
-                        env,
-                        layout_interner,
-                        layout_ids,
-                        func_spec_solutions,
-                        scope,
-                        parent,
-                        cont,
-                    )
+                    strategy.free(env, layout_interner, value, layout);
                 }
             }
+
+            build_exp_stmt(
+                env,
+                layout_interner,
+                layout_ids,
+                func_spec_solutions,
+                scope,
+                parent,
+                cont,
+            )
         }
 
         Dbg {
@@ -4024,11 +3934,7 @@ fn get_tag_id_wrapped<'a, 'ctx>(
     from_value: PointerValue<'ctx>,
 ) -> IntValue<'ctx> {
     let union_struct_type = struct_type_from_union_layout(env, layout_interner, &union_layout);
-    let tag_id_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(union_layout.tag_id_layout()),
-    );
+    let tag_id_type = basic_type_from_in_layout(env, layout_interner, union_layout.tag_id_layout());
 
     let tag_id_ptr = env.builder.new_build_struct_gep(
         union_struct_type,
@@ -4113,13 +4019,11 @@ fn build_switch_ir<'a, 'ctx>(
     let (cond_value, stored_layout) = scope.load_symbol_and_layout(cond_symbol);
 
     debug_assert_eq!(
-        basic_type_from_layout(env, layout_interner, layout_interner.get_repr(cond_layout)),
-        basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(stored_layout)
-        ),
-        "This switch matches on {cond_layout:?}, but the matched-on symbol {cond_symbol:?} has layout {stored_layout:?}"
+        basic_type_from_in_layout(env, layout_interner, cond_layout),
+        basic_type_from_in_layout(env, layout_interner, stored_layout),
+        "This switch matches on {}, but the matched-on symbol {cond_symbol:?} has layout {}",
+        layout_interner.dbg(cond_layout),
+        layout_interner.dbg(stored_layout)
     );
 
     let cont_block = context.append_basic_block(parent, "cont");
@@ -4145,7 +4049,10 @@ fn build_switch_ir<'a, 'ctx>(
             get_tag_id(env, layout_interner, parent, &variant, cond_value)
         }
         LayoutRepr::Builtin(_) => cond_value.into_int_value(),
-        other => todo!("Build switch value from layout: {:?}", other),
+        other => todo!(
+            "Build switch value from layout: {}",
+            layout_interner.dbg_repr(other)
+        ),
     };
 
     // Build the cases
@@ -4549,11 +4456,7 @@ fn expose_function_to_host_help_c_abi_gen_test<'a, 'ctx>(
     // does not seem to be a smarter solution
     let wrapper_return_type = roc_call_result_type(
         env,
-        basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(return_layout),
-        ),
+        basic_type_from_in_layout(env, layout_interner, return_layout),
     );
 
     let mut cc_argument_types = Vec::with_capacity_in(arguments.len(), env.arena);
@@ -4625,8 +4528,12 @@ fn expose_function_to_host_help_c_abi_gen_test<'a, 'ctx>(
                     arguments_for_call.push(cast);
                 }
                 _ => {
-                    let cast =
-                        complex_bitcast_check_size(env, *arg, fastcc_type, "to_fastcc_type_1");
+                    let cast = convert_between_calling_conventions(
+                        env,
+                        *arg,
+                        fastcc_type,
+                        "to_fastcc_type_1",
+                    );
                     arguments_for_call.push(cast);
                 }
             }
@@ -4720,17 +4627,15 @@ fn expose_function_to_host_help_c_abi_v2<'a, 'ctx>(
         .map(|l| to_cc_type(env, layout_interner, *l));
     let argument_types = Vec::from_iter_in(it, env.arena);
 
-    let return_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(return_layout),
-    );
+    let return_type = basic_type_from_in_layout(env, layout_interner, return_layout);
 
     let cc_return = to_cc_return(env, layout_interner, return_layout);
+    let cc_return_type = to_cc_return_type(env, layout_interner, return_layout);
     let roc_return =
         RocReturn::from_layout(layout_interner, layout_interner.get_repr(return_layout));
 
-    let c_function_spec = FunctionSpec::cconv(env, cc_return, Some(return_type), &argument_types);
+    let c_function_spec =
+        FunctionSpec::cconv(env, cc_return, Some(cc_return_type), &argument_types);
 
     let c_function = add_func(
         env.context,
@@ -4753,7 +4658,8 @@ fn expose_function_to_host_help_c_abi_v2<'a, 'ctx>(
     );
 
     // a temporary solution to be able to pass RocStr by-value from a host language.
-    {
+    // The Windows x64 and RISC-V ABIs never pass arguments byval.
+    if !matches!(env.target, Target::WinX64 | Target::LinuxRiscv64) {
         let extra = match cc_return {
             CCReturn::Return => 0,
             CCReturn::ByPointer => 1,
@@ -4862,7 +4768,8 @@ fn expose_function_to_host_help_c_abi_v2<'a, 'ctx>(
                     if matches!(
                         env.target.architecture(),
                         roc_target::Architecture::X86_32 | roc_target::Architecture::X86_64
-                    ) {
+                    ) && env.target != Target::WinX64
+                    {
                         let c_abi_type = match layout_interner.get_repr(*layout) {
                             LayoutRepr::Builtin(Builtin::Str | Builtin::List(_)) => {
                                 c_abi_roc_str_type
@@ -4893,7 +4800,7 @@ fn expose_function_to_host_help_c_abi_v2<'a, 'ctx>(
                     env.builder
                         .new_build_load(*fastcc_type, fastcc_ptr, "load_arg")
                 } else {
-                    complex_bitcast_check_size(env, *arg, *fastcc_type, "to_fastcc_type_2")
+                    convert_between_calling_conventions(env, *arg, *fastcc_type, "to_fastcc_type_2")
                 }
             }
         });
@@ -4909,19 +4816,20 @@ fn expose_function_to_host_help_c_abi_v2<'a, 'ctx>(
     );
 
     match cc_return {
-        CCReturn::Return => match roc_return {
-            RocReturn::Return => {
-                env.builder.new_build_return(Some(&value));
-            }
-            RocReturn::ByPointer => {
-                let loaded = env.builder.new_build_load(
+        CCReturn::Return => {
+            let value = match roc_return {
+                RocReturn::Return => value,
+                RocReturn::ByPointer => env.builder.new_build_load(
                     return_type,
                     value.into_pointer_value(),
                     "load_result",
-                );
-                env.builder.new_build_return(Some(&loaded));
-            }
-        },
+                ),
+            };
+
+            let value =
+                convert_between_calling_conventions(env, value, cc_return_type, "to_cc_return");
+            env.builder.new_build_return(Some(&value));
+        }
         CCReturn::ByPointer => {
             let out_ptr = c_function.get_nth_param(0).unwrap().into_pointer_value();
             match roc_return {
@@ -5026,11 +4934,7 @@ fn expose_function_to_host_help_c_abi<'a, 'ctx>(
         }
 
         LlvmBackendMode::Binary | LlvmBackendMode::BinaryDev | LlvmBackendMode::BinaryGlue => {
-            basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(return_layout),
-            )
+            basic_type_from_in_layout(env, layout_interner, return_layout)
         }
     };
 
@@ -5191,11 +5095,7 @@ fn set_jump_and_catch_long_jump<'a, 'ctx>(
     let context = env.context;
     let builder = env.builder;
 
-    let return_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(roc_return_layout),
-    );
+    let return_type = basic_type_from_in_layout(env, layout_interner, roc_return_layout);
     let call_result_return_conv = {
         let layout = roc_call_result_layout(env.arena, roc_return_layout);
         RocReturn::from_layout(layout_interner, layout)
@@ -5330,19 +5230,11 @@ fn make_good_roc_result<'a, 'ctx>(
     let context = env.context;
     let builder = env.builder;
 
-    let return_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(return_layout),
-    );
+    let return_type = basic_type_from_in_layout(env, layout_interner, return_layout);
 
     let v1 = roc_call_result_type(
         env,
-        basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(return_layout),
-        ),
+        basic_type_from_in_layout(env, layout_interner, return_layout),
     )
     .const_zero();
 
@@ -5385,11 +5277,7 @@ fn make_exception_catching_wrapper<'a, 'ctx>(
 
     let wrapper_return_type = roc_call_result_type(
         env,
-        basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(return_layout),
-        ),
+        basic_type_from_in_layout(env, layout_interner, return_layout),
     );
 
     let roc_function_type = roc_function.get_type();
@@ -5532,14 +5420,13 @@ pub fn build_procedures<'a>(
     debug_output_file: Option<&Path>,
     glue_layouts: &GlueLayouts<'a>,
 ) {
-    let mod_solutions = build_procedures_help(
+    let (mod_solutions, built) = build_procedures_help(
         env,
         layout_interner,
         opt_level,
         procedures,
         host_exposed_lambda_sets,
         entry_point,
-        debug_output_file,
     );
 
     let niche = Niche::NONE;
@@ -5577,6 +5464,68 @@ pub fn build_procedures<'a>(
             getter_name,
         );
     }
+
+    finish_procedures(env, opt_level, &built, debug_output_file);
+
+    if env.target.operating_system() == roc_target::OperatingSystem::Windows {
+        add_unwind_tables(env);
+    }
+
+    if env.profiling || env.crash_dumps {
+        add_frame_pointers(env);
+    }
+
+    if let OptLevel::Size = opt_level {
+        add_size_attributes(env);
+    }
+}
+
+/// Windows needs unwind information for every function that is not a leaf. Without it, walking
+/// the stack through roc code fails, e.g. when the host uses structured exception handling, or
+/// in the debugger.
+fn add_unwind_tables(env: &Env<'_, '_, '_>) {
+    let kind_id = Attribute::get_named_enum_kind_id("uwtable");
+    debug_assert!(kind_id > 0);
+
+    // 2 means asynchronous unwind tables, which is what MSVC and clang emit for Windows
+    let attribute = env.context.create_enum_attribute(kind_id, 2);
+
+    for function in env.module.get_functions() {
+        if function.count_basic_blocks() > 0 {
+            function.add_attribute(AttributeLoc::Function, attribute);
+        }
+    }
+}
+
+/// Profilers and crash dumps walk the stack by following frame pointers, so every function must
+/// keep one. That includes the zig builtins, which were compiled without them.
+fn add_frame_pointers(env: &Env<'_, '_, '_>) {
+    let attribute = env.context.create_string_attribute("frame-pointer", "all");
+
+    for function in env.module.get_functions() {
+        if function.count_basic_blocks() > 0 {
+            function.add_attribute(AttributeLoc::Function, attribute);
+        }
+    }
+}
+
+/// The size level of the pass manager only affects the optimization passes; code generation (and
+/// some passes) look at these attributes instead, like they would for clang's `-Oz`.
+fn add_size_attributes(env: &Env<'_, '_, '_>) {
+    let attributes = ["optsize", "minsize"].map(|name| {
+        let kind_id = Attribute::get_named_enum_kind_id(name);
+        debug_assert!(kind_id > 0);
+
+        env.context.create_enum_attribute(kind_id, 0)
+    });
+
+    for function in env.module.get_functions() {
+        if function.count_basic_blocks() > 0 {
+            for attribute in attributes {
+                function.add_attribute(AttributeLoc::Function, attribute);
+            }
+        }
+    }
 }
 
 pub fn build_wasm_test_wrapper<'a, 'ctx>(
@@ -5586,23 +5535,27 @@ pub fn build_wasm_test_wrapper<'a, 'ctx>(
     procedures: MutMap<(Symbol, ProcLayout<'a>), roc_mono::ir::Proc<'a>>,
     entry_point: SingleEntryPoint<'a>,
 ) -> (&'static str, FunctionValue<'ctx>) {
-    let mod_solutions = build_procedures_help(
+    let (mod_solutions, built) = build_procedures_help(
         env,
         layout_interner,
         opt_level,
         procedures,
         vec![],
         EntryPoint::Program(env.arena.alloc([entry_point])),
-        Some(&std::env::temp_dir().join("test.ll")),
     );
 
-    promote_to_wasm_test_wrapper(
+    let wrapper = promote_to_wasm_test_wrapper(
         env,
         layout_interner,
         mod_solutions,
         entry_point.symbol,
         entry_point.layout,
-    )
+    );
+
+    let debug_output_file = std::env::temp_dir().join("test.ll");
+    finish_procedures(env, opt_level, &built, Some(&debug_output_file));
+
+    wrapper
 }
 
 pub fn build_procedures_return_main<'a, 'ctx>(
@@ -5613,23 +5566,27 @@ pub fn build_procedures_return_main<'a, 'ctx>(
     host_exposed_lambda_sets: HostExposedLambdaSets<'a>,
     entry_point: SingleEntryPoint<'a>,
 ) -> (&'static str, FunctionValue<'ctx>) {
-    let mod_solutions = build_procedures_help(
+    let (mod_solutions, built) = build_procedures_help(
         env,
         layout_interner,
         opt_level,
         procedures,
         host_exposed_lambda_sets,
         EntryPoint::Program(env.arena.alloc([entry_point])),
-        Some(&std::env::temp_dir().join("test.ll")),
     );
 
-    promote_to_main_function(
+    let main = promote_to_main_function(
         env,
         layout_interner,
         mod_solutions,
         entry_point.symbol,
         entry_point.layout,
-    )
+    );
+
+    let debug_output_file = std::env::temp_dir().join("test.ll");
+    finish_procedures(env, opt_level, &built, Some(&debug_output_file));
+
+    main
 }
 
 pub fn build_procedures_expose_expects<'a>(
@@ -5647,14 +5604,13 @@ pub fn build_procedures_expose_expects<'a>(
         symbols: &flattened_symbols,
     };
 
-    let mod_solutions = build_procedures_help(
+    let (mod_solutions, built) = build_procedures_help(
         env,
         layout_interner,
         opt_level,
         procedures,
         vec![],
         entry_point,
-        Some(&std::env::temp_dir().join("test.ll")),
     );
 
     let captures_niche = Niche::NONE;
@@ -5719,21 +5675,25 @@ pub fn build_procedures_expose_expects<'a>(
         expect_names_by_module.insert(module_id, expect_names);
     }
 
+    let debug_output_file = std::env::temp_dir().join("test.ll");
+    finish_procedures(env, opt_level, &built, Some(&debug_output_file));
+
     expect_names_by_module
 }
 
-fn build_procedures_help<'a>(
-    env: &Env<'a, '_, '_>,
+fn build_procedures_help<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
     layout_interner: &STLayoutInterner<'a>,
     opt_level: OptLevel,
     procedures: MutMap<(Symbol, ProcLayout<'a>), roc_mono::ir::Proc<'a>>,
     host_exposed_lambda_sets: HostExposedLambdaSets<'a>,
     entry_point: EntryPoint<'a>,
-    debug_output_file: Option<&Path>,
-) -> &'a ModSolutions {
+) -> (&'a ModSolutions, std::vec::Vec<FunctionValue<'ctx>>) {
     let mut layout_ids = roc_mono::layout::LayoutIds::default();
     let mut scope = Scope::default();
 
+    env.sendable_layouts.configure_builtins(env.module);
+
     let it1 = procedures.iter().map(|x| x.1);
     let it2 = host_exposed_lambda_sets.iter().map(|(_, _, hels)| hels);
 
@@ -5755,6 +5715,15 @@ fn build_procedures_help<'a>(
         .mod_solutions(roc_alias_analysis::MOD_APP)
         .unwrap();
 
+    let exposed_procs = std::vec::Vec::from_iter(
+        procedures
+            .keys()
+            .filter(|(symbol, _)| env.exposed_to_host.contains(symbol))
+            .copied(),
+    );
+
+    let reporting_procs = procs_that_report(&procedures);
+
     // Add all the Proc headers to the module.
     // We have to do this in a separate pass first,
     // because their bodies may reference each other.
@@ -5767,7 +5736,7 @@ fn build_procedures_help<'a>(
         &mut layout_ids,
     );
 
-    let (_, function_pass) = construct_optimization_passes(env.module, opt_level);
+    let mut built = std::vec::Vec::with_capacity(headers.len());
 
     for (proc, fn_vals) in headers {
         for (func_spec_solutions, fn_val) in fn_vals {
@@ -5778,52 +5747,32 @@ fn build_procedures_help<'a>(
             let home = proc.name.name().module_id();
             current_scope.retain_top_level_thunks_for_module(home);
 
+            if let Some(symbol_map) = env.symbol_map {
+                symbol_map.record(fn_val.get_name().to_str().unwrap(), || SymbolOrigin::Proc {
+                    definition: roc_name(&env.interns, proc.name.name()),
+                    arguments: std::vec::Vec::from_iter(
+                        proc.args
+                            .iter()
+                            .map(|(layout, _)| layout_interner.dbg(*layout)),
+                    ),
+                    result: layout_interner.dbg(proc.ret_layout),
+                });
+            }
+
             build_proc(
                 env,
                 layout_interner,
                 &mut layout_ids,
                 func_spec_solutions,
                 scope.clone(),
+                &reporting_procs,
                 &proc,
                 fn_val,
             );
 
-            // call finalize() before any code generation/verification
-            env.dibuilder.finalize();
-
-            if fn_val.verify(true) {
-                function_pass.run_on(&fn_val);
-            } else {
-                let mode = "NON-OPTIMIZED";
-
-                eprintln!(
-                    "\n\nFunction {:?} failed LLVM verification in {} build. Its content was:\n",
-                    fn_val.get_name().to_str().unwrap(),
-                    mode,
-                );
-
-                fn_val.print_to_stderr();
-
-                if let Some(app_ll_file) = debug_output_file {
-                    env.module.print_to_file(app_ll_file).unwrap();
-
-                    panic!(
-                        r"😱 LLVM errors when defining function {:?}; I wrote the full LLVM IR to {:?}",
-                        fn_val.get_name().to_str().unwrap(),
-                        app_ll_file,
-                    );
-                } else {
-                    env.module.print_to_stderr();
-
-                    panic!(
-                    "The preceding code was from {:?}, which failed LLVM verification in {} build.",
-                     fn_val.get_name().to_str().unwrap(),
-                    mode,
-                    )
-                }
-            }
-        }
-    }
+            built.push((fn_val, proc.name.name()));
+        }
+    }
 
     use LlvmBackendMode::*;
     match env.mode {
@@ -5840,12 +5789,79 @@ fn build_procedures_help<'a>(
                     &fn_name,
                     *alias_name,
                     hels,
-                )
+                );
+            }
+
+            if env.memory_api {
+                expose_memory_api(
+                    env,
+                    layout_interner,
+                    &mut layout_ids,
+                    &exposed_procs,
+                    &host_exposed_lambda_sets,
+                );
             }
         }
     }
 
-    mod_solutions
+    env.memory_strategy.finish(env);
+
+    if env.crash_dumps {
+        crash_dump::add_crash_reports(env, &built);
+    }
+
+    let built = built.into_iter().map(|(fn_val, _)| fn_val).collect();
+
+    (mod_solutions, built)
+}
+
+/// Verifies and optimizes the procedures that [build_procedures_help] built. The verifier rejects
+/// subprograms that are not finalized yet, so this finalizes the debug info first. Call it once
+/// every function of the module exists; the subprograms of functions added later are never
+/// finalized.
+fn finish_procedures<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    opt_level: OptLevel,
+    built: &[FunctionValue<'ctx>],
+    debug_output_file: Option<&Path>,
+) {
+    env.dibuilder.finalize();
+
+    let (_, function_pass) = construct_optimization_passes(env.module, opt_level);
+
+    for fn_val in built.iter().copied() {
+        if fn_val.verify(true) {
+            function_pass.run_on(&fn_val);
+        } else {
+            let mode = "NON-OPTIMIZED";
+
+            eprintln!(
+                "\n\nFunction {:?} failed LLVM verification in {} build. Its content was:\n",
+                fn_val.get_name().to_str().unwrap(),
+                mode,
+            );
+
+            fn_val.print_to_stderr();
+
+            if let Some(app_ll_file) = debug_output_file {
+                env.module.print_to_file(app_ll_file).unwrap();
+
+                panic!(
+                    r"😱 LLVM errors when defining function {:?}; I wrote the full LLVM IR to {:?}",
+                    fn_val.get_name().to_str().unwrap(),
+                    app_ll_file,
+                );
+            } else {
+                env.module.print_to_stderr();
+
+                panic!(
+                    "The preceding code was from {:?}, which failed LLVM verification in {} build.",
+                    fn_val.get_name().to_str().unwrap(),
+                    mode,
+                )
+            }
+        }
+    }
 }
 
 pub enum FuncBorrowSpec {
@@ -5895,11 +5911,7 @@ fn build_proc_header<'a, 'ctx>(
 
     let fn_name = func_spec_name(env.arena, &env.interns, symbol, func_spec);
 
-    let ret_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(proc.ret_layout),
-    );
+    let ret_type = basic_type_from_in_layout(env, layout_interner, proc.ret_layout);
     let mut arg_basic_types = Vec::with_capacity_in(args.len(), arena);
 
     for (layout, _) in args.iter() {
@@ -6019,8 +6031,7 @@ fn expose_alias_to_host<'a>(
             //
             // * roc__mainForHost_1_Update_result_size() -> i64
 
-            let result_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(result));
+            let result_type = basic_type_from_in_layout(env, layout_interner, result);
 
             build_host_exposed_alias_size_help(
                 env,
@@ -6033,6 +6044,90 @@ fn expose_alias_to_host<'a>(
     }
 }
 
+/// Add the [MemoryApi] for the arguments and results of the exposed procedures and lambda sets
+fn expose_memory_api<'a>(
+    env: &Env<'a, '_, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    exposed_procs: &[(Symbol, ProcLayout<'a>)],
+    host_exposed_lambda_sets: &[(LambdaName<'a>, Symbol, HostExposedLambdaSet<'a>)],
+) {
+    let mut memory_api = MemoryApi::default();
+
+    for (symbol, proc_layout) in exposed_procs.iter().copied() {
+        let c_function_name = layout_ids
+            .get_toplevel(symbol, &proc_layout)
+            .to_exposed_symbol_string(symbol, &env.interns);
+        let name = c_function_name.trim_start_matches("roc__");
+
+        for (i, argument) in proc_layout.arguments.iter().enumerate() {
+            let arg_name = format!("{name}_arg{i}");
+            memory_api.expose(env, layout_interner, layout_ids, arg_name, *argument);
+        }
+
+        let result_name = format!("{name}_result");
+        memory_api.expose(
+            env,
+            layout_interner,
+            layout_ids,
+            result_name,
+            proc_layout.result,
+        );
+    }
+
+    for (proc_name, _, hels) in host_exposed_lambda_sets.iter() {
+        let ident_string = proc_name.name().as_unsuffixed_str(&env.interns);
+        let fn_name: String = format!("{}_{}", ident_string, hels.id.0);
+
+        expose_alias_memory_api(
+            env,
+            layout_interner,
+            layout_ids,
+            &mut memory_api,
+            &fn_name,
+            hels,
+        );
+    }
+
+    memory_api.finish(env);
+}
+
+fn expose_alias_memory_api<'a>(
+    env: &Env<'a, '_, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    memory_api: &mut MemoryApi,
+    fn_name: &str,
+    hels: &HostExposedLambdaSet<'a>,
+) {
+    match hels.raw_function_layout {
+        RawFunctionLayout::Function(arguments, closure, result) => {
+            for (i, argument) in arguments.iter().enumerate() {
+                let arg_name = format!("{fn_name}_arg{i}");
+                memory_api.expose(env, layout_interner, layout_ids, arg_name, *argument);
+            }
+
+            let closure_name = format!("{fn_name}_closure");
+            let closure_layout = closure.runtime_representation();
+            memory_api.expose(
+                env,
+                layout_interner,
+                layout_ids,
+                closure_name,
+                closure_layout,
+            );
+
+            let result_name = format!("{fn_name}_result");
+            memory_api.expose(env, layout_interner, layout_ids, result_name, result);
+        }
+        RawFunctionLayout::ErasedFunction(..) => todo_lambda_erasure!(),
+        RawFunctionLayout::ZeroArgumentThunk(result) => {
+            let result_name = format!("{fn_name}_result");
+            memory_api.expose(env, layout_interner, layout_ids, result_name, result);
+        }
+    }
+}
+
 fn build_closure_caller<'a, 'ctx>(
     env: &Env<'a, 'ctx, '_>,
     layout_interner: &STLayoutInterner<'a>,
@@ -6047,19 +6142,15 @@ fn build_closure_caller<'a, 'ctx>(
     let mut argument_types = Vec::with_capacity_in(arguments.len() + 3, env.arena);
 
     for layout in arguments {
-        let arg_type =
-            basic_type_from_layout(env, layout_interner, layout_interner.get_repr(*layout));
+        let arg_type = basic_type_from_in_layout(env, layout_interner, *layout);
         let arg_ptr_type = arg_type.ptr_type(AddressSpace::default());
 
         argument_types.push(arg_ptr_type.into());
     }
 
     let closure_argument_type = {
-        let basic_type = basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(lambda_set.runtime_representation()),
-        );
+        let basic_type =
+            basic_type_from_in_layout(env, layout_interner, lambda_set.runtime_representation());
 
         basic_type.ptr_type(AddressSpace::default())
     };
@@ -6068,8 +6159,7 @@ fn build_closure_caller<'a, 'ctx>(
     let context = &env.context;
     let builder = env.builder;
 
-    let result_type =
-        basic_type_from_layout(env, layout_interner, layout_interner.get_repr(result));
+    let result_type = basic_type_from_in_layout(env, layout_interner, result);
 
     let output_type = { result_type.ptr_type(AddressSpace::default()) };
     argument_types.push(output_type.into());
@@ -6106,8 +6196,7 @@ fn build_closure_caller<'a, 'ctx>(
     let layouts_it = arguments.iter().chain(std::iter::once(&closure_layout));
     for (param, layout) in evaluator_arguments.iter_mut().zip(layouts_it) {
         if param.is_pointer_value() && !layout_interner.is_passed_by_reference(*layout) {
-            let basic_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(*layout));
+            let basic_type = basic_type_from_in_layout(env, layout_interner, *layout);
             *param = builder.new_build_load(basic_type, param.into_pointer_value(), "load_param");
         }
     }
@@ -6172,7 +6261,7 @@ fn build_host_exposed_alias_size<'a, 'r>(
         def_name,
         alias_symbol,
         None,
-        basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout)),
+        basic_type_from_in_layout(env, layout_interner, layout),
     )
 }
 
@@ -6216,12 +6305,17 @@ fn build_proc<'a, 'ctx>(
     layout_ids: &mut LayoutIds<'a>,
     func_spec_solutions: &FuncSpecSolutions,
     mut scope: Scope<'a, 'ctx>,
+    reporting_procs: &MutSet<Symbol>,
     proc: &roc_mono::ir::Proc<'a>,
     fn_val: FunctionValue<'ctx>,
 ) {
     let args = proc.args;
     let context = &env.context;
 
+    // a thunk that caches its value keeps its body in a separate function
+    let fn_val =
+        build_thunk_cache(env, layout_interner, reporting_procs, proc, fn_val).unwrap_or(fn_val);
+
     // Add a basic block for the entry point
     let entry = context.append_basic_block(fn_val, "entry");
     let builder = env.builder;
@@ -6254,6 +6348,339 @@ fn build_proc<'a, 'ctx>(
     }
 }
 
+/// Top-level values that can't be computed at compile time become procedures without arguments
+/// (thunks), which run again at every use. When a thunk calls functions, we remember the value
+/// after the first run instead: `fn_val` becomes a wrapper that checks the cache, and the body of
+/// the thunk goes into the returned function. The allocations of a cached value become constants,
+/// see [make_static], so every caller can use it without an increment.
+///
+/// Two threads may both find the cache empty and compute the value, which is fine because thunks
+/// are pure. Only the thread that claims the cache writes to it, and readers only look at the
+/// value once it is complete. Nothing waits on the claim, so a crash while computing can't
+/// deadlock anyone; the value is just computed at every use from then on.
+fn build_thunk_cache<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    reporting_procs: &MutSet<Symbol>,
+    proc: &roc_mono::ir::Proc<'a>,
+    fn_val: FunctionValue<'ctx>,
+) -> Option<FunctionValue<'ctx>> {
+    // a value returned by pointer would need a copy at every use
+    let cacheable = proc.args.is_empty()
+        && fn_val.count_params() == 0
+        && layout_interner.stack_size(proc.ret_layout) > 0
+        && can_make_static(layout_interner, proc.ret_layout)
+        && thunk_is_worth_caching(&proc.body, reporting_procs);
+
+    if !cacheable {
+        return None;
+    }
+
+    let ret_type = fn_val.get_type().get_return_type()?;
+    let fn_name = fn_val.get_name().to_str().unwrap();
+
+    let uncached_name = format!("{fn_name}_uncached");
+    let uncached = add_func(
+        env.context,
+        env.module,
+        &uncached_name,
+        FunctionSpec::known_fastcc(fn_val.get_type()),
+        Linkage::Internal,
+    );
+    uncached.set_subprogram(env.new_subprogram(&uncached_name));
+
+    let value = env
+        .module
+        .add_global(ret_type, None, &format!("{fn_name}_cached_value"));
+    value.set_linkage(Linkage::Internal);
+    value.set_initializer(&ret_type.const_zero());
+
+    // 0: empty, 1: claimed by a thread that is writing the value, 2: the value is complete
+    let state_type = env.context.i8_type();
+    let state = env
+        .module
+        .add_global(state_type, None, &format!("{fn_name}_cache_state"));
+    state.set_linkage(Linkage::Internal);
+    state.set_initializer(&state_type.const_zero());
+
+    let empty = state_type.const_int(0, false);
+    let claimed = state_type.const_int(1, false);
+    let complete = state_type.const_int(2, false);
+
+    let context = env.context;
+    let builder = env.builder;
+
+    let entry = context.append_basic_block(fn_val, "entry");
+    let cached_block = context.append_basic_block(fn_val, "cached");
+    let compute_block = context.append_basic_block(fn_val, "compute");
+    let store_block = context.append_basic_block(fn_val, "store");
+    let return_block = context.append_basic_block(fn_val, "return");
+
+    builder.position_at_end(entry);
+
+    debug_info_init!(env, fn_val);
+
+    let current = builder
+        .new_build_load(state_type, state.as_pointer_value(), "cache_state")
+        .into_int_value();
+    let load = current.as_instruction().unwrap();
+    load.set_alignment(1).unwrap();
+    load.set_atomic_ordering(AtomicOrdering::Acquire).unwrap();
+
+    let is_complete =
+        builder.new_build_int_compare(IntPredicate::EQ, current, complete, "is_complete");
+    builder.new_build_conditional_branch(is_complete, cached_block, compute_block);
+
+    builder.position_at_end(cached_block);
+    let cached = builder.new_build_load(ret_type, value.as_pointer_value(), "cached_value");
+    builder.new_build_return(Some(&cached));
+
+    builder.position_at_end(compute_block);
+    let call = builder.new_build_call(uncached, &[], "computed_value");
+    call.set_call_convention(FAST_CALL_CONV);
+    let computed = call.try_as_basic_value().left().unwrap();
+
+    let exchange = builder
+        .build_cmpxchg(
+            state.as_pointer_value(),
+            empty,
+            claimed,
+            AtomicOrdering::Monotonic,
+            AtomicOrdering::Monotonic,
+        )
+        .unwrap();
+    let is_claimed = builder
+        .build_extract_value(exchange, 1, "is_claimed")
+        .unwrap()
+        .into_int_value();
+    builder.new_build_conditional_branch(is_claimed, store_block, return_block);
+
+    builder.position_at_end(store_block);
+    make_static(env, layout_interner, computed, proc.ret_layout);
+    builder.new_build_store(value.as_pointer_value(), computed);
+    let store = builder.new_build_store(state.as_pointer_value(), complete);
+    store.set_alignment(1).unwrap();
+    store.set_atomic_ordering(AtomicOrdering::Release).unwrap();
+    builder.new_build_unconditional_branch(return_block);
+
+    builder.position_at_end(return_block);
+    builder.new_build_return(Some(&computed));
+
+    Some(uncached)
+}
+
+/// Whether [make_static] can turn every allocation of a value into a constant. Callers on several
+/// threads may share a cached value, so no refcount inside it may change later: an allocation
+/// must not contain refcounted values of its own.
+fn can_make_static<'a>(layout_interner: &STLayoutInterner<'a>, layout: InLayout<'a>) -> bool {
+    match layout_interner.get_repr(layout) {
+        LayoutRepr::Builtin(Builtin::Str) => true,
+        LayoutRepr::Builtin(Builtin::List(element)) => {
+            !layout_interner.contains_refcounted(element)
+        }
+        LayoutRepr::Struct(fields) => fields
+            .iter()
+            .all(|field| can_make_static(layout_interner, *field)),
+        _ => !layout_interner.contains_refcounted(layout),
+    }
+}
+
+/// Give every allocation of `value` the refcount of zero that string literals have. The builtins
+/// then skip its refcount updates, so it is never freed nor updated in place.
+fn make_static<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    value: BasicValueEnum<'ctx>,
+    layout: InLayout<'a>,
+) {
+    let builder = env.builder;
+    let repr = layout_interner.get_repr(layout);
+
+    let (allocation, has_allocation) = match repr {
+        LayoutRepr::Builtin(Builtin::Str) => {
+            let capacity = builder
+                .build_extract_value(
+                    value.into_struct_value(),
+                    Builtin::WRAPPER_CAPACITY,
+                    "str_capacity",
+                )
+                .unwrap()
+                .into_int_value();
+
+            // small strings have no allocation, and set the sign bit of the capacity
+            let is_big = builder.new_build_int_compare(
+                IntPredicate::SGT,
+                capacity,
+                env.ptr_int().const_zero(),
+                "is_big_str",
+            );
+
+            let string = use_roc_value(env, layout_interner, repr, value, "string");
+            let allocation = str_allocation_ptr(env, string);
+            let is_not_null = builder.new_build_is_not_null(allocation, "has_allocation");
+
+            (
+                allocation,
+                builder.new_build_and(is_big, is_not_null, "has_allocation"),
+            )
+        }
+        LayoutRepr::Builtin(Builtin::List(_)) => {
+            let allocation = list_allocation_ptr(env, value.into_struct_value());
+
+            (
+                allocation,
+                builder.new_build_is_not_null(allocation, "has_allocation"),
+            )
+        }
+        LayoutRepr::Struct(fields) => {
+            for (index, field) in fields.iter().enumerate() {
+                if layout_interner.contains_refcounted(*field) {
+                    let field_value = builder
+                        .build_extract_value(value.into_struct_value(), index as u32, "field")
+                        .unwrap();
+
+                    make_static(env, layout_interner, field_value, *field);
+                }
+            }
+
+            return;
+        }
+        _ => return,
+    };
+
+    let parent = builder.get_insert_block().unwrap().get_parent().unwrap();
+    let static_block = env.context.append_basic_block(parent, "make_static");
+    let cont_block = env.context.append_basic_block(parent, "make_static_cont");
+
+    builder.new_build_conditional_branch(has_allocation, static_block, cont_block);
+
+    builder.position_at_end(static_block);
+    let refcount_ptr = PointerToRefcount::from_ptr_to_data(env, allocation);
+    refcount_ptr.set_refcount(env, env.ptr_int().const_zero());
+    builder.new_build_unconditional_branch(cont_block);
+
+    builder.position_at_end(cont_block);
+}
+
+/// Whether a thunk does enough work to be worth caching. Thunks that only build a value from
+/// literals are cheap, and ones that run a `dbg` or `expect`, themselves or in any function they
+/// call, should report every time they run.
+fn thunk_is_worth_caching(body: &roc_mono::ir::Stmt<'_>, reporting_procs: &MutSet<Symbol>) -> bool {
+    let mut calls_functions = false;
+    let mut calls_reporting_proc = false;
+
+    let reports = visit_calls(body, |call_type| match call_type {
+        CallType::ByName {
+            name, arg_layouts, ..
+        } => {
+            calls_functions |= !arg_layouts.is_empty();
+            calls_reporting_proc |= reporting_procs.contains(&name.name());
+        }
+        CallType::HigherOrder(higher_order) => {
+            calls_functions = true;
+            calls_reporting_proc |=
+                reporting_procs.contains(&higher_order.passed_function.name.name());
+        }
+        // the pointer could be to any procedure
+        CallType::ByPointer { .. } => calls_reporting_proc = true,
+        CallType::Foreign { .. } | CallType::LowLevel { .. } => {}
+    });
+
+    calls_functions && !reports && !calls_reporting_proc
+}
+
+/// The procedures that run a `dbg` or `expect`, themselves or in any procedure they call.
+/// Procedures are told apart by symbol only, so one specialization that reports marks all of
+/// them.
+fn procs_that_report<'a>(
+    procedures: &MutMap<(Symbol, ProcLayout<'a>), roc_mono::ir::Proc<'a>>,
+) -> MutSet<Symbol> {
+    let mut reporting = MutSet::default();
+    let mut callers = std::vec::Vec::with_capacity(procedures.len());
+
+    for ((symbol, _), proc) in procedures.iter() {
+        let mut callees = std::vec::Vec::new();
+        let mut calls_pointer = false;
+
+        let reports = visit_calls(&proc.body, |call_type| match call_type {
+            CallType::ByName { name, .. } => callees.push(name.name()),
+            CallType::HigherOrder(higher_order) => {
+                callees.push(higher_order.passed_function.name.name())
+            }
+            CallType::ByPointer { .. } => calls_pointer = true,
+            CallType::Foreign { .. } | CallType::LowLevel { .. } => {}
+        });
+
+        if reports || calls_pointer {
+            reporting.insert(*symbol);
+        } else {
+            callers.push((*symbol, callees));
+        }
+    }
+
+    // a caller of a reporting procedure reports too, until nothing changes
+    loop {
+        let before = reporting.len();
+
+        callers.retain(|(symbol, callees)| {
+            if callees.iter().any(|callee| reporting.contains(callee)) {
+                reporting.insert(*symbol);
+                false
+            } else {
+                true
+            }
+        });
+
+        if reporting.len() == before {
+            return reporting;
+        }
+    }
+}
+
+/// Calls `on_call` with every call in `body`, and returns whether `body` runs a `dbg` or `expect`
+fn visit_calls<'a>(body: &roc_mono::ir::Stmt<'a>, mut on_call: impl FnMut(&CallType<'a>)) -> bool {
+    use roc_mono::ir::{Expr, Stmt};
+
+    let mut reports = false;
+    let mut stack = vec![body];
+
+    while let Some(stmt) = stack.pop() {
+        match stmt {
+            Stmt::Let(_, expr, _, cont) => {
+                if let Expr::Call(call) = expr {
+                    on_call(&call.call_type);
+                }
+
+                stack.push(cont);
+            }
+            Stmt::Switch {
+                branches,
+                default_branch,
+                ..
+            } => {
+                stack.extend(branches.iter().map(|(_, _, branch)| branch));
+                stack.push(default_branch.1);
+            }
+            Stmt::Join {
+                body, remainder, ..
+            } => {
+                stack.push(body);
+                stack.push(remainder);
+            }
+            Stmt::Refcounting(_, cont) => stack.push(cont),
+            Stmt::Expect { remainder, .. }
+            | Stmt::ExpectFx { remainder, .. }
+            | Stmt::Dbg { remainder, .. } => {
+                reports = true;
+                stack.push(remainder);
+            }
+            Stmt::Ret(_) | Stmt::Jump(..) | Stmt::Crash(..) => {}
+        }
+    }
+
+    reports
+}
+
 pub fn verify_fn(fn_val: FunctionValue<'_>) {
     if !fn_val.verify(print_fn_verification_output()) {
         unsafe {
@@ -6477,11 +6904,8 @@ pub(crate) fn roc_function_call<'a, 'ctx>(
 ) -> RocFunctionCall<'ctx> {
     use crate::llvm::bitcode::{build_inc_n_wrapper, build_transform_caller};
 
-    let closure_data_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(lambda_set.runtime_representation()),
-    );
+    let closure_data_type =
+        basic_type_from_in_layout(env, layout_interner, lambda_set.runtime_representation());
 
     let closure_data_ptr = create_entry_block_alloca(env, closure_data_type, "closure_data_ptr");
 
@@ -6538,6 +6962,25 @@ fn to_cc_type<'a, 'ctx>(
     let layout_repr = layout_interner.runtime_representation(layout);
     match layout_repr {
         LayoutRepr::Builtin(builtin) => to_cc_type_builtin(env, &builtin),
+        _ if is_riscv64_integer_aggregate(env, layout_interner, layout_repr) => {
+            let stack_type = basic_type_from_layout(env, layout_interner, layout_repr);
+
+            match layout_repr.stack_size(layout_interner) {
+                0 => stack_type,
+                1..=16 => riscv64_register_type(env, layout_interner, layout_repr),
+                _ => stack_type.ptr_type(AddressSpace::default()).into(),
+            }
+        }
+        _ if is_win_x64_aggregate(env, layout_repr) => {
+            let stack_type = basic_type_from_layout(env, layout_interner, layout_repr);
+
+            match layout_repr.stack_size(layout_interner) {
+                // an empty struct takes no register and no stack slot, so the argument is skipped
+                0 => stack_type,
+                size @ (1 | 2 | 4 | 8) => env.context.custom_width_int_type(size * 8).into(),
+                _ => stack_type.ptr_type(AddressSpace::default()).into(),
+            }
+        }
         LayoutRepr::Struct(_) => {
             let stack_type = basic_type_from_layout(env, layout_interner, layout_repr);
 
@@ -6549,8 +6992,126 @@ fn to_cc_type<'a, 'ctx>(
         }
         _ => {
             // TODO this is almost certainly incorrect for bigger structs
-            basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout))
+            basic_type_from_in_layout(env, layout_interner, layout)
+        }
+    }
+}
+
+/// The Windows x64 calling convention passes and returns aggregates of 1, 2, 4 or 8 bytes as an
+/// integer of that size. Other aggregates are passed by reference to a copy made by the caller
+/// (not `byval`: the copy need not be on the stack), and returned through an output pointer.
+fn is_win_x64_aggregate(env: &Env<'_, '_, '_>, layout_repr: LayoutRepr<'_>) -> bool {
+    env.target == Target::WinX64
+        && matches!(
+            layout_repr,
+            LayoutRepr::Struct(_) | LayoutRepr::Union(UnionLayout::NonRecursive(_))
+        )
+}
+
+/// The RISC-V LP64D calling convention passes and returns aggregates of up to 16 bytes in one or
+/// two integer registers, and larger ones by reference to a copy made by the caller (not `byval`).
+///
+/// Aggregates with floating-point fields may use the floating-point registers instead. LLVM
+/// already does that when it splits a struct into its fields, so we leave those alone.
+fn is_riscv64_integer_aggregate<'a>(
+    env: &Env<'a, '_, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_repr: LayoutRepr<'a>,
+) -> bool {
+    env.target == Target::LinuxRiscv64
+        && matches!(
+            layout_repr,
+            LayoutRepr::Struct(_) | LayoutRepr::Union(UnionLayout::NonRecursive(_))
+        )
+        && !contains_float(layout_interner, layout_repr)
+}
+
+fn contains_float<'a>(layout_interner: &STLayoutInterner<'a>, layout_repr: LayoutRepr<'a>) -> bool {
+    let any_float = |fields: &[InLayout<'a>]| {
+        fields.iter().any(|field| {
+            contains_float(
+                layout_interner,
+                layout_interner.runtime_representation(*field),
+            )
+        })
+    };
+
+    match layout_repr {
+        LayoutRepr::Builtin(Builtin::Float(_)) => true,
+        LayoutRepr::Struct(fields) => any_float(fields),
+        LayoutRepr::Union(UnionLayout::NonRecursive(tags)) => tags.iter().any(|tag| any_float(tag)),
+        _ => false,
+    }
+}
+
+/// The registers that hold an aggregate of at most 16 bytes: `i64`, `[2 x i64]`, or an `i128`
+/// when the aggregate is 16-byte aligned (it then goes in an aligned register pair).
+fn riscv64_register_type<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_repr: LayoutRepr<'a>,
+) -> BasicTypeEnum<'ctx> {
+    let i64_type = env.context.i64_type();
+
+    if layout_repr.stack_size(layout_interner) <= 8 {
+        i64_type.into()
+    } else if layout_repr.alignment_bytes(layout_interner) == 16 {
+        env.context.i128_type().into()
+    } else {
+        i64_type.array_type(2).into()
+    }
+}
+
+/// The type of a value of this layout, when a C function returns it in registers
+fn to_cc_return_type<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout: InLayout<'a>,
+) -> BasicTypeEnum<'ctx> {
+    let layout_repr = layout_interner.runtime_representation(layout);
+
+    match layout_repr.stack_size(layout_interner) {
+        size @ (1 | 2 | 4 | 8) if is_win_x64_aggregate(env, layout_repr) => {
+            env.context.custom_width_int_type(size * 8).into()
         }
+        1..=16 if is_riscv64_integer_aggregate(env, layout_interner, layout_repr) => {
+            riscv64_register_type(env, layout_interner, layout_repr)
+        }
+        // zero-sized results are `CCReturn::Void`, and larger aggregates are returned by pointer,
+        // so this type only matters for the remaining layouts
+        _ => basic_type_from_layout(env, layout_interner, layout_repr),
+    }
+}
+
+/// Convert a value between its C and fastcc representations, which may disagree on whether it is
+/// passed by reference, and on its type
+fn convert_between_calling_conventions<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    value: BasicValueEnum<'ctx>,
+    to_type: BasicTypeEnum<'ctx>,
+    name: &str,
+) -> BasicValueEnum<'ctx> {
+    let from_type = value.get_type();
+
+    if from_type == to_type {
+        value
+    } else if from_type.is_pointer_type() && !to_type.is_pointer_type() {
+        let ptr = env.builder.new_build_pointer_cast(
+            value.into_pointer_value(),
+            to_type.ptr_type(AddressSpace::default()),
+            name,
+        );
+
+        env.builder.new_build_load(to_type, ptr, name)
+    } else if !from_type.is_pointer_type() && to_type.is_pointer_type() {
+        let alloca = create_entry_block_alloca(env, from_type, name);
+        env.builder.new_build_store(alloca, value);
+
+        env.builder
+            .new_build_pointer_cast(alloca, to_type.into_pointer_type(), name)
+            .into()
+    } else {
+        complex_bitcast_check_size(env, value, to_type, name)
     }
 }
 
@@ -6737,8 +7298,16 @@ pub fn to_cc_return<'a>(
     layout: InLayout<'a>,
 ) -> CCReturn {
     let return_size = layout_interner.stack_size(layout);
+
+    if return_size == 0 {
+        return CCReturn::Void;
+    }
+
     // TODO: loop back and update this. It actually cares about the full abi (arch + os)
     let pass_result_by_pointer = match env.target.operating_system() {
+        roc_target::OperatingSystem::Windows if env.target == Target::WinX64 => {
+            !matches!(return_size, 1 | 2 | 4 | 8)
+        }
         roc_target::OperatingSystem::Windows => return_size > env.target.ptr_width() as u32,
         roc_target::OperatingSystem::Linux
         | roc_target::OperatingSystem::Mac
@@ -6747,9 +7316,7 @@ pub fn to_cc_return<'a>(
         }
     };
 
-    if return_size == 0 {
-        CCReturn::Void
-    } else if pass_result_by_pointer {
+    if pass_result_by_pointer {
         CCReturn::ByPointer
     } else {
         CCReturn::Return
@@ -6798,8 +7365,7 @@ fn build_foreign_symbol<'a, 'ctx>(
             //      and can use in the wrapper
             // - a FAST_CALL_CONV wrapper that we make here, e.g. `roc_fx_putLine_fastcc_wrapper`
 
-            let return_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(ret_layout));
+            let return_type = basic_type_from_in_layout(env, layout_interner, ret_layout);
             let roc_return =
                 RocReturn::from_layout(layout_interner, layout_interner.get_repr(ret_layout));
             let cc_return = to_cc_return(env, layout_interner, ret_layout);
@@ -6825,8 +7391,9 @@ fn build_foreign_symbol<'a, 'ctx>(
                 arguments.push(value);
             }
 
+            let cc_return_type = to_cc_return_type(env, layout_interner, ret_layout);
             let cc_type =
-                FunctionSpec::cconv(env, cc_return, Some(return_type), &cc_argument_types);
+                FunctionSpec::cconv(env, cc_return, Some(cc_return_type), &cc_argument_types);
             let cc_function = get_foreign_symbol(env, foreign.clone(), cc_type);
 
             let fastcc_type =
@@ -6881,6 +7448,16 @@ fn build_foreign_symbol<'a, 'ctx>(
                                 "to_cc_type_ptr",
                             );
 
+                            cc_arguments.push(as_cc_type.into());
+                        } else if !cc_type.is_pointer_type() {
+                            // e.g. a small aggregate that Windows x64 passes as an integer
+                            let as_cc_type = convert_between_calling_conventions(
+                                env,
+                                param,
+                                *cc_type,
+                                "to_cc_type",
+                            );
+
                             cc_arguments.push(as_cc_type.into());
                         } else {
                             // eprintln!("C type: {:?}", cc_type);
@@ -6897,15 +7474,26 @@ fn build_foreign_symbol<'a, 'ctx>(
                     }
                 }
 
-                let call = env
-                    .builder
-                    .new_build_call(cc_function, &cc_arguments, "tmp");
-                call.set_call_convention(C_CALL_CONV);
+                let call_result = if let LlvmBackendMode::CliTest = env.mode {
+                    call_effect_or_mock(env, cc_function, &cc_arguments)
+                } else {
+                    let call = env
+                        .builder
+                        .new_build_call(cc_function, &cc_arguments, "tmp");
+                    call.set_call_convention(C_CALL_CONV);
+
+                    call.try_as_basic_value().left()
+                };
 
                 match roc_return {
                     RocReturn::Return => {
                         let return_value = match cc_return {
-                            CCReturn::Return => call.try_as_basic_value().left().unwrap(),
+                            CCReturn::Return => convert_between_calling_conventions(
+                                env,
+                                call_result.unwrap(),
+                                return_type,
+                                "from_cc_return",
+                            ),
 
                             CCReturn::ByPointer => env.builder.new_build_load(
                                 return_type,
@@ -6920,7 +7508,12 @@ fn build_foreign_symbol<'a, 'ctx>(
                     RocReturn::ByPointer => {
                         match cc_return {
                             CCReturn::Return => {
-                                let result = call.try_as_basic_value().left().unwrap();
+                                let result = convert_between_calling_conventions(
+                                    env,
+                                    call_result.unwrap(),
+                                    return_type,
+                                    "from_cc_return",
+                                );
                                 env.builder.new_build_store(return_pointer, result);
                             }
 
@@ -6961,15 +7554,14 @@ fn define_global_str_literal_ptr<'ctx>(
         "to_opaque",
     );
 
-    // a pointer to the first actual data (skipping over the refcount)
+    // a pointer to the first actual data (skipping over the header)
+    let header = HeapHeader::refcount_only(env.target);
     let ptr = unsafe {
         env.builder.new_build_in_bounds_gep(
             env.context.i8_type(),
             ptr,
-            &[env
-                .ptr_int()
-                .const_int(env.target.ptr_width() as u64, false)],
-            "get_rc_ptr",
+            &[env.ptr_int().const_int(header.size() as u64, false)],
+            "get_data_ptr",
         )
     };
 
@@ -6998,11 +7590,12 @@ fn define_global_str_literal<'ctx>(
         Some(current) => current,
 
         None => {
-            let size = message.bytes().len() + env.target.ptr_width() as usize;
+            let header = HeapHeader::refcount_only(env.target);
+            let size = header.size() as usize + message.bytes().len();
             let mut bytes = Vec::with_capacity_in(size, env.arena);
 
-            // insert NULL bytes for the refcount
-            for _ in 0..env.target.ptr_width() as usize {
+            // a refcount of zero marks the string as constant
+            for _ in 0..header.size() {
                 bytes.push(env.context.i8_type().const_zero());
             }
 
@@ -7030,6 +7623,37 @@ fn define_global_str_literal<'ctx>(
     }
 }
 
+/// Which successor of a conditional branch is rarely taken
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ColdSuccessor {
+    Then,
+    Else,
+}
+
+/// Tell LLVM that one successor of `branch` is rarely taken, like `__builtin_expect` does. Code
+/// generation then moves that block out of line, which keeps the hot path dense.
+pub(crate) fn set_cold_successor<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    branch: InstructionValue<'ctx>,
+    cold: ColdSuccessor,
+) {
+    // the weights that clang uses for `__builtin_expect`
+    let (then_weight, else_weight) = match cold {
+        ColdSuccessor::Then => (1, 2000),
+        ColdSuccessor::Else => (2000, 1),
+    };
+
+    let i32_type = env.context.i32_type();
+    let weights = env.context.metadata_node(&[
+        env.context.metadata_string("branch_weights").into(),
+        i32_type.const_int(then_weight, false).into(),
+        i32_type.const_int(else_weight, false).into(),
+    ]);
+
+    let kind_id = env.context.get_kind_id("prof");
+    branch.set_metadata(weights, kind_id).unwrap();
+}
+
 pub(crate) fn throw_internal_exception(env: &Env<'_, '_, '_>, message: &str) {
     let builder = env.builder;
 
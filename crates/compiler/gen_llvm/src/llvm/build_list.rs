@@ -1,6 +1,7 @@
 use crate::llvm::bitcode::build_dec_wrapper;
-use crate::llvm::build::{allocate_with_refcount_help, cast_basic_basic, Env, RocFunctionCall};
-use crate::llvm::convert::basic_type_from_layout;
+use crate::llvm::build::{allocate_with_refcount, cast_basic_basic, Env, RocFunctionCall};
+use crate::llvm::convert::{basic_type_from_in_layout, basic_type_from_layout};
+use crate::llvm::heap_header::HeapHeader;
 use inkwell::builder::Builder;
 use inkwell::types::{BasicType, PointerType};
 use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue, StructValue};
@@ -59,8 +60,7 @@ fn pass_element_as_opaque<'a, 'ctx>(
     element: BasicValueEnum<'ctx>,
     layout: InLayout<'a>,
 ) -> BasicValueEnum<'ctx> {
-    let element_type =
-        basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
+    let element_type = basic_type_from_in_layout(env, layout_interner, layout);
     let element_ptr = create_entry_block_alloca(env, element_type, "element_to_pass_as_opaque");
     store_roc_value(
         env,
@@ -148,11 +148,7 @@ pub(crate) fn list_get_unsafe<'a, 'ctx>(
 ) -> BasicValueEnum<'ctx> {
     let builder = env.builder;
 
-    let elem_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(element_layout),
-    );
+    let elem_type = basic_type_from_in_layout(env, layout_interner, element_layout);
     // listGetUnsafe takes a U64, but we need to convert that to usize for index calculation.
     let elem_index = builder.new_build_int_cast(elem_index, env.ptr_int(), "u64_to_usize");
     let ptr_type = elem_type.ptr_type(AddressSpace::default());
@@ -398,11 +394,7 @@ pub(crate) fn list_replace_unsafe<'a, 'ctx>(
     element_layout: InLayout<'a>,
     update_mode: UpdateMode,
 ) -> BasicValueEnum<'ctx> {
-    let element_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(element_layout),
-    );
+    let element_type = basic_type_from_in_layout(env, layout_interner, element_layout);
     let element_ptr = create_entry_block_alloca(env, element_type, "output_element_as_opaque");
     let copy_fn = build_copy_wrapper(env, layout_interner, layout_ids, element_layout);
 
@@ -501,6 +493,22 @@ fn bounds_check_comparison<'ctx>(
 }
 
 /// List.len : List * -> usize (return value will be cast to U64 in user-facing API)
+/// A pointer to just after the refcount of the allocation of a list or seamless slice, or null
+/// when the list has no allocation
+pub(crate) fn list_allocation_ptr<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    list: StructValue<'ctx>,
+) -> PointerValue<'ctx> {
+    call_list_bitcode_fn(
+        env,
+        &[list],
+        &[],
+        BitcodeReturns::Basic,
+        bitcode::LIST_ALLOCATION_PTR,
+    )
+    .into_pointer_value()
+}
+
 pub(crate) fn list_len_usize<'ctx>(
     builder: &Builder<'ctx>,
     wrapper_struct: StructValue<'ctx>,
@@ -566,6 +574,42 @@ pub(crate) fn list_sort_with<'a, 'ctx>(
     )
 }
 
+/// List.mapParallel : List a, (a -> b) -> List b
+///
+/// The zig builtin hands chunks of the list to the host's `roc_parallel_for`, so the elements, the
+/// results and the captured environment must have sendable layouts.
+pub(crate) fn list_map_parallel<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    roc_function_call: RocFunctionCall<'ctx>,
+    list: BasicValueEnum<'ctx>,
+    element_layout: InLayout<'a>,
+    result_layout: InLayout<'a>,
+) -> BasicValueEnum<'ctx> {
+    let inc_element_fn = build_inc_wrapper(env, layout_interner, layout_ids, element_layout);
+    let dec_element_fn = build_dec_wrapper(env, layout_interner, layout_ids, element_layout);
+    call_list_bitcode_fn_1(
+        env,
+        list.into_struct_value(),
+        &[
+            roc_function_call.caller.into(),
+            pass_as_opaque(env, roc_function_call.data),
+            roc_function_call.inc_n_data.into(),
+            roc_function_call.data_is_owned.into(),
+            env.alignment_intvalue(layout_interner, element_layout),
+            layout_width(env, layout_interner, element_layout),
+            layout_refcounted(env, layout_interner, element_layout),
+            inc_element_fn.as_global_value().as_pointer_value().into(),
+            dec_element_fn.as_global_value().as_pointer_value().into(),
+            env.alignment_intvalue(layout_interner, result_layout),
+            layout_width(env, layout_interner, result_layout),
+            layout_refcounted(env, layout_interner, result_layout),
+        ],
+        bitcode::LIST_MAP_PARALLEL,
+    )
+}
+
 /// List.concat : List elem, List elem -> List elem
 pub(crate) fn list_concat<'a, 'ctx>(
     env: &Env<'a, 'ctx, '_>,
@@ -607,11 +651,7 @@ where
 {
     let builder = env.builder;
 
-    let element_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(element_layout),
-    );
+    let element_type = basic_type_from_in_layout(env, layout_interner, element_layout);
 
     incrementing_index_loop(
         env,
@@ -729,17 +769,14 @@ pub(crate) fn allocate_list<'a, 'ctx>(
     let number_of_data_bytes =
         builder.new_build_int_mul(bytes_per_element, number_of_elements, "data_length");
 
-    let basic_type =
-        basic_type_from_layout(env, layout_interner, layout_interner.get_repr(elem_layout));
     let alignment_bytes = layout_interner.alignment_bytes(elem_layout);
-    let elem_refcounted = layout_refcounted(env, layout_interner, elem_layout);
-    allocate_with_refcount_help(
-        env,
-        basic_type,
-        alignment_bytes,
-        number_of_data_bytes,
-        elem_refcounted,
-    )
+    let header = HeapHeader::new(env.target, layout_interner.contains_refcounted(elem_layout));
+    let ptr = allocate_with_refcount(env, number_of_data_bytes, alignment_bytes, header);
+
+    let basic_type = basic_type_from_in_layout(env, layout_interner, elem_layout);
+    let ptr_type = basic_type.ptr_type(AddressSpace::default());
+
+    builder.new_build_pointer_cast(ptr, ptr_type, "alloc_cast_to_desired")
 }
 
 pub(crate) fn store_list<'ctx>(
@@ -16,6 +16,7 @@ use roc_mono::layout::{
 
 use super::build::{create_entry_block_alloca, load_roc_value, BuilderExt};
 use super::convert::{argument_type_from_layout, argument_type_from_union_layout};
+use super::helper_cache::{CachedHelper, HelperCache};
 use super::lowlevel::dec_binop_with_unchecked;
 use super::struct_;
 
@@ -192,23 +193,6 @@ fn build_eq<'a, 'ctx>(
         ),
 
         LayoutRepr::RecursivePointer(rec_layout) => {
-            let layout = rec_layout;
-
-            let bt = basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
-
-            // cast the i64 pointer to a pointer to block of memory
-            let field1_cast = env.builder.new_build_pointer_cast(
-                lhs_val.into_pointer_value(),
-                bt.into_pointer_type(),
-                "i64_to_opaque",
-            );
-
-            let field2_cast = env.builder.new_build_pointer_cast(
-                rhs_val.into_pointer_value(),
-                bt.into_pointer_type(),
-                "i64_to_opaque",
-            );
-
             let union_layout = match layout_interner.get_repr(rec_layout) {
                 LayoutRepr::Union(union_layout) => {
                     debug_assert!(!matches!(union_layout, UnionLayout::NonRecursive(..)));
@@ -223,8 +207,8 @@ fn build_eq<'a, 'ctx>(
                 layout_ids,
                 layout_interner.get_repr(rec_layout),
                 &union_layout,
-                field1_cast.into(),
-                field2_cast.into(),
+                lhs_val,
+                rhs_val,
             )
         }
     }
@@ -331,7 +315,9 @@ fn build_neq<'a, 'ctx>(
 ) -> BasicValueEnum<'ctx> {
     if lhs_layout != rhs_layout {
         panic!(
-            "Inequality of different layouts; did you have a type mismatch?\n{lhs_layout:?} != {rhs_layout:?}"
+            "Inequality of different layouts; did you have a type mismatch?\n{} != {}",
+            layout_interner.dbg(lhs_layout),
+            layout_interner.dbg(rhs_layout)
         );
     }
 
@@ -406,6 +392,41 @@ fn build_neq<'a, 'ctx>(
     }
 }
 
+/// Find the equality helper `fn_name`, or build it. Like refcounting helpers, equality helpers are
+/// cached across builds; see [HelperCache].
+fn eq_helper<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    fn_name: &str,
+    kind: CachedHelper,
+    repr: LayoutRepr<'a>,
+    build: impl FnOnce() -> FunctionValue<'ctx>,
+) -> FunctionValue<'ctx> {
+    if let Some(function) = env.module.get_function(fn_name) {
+        return function;
+    }
+
+    // the helper of a list is keyed by its element, which can be any layout
+    let cacheable = matches!(kind, CachedHelper::ListEq) || HelperCache::is_cacheable(repr);
+
+    match env.helper_cache {
+        Some(cache) if cacheable => {
+            let key = cache.key(layout_interner, &env.sendable_layouts, kind, repr);
+
+            match cache.lookup(env, &key) {
+                Some(function) => function,
+                None => {
+                    let function = build();
+                    cache.record(key, function);
+
+                    function
+                }
+            }
+        }
+        _ => build(),
+    }
+}
+
 fn build_list_eq<'a, 'ctx>(
     env: &Env<'a, 'ctx, '_>,
     layout_interner: &STLayoutInterner<'a>,
@@ -427,10 +448,15 @@ fn build_list_eq<'a, 'ctx>(
     let fn_name = layout_ids
         .get(symbol, &element_layout)
         .to_symbol_string(symbol, &env.interns);
+    env.record_helper(layout_interner, &fn_name, symbol, element_layout);
 
-    let function = match env.module.get_function(fn_name.as_str()) {
-        Some(function_value) => function_value,
-        None => {
+    let function = eq_helper(
+        env,
+        layout_interner,
+        &fn_name,
+        CachedHelper::ListEq,
+        element_layout,
+        || {
             let arg_type = basic_type_from_layout(env, layout_interner, list_layout);
 
             let function_value = crate::llvm::refcounting::build_header_help(
@@ -449,8 +475,8 @@ fn build_list_eq<'a, 'ctx>(
             );
 
             function_value
-        }
-    };
+        },
+    );
 
     env.builder.position_at_end(block);
     env.builder.set_current_debug_location(di_location);
@@ -637,10 +663,15 @@ fn build_struct_eq<'a, 'ctx>(
     let fn_name = layout_ids
         .get(symbol, &struct_layout)
         .to_symbol_string(symbol, &env.interns);
+    env.record_helper(layout_interner, &fn_name, symbol, struct_layout);
 
-    let function = match env.module.get_function(fn_name.as_str()) {
-        Some(function_value) => function_value,
-        None => {
+    let function = eq_helper(
+        env,
+        layout_interner,
+        &fn_name,
+        CachedHelper::Eq,
+        struct_layout,
+        || {
             let arg_type = argument_type_from_layout(env, layout_interner, struct_layout);
 
             let function_value = crate::llvm::refcounting::build_header_help(
@@ -660,8 +691,8 @@ fn build_struct_eq<'a, 'ctx>(
             );
 
             function_value
-        }
-    };
+        },
+    );
 
     env.builder.position_at_end(block);
     env.builder.set_current_debug_location(di_location);
@@ -748,35 +779,14 @@ fn build_struct_eq_help<'a, 'ctx>(
                 matches!(layout_interner.get_repr(rec_layout), LayoutRepr::Union(union_layout) if !matches!(union_layout, UnionLayout::NonRecursive(..)))
             );
 
-            let field_layout = rec_layout;
-
-            let bt = basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(field_layout),
-            );
-
-            // cast the i64 pointer to a pointer to block of memory
-            let field1_cast = env.builder.new_build_pointer_cast(
-                field1.into_pointer_value(),
-                bt.into_pointer_type(),
-                "i64_to_opaque",
-            );
-
-            let field2_cast = env.builder.new_build_pointer_cast(
-                field2.into_pointer_value(),
-                bt.into_pointer_type(),
-                "i64_to_opaque",
-            );
-
             build_eq(
                 env,
                 layout_interner,
                 layout_ids,
-                field1_cast.into(),
-                field2_cast.into(),
-                layout_interner.get_repr(field_layout),
-                layout_interner.get_repr(field_layout),
+                field1,
+                field2,
+                layout_interner.get_repr(rec_layout),
+                layout_interner.get_repr(rec_layout),
             )
             .into_int_value()
         } else {
@@ -830,10 +840,15 @@ fn build_tag_eq<'a, 'ctx>(
     let fn_name = layout_ids
         .get(symbol, &tag_layout)
         .to_symbol_string(symbol, &env.interns);
+    env.record_helper(layout_interner, &fn_name, symbol, tag_layout);
 
-    let function = match env.module.get_function(fn_name.as_str()) {
-        Some(function_value) => function_value,
-        None => {
+    let function = eq_helper(
+        env,
+        layout_interner,
+        &fn_name,
+        CachedHelper::Eq,
+        tag_layout,
+        || {
             let arg_type = argument_type_from_union_layout(env, layout_interner, union_layout);
 
             let function_value = crate::llvm::refcounting::build_header_help(
@@ -852,8 +867,8 @@ fn build_tag_eq<'a, 'ctx>(
             );
 
             function_value
-        }
-    };
+        },
+    );
 
     env.builder.position_at_end(block);
     env.builder.set_current_debug_location(di_location);
@@ -1283,33 +1298,9 @@ fn eq_ptr_to_struct<'a, 'ctx>(
     let wrapper_type = basic_type_from_layout(env, layout_interner, struct_layout);
     debug_assert!(wrapper_type.is_struct_type());
 
-    // cast the opaque pointer to a pointer of the correct shape
-    let struct1_ptr = env.builder.new_build_pointer_cast(
-        tag1,
-        wrapper_type.ptr_type(AddressSpace::default()),
-        "opaque_to_correct",
-    );
-
-    let struct2_ptr = env.builder.new_build_pointer_cast(
-        tag2,
-        wrapper_type.ptr_type(AddressSpace::default()),
-        "opaque_to_correct",
-    );
-
-    let struct1 = load_roc_value(
-        env,
-        layout_interner,
-        struct_layout,
-        struct1_ptr,
-        "load_struct1",
-    );
-    let struct2 = load_roc_value(
-        env,
-        layout_interner,
-        struct_layout,
-        struct2_ptr,
-        "load_struct2",
-    );
+    // the pointers are opaque, `load_roc_value` gives them the shape of the struct
+    let struct1 = load_roc_value(env, layout_interner, struct_layout, tag1, "load_struct1");
+    let struct2 = load_roc_value(env, layout_interner, struct_layout, tag2, "load_struct2");
 
     build_struct_eq(
         env,
@@ -1341,10 +1332,15 @@ fn build_box_eq<'a, 'ctx>(
     let fn_name = layout_ids
         .get(symbol, &box_layout)
         .to_symbol_string(symbol, &env.interns);
+    env.record_helper(layout_interner, &fn_name, symbol, box_layout);
 
-    let function = match env.module.get_function(fn_name.as_str()) {
-        Some(function_value) => function_value,
-        None => {
+    let function = eq_helper(
+        env,
+        layout_interner,
+        &fn_name,
+        CachedHelper::Eq,
+        box_layout,
+        || {
             let arg_type = basic_type_from_layout(env, layout_interner, box_layout);
 
             let function_value = crate::llvm::refcounting::build_header_help(
@@ -1363,8 +1359,8 @@ fn build_box_eq<'a, 'ctx>(
             );
 
             function_value
-        }
-    };
+        },
+    );
 
     env.builder.position_at_end(block);
     env.builder.set_current_debug_location(di_location);
@@ -7,14 +7,44 @@ use inkwell::types::{BasicType, BasicTypeEnum, FloatType, IntType, StructType};
 use inkwell::values::PointerValue;
 use inkwell::AddressSpace;
 use roc_builtins::bitcode::{FloatWidth, IntWidth};
+use roc_collections::all::MutMap;
 use roc_mono::layout::{
     round_up_to_alignment, Builtin, FunctionPointer, InLayout, Layout, LayoutInterner, LayoutRepr,
     STLayoutInterner, UnionLayout,
 };
 use roc_target::Target;
+use std::cell::RefCell;
 
 use super::struct_::RocStruct;
 
+/// The LLVM types of the interned layouts that we have converted so far. Converting a layout
+/// walks all of its fields, and we convert the same layouts over and over while building
+/// function bodies and refcounting helpers.
+#[derive(Default)]
+pub struct BasicTypeCache<'ctx> {
+    types: RefCell<MutMap<usize, BasicTypeEnum<'ctx>>>,
+}
+
+/// [`basic_type_from_layout`] for an interned layout, which is only converted the first time
+pub fn basic_type_from_in_layout<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout: InLayout<'_>,
+) -> BasicTypeEnum<'ctx> {
+    if let Some(basic_type) = env.basic_types.types.borrow().get(&layout.index()) {
+        return *basic_type;
+    }
+
+    let basic_type = basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
+
+    env.basic_types
+        .types
+        .borrow_mut()
+        .insert(layout.index(), basic_type);
+
+    basic_type
+}
+
 pub fn basic_type_from_layout<'a, 'ctx>(
     env: &Env<'a, 'ctx, '_>,
     layout_interner: &STLayoutInterner<'a>,
@@ -26,18 +56,12 @@ pub fn basic_type_from_layout<'a, 'ctx>(
         Struct(sorted_fields, ..) => {
             basic_type_from_record(env, layout_interner, sorted_fields).into()
         }
-        LambdaSet(lambda_set) => basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(lambda_set.runtime_representation()),
-        ),
+        LambdaSet(lambda_set) => {
+            basic_type_from_in_layout(env, layout_interner, lambda_set.runtime_representation())
+        }
 
         Ptr(inner_layout) => {
-            let inner_type = basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(inner_layout),
-            );
+            let inner_type = basic_type_from_in_layout(env, layout_interner, inner_layout);
 
             inner_type.ptr_type(AddressSpace::default()).into()
         }
@@ -50,9 +74,9 @@ pub fn basic_type_from_layout<'a, 'ctx>(
             .as_basic_type_enum(),
 
         FunctionPointer(self::FunctionPointer { args, ret }) => {
-            let args = args.iter().map(|arg| {
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(*arg))
-            });
+            let args = args
+                .iter()
+                .map(|arg| basic_type_from_in_layout(env, layout_interner, *arg));
 
             let ret_repr = layout_interner.get_repr(ret);
             let ret = basic_type_from_layout(env, layout_interner, ret_repr);
@@ -77,11 +101,7 @@ fn basic_type_from_record<'a, 'ctx>(
     let mut field_types = AVec::with_capacity_in(fields.len(), env.arena);
 
     for field_layout in fields.iter() {
-        let typ = basic_type_from_layout(
-            env,
-            layout_interner,
-            layout_interner.get_repr(*field_layout),
-        );
+        let typ = basic_type_from_in_layout(env, layout_interner, *field_layout);
 
         field_types.push(typ);
     }
@@ -0,0 +1,296 @@
+//! With `--crash-dumps`, a panic first hands the host a [CrashReport] with the call stack, and only
+//! then calls `roc_panic` like always.
+//!
+//! We walk the stack by following frame pointers, which every function keeps in this mode. To turn
+//! return addresses back into Roc names, the module has a table of its functions in a section of
+//! its own (see [crash_symbols_section]), which the report points to as well.
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::module::Linkage;
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue, PointerValue};
+use inkwell::{AddressSpace, IntPredicate};
+use roc_collections::all::MutMap;
+use roc_module::symbol::Symbol;
+use roc_std::RocStr;
+use roc_target::{Architecture, OperatingSystem, Target};
+
+use super::build::{BuilderExt, Env, C_CALL_CONV};
+use super::intrinsics::LLVM_FRAME_ADDRESS;
+use super::symbol_map::roc_name;
+
+/// The host function that receives the [CrashReport], as in `roc_crash_report(*const CrashReport)`
+pub const CRASH_REPORT_FN: &str = "roc_crash_report";
+
+/// We stop walking the stack after this many frames
+pub const MAX_CRASH_FRAMES: u32 = 64;
+
+/// What the host gets in [CRASH_REPORT_FN], right before `roc_panic` is called with the same
+/// message and tag. The report and its frames only live until `roc_crash_report` returns.
+#[repr(C)]
+#[derive(Debug)]
+pub struct CrashReport {
+    pub message: *const RocStr,
+    pub tag_id: u32,
+    /// Return addresses, starting in the function that panicked. The walk stops at the first frame
+    /// that does not look like one, which may be a few frames into the host.
+    pub frames: *const usize,
+    pub frame_count: usize,
+    /// Every function of the Roc module, in no particular order. A return address belongs to the
+    /// function with the closest start below it, if the address is in Roc code at all.
+    pub symbols: *const CrashSymbol,
+    pub symbol_count: usize,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct CrashSymbol {
+    pub address: usize,
+    /// A nul-terminated name like `List.map`. Functions without a Roc name, like refcounting
+    /// helpers and the zig builtins, keep their symbol name.
+    pub name: *const std::ffi::c_char,
+}
+
+/// Walking the stack assumes that a frame pointer points at the caller's frame pointer, with the
+/// return address right after it
+pub fn supports_crash_dumps(target: Target) -> bool {
+    matches!(
+        target.architecture(),
+        Architecture::X86_32 | Architecture::X86_64 | Architecture::Aarch64
+    )
+}
+
+/// The section with the [CrashSymbol] table. On ELF it is a valid C identifier, so that the linker
+/// defines `__start_roc_crash_symbols` and `__stop_roc_crash_symbols` for hosts that look for it.
+pub fn crash_symbols_section(target: Target) -> &'static str {
+    match target.operating_system() {
+        OperatingSystem::Linux | OperatingSystem::Freestanding => "roc_crash_symbols",
+        OperatingSystem::Mac => "__DATA,__roc_crash_sym",
+        // image section names can't be longer than 8 bytes
+        OperatingSystem::Windows => ".rocsyms",
+    }
+}
+
+/// Make every call to `roc_panic`, including the ones in the builtins, go through a wrapper that
+/// reports the crash first. Called once all procedures are built; `roc_functions` are the ones that
+/// have a Roc name.
+pub fn add_crash_reports<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    roc_functions: &[(FunctionValue<'ctx>, Symbol)],
+) {
+    let Some(roc_panic) = env.module.get_function("roc_panic") else {
+        return;
+    };
+
+    let (symbols, symbol_count) = add_crash_symbols(env, roc_functions);
+
+    let context = env.context;
+    let builder = env.builder;
+    let ptr_int = env.ptr_int();
+    let ptr_type = context.i8_type().ptr_type(AddressSpace::default());
+
+    let report_fn = env.module.get_function(CRASH_REPORT_FN).unwrap_or_else(|| {
+        let fn_type = context.void_type().fn_type(&[ptr_type.into()], false);
+        let function = env
+            .module
+            .add_function(CRASH_REPORT_FN, fn_type, Some(Linkage::External));
+        function.set_call_conventions(C_CALL_CONV);
+
+        function
+    });
+
+    let wrapper = env.module.add_function(
+        "roc_panic_with_crash_report",
+        roc_panic.get_type(),
+        Some(Linkage::Internal),
+    );
+    wrapper.set_call_conventions(roc_panic.get_call_conventions());
+
+    // the walk starts at the wrapper's own frame, so it must not be inlined into the function
+    // that panicked
+    let kind_id = Attribute::get_named_enum_kind_id("noinline");
+    debug_assert!(kind_id > 0);
+    wrapper.add_attribute(
+        AttributeLoc::Function,
+        context.create_enum_attribute(kind_id, 0),
+    );
+
+    // before the wrapper calls roc_panic, or the wrapper would call itself
+    roc_panic.replace_all_uses_with(wrapper);
+
+    let entry = context.append_basic_block(wrapper, "entry");
+    let walk = context.append_basic_block(wrapper, "walk");
+    let frame = context.append_basic_block(wrapper, "frame");
+    let report = context.append_basic_block(wrapper, "report");
+
+    builder.position_at_end(entry);
+
+    // the wrapper has no debug info, so it must not point at the last function we built
+    builder.unset_current_debug_location();
+
+    let frames_type = ptr_int.array_type(MAX_CRASH_FRAMES);
+    let frames = builder.new_build_alloca(frames_type, "frames");
+    let first_frame = env
+        .call_intrinsic(
+            LLVM_FRAME_ADDRESS,
+            &[context.i32_type().const_zero().into()],
+        )
+        .into_pointer_value();
+
+    builder.new_build_unconditional_branch(walk);
+
+    builder.position_at_end(walk);
+
+    let index = builder.new_build_phi(ptr_int, "index");
+    let frame_pointer = builder.new_build_phi(ptr_type, "frame_pointer");
+    let index_value = index.as_basic_value().into_int_value();
+    let frame_pointer_value = frame_pointer.as_basic_value().into_pointer_value();
+
+    let max_frames = ptr_int.const_int(MAX_CRASH_FRAMES as u64, false);
+    let has_room =
+        builder.new_build_int_compare(IntPredicate::ULT, index_value, max_frames, "has_room");
+    let is_frame = builder.new_build_is_not_null(frame_pointer_value, "is_frame");
+    let keep_walking = builder.new_build_and(has_room, is_frame, "keep_walking");
+
+    builder.new_build_conditional_branch(keep_walking, frame, report);
+
+    builder.position_at_end(frame);
+
+    // a frame starts with the caller's frame pointer, followed by the return address
+    let caller_frame = builder
+        .new_build_load(ptr_type, frame_pointer_value, "caller_frame")
+        .into_pointer_value();
+    let return_address_ptr = unsafe {
+        builder.new_build_in_bounds_gep(
+            ptr_int,
+            frame_pointer_value,
+            &[ptr_int.const_int(1, false)],
+            "return_address_ptr",
+        )
+    };
+    let return_address = builder.new_build_load(ptr_int, return_address_ptr, "return_address");
+
+    let slot = unsafe {
+        builder.new_build_in_bounds_gep(
+            frames_type,
+            frames,
+            &[ptr_int.const_zero(), index_value],
+            "slot",
+        )
+    };
+    builder.new_build_store(slot, return_address);
+
+    // the stack grows down, so a caller's frame is always above ours. Anything else means we
+    // reached a function that does not keep a frame pointer.
+    let caller_frame_int = builder.new_build_ptr_to_int(caller_frame, ptr_int, "caller_frame_int");
+    let frame_int = builder.new_build_ptr_to_int(frame_pointer_value, ptr_int, "frame_int");
+    let ascends =
+        builder.new_build_int_compare(IntPredicate::UGT, caller_frame_int, frame_int, "ascends");
+    let next_frame = builder
+        .new_build_select(ascends, caller_frame, ptr_type.const_null(), "next_frame")
+        .into_pointer_value();
+    let next_index =
+        builder.new_build_int_add(index_value, ptr_int.const_int(1, false), "next_index");
+
+    builder.new_build_unconditional_branch(walk);
+
+    index.add_incoming(&[(&ptr_int.const_zero(), entry), (&next_index, frame)]);
+    frame_pointer.add_incoming(&[(&first_frame, entry), (&next_frame, frame)]);
+
+    builder.position_at_end(report);
+
+    // laid out like CrashReport
+    let report_type = context.struct_type(
+        &[
+            ptr_type.into(),
+            context.i32_type().into(),
+            ptr_type.into(),
+            ptr_int.into(),
+            ptr_type.into(),
+            ptr_int.into(),
+        ],
+        false,
+    );
+
+    let arguments = wrapper.get_params();
+    let fields = [
+        arguments[0],
+        arguments[1],
+        frames.into(),
+        index_value.into(),
+        symbols.into(),
+        ptr_int.const_int(symbol_count as u64, false).into(),
+    ];
+
+    let report_ptr = builder.new_build_alloca(report_type, "report");
+
+    for (i, field) in fields.into_iter().enumerate() {
+        let field_ptr = builder.new_build_struct_gep(report_type, report_ptr, i as u32, "field");
+        builder.new_build_store(field_ptr, field);
+    }
+
+    let call = builder.new_build_call(report_fn, &[report_ptr.into()], "roc_crash_report");
+    call.set_call_convention(C_CALL_CONV);
+
+    let panic_arguments: Vec<BasicMetadataValueEnum> =
+        Vec::from_iter(arguments.iter().map(|argument| (*argument).into()));
+    let call = builder.new_build_call(roc_panic, &panic_arguments, "roc_panic");
+    call.set_call_convention(roc_panic.get_call_conventions());
+
+    builder.new_build_return(None);
+}
+
+/// Add the [CrashSymbol] table of every function in the module that has a body
+fn add_crash_symbols<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    roc_functions: &[(FunctionValue<'ctx>, Symbol)],
+) -> (PointerValue<'ctx>, usize) {
+    let context = env.context;
+    let ptr_type = context.i8_type().ptr_type(AddressSpace::default());
+    let entry_type = context.struct_type(&[ptr_type.into(), ptr_type.into()], false);
+
+    let mut roc_names = MutMap::default();
+
+    for (function, symbol) in roc_functions {
+        let name = roc_name(&env.interns, *symbol);
+
+        roc_names.insert(function.get_name().to_string_lossy().into_owned(), name);
+    }
+
+    let mut entries = Vec::new();
+
+    for function in env.module.get_functions() {
+        if function.count_basic_blocks() == 0 {
+            continue;
+        }
+
+        let symbol_name = function.get_name().to_string_lossy().into_owned();
+        let name = roc_names.get(&symbol_name).unwrap_or(&symbol_name);
+
+        let name_value = context.const_string(name.as_bytes(), true);
+        let name_global = env.module.add_global(
+            name_value.get_type(),
+            None,
+            &format!("roc_crash_symbol_name_{}", entries.len()),
+        );
+        name_global.set_initializer(&name_value);
+        name_global.set_constant(true);
+        name_global.set_linkage(Linkage::Private);
+        name_global.set_unnamed_addr(true);
+
+        let address = function.as_global_value().as_pointer_value();
+
+        entries.push(
+            entry_type.const_named_struct(&[address.into(), name_global.as_pointer_value().into()]),
+        );
+    }
+
+    let table_value = entry_type.const_array(&entries);
+    let table = env
+        .module
+        .add_global(table_value.get_type(), None, "roc_crash_symbols");
+    table.set_initializer(&table_value);
+    table.set_constant(true);
+    table.set_linkage(Linkage::Internal);
+    table.set_section(Some(crash_symbols_section(env.target)));
+
+    (table.as_pointer_value(), entries.len())
+}
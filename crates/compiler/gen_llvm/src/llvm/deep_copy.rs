@@ -0,0 +1,507 @@
+//! Copy a value so that the copy shares no heap allocations with the original.
+//!
+//! Every string, list and recursive tag union that the value (transitively) points to is copied
+//! into a fresh allocation with a refcount of one. Strings that fit on the stack, and values
+//! without any heap allocations, are copied with a plain `memcpy`. Constant strings and lists are
+//! never freed or updated in place, so the copy shares them.
+use crate::debug_info_init;
+use crate::llvm::bitcode::{
+    build_copy_wrapper, call_list_bitcode_fn, call_str_bitcode_fn, BitcodeReturns,
+};
+use crate::llvm::build::{
+    allocate_with_refcount, get_tag_id, load_roc_value, store_roc_value, tag_pointer_clear_tag_id,
+    tag_pointer_tag_id_bits_and_mask, BuilderExt, Env, C_CALL_CONV,
+};
+use crate::llvm::build_list::{layout_refcounted, layout_width};
+use crate::llvm::convert::{basic_type_from_in_layout, basic_type_from_layout, RocUnion};
+use crate::llvm::heap_header::HeapHeader;
+use crate::llvm::memcpy::build_memcpy;
+use crate::llvm::refcounting::build_header_help;
+use bumpalo::collections::Vec;
+use inkwell::types::{BasicType, StructType};
+use inkwell::values::{FunctionValue, PointerValue};
+use inkwell::AddressSpace;
+use roc_builtins::bitcode;
+use roc_error_macros::todo_lambda_erasure;
+use roc_module::symbol::Symbol;
+use roc_mono::layout::{
+    Builtin, InLayout, LayoutIds, LayoutInterner, LayoutRepr, STLayoutInterner, UnionLayout,
+};
+
+/// Deep-copy the value of `layout` behind `source` into `destination`
+pub fn deep_copy_layout<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    layout: InLayout<'a>,
+    destination: PointerValue<'ctx>,
+    source: PointerValue<'ctx>,
+) {
+    let repr = layout_interner.get_repr(layout);
+
+    if !needs_deep_copy(layout_interner, layout) {
+        build_memcpy(env, layout_interner, repr, destination, source);
+        return;
+    }
+
+    match repr {
+        LayoutRepr::Builtin(Builtin::Str) => {
+            let string = load_roc_value(env, layout_interner, repr, source, "string");
+
+            let copy = call_str_bitcode_fn(
+                env,
+                &[string],
+                &[],
+                BitcodeReturns::Str,
+                bitcode::STR_DEEP_COPY,
+            );
+
+            store_roc_value(env, layout_interner, repr, destination, copy);
+        }
+        LayoutRepr::Builtin(Builtin::List(element_layout)) => {
+            let list = load_roc_value(env, layout_interner, repr, source, "list");
+
+            // zig calls back into this function for every element
+            let copy_element_fn = if needs_deep_copy(layout_interner, element_layout) {
+                build_deep_copy_function(env, layout_interner, layout_ids, element_layout)
+            } else {
+                build_copy_wrapper(env, layout_interner, layout_ids, element_layout)
+            };
+
+            let copy = call_list_bitcode_fn(
+                env,
+                &[list.into_struct_value()],
+                &[
+                    env.alignment_intvalue(layout_interner, element_layout),
+                    layout_width(env, layout_interner, element_layout),
+                    layout_refcounted(env, layout_interner, element_layout),
+                    copy_element_fn.as_global_value().as_pointer_value().into(),
+                ],
+                BitcodeReturns::List,
+                bitcode::LIST_DEEP_COPY,
+            );
+
+            store_roc_value(env, layout_interner, repr, destination, copy);
+        }
+        LayoutRepr::Struct(_) | LayoutRepr::Union(_) => {
+            let function = build_deep_copy_function(env, layout_interner, layout_ids, layout);
+
+            let call = env.builder.new_build_call(
+                function,
+                &[destination.into(), source.into()],
+                "deep_copy",
+            );
+
+            call.set_call_convention(C_CALL_CONV);
+        }
+        LayoutRepr::LambdaSet(lambda_set) => deep_copy_layout(
+            env,
+            layout_interner,
+            layout_ids,
+            lambda_set.runtime_representation(),
+            destination,
+            source,
+        ),
+        LayoutRepr::RecursivePointer(union_layout) => deep_copy_layout(
+            env,
+            layout_interner,
+            layout_ids,
+            union_layout,
+            destination,
+            source,
+        ),
+        LayoutRepr::Erased(_) => todo_lambda_erasure!(),
+        LayoutRepr::Builtin(_) | LayoutRepr::Ptr(_) | LayoutRepr::FunctionPointer(_) => {
+            build_memcpy(env, layout_interner, repr, destination, source)
+        }
+    }
+}
+
+fn needs_deep_copy<'a>(layout_interner: &STLayoutInterner<'a>, layout: InLayout<'a>) -> bool {
+    let repr = layout_interner.get_repr(layout);
+
+    matches!(repr, LayoutRepr::RecursivePointer(_))
+        || repr.is_refcounted(layout_interner)
+        || repr.contains_refcounted(layout_interner)
+}
+
+/// A `void (i8* destination, i8* source)` function that deep-copies a value of `layout`. It uses
+/// the C calling convention, so that the zig builtins can call it for list elements.
+fn build_deep_copy_function<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    layout: InLayout<'a>,
+) -> FunctionValue<'ctx> {
+    let repr = layout_interner.get_repr(layout);
+
+    let symbol = Symbol::GENERIC_COPY_REF;
+    let fn_name = layout_ids
+        .get(symbol, &repr)
+        .to_symbol_string(symbol, &env.interns);
+    let fn_name = format!("{fn_name}_deep");
+    env.record_helper(layout_interner, &fn_name, symbol, repr);
+
+    if let Some(function_value) = env.module.get_function(&fn_name) {
+        return function_value;
+    }
+
+    let block = env.builder.get_insert_block().expect("to be in a function");
+    let di_location = env.builder.get_current_debug_location().unwrap();
+
+    let arg_type = env.context.i8_type().ptr_type(AddressSpace::default());
+
+    let function_value = build_header_help(
+        env,
+        &fn_name,
+        env.context.void_type().into(),
+        &[arg_type.into(), arg_type.into()],
+    );
+
+    // called from zig, must use C calling convention
+    function_value.set_call_conventions(C_CALL_CONV);
+
+    let entry = env.context.append_basic_block(function_value, "entry");
+    env.builder.position_at_end(entry);
+
+    debug_info_init!(env, function_value);
+
+    let mut it = function_value.get_param_iter();
+    let dst_ptr = it.next().unwrap().into_pointer_value();
+    let src_ptr = it.next().unwrap().into_pointer_value();
+
+    dst_ptr.set_name(Symbol::ARG_1.as_str(&env.interns));
+    src_ptr.set_name(Symbol::ARG_2.as_str(&env.interns));
+
+    match repr {
+        LayoutRepr::Struct(field_layouts) => {
+            build_memcpy(env, layout_interner, repr, dst_ptr, src_ptr);
+
+            let struct_type = basic_type_from_layout(env, layout_interner, repr).into_struct_type();
+
+            deep_copy_fields(
+                env,
+                layout_interner,
+                layout_ids,
+                struct_type,
+                field_layouts,
+                dst_ptr,
+                src_ptr,
+            );
+
+            env.builder.new_build_return(None);
+        }
+        LayoutRepr::Union(UnionLayout::NonRecursive(tags)) => {
+            build_memcpy(env, layout_interner, repr, dst_ptr, src_ptr);
+
+            build_deep_copy_nonrecursive_help(
+                env,
+                layout_interner,
+                layout_ids,
+                function_value,
+                tags,
+                dst_ptr,
+                src_ptr,
+            );
+        }
+        LayoutRepr::Union(union_layout) => {
+            build_deep_copy_recursive_help(
+                env,
+                layout_interner,
+                layout_ids,
+                function_value,
+                union_layout,
+                dst_ptr,
+                src_ptr,
+            );
+        }
+        _ => {
+            deep_copy_layout(env, layout_interner, layout_ids, layout, dst_ptr, src_ptr);
+
+            env.builder.new_build_return(None);
+        }
+    }
+
+    env.builder.position_at_end(block);
+    env.builder.set_current_debug_location(di_location);
+
+    function_value
+}
+
+/// The fields were already copied with a `memcpy`; replace the ones that point to the heap
+/// by deep copies
+fn deep_copy_fields<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    struct_type: StructType<'ctx>,
+    field_layouts: &[InLayout<'a>],
+    destination: PointerValue<'ctx>,
+    source: PointerValue<'ctx>,
+) {
+    for (i, field_layout) in field_layouts.iter().enumerate() {
+        if !needs_deep_copy(layout_interner, *field_layout) {
+            continue;
+        }
+
+        let dst_field =
+            env.builder
+                .new_build_struct_gep(struct_type, destination, i as u32, "dst_field");
+        let src_field =
+            env.builder
+                .new_build_struct_gep(struct_type, source, i as u32, "src_field");
+
+        deep_copy_layout(
+            env,
+            layout_interner,
+            layout_ids,
+            *field_layout,
+            dst_field,
+            src_field,
+        );
+    }
+}
+
+fn build_deep_copy_nonrecursive_help<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    parent: FunctionValue<'ctx>,
+    tags: &'a [&'a [InLayout<'a>]],
+    destination: PointerValue<'ctx>,
+    source: PointerValue<'ctx>,
+) {
+    let union_layout = UnionLayout::NonRecursive(tags);
+    let union_struct_type =
+        basic_type_from_layout(env, layout_interner, LayoutRepr::Union(union_layout))
+            .into_struct_type();
+
+    let tag_id = get_tag_id(env, layout_interner, parent, &union_layout, source.into());
+    let tag_id_int_type = tag_id.get_type();
+
+    let before_block = env.builder.get_insert_block().expect("to be in a function");
+    let merge_block = env.context.append_basic_block(parent, "deep_copy_merge");
+
+    let mut cases = Vec::with_capacity_in(tags.len(), env.arena);
+
+    for (tag_id, field_layouts) in tags.iter().enumerate() {
+        if !field_layouts
+            .iter()
+            .any(|field| needs_deep_copy(layout_interner, *field))
+        {
+            continue;
+        }
+
+        let block = env.context.append_basic_block(parent, "tag_id_deep_copy");
+        env.builder.position_at_end(block);
+
+        let data_struct_type =
+            basic_type_from_layout(env, layout_interner, LayoutRepr::struct_(field_layouts))
+                .into_struct_type();
+
+        let tag_data_ptr = |ptr, name| {
+            env.builder
+                .new_build_struct_gep(union_struct_type, ptr, RocUnion::TAG_DATA_INDEX, name)
+        };
+
+        let dst_data = tag_data_ptr(destination, "dst_data");
+        let src_data = tag_data_ptr(source, "src_data");
+
+        deep_copy_fields(
+            env,
+            layout_interner,
+            layout_ids,
+            data_struct_type,
+            field_layouts,
+            dst_data,
+            src_data,
+        );
+
+        env.builder.new_build_unconditional_branch(merge_block);
+
+        cases.push((tag_id_int_type.const_int(tag_id as u64, false), block));
+    }
+
+    env.builder.position_at_end(before_block);
+    env.builder.new_build_switch(tag_id, merge_block, &cases);
+
+    env.builder.position_at_end(merge_block);
+    env.builder.new_build_return(None);
+}
+
+/// Copy the cell that `source` points to into a new allocation, and write a pointer to that
+/// allocation (with the same tag id) to `destination`
+fn build_deep_copy_recursive_help<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    parent: FunctionValue<'ctx>,
+    union_layout: UnionLayout<'a>,
+    destination: PointerValue<'ctx>,
+    source: PointerValue<'ctx>,
+) {
+    use UnionLayout::*;
+
+    let union_type = basic_type_from_layout(env, layout_interner, LayoutRepr::Union(union_layout))
+        .into_pointer_type();
+
+    let tagged_ptr = env
+        .builder
+        .new_build_load(union_type, source, "load_cell_ptr")
+        .into_pointer_value();
+
+    let stores_tag_id_in_pointer = union_layout.stores_tag_id_in_pointer(env.target);
+
+    let cell_ptr = if stores_tag_id_in_pointer {
+        tag_pointer_clear_tag_id(env, tagged_ptr)
+    } else {
+        tagged_ptr
+    };
+
+    let copy_block = env.context.append_basic_block(parent, "copy_cell");
+
+    if union_layout.is_nullable() {
+        let is_null = env.builder.new_build_is_null(cell_ptr, "is_null");
+        let null_block = env.context.append_basic_block(parent, "null");
+
+        env.builder
+            .new_build_conditional_branch(is_null, null_block, copy_block);
+
+        // the null variant has no cell to copy
+        env.builder.position_at_end(null_block);
+        env.builder.new_build_store(destination, tagged_ptr);
+        env.builder.new_build_return(None);
+    } else {
+        env.builder.new_build_unconditional_branch(copy_block);
+    }
+
+    env.builder.position_at_end(copy_block);
+
+    let (nullable_id, tags) = match union_layout {
+        NonRecursive(_) => unreachable!("non-recursive unions are not behind a pointer"),
+        Recursive(tags) => (None, tags),
+        NonNullableUnwrapped(fields) => (None, std::slice::from_ref(env.arena.alloc(fields))),
+        NullableWrapped {
+            nullable_id,
+            other_tags,
+        } => (Some(nullable_id), other_tags),
+        NullableUnwrapped {
+            nullable_id,
+            other_fields,
+        } => (
+            Some(nullable_id as u16),
+            std::slice::from_ref(env.arena.alloc(other_fields)),
+        ),
+    };
+
+    let roc_union = if union_layout.stores_tag_id_as_data(env.target) {
+        RocUnion::tagged_from_slices(layout_interner, env.context, tags)
+    } else {
+        RocUnion::untagged_from_slices(layout_interner, env.context, tags)
+    };
+
+    let cell_bytes = env.ptr_int().const_int(roc_union.tag_width() as u64, false);
+    let alignment = roc_union.tag_alignment();
+
+    let header = HeapHeader::refcount_only(env.target);
+    let new_cell_ptr = allocate_with_refcount(env, cell_bytes, alignment, header);
+
+    // copies the tag id too, when it is stored in the cell
+    env.builder
+        .build_memcpy(new_cell_ptr, alignment, cell_ptr, alignment, cell_bytes)
+        .unwrap();
+
+    let tag_id_int_type =
+        basic_type_from_in_layout(env, layout_interner, union_layout.tag_id_layout())
+            .into_int_type();
+
+    let done_block = env.context.append_basic_block(parent, "copy_cell_done");
+    let mut cases = Vec::with_capacity_in(tags.len(), env.arena);
+
+    for (tag_id, field_layouts) in tags.iter().enumerate() {
+        let tag_id = match nullable_id {
+            // tags after the nullable tag have an id that is one higher than their index
+            Some(null_id) if tag_id as u16 >= null_id => tag_id + 1,
+            _ => tag_id,
+        };
+
+        if !field_layouts
+            .iter()
+            .any(|field| needs_deep_copy(layout_interner, *field))
+        {
+            continue;
+        }
+
+        let block = env.context.append_basic_block(parent, "tag_id_deep_copy");
+        env.builder.position_at_end(block);
+
+        let fields_struct_type =
+            basic_type_from_layout(env, layout_interner, LayoutRepr::struct_(field_layouts))
+                .into_struct_type();
+
+        deep_copy_fields(
+            env,
+            layout_interner,
+            layout_ids,
+            fields_struct_type,
+            field_layouts,
+            new_cell_ptr,
+            cell_ptr,
+        );
+
+        env.builder.new_build_unconditional_branch(done_block);
+
+        cases.push((tag_id_int_type.const_int(tag_id as u64, false), block));
+    }
+
+    env.builder.position_at_end(copy_block);
+
+    match union_layout {
+        NonNullableUnwrapped(_) | NullableUnwrapped { .. } => {
+            // there is only one tag with a cell, so there is no tag id to read
+            match cases.pop() {
+                Some((_, only_block)) => env.builder.new_build_unconditional_branch(only_block),
+                None => env.builder.new_build_unconditional_branch(done_block),
+            };
+        }
+        _ => {
+            let tag_id = get_tag_id(
+                env,
+                layout_interner,
+                parent,
+                &union_layout,
+                tagged_ptr.into(),
+            );
+
+            env.builder.new_build_switch(tag_id, done_block, &cases);
+        }
+    }
+
+    env.builder.position_at_end(done_block);
+
+    let new_tagged_ptr = if stores_tag_id_in_pointer {
+        // move the tag id bits of the original pointer over to the new one
+        let (_, mask) = tag_pointer_tag_id_bits_and_mask(env.target);
+
+        let as_int = env
+            .builder
+            .new_build_ptr_to_int(tagged_ptr, env.ptr_int(), "to_int");
+        let tag_id_bits =
+            env.builder
+                .new_build_and(as_int, env.ptr_int().const_int(mask, false), "tag_id_bits");
+
+        unsafe {
+            env.builder.new_build_in_bounds_gep(
+                env.context.i8_type(),
+                new_cell_ptr,
+                &[tag_id_bits],
+                "tag_new_cell",
+            )
+        }
+    } else {
+        new_cell_ptr
+    };
+
+    env.builder.new_build_store(destination, new_tagged_ptr);
+    env.builder.new_build_return(None);
+}
@@ -0,0 +1,89 @@
+//! `roc test` runs effectful expects without the platform, so their effects can be replaced by
+//! mocks. In a test build, every effect is called through a mock slot: a global named after the
+//! effect's host function, like `roc_fx_putLine_mock`. The test runner can put a function with the
+//! same C signature in the slot. When the slot is empty, the host function is called like always.
+//!
+//! The runner gets its mocks from a library that exports a [MOCK_TABLE].
+use std::ffi::{c_char, c_void};
+
+use inkwell::module::Linkage;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue};
+use inkwell::AddressSpace;
+
+use super::build::{BuilderExt, Env, C_CALL_CONV};
+
+/// The table of mocks that a mock library exports, as in `const RocFxMock roc_fx_mocks[]`
+pub const MOCK_TABLE: &str = "roc_fx_mocks";
+
+/// An entry of the [MOCK_TABLE]. The table ends with an entry whose `name` is null.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RocFxMock {
+    /// The host function that this replaces, like `roc_fx_putLine`
+    pub name: *const c_char,
+    /// A function with the same C signature as that host function
+    pub function: *const c_void,
+}
+
+/// The global that holds the mock of the effect with this host function
+pub fn mock_slot_name(host_function: &str) -> String {
+    format!("{host_function}_mock")
+}
+
+/// Calls the mock of `host_function` when the runner has put one in its slot, and
+/// `host_function` itself otherwise. Returns what the call returns, if anything.
+///
+/// We branch instead of picking the function to call, because taking the address of the host
+/// function would make loading the test build fail when the platform does not provide it.
+pub(crate) fn call_effect_or_mock<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    host_function: FunctionValue<'ctx>,
+    arguments: &[BasicMetadataValueEnum<'ctx>],
+) -> Option<BasicValueEnum<'ctx>> {
+    let builder = env.builder;
+    let ptr_type = env.context.i8_type().ptr_type(AddressSpace::default());
+
+    let slot_name = mock_slot_name(host_function.get_name().to_str().unwrap());
+    let slot = match env.module.get_global(&slot_name) {
+        Some(slot) => slot,
+        None => {
+            let slot = env.module.add_global(ptr_type, None, &slot_name);
+            slot.set_linkage(Linkage::External);
+            slot.set_initializer(&ptr_type.const_null());
+            slot
+        }
+    };
+
+    let mock = builder
+        .new_build_load(ptr_type, slot.as_pointer_value(), "load_mock")
+        .into_pointer_value();
+
+    let parent = builder.get_insert_block().unwrap().get_parent().unwrap();
+    let call_host = env.context.append_basic_block(parent, "call_host_function");
+    let call_mock = env.context.append_basic_block(parent, "call_mock");
+    let done = env.context.append_basic_block(parent, "effect_done");
+
+    let is_mocked = builder.new_build_is_not_null(mock, "is_mocked");
+    builder.new_build_conditional_branch(is_mocked, call_mock, call_host);
+
+    builder.position_at_end(call_host);
+    let host_call = builder.new_build_call(host_function, arguments, "call_host_function");
+    host_call.set_call_convention(C_CALL_CONV);
+    builder.new_build_unconditional_branch(done);
+
+    builder.position_at_end(call_mock);
+    let mock_call =
+        builder.new_build_indirect_call(host_function.get_type(), mock, arguments, "call_mock");
+    mock_call.set_call_convention(C_CALL_CONV);
+    builder.new_build_unconditional_branch(done);
+
+    builder.position_at_end(done);
+
+    let host_result = host_call.try_as_basic_value().left()?;
+    let mock_result = mock_call.try_as_basic_value().left()?;
+
+    let phi = builder.new_build_phi(host_result.get_type(), "effect_result");
+    phi.add_incoming(&[(&host_result, call_host), (&mock_result, call_mock)]);
+
+    Some(phi.as_basic_value())
+}
@@ -4,7 +4,7 @@ use crate::llvm::build::{
     create_entry_block_alloca, get_tag_id, store_roc_value, tag_pointer_clear_tag_id, Env,
 };
 use crate::llvm::build_list::{self, incrementing_elem_loop};
-use crate::llvm::convert::{basic_type_from_layout, RocUnion};
+use crate::llvm::convert::{basic_type_from_in_layout, basic_type_from_layout, RocUnion};
 use inkwell::builder::Builder;
 use inkwell::module::Linkage;
 use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
@@ -349,7 +349,7 @@ fn build_clone<'a, 'ctx>(
         LayoutRepr::RecursivePointer(rec_layout) => {
             let layout = rec_layout;
 
-            let bt = basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
+            let bt = basic_type_from_in_layout(env, layout_interner, layout);
 
             // cast the i64 pointer to a pointer to block of memory
             let field1_cast = env.builder.new_build_pointer_cast(
@@ -437,6 +437,7 @@ fn build_clone_tag<'a, 'ctx>(
     let layout = LayoutRepr::Union(union_layout);
     let layout_id = layout_ids.get(Symbol::CLONE, &layout);
     let fn_name = layout_id.to_symbol_string(Symbol::CLONE, &env.interns);
+    env.record_helper(layout_interner, &fn_name, Symbol::CLONE, layout);
 
     let function = match env.module.get_function(fn_name.as_str()) {
         Some(function_value) => function_value,
@@ -470,8 +471,6 @@ fn build_clone_tag<'a, 'ctx>(
 
             debug_info_init!(env, function_value);
 
-            env.dibuilder.finalize();
-
             build_clone_tag_help(
                 env,
                 layout_interner,
@@ -1023,8 +1022,7 @@ fn build_clone_builtin<'a, 'ctx>(
 
                 bd.new_build_int_add(elements_start_offset, elements_width, "new_offset")
             } else {
-                let element_type =
-                    basic_type_from_layout(env, layout_interner, layout_interner.get_repr(elem));
+                let element_type = basic_type_from_in_layout(env, layout_interner, elem);
                 let elements = bd.new_build_pointer_cast(
                     elements,
                     element_type.ptr_type(AddressSpace::default()),
@@ -1,11 +1,15 @@
 use crate::debug_info_init;
 use crate::llvm::bitcode::call_void_bitcode_fn;
-use crate::llvm::build::{add_func, get_panic_msg_ptr, get_panic_tag_ptr, BuilderExt, C_CALL_CONV};
+use crate::llvm::build::{
+    add_func, get_panic_msg_ptr, get_panic_tag_ptr, throw_internal_exception, BuilderExt,
+    C_CALL_CONV,
+};
 use crate::llvm::build::{CCReturn, Env, FunctionSpec};
 use crate::llvm::convert::zig_str_type;
-use inkwell::module::Linkage;
+use crate::llvm::memory::MemoryCounter;
+use inkwell::module::{Linkage, Module};
 use inkwell::types::BasicType;
-use inkwell::AddressSpace;
+use inkwell::{AddressSpace, IntPredicate};
 use roc_builtins::bitcode;
 
 use super::build::get_sjlj_buffer;
@@ -55,6 +59,8 @@ pub fn add_default_roc_externs(env: &Env<'_, '_, '_>) {
 
             builder.position_at_end(entry);
 
+            env.memory_strategy.count(env, MemoryCounter::Allocations);
+
             // Call libc malloc()
             let retval = builder
                 .build_array_malloc(ctx.i8_type(), size_arg.into_int_value(), "call_malloc")
@@ -151,6 +157,8 @@ pub fn add_default_roc_externs(env: &Env<'_, '_, '_>) {
 
             builder.position_at_end(entry);
 
+            env.memory_strategy.count(env, MemoryCounter::Deallocations);
+
             // Call libc free()
             builder.new_build_free(ptr_arg.into_pointer_value());
 
@@ -208,6 +216,79 @@ pub fn add_default_roc_externs(env: &Env<'_, '_, '_>) {
 
         add_sjlj_roc_panic(env)
     }
+
+    add_parallel_for(env);
+}
+
+/// Defines roc_parallel_for when the tasks of List.mapParallel should not go to a thread pool:
+/// without a host they run in order, and with `--shuffle-parallel` in a shuffled order.
+fn add_parallel_for(env: &Env<'_, '_, '_>) {
+    let ctx = env.context;
+    let builder = env.builder;
+
+    if env.shuffle_parallel_tasks.is_none() && env.mode.has_host() {
+        return;
+    }
+
+    // The type of this function (but not the implementation) should have
+    // already been defined by the builtins, which rely on it.
+    let fn_val = env.module.get_function("roc_parallel_for").unwrap();
+
+    // Internal, so that it takes precedence over the host's thread pool
+    fn_val.set_linkage(Linkage::Internal);
+
+    let mut params = fn_val.get_param_iter();
+    let context_arg = params.next().unwrap();
+    let task_count_arg = params.next().unwrap();
+    let task_arg = params.next().unwrap();
+
+    debug_assert!(params.next().is_none());
+
+    // Add a basic block for the entry point
+    let entry = ctx.append_basic_block(fn_val, "entry");
+
+    builder.position_at_end(entry);
+
+    let call = match env.shuffle_parallel_tasks {
+        Some(seed) => {
+            let parallel_for_shuffled = env
+                .module
+                .get_function(bitcode::UTILS_PARALLEL_FOR_SHUFFLED)
+                .unwrap();
+            let seed = ctx.i64_type().const_int(seed, false);
+
+            builder.new_build_call(
+                parallel_for_shuffled,
+                &[
+                    context_arg.into(),
+                    task_count_arg.into(),
+                    task_arg.into(),
+                    seed.into(),
+                ],
+                "call_utils_parallel_for_shuffled",
+            )
+        }
+        None => {
+            let parallel_for_sequential = env
+                .module
+                .get_function(bitcode::UTILS_PARALLEL_FOR_SEQUENTIAL)
+                .unwrap();
+
+            builder.new_build_call(
+                parallel_for_sequential,
+                &[context_arg.into(), task_count_arg.into(), task_arg.into()],
+                "call_utils_parallel_for_sequential",
+            )
+        }
+    };
+
+    call.set_call_convention(C_CALL_CONV);
+
+    builder.new_build_return(None);
+
+    if cfg!(debug_assertions) {
+        crate::llvm::build::verify_fn(fn_val);
+    }
 }
 
 fn unreachable_function(env: &Env, name: &str) {
@@ -257,8 +338,6 @@ pub fn add_sjlj_roc_panic(env: &Env<'_, '_, '_>) {
 
         debug_info_init!(env, fn_val);
 
-        env.dibuilder.finalize();
-
         // Add a basic block for the entry point
         let entry = ctx.append_basic_block(fn_val, "entry");
 
@@ -337,3 +416,204 @@ pub fn build_longjmp_call(env: &Env) {
         let _call = env.build_intrinsic_call(LLVM_LONGJMP, &[jmp_buf_i8p.into()]);
     }
 }
+
+const STATIC_HEAP: &str = "roc_static_heap";
+const STATIC_HEAP_USED: &str = "roc_static_heap_used";
+const STATIC_HEAP_ALIGNMENT: u32 = 16;
+
+/// Define roc_alloc, roc_realloc, and roc_dealloc on top of a static buffer of `heap_bytes`
+/// bytes, for platforms that have no heap at all.
+///
+/// Memory is handed out by bumping a pointer and is never reused: roc_dealloc does nothing, and
+/// roc_realloc always moves the allocation. When the buffer is exhausted, the program panics
+/// through roc_panic.
+pub fn add_static_heap_externs(env: &Env<'_, '_, '_>, heap_bytes: u32) {
+    let ctx = env.context;
+    let module = env.module;
+    let builder = env.builder;
+
+    let usize_type = env.ptr_int();
+    let i8_ptr_type = ctx.i8_type().ptr_type(AddressSpace::default());
+
+    let heap_type = ctx.i8_type().array_type(heap_bytes);
+    let heap = module.add_global(heap_type, None, STATIC_HEAP);
+    heap.set_linkage(Linkage::Internal);
+    heap.set_initializer(&heap_type.const_zero());
+    heap.set_alignment(STATIC_HEAP_ALIGNMENT);
+
+    let used = module.add_global(usize_type, None, STATIC_HEAP_USED);
+    used.set_linkage(Linkage::Internal);
+    used.set_initializer(&usize_type.const_zero());
+
+    // roc_alloc
+    let roc_alloc = {
+        // The type of this function (but not the implementation) should have
+        // already been defined by the builtins, which rely on it.
+        let fn_val = module.get_function("roc_alloc").unwrap();
+        fn_val.set_linkage(Linkage::Internal);
+
+        let mut params = fn_val.get_param_iter();
+        let size_arg = params.next().unwrap().into_int_value();
+        let alignment_arg = params.next().unwrap().into_int_value();
+
+        debug_assert!(params.next().is_none());
+
+        let entry = ctx.append_basic_block(fn_val, "entry");
+        let out_of_memory = ctx.append_basic_block(fn_val, "out_of_memory");
+        let success = ctx.append_basic_block(fn_val, "success");
+
+        builder.position_at_end(entry);
+
+        let heap_start =
+            builder.new_build_ptr_to_int(heap.as_pointer_value(), usize_type, "heap_start");
+        let heap_end = builder.new_build_int_add(
+            heap_start,
+            usize_type.const_int(heap_bytes as u64, false),
+            "heap_end",
+        );
+
+        let used_bytes = builder
+            .new_build_load(usize_type, used.as_pointer_value(), "used_bytes")
+            .into_int_value();
+        let next_free = builder.new_build_int_add(heap_start, used_bytes, "next_free");
+
+        // round up to the requested alignment, which is always a power of two
+        let alignment = builder.new_build_int_z_extend(alignment_arg, usize_type, "alignment");
+        let alignment_mask =
+            builder.new_build_int_sub(alignment, usize_type.const_int(1, false), "alignment_mask");
+        let unaligned = builder.new_build_int_add(next_free, alignment_mask, "unaligned");
+        let allocation_start = builder.new_build_and(
+            unaligned,
+            builder.new_build_not(alignment_mask, "not_alignment_mask"),
+            "allocation_start",
+        );
+        let allocation_end =
+            builder.new_build_int_add(allocation_start, size_arg, "allocation_end");
+
+        // also catches the allocation wrapping around the address space
+        let fits = builder.new_build_and(
+            builder.new_build_int_compare(
+                IntPredicate::ULE,
+                allocation_end,
+                heap_end,
+                "fits_in_heap",
+            ),
+            builder.new_build_int_compare(
+                IntPredicate::UGE,
+                allocation_end,
+                allocation_start,
+                "no_overflow",
+            ),
+            "fits",
+        );
+
+        builder.new_build_conditional_branch(fits, success, out_of_memory);
+
+        builder.position_at_end(out_of_memory);
+        throw_internal_exception(env, "The static heap of this program is exhausted.");
+
+        builder.position_at_end(success);
+
+        let new_used = builder.new_build_int_sub(allocation_end, heap_start, "new_used");
+        builder.new_build_store(used.as_pointer_value(), new_used);
+
+        let allocation = builder.new_build_int_to_ptr(allocation_start, i8_ptr_type, "allocation");
+        builder.new_build_return(Some(&allocation));
+
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
+        }
+
+        fn_val
+    };
+
+    // roc_realloc
+    {
+        // The type of this function (but not the implementation) should have
+        // already been defined by the builtins, which rely on it.
+        let fn_val = module.get_function("roc_realloc").unwrap();
+        fn_val.set_linkage(Linkage::Internal);
+
+        let mut params = fn_val.get_param_iter();
+        let ptr_arg = params.next().unwrap().into_pointer_value();
+        let new_size_arg = params.next().unwrap().into_int_value();
+        let old_size_arg = params.next().unwrap().into_int_value();
+        let alignment_arg = params.next().unwrap();
+
+        debug_assert!(params.next().is_none());
+
+        let entry = ctx.append_basic_block(fn_val, "entry");
+
+        builder.position_at_end(entry);
+
+        let call = builder.new_build_call(
+            roc_alloc,
+            &[new_size_arg.into(), alignment_arg.into()],
+            "call_roc_alloc",
+        );
+        call.set_call_convention(C_CALL_CONV);
+
+        let new_ptr = call
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+
+        let is_smaller = builder.new_build_int_compare(
+            IntPredicate::ULT,
+            new_size_arg,
+            old_size_arg,
+            "is_smaller",
+        );
+        let copy_bytes = builder
+            .new_build_select(is_smaller, new_size_arg, old_size_arg, "copy_bytes")
+            .into_int_value();
+
+        builder
+            .build_memcpy(new_ptr, 1, ptr_arg, 1, copy_bytes)
+            .unwrap();
+
+        builder.new_build_return(Some(&new_ptr));
+
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
+        }
+    }
+
+    // roc_dealloc
+    {
+        // The type of this function (but not the implementation) should have
+        // already been defined by the builtins, which rely on it.
+        let fn_val = module.get_function("roc_dealloc").unwrap();
+        fn_val.set_linkage(Linkage::Internal);
+
+        let entry = ctx.append_basic_block(fn_val, "entry");
+
+        builder.position_at_end(entry);
+        builder.new_build_return(None);
+
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
+        }
+    }
+}
+
+/// Functions that a freestanding C environment must provide, and that LLVM may emit calls to
+/// on its own.
+const FREESTANDING_FUNCTIONS: &[&str] = &["memcpy", "memmove", "memset", "memcmp"];
+
+/// Functions the (optimized) module calls but does not define, that a freestanding platform
+/// would not provide: everything except the `roc_` functions the host implements, LLVM
+/// intrinsics, and the functions every freestanding C environment has.
+pub fn libc_dependencies(module: &Module<'_>) -> Vec<String> {
+    module
+        .get_functions()
+        .filter(|function| function.count_basic_blocks() == 0)
+        .map(|function| function.get_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            !name.starts_with("roc_")
+                && !name.starts_with("llvm.")
+                && !FREESTANDING_FUNCTIONS.contains(&name.as_str())
+        })
+        .collect()
+}
@@ -0,0 +1,51 @@
+//! The header in front of the data of a refcounted allocation, as the generated code sees it.
+//!
+//! `utils.zig` in the builtins lays out the same header, and is the one that writes it when memory
+//! is allocated:
+//!
+//!     [ element count ][ refcount ][ data ... ]
+//!
+//! The refcount is always the word right before the data. Lists with refcounted elements also
+//! store their element count in front of it, so that a seamless slice can free the elements of the
+//! list that it slices. Over-aligned allocations have padding in front of all that, but only zig
+//! needs to know about the padding.
+use roc_target::Target;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapHeader {
+    word_bytes: u32,
+    element_count: bool,
+}
+
+impl HeapHeader {
+    pub fn new(target: Target, elements_refcounted: bool) -> Self {
+        Self {
+            word_bytes: target.ptr_size() as u32,
+            element_count: elements_refcounted,
+        }
+    }
+
+    /// The header of everything but lists with refcounted elements: strings, boxes, recursive tag
+    /// unions, and other lists
+    pub fn refcount_only(target: Target) -> Self {
+        Self::new(target, false)
+    }
+
+    pub fn elements_refcounted(self) -> bool {
+        self.element_count
+    }
+
+    /// How many bytes before the data the refcount starts
+    pub fn refcount_offset(self) -> u32 {
+        self.word_bytes
+    }
+
+    /// The size of the header in bytes, without any padding for alignment
+    pub fn size(self) -> u32 {
+        if self.element_count {
+            2 * self.word_bytes
+        } else {
+            self.word_bytes
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! An on-disk, content-addressed cache of refcounting and equality helper bodies.
+//!
+//! Every inc/dec/eq helper is keyed by a hash of the *structure* of its layout (not the interned
+//! layout id, which changes from one compilation to the next), the target, the optimization
+//! level, and a fingerprint of the compiler binary. Helpers generated during a build are
+//! optimized and written to the cache as a single bitcode bundle when the build finishes; later
+//! builds link cached helpers back in instead of generating and optimizing them again.
+//!
+//! Inside a bundle, every cached helper is named `roc_cached_helper.<key>` and has external
+//! linkage. Everything else the helpers need (the zig builtins they call, wrapper functions) is
+//! kept as private definitions, so a bundle can be linked into any module for the same target.
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::memory_buffer::MemoryBuffer;
+use inkwell::module::{Linkage, Module};
+use inkwell::values::{BasicValue, FunctionValue};
+use roc_mono::ir::OptLevel;
+use roc_mono::layout::{
+    Builtin, InLayout, LayoutInterner, LayoutRepr, STLayoutInterner, UnionLayout,
+};
+use roc_target::{OperatingSystem, Target};
+
+use super::build::{construct_optimization_passes, Env};
+use super::refcounting::Mode;
+use super::sendable::SendableLayouts;
+
+const HELPER_PREFIX: &str = "roc_cached_helper.";
+const BUNDLE_EXTENSION: &str = "bc";
+
+/// What a cached helper does with values of its layout
+#[derive(Clone, Copy)]
+pub enum CachedHelper {
+    Inc,
+    Dec,
+    Eq,
+    /// Compares two lists; keyed by the layout of their elements
+    ListEq,
+}
+
+impl From<Mode> for CachedHelper {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Inc => CachedHelper::Inc,
+            Mode::Dec => CachedHelper::Dec,
+        }
+    }
+}
+
+pub struct HelperCache {
+    dir: PathBuf,
+    opt_level: OptLevel,
+    fingerprint: String,
+    /// Helpers that were generated in this compilation, as `(key, llvm function name)`.
+    /// They are written out as one bundle by [HelperCache::finish].
+    misses: RefCell<Vec<(String, String)>>,
+}
+
+impl HelperCache {
+    /// Returns `None` if the cache directory cannot be created; the build then proceeds
+    /// without caching.
+    pub fn new(dir: PathBuf, target: Target, opt_level: OptLevel) -> Option<Self> {
+        std::fs::create_dir_all(&dir).ok()?;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(format!("{target:?}").as_bytes());
+        hasher.update(format!("{opt_level:?}").as_bytes());
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+
+        // Any change to the compiler may change the code we generate for a helper, so the
+        // compiler binary itself is part of the key.
+        if let Ok(metadata) = std::env::current_exe().and_then(std::fs::metadata) {
+            hasher.update(&metadata.len().to_le_bytes());
+
+            if let Ok(modified) = metadata.modified() {
+                hasher.update(format!("{modified:?}").as_bytes());
+            }
+        }
+
+        Some(Self {
+            dir,
+            opt_level,
+            fingerprint: hasher.finalize().to_hex().to_string(),
+            misses: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Only layouts that get a dedicated helper are cached; pointers to recursive unions and
+    /// lambda sets are resolved to their underlying layout first by the caller.
+    pub fn is_cacheable(repr: LayoutRepr<'_>) -> bool {
+        matches!(
+            repr,
+            LayoutRepr::Builtin(_) | LayoutRepr::Struct(_) | LayoutRepr::Union(_)
+        )
+    }
+
+    pub fn key<'a>(
+        &self,
+        layout_interner: &STLayoutInterner<'a>,
+        sendable: &SendableLayouts<'a>,
+        kind: CachedHelper,
+        repr: LayoutRepr<'a>,
+    ) -> String {
+        let mut description = String::with_capacity(64);
+
+        description.push_str(&self.fingerprint);
+        description.push_str(match kind {
+            CachedHelper::Inc => "inc:",
+            CachedHelper::Dec => "dec:",
+            CachedHelper::Eq => "eq:",
+            CachedHelper::ListEq => "list_eq:",
+        });
+
+        let mut writer = CanonicalWriter {
+            layout_interner,
+            sendable,
+            unions: Vec::new(),
+            buf: &mut description,
+        };
+        writer.write_repr(repr);
+
+        blake3::hash(description.as_bytes()).to_hex().to_string()
+    }
+
+    /// Find the helper for `key`, either because it was already linked into this module or
+    /// because a bundle from an earlier compilation contains it.
+    pub fn lookup<'ctx>(&self, env: &Env<'_, 'ctx, '_>, key: &str) -> Option<FunctionValue<'ctx>> {
+        let fn_name = helper_name(key);
+
+        if let Some(function) = env.module.get_function(&fn_name) {
+            return Some(function);
+        }
+
+        // we already know this helper is not cached, and generated it ourselves
+        if self.misses.borrow().iter().any(|(k, _)| k == key) {
+            return None;
+        }
+
+        let bundle_name = std::fs::read_to_string(self.index_path(key)).ok()?;
+        let bundle_path = self
+            .dir
+            .join(bundle_name.trim())
+            .with_extension(BUNDLE_EXTENSION);
+
+        let buffer = MemoryBuffer::create_from_file(&bundle_path).ok()?;
+        let bundle = Module::parse_bitcode_from_buffer(&buffer, env.context).ok()?;
+
+        // An earlier bundle may already have provided some of these helpers. Making the
+        // duplicates private lets the linker rename them instead of reporting a conflict.
+        for function in bundle.get_functions() {
+            let name = function.get_name().to_string_lossy();
+
+            if function.count_basic_blocks() > 0 && env.module.get_function(&name).is_some() {
+                function.set_linkage(Linkage::Private);
+            }
+        }
+
+        env.module.link_in_module(bundle).ok()?;
+
+        env.module.get_function(&fn_name)
+    }
+
+    pub fn record(&self, key: String, function: FunctionValue<'_>) {
+        let name = function.get_name().to_string_lossy().into_owned();
+
+        let mut misses = self.misses.borrow_mut();
+
+        if !misses.iter().any(|(k, _)| *k == key) {
+            misses.push((key, name));
+        }
+    }
+
+    /// Write the helpers generated by this compilation to the cache, and give all cached
+    /// helpers in `env.module` the linkage that generated helpers normally have.
+    ///
+    /// Must be called after all procedures have been built, and before the module is optimized.
+    /// The bundle is optimized on its own before it is written, so the helpers that later builds
+    /// link in are already optimized.
+    pub fn finish(&self, env: &Env<'_, '_, '_>) {
+        let misses = self.misses.take();
+
+        if !misses.is_empty() {
+            // Failing to write the cache must never fail the build.
+            let _ = self.write_bundle(env.module, &misses);
+        }
+
+        // this should be `Linkage::Private`, see `build_header_help` for why it is not on windows
+        let linkage = if let OperatingSystem::Windows = env.target.operating_system() {
+            Linkage::External
+        } else {
+            Linkage::Private
+        };
+
+        for function in env.module.get_functions() {
+            if is_cached_helper(function) {
+                function.set_linkage(linkage);
+            }
+        }
+    }
+
+    fn write_bundle(&self, module: &Module<'_>, misses: &[(String, String)]) -> Option<()> {
+        let bundle = module.clone();
+
+        // Make everything private, so that global DCE removes anything the helpers do not use.
+        for function in bundle.get_functions() {
+            if function.count_basic_blocks() > 0 {
+                function.set_linkage(Linkage::Private);
+            }
+        }
+
+        for global in bundle.get_globals() {
+            if global.get_initializer().is_some() {
+                global.set_linkage(Linkage::Private);
+            }
+        }
+
+        let mut bundle_hasher = blake3::Hasher::new();
+
+        for (key, name) in misses {
+            let function = bundle.get_function(name)?;
+
+            function
+                .as_global_value()
+                .as_pointer_value()
+                .set_name(&helper_name(key));
+            function.set_linkage(Linkage::External);
+
+            // whether to inline a helper, or optimize it for size, is decided by the build that
+            // uses it
+            for attribute in ["alwaysinline", "noinline", "optsize", "minsize"] {
+                let kind_id = Attribute::get_named_enum_kind_id(attribute);
+                function.remove_enum_attribute(AttributeLoc::Function, kind_id);
+            }
+
+            // and so is whether it must keep its frame for profilers
+            for attribute in ["frame-pointer", "disable-tail-calls"] {
+                function.remove_string_attribute(AttributeLoc::Function, attribute);
+            }
+
+            bundle_hasher.update(key.as_bytes());
+        }
+
+        // Earlier bundles linked into this module stay external too, because our new helpers
+        // may call them; the copies in this bundle are only used when they are missing.
+        for function in bundle.get_functions() {
+            if is_cached_helper(function) && function.count_basic_blocks() > 0 {
+                function.set_linkage(Linkage::External);
+            }
+        }
+
+        bundle.strip_debug_info();
+
+        // the module pass starts with a global DCE, which removes anything the helpers do not use
+        let (mpm, fpm) = construct_optimization_passes(&bundle, self.opt_level);
+
+        for function in bundle.get_functions() {
+            if is_cached_helper(function) && function.count_basic_blocks() > 0 {
+                fpm.run_on(&function);
+            }
+        }
+
+        mpm.run_on(&bundle);
+
+        let bundle_name = bundle_hasher.finalize().to_hex().to_string();
+        let bundle_path = self.dir.join(&bundle_name).with_extension(BUNDLE_EXTENSION);
+
+        self.write_file(&bundle_path, bundle.write_bitcode_to_memory().as_slice())?;
+
+        for (key, _) in misses {
+            self.write_file(&self.index_path(key), bundle_name.as_bytes())?;
+        }
+
+        Some(())
+    }
+
+    /// Write to a fresh temporary file first, so a concurrent build never reads half a file.
+    fn write_file(&self, path: &Path, bytes: &[u8]) -> Option<()> {
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir).ok()?;
+        file.write_all(bytes).ok()?;
+        file.persist(path).ok()?;
+
+        Some(())
+    }
+
+    fn index_path(&self, key: &str) -> PathBuf {
+        Path::new(&self.dir).join(key)
+    }
+}
+
+fn helper_name(key: &str) -> String {
+    format!("{HELPER_PREFIX}{key}")
+}
+
+fn is_cached_helper(function: FunctionValue<'_>) -> bool {
+    function
+        .get_name()
+        .to_bytes()
+        .starts_with(HELPER_PREFIX.as_bytes())
+}
+
+/// Writes a description of a layout that only depends on its structure. Two layouts with the
+/// same description get the same helper, regardless of how they were interned.
+///
+/// Layouts with atomic refcounts are marked with a `!`, because their helpers differ.
+struct CanonicalWriter<'a, 'r> {
+    layout_interner: &'r STLayoutInterner<'a>,
+    sendable: &'r SendableLayouts<'a>,
+    /// The recursive unions we are inside of, innermost last
+    unions: Vec<InLayout<'a>>,
+    buf: &'r mut String,
+}
+
+impl<'a> CanonicalWriter<'a, '_> {
+    fn write_layout(&mut self, layout: InLayout<'a>) {
+        let repr = self.layout_interner.get_repr(layout);
+
+        if let LayoutRepr::Union(_) = repr {
+            self.unions.push(layout);
+            self.write_repr(repr);
+            self.unions.pop();
+        } else {
+            self.write_repr(repr);
+        }
+    }
+
+    fn write_repr(&mut self, repr: LayoutRepr<'a>) {
+        use LayoutRepr::*;
+
+        if self.sendable.contains(repr) {
+            self.buf.push('!');
+        }
+
+        match repr {
+            Builtin(builtin) => match builtin {
+                Builtin::Int(int_width) => write!(self.buf, "{int_width:?}").unwrap(),
+                Builtin::Float(float_width) => write!(self.buf, "{float_width:?}").unwrap(),
+                Builtin::Bool => self.buf.push_str("Bool"),
+                Builtin::Decimal => self.buf.push_str("Dec"),
+                Builtin::Str => self.buf.push_str("Str"),
+                Builtin::List(element) => {
+                    self.buf.push_str("List(");
+                    self.write_layout(element);
+                    self.buf.push(')');
+                }
+            },
+            Struct(fields) => {
+                self.buf.push('{');
+                self.write_fields(fields);
+                self.buf.push('}');
+            }
+            Ptr(inner) => {
+                self.buf.push_str("Ptr(");
+                self.write_layout(inner);
+                self.buf.push(')');
+            }
+            Union(union_layout) => self.write_union(union_layout),
+            LambdaSet(lambda_set) => self.write_layout(lambda_set.runtime_representation()),
+            // Refer to an enclosing union by how many unions out it is. The helpers of e.g. the
+            // payload of a tag are built outside of their union, so it is written out in full
+            // the first time.
+            RecursivePointer(union) => match self.unions.iter().rposition(|u| *u == union) {
+                Some(index) => write!(self.buf, "Rec{}", self.unions.len() - 1 - index).unwrap(),
+                None => self.write_layout(union),
+            },
+            FunctionPointer(_) => self.buf.push_str("FnPtr"),
+            Erased(_) => self.buf.push_str("Erased"),
+        }
+    }
+
+    fn write_union(&mut self, union_layout: UnionLayout<'a>) {
+        use UnionLayout::*;
+
+        match union_layout {
+            NonRecursive(tags) => {
+                self.buf.push_str("NonRecursive(");
+                self.write_tags(tags);
+            }
+            Recursive(tags) => {
+                self.buf.push_str("Recursive(");
+                self.write_tags(tags);
+            }
+            NonNullableUnwrapped(fields) => {
+                self.buf.push_str("NonNullableUnwrapped(");
+                self.write_tags(&[fields]);
+            }
+            NullableWrapped {
+                nullable_id,
+                other_tags,
+            } => {
+                write!(self.buf, "NullableWrapped({nullable_id},").unwrap();
+                self.write_tags(other_tags);
+            }
+            NullableUnwrapped {
+                nullable_id,
+                other_fields,
+            } => {
+                write!(self.buf, "NullableUnwrapped({nullable_id},").unwrap();
+                self.write_tags(&[other_fields]);
+            }
+        }
+
+        self.buf.push(')');
+    }
+
+    fn write_tags(&mut self, tags: &[&[InLayout<'a>]]) {
+        for tag in tags {
+            self.buf.push('[');
+            self.write_fields(tag);
+            self.buf.push(']');
+        }
+    }
+
+    fn write_fields(&mut self, fields: &[InLayout<'a>]) {
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                self.buf.push(',');
+            }
+
+            self.write_layout(*field);
+        }
+    }
+}
@@ -7,12 +7,11 @@ use inkwell::{
 };
 use roc_builtins::{
     bitcode::{FloatWidth, IntWidth, IntrinsicName},
-    llvm_int_intrinsic,
+    float_intrinsic, llvm_int_intrinsic,
 };
 
 use super::build::{add_func, FunctionSpec};
 
-#[allow(dead_code)]
 fn add_float_intrinsic<'ctx, F>(
     ctx: &'ctx Context,
     module: &Module<'ctx>,
@@ -133,6 +132,22 @@ pub(crate) fn add_intrinsics<'ctx>(ctx: &'ctx Context, module: &Module<'ctx>) {
     add_int_intrinsic(ctx, module, &LLVM_SUB_SATURATED, |t| {
         t.fn_type(&[t.into(), t.into()], false)
     });
+
+    for unary in [
+        &LLVM_SQRT,
+        &LLVM_LOG,
+        &LLVM_SIN,
+        &LLVM_COS,
+        &LLVM_FLOOR,
+        &LLVM_CEILING,
+        &LLVM_ROUND,
+    ] {
+        add_float_intrinsic(ctx, module, unary, |t| t.fn_type(&[t.into()], false));
+    }
+
+    add_float_intrinsic(ctx, module, &LLVM_POW, |t| {
+        t.fn_type(&[t.into(), t.into()], false)
+    });
 }
 
 pub static LLVM_MEMSET_I64: &str = "llvm.memset.p0i8.i64";
@@ -154,6 +169,45 @@ pub const LLVM_MUL_WITH_OVERFLOW: IntrinsicName =
 pub const LLVM_ADD_SATURATED: IntrinsicName = llvm_int_intrinsic!("llvm.sadd.sat", "llvm.uadd.sat");
 pub const LLVM_SUB_SATURATED: IntrinsicName = llvm_int_intrinsic!("llvm.ssub.sat", "llvm.usub.sat");
 
+// Float math intrinsics. When a target has no instruction for one of these, LLVM lowers it to a
+// call to the libm-named function (e.g. `floorf`), which the builtins bitcode provides through
+// the bundled compiler-rt. compiler-rt has no `pow`, so the builtins define `pow` and `powf`
+// themselves. See `module_from_builtins` for the list of symbols we keep alive.
+pub const LLVM_SQRT: IntrinsicName = float_intrinsic!("llvm.sqrt");
+pub const LLVM_LOG: IntrinsicName = float_intrinsic!("llvm.log");
+pub const LLVM_SIN: IntrinsicName = float_intrinsic!("llvm.sin");
+pub const LLVM_COS: IntrinsicName = float_intrinsic!("llvm.cos");
+pub const LLVM_POW: IntrinsicName = float_intrinsic!("llvm.pow");
+pub const LLVM_FLOOR: IntrinsicName = float_intrinsic!("llvm.floor");
+pub const LLVM_CEILING: IntrinsicName = float_intrinsic!("llvm.ceil");
+pub const LLVM_ROUND: IntrinsicName = float_intrinsic!("llvm.round");
+
+/// `llvm.fptosi.sat` or `llvm.fptoui.sat` from `float_width` to `int_width`. Out of range values
+/// saturate and NaN becomes 0, where a plain `fptosi` would give poison. These are overloaded on
+/// both widths, so they are declared on first use rather than in [add_intrinsics].
+pub(crate) fn float_to_int_saturated<'ctx>(
+    ctx: &'ctx Context,
+    module: &Module<'ctx>,
+    float_width: FloatWidth,
+    int_width: IntWidth,
+) -> FunctionValue<'ctx> {
+    let int_bits = int_width.stack_size() * 8;
+    let (float_name, float_type) = match float_width {
+        FloatWidth::F32 => ("f32", ctx.f32_type()),
+        FloatWidth::F64 => ("f64", ctx.f64_type()),
+    };
+    let sign = if int_width.is_signed() { 's' } else { 'u' };
+    let name = format!("llvm.fpto{sign}i.sat.i{int_bits}.{float_name}");
+
+    module.get_function(&name).unwrap_or_else(|| {
+        let fn_type = ctx
+            .custom_width_int_type(int_bits)
+            .fn_type(&[float_type.into()], false);
+
+        add_intrinsic(ctx, module, &name, fn_type)
+    })
+}
+
 fn add_intrinsic<'ctx>(
     context: &Context,
     module: &Module<'ctx>,
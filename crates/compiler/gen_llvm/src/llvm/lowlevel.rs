@@ -36,14 +36,14 @@ use crate::llvm::{
     },
     build_list::{
         list_append_unsafe, list_clone, list_concat, list_drop_at, list_get_unsafe, list_len_usize,
-        list_prepend, list_release_excess_capacity, list_replace_unsafe, list_reserve,
-        list_sort_with, list_sublist, list_swap, list_symbol_to_c_abi, list_with_capacity,
-        pass_update_mode,
+        list_map_parallel, list_prepend, list_release_excess_capacity, list_replace_unsafe,
+        list_reserve, list_sort_with, list_sublist, list_swap, list_symbol_to_c_abi,
+        list_with_capacity, pass_update_mode,
     },
     compare::{generic_eq, generic_neq},
     convert::{
-        self, argument_type_from_layout, basic_type_from_layout, zig_num_parse_result_type,
-        zig_to_int_checked_result_type,
+        self, argument_type_from_layout, basic_type_from_in_layout, basic_type_from_layout,
+        zig_num_parse_result_type, zig_to_int_checked_result_type,
     },
     intrinsics::{
         // These instrinsics do not generate calls to libc and are safe to keep.
@@ -57,9 +57,18 @@ use crate::llvm::{
     refcounting::PointerToRefcount,
 };
 
+// These may lower to libm-named or compiler-rt calls, which the builtins resolve (see
+// `module_from_builtins`).
+use crate::llvm::intrinsics::{
+    float_to_int_saturated, LLVM_CEILING, LLVM_COS, LLVM_FLOOR, LLVM_LOG, LLVM_POW, LLVM_ROUND,
+    LLVM_SIN, LLVM_SQRT,
+};
+
 use super::{build::Env, convert::zig_dec_type};
 use super::{
-    build::{throw_internal_exception, use_roc_value, FAST_CALL_CONV},
+    build::{
+        set_cold_successor, throw_internal_exception, use_roc_value, ColdSuccessor, FAST_CALL_CONV,
+    },
     convert::zig_with_overflow_roc_dec,
     scope::Scope,
 };
@@ -126,6 +135,18 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                 bitcode::STR_CONCAT,
             )
         }
+        StrAppendUnchecked => {
+            // Str.appendUnchecked : Str, Str -> Str
+            arguments!(string, suffix);
+
+            call_str_bitcode_fn(
+                env,
+                &[string, suffix],
+                &[],
+                BitcodeReturns::Str,
+                bitcode::STR_APPEND_UNCHECKED,
+            )
+        }
         StrJoinWith => {
             // Str.joinWith : List Str, Str -> Str
             arguments!(list, string);
@@ -237,12 +258,9 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                                 intrinsic,
                             );
 
-                            let roc_return_type = basic_type_from_layout(
-                                env,
-                                layout_interner,
-                                layout_interner.get_repr(layout),
-                            )
-                            .ptr_type(AddressSpace::default());
+                            let roc_return_type =
+                                basic_type_from_in_layout(env, layout_interner, layout)
+                                    .ptr_type(AddressSpace::default());
 
                             let roc_return_alloca = env.builder.new_build_pointer_cast(
                                 zig_return_alloca,
@@ -260,7 +278,7 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                         }
                     }
                 }
-                Aarch64 | X86_64 => {
+                Aarch64 | X86_64 | Riscv64 => {
                     let (type_name, width) = {
                         match layout_interner.get_repr(number_layout) {
                             LayoutRepr::Builtin(Builtin::Int(int_width)) => {
@@ -331,12 +349,8 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                         intrinsic,
                     );
 
-                    let roc_return_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    )
-                    .ptr_type(AddressSpace::default());
+                    let roc_return_type = basic_type_from_in_layout(env, layout_interner, layout)
+                        .ptr_type(AddressSpace::default());
 
                     let roc_return_alloca = env.builder.new_build_pointer_cast(
                         zig_return_alloca,
@@ -427,7 +441,7 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                         bitcode::STR_FROM_UTF8,
                     );
                 }
-                Aarch64 | X86_64 | Wasm32 => {
+                Aarch64 | X86_64 | Riscv64 | Wasm32 => {
                     arguments!(_list);
 
                     // we use the symbol here instead
@@ -969,14 +983,15 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                         }
 
                         _ => {
-                            unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid builtin layout: ({:?})", op, arg_layout);
+                            unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid builtin layout: ({})", op, layout_interner.dbg(arg_layout));
                         }
                     }
                 }
                 _ => {
                     unreachable!(
-                        "Compiler bug: tried to run numeric operation {:?} on invalid layout: {:?}",
-                        op, arg_layout
+                        "Compiler bug: tried to run numeric operation {:?} on invalid layout: {}",
+                        op,
+                        layout_interner.dbg(arg_layout)
                     );
                 }
             }
@@ -1072,12 +1087,12 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                         }
 
                         _ => {
-                            unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid builtin layout: ({:?})", op, lhs_layout);
+                            unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid builtin layout: ({})", op, layout_interner.dbg(lhs_layout));
                         }
                     }
                 }
                 _ => {
-                    unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid layouts. The 2 layouts were: ({:?}) and ({:?})", op, lhs_layout, rhs_layout);
+                    unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid layouts. The 2 layouts were: ({}) and ({})", op, layout_interner.dbg(lhs_layout), layout_interner.dbg(rhs_layout));
                 }
             }
         }
@@ -1149,8 +1164,7 @@ pub(crate) fn run_low_level<'a, 'ctx>(
         NumIntCast => {
             arguments!(arg);
 
-            let to = basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout))
-                .into_int_type();
+            let to = basic_type_from_in_layout(env, layout_interner, layout).into_int_type();
             let to_signed = intwidth_from_layout(layout).is_signed();
 
             env.builder
@@ -1164,12 +1178,8 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                 LayoutRepr::Builtin(Builtin::Int(width)) => {
                     // Converting from int to float
                     let int_val = arg.into_int_value();
-                    let dest = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    )
-                    .into_float_type();
+                    let dest =
+                        basic_type_from_in_layout(env, layout_interner, layout).into_float_type();
 
                     if width.is_signed() {
                         env.builder
@@ -1183,12 +1193,8 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                 }
                 LayoutRepr::Builtin(Builtin::Float(_)) => {
                     // Converting from float to float - e.g. F64 to F32, or vice versa
-                    let dest = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    )
-                    .into_float_type();
+                    let dest =
+                        basic_type_from_in_layout(env, layout_interner, layout).into_float_type();
 
                     env.builder
                         .new_build_float_cast(arg.into_float_value(), dest, "cast_float_to_float")
@@ -1198,7 +1204,10 @@ pub(crate) fn run_low_level<'a, 'ctx>(
                     todo!("Support converting Dec values to floats.");
                 }
                 other => {
-                    unreachable!("Tried to do a float cast to non-float layout {:?}", other);
+                    unreachable!(
+                        "Tried to do a float cast to non-float layout {}",
+                        layout_interner.dbg_repr(other)
+                    );
                 }
             }
         }
@@ -1307,11 +1316,11 @@ pub(crate) fn run_low_level<'a, 'ctx>(
             unimplemented!()
         }
 
-        ListSortWith => {
+        ListSortWith | ListMapParallel => {
             unreachable!("these are higher order, and are handled elsewhere")
         }
 
-        BoxExpr | UnboxExpr => {
+        BoxExpr | UnboxExpr | SizeOf | AlignOf => {
             unreachable!("The {:?} operation is turned into mono Expr", op)
         }
 
@@ -1319,8 +1328,7 @@ pub(crate) fn run_low_level<'a, 'ctx>(
             arguments!(data_ptr);
 
             let target_type =
-                basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout))
-                    .into_pointer_type();
+                basic_type_from_in_layout(env, layout_interner, layout).into_pointer_type();
 
             debug_assert!(data_ptr.is_pointer_value());
 
@@ -1384,19 +1392,11 @@ pub(crate) fn run_low_level<'a, 'ctx>(
         Unreachable => {
             match RocReturn::from_layout(layout_interner, layout_interner.get_repr(layout)) {
                 RocReturn::Return => {
-                    let basic_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    );
+                    let basic_type = basic_type_from_in_layout(env, layout_interner, layout);
                     basic_type.const_zero()
                 }
                 RocReturn::ByPointer => {
-                    let basic_type = basic_type_from_layout(
-                        env,
-                        layout_interner,
-                        layout_interner.get_repr(layout),
-                    );
+                    let basic_type = basic_type_from_in_layout(env, layout_interner, layout);
                     let ptr = create_entry_block_alloca(env, basic_type, "unreachable_alloca");
                     env.builder.new_build_store(ptr, basic_type.const_zero());
 
@@ -1730,12 +1730,12 @@ pub fn build_num_binop<'a, 'ctx>(
                     op,
                 ),
                 _ => {
-                    unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid builtin layout: ({:?})", op, lhs_layout);
+                    unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid builtin layout: ({})", op, layout_interner.dbg(lhs_layout));
                 }
             }
         }
         _ => {
-            unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid layouts. The 2 layouts were: ({:?}) and ({:?})", op, lhs_layout, rhs_layout);
+            unreachable!("Compiler bug: tried to run numeric operation {:?} on invalid layouts. The 2 layouts were: ({}) and ({})", op, layout_interner.dbg(lhs_layout), layout_interner.dbg(rhs_layout));
         }
     }
 }
@@ -1849,11 +1849,7 @@ fn build_float_binop<'ctx>(
             .new_build_float_compare(OLE, lhs, rhs, "float_lte")
             .into(),
         NumDivFrac => bd.new_build_float_div(lhs, rhs, "div_float").into(),
-        NumPow => call_bitcode_fn(
-            env,
-            &[lhs.into(), rhs.into()],
-            &bitcode::NUM_POW[float_width],
-        ),
+        NumPow => env.call_intrinsic(&LLVM_POW[float_width], &[lhs.into(), rhs.into()]),
         _ => {
             unreachable!("Unrecognized int binary operation: {:?}", op);
         }
@@ -1881,7 +1877,8 @@ fn throw_on_overflow<'ctx>(
     let then_block = context.append_basic_block(parent, "then_block");
     let throw_block = context.append_basic_block(parent, "throw_block");
 
-    bd.new_build_conditional_branch(condition, then_block, throw_block);
+    let branch = bd.new_build_conditional_branch(condition, then_block, throw_block);
+    set_cold_successor(env, branch, ColdSuccessor::Else);
 
     bd.position_at_end(throw_block);
 
@@ -2185,11 +2182,7 @@ fn change_with_overflow_to_roc_type<'a, 'ctx>(
     val: impl BasicValue<'ctx>,
     return_layout: InLayout<'a>,
 ) -> BasicValueEnum<'ctx> {
-    let return_type = convert::basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(return_layout),
-    );
+    let return_type = convert::basic_type_from_in_layout(env, layout_interner, return_layout);
     let casted = cast_basic_basic(env, val.as_basic_value_enum(), return_type);
 
     use_roc_value(
@@ -2384,8 +2377,8 @@ fn build_int_unary_op<'a, 'ctx, 'env>(
                     field_layouts[0].to_int_width()
                 }
                 layout => internal_error!(
-                    "There can only be a result layout here, found {:?}!",
-                    layout
+                    "There can only be a result layout here, found {}!",
+                    layout_interner.dbg_repr(layout)
                 ),
             };
 
@@ -2403,12 +2396,9 @@ fn build_int_unary_op<'a, 'ctx, 'env>(
                 arg_width == target_int_width;
 
             // How the return type needs to be stored on the stack.
-            let return_type_stack_type = convert::basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(return_layout),
-            )
-            .into_struct_type();
+            let return_type_stack_type =
+                convert::basic_type_from_in_layout(env, layout_interner, return_layout)
+                    .into_struct_type();
             // How the return type is actually used, in the Roc calling convention.
             let return_type_use_type = convert::argument_type_from_layout(
                 env,
@@ -2480,12 +2470,9 @@ fn build_int_unary_op<'a, 'ctx, 'env>(
                                     intrinsic,
                                 );
 
-                                let roc_return_type = basic_type_from_layout(
-                                    env,
-                                    layout_interner,
-                                    layout_interner.get_repr(return_layout),
-                                )
-                                .ptr_type(AddressSpace::default());
+                                let roc_return_type =
+                                    basic_type_from_in_layout(env, layout_interner, return_layout)
+                                        .ptr_type(AddressSpace::default());
 
                                 let roc_return_alloca = env.builder.new_build_pointer_cast(
                                     zig_return_alloca,
@@ -2567,8 +2554,10 @@ fn int_neg_raise_on_overflow<'ctx>(
     let then_block = env.context.append_basic_block(parent, "then");
     let else_block = env.context.append_basic_block(parent, "else");
 
-    env.builder
+    let branch = env
+        .builder
         .new_build_conditional_branch(condition, then_block, else_block);
+    set_cold_successor(env, branch, ColdSuccessor::Then);
 
     builder.position_at_end(then_block);
 
@@ -2597,8 +2586,10 @@ fn int_abs_raise_on_overflow<'ctx>(
     let then_block = env.context.append_basic_block(parent, "then");
     let else_block = env.context.append_basic_block(parent, "else");
 
-    env.builder
+    let branch = env
+        .builder
         .new_build_conditional_branch(condition, then_block, else_block);
+    set_cold_successor(env, branch, ColdSuccessor::Then);
 
     builder.position_at_end(then_block);
 
@@ -2671,12 +2662,15 @@ fn build_float_unary_op<'a, 'ctx>(
     match op {
         NumNeg => bd.new_build_float_neg(arg, "negate_float").into(),
         NumAbs => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_FABS[float_width]),
-        NumSqrtUnchecked => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_SQRT[float_width]),
-        NumLogUnchecked => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_LOG[float_width]),
+        NumSqrtUnchecked => env.call_intrinsic(&LLVM_SQRT[float_width], &[arg.into()]),
+        NumLogUnchecked => env.call_intrinsic(&LLVM_LOG[float_width], &[arg.into()]),
         NumToFrac => {
             let return_width = match layout_interner.get_repr(layout) {
                 LayoutRepr::Builtin(Builtin::Float(return_width)) => return_width,
-                _ => internal_error!("Layout for returning is not Float : {:?}", layout),
+                _ => internal_error!(
+                    "Layout for returning is not Float : {}",
+                    layout_interner.dbg(layout)
+                ),
             };
             match (float_width, return_width) {
                 (FloatWidth::F32, FloatWidth::F32) => arg.into(),
@@ -2698,41 +2692,47 @@ fn build_float_unary_op<'a, 'ctx>(
         NumCeiling => {
             let int_width = match layout_interner.get_repr(layout) {
                 LayoutRepr::Builtin(Builtin::Int(int_width)) => int_width,
-                _ => internal_error!("Ceiling return layout is not int: {:?}", layout),
+                _ => internal_error!(
+                    "Ceiling return layout is not int: {}",
+                    layout_interner.dbg(layout)
+                ),
             };
 
-            let intrinsic = match float_width {
-                FloatWidth::F32 => &bitcode::NUM_CEILING_F32[int_width],
-                FloatWidth::F64 => &bitcode::NUM_CEILING_F64[int_width],
-            };
+            let rounded = env
+                .call_intrinsic(&LLVM_CEILING[float_width], &[arg.into()])
+                .into_float_value();
 
-            call_bitcode_fn(env, &[arg.into()], intrinsic)
+            rounded_float_to_int(env, rounded, float_width, int_width)
         }
         NumFloor => {
             let int_width = match layout_interner.get_repr(layout) {
                 LayoutRepr::Builtin(Builtin::Int(int_width)) => int_width,
-                _ => internal_error!("Floor return layout is not int: {:?}", layout),
+                _ => internal_error!(
+                    "Floor return layout is not int: {}",
+                    layout_interner.dbg(layout)
+                ),
             };
 
-            let intrinsic = match float_width {
-                FloatWidth::F32 => &bitcode::NUM_FLOOR_F32[int_width],
-                FloatWidth::F64 => &bitcode::NUM_FLOOR_F64[int_width],
-            };
+            let rounded = env
+                .call_intrinsic(&LLVM_FLOOR[float_width], &[arg.into()])
+                .into_float_value();
 
-            call_bitcode_fn(env, &[arg.into()], intrinsic)
+            rounded_float_to_int(env, rounded, float_width, int_width)
         }
         NumRound => {
             let int_width = match layout_interner.get_repr(layout) {
                 LayoutRepr::Builtin(Builtin::Int(int_width)) => int_width,
-                _ => internal_error!("Round return layout is not int: {:?}", layout),
+                _ => internal_error!(
+                    "Round return layout is not int: {}",
+                    layout_interner.dbg(layout)
+                ),
             };
 
-            let intrinsic = match float_width {
-                FloatWidth::F32 => &bitcode::NUM_ROUND_F32[int_width],
-                FloatWidth::F64 => &bitcode::NUM_ROUND_F64[int_width],
-            };
+            let rounded = env
+                .call_intrinsic(&LLVM_ROUND[float_width], &[arg.into()])
+                .into_float_value();
 
-            call_bitcode_fn(env, &[arg.into()], intrinsic)
+            rounded_float_to_int(env, rounded, float_width, int_width)
         }
         NumIsNan => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_IS_NAN[float_width]),
         NumIsInfinite => {
@@ -2741,8 +2741,8 @@ fn build_float_unary_op<'a, 'ctx>(
         NumIsFinite => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_IS_FINITE[float_width]),
 
         // trigonometry
-        NumSin => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_SIN[float_width]),
-        NumCos => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_COS[float_width]),
+        NumSin => env.call_intrinsic(&LLVM_SIN[float_width], &[arg.into()]),
+        NumCos => env.call_intrinsic(&LLVM_COS[float_width], &[arg.into()]),
         NumTan => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_TAN[float_width]),
 
         NumAtan => call_bitcode_fn(env, &[arg.into()], &bitcode::NUM_ATAN[float_width]),
@@ -2755,6 +2755,23 @@ fn build_float_unary_op<'a, 'ctx>(
     }
 }
 
+/// Convert a float that already holds an integral value (the result of `llvm.floor` and friends)
+/// to an integer of the given width. Values that do not fit saturate.
+fn rounded_float_to_int<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    rounded: FloatValue<'ctx>,
+    float_width: FloatWidth,
+    int_width: IntWidth,
+) -> BasicValueEnum<'ctx> {
+    let fn_val = float_to_int_saturated(env.context, env.module, float_width, int_width);
+
+    env.builder
+        .new_build_call(fn_val, &[rounded.into()], "float_to_int")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+}
+
 pub(crate) fn run_higher_order_low_level<'a, 'ctx>(
     env: &Env<'a, 'ctx, '_>,
     layout_interner: &STLayoutInterner<'a>,
@@ -2847,6 +2864,41 @@ pub(crate) fn run_higher_order_low_level<'a, 'ctx>(
                 _ => unreachable!("invalid list layout"),
             }
         }
+        ListMapParallel { xs } => {
+            // List.mapParallel : List a, (a -> b) -> List b
+            let (list, list_layout) = scope.load_symbol_and_layout(xs);
+
+            let (function, closure, closure_layout) = function_details!();
+
+            match layout_interner.get_repr(list_layout) {
+                LayoutRepr::Builtin(Builtin::List(element_layout)) => {
+                    let argument_layouts = &[element_layout];
+
+                    let roc_function_call = roc_function_call(
+                        env,
+                        layout_interner,
+                        layout_ids,
+                        function,
+                        closure,
+                        closure_layout,
+                        function_owns_closure_data,
+                        argument_layouts,
+                        result_layout,
+                    );
+
+                    list_map_parallel(
+                        env,
+                        layout_interner,
+                        layout_ids,
+                        roc_function_call,
+                        list,
+                        element_layout,
+                        result_layout,
+                    )
+                }
+                _ => unreachable!("invalid list layout"),
+            }
+        }
     }
 }
 
@@ -2858,6 +2910,9 @@ fn load_symbol_and_lambda_set<'a, 'ctx>(
     let (ptr, layout) = scope.load_symbol_and_layout(symbol);
     match layout_interner.get_repr(layout) {
         LayoutRepr::LambdaSet(lambda_set) => (ptr, lambda_set),
-        other => panic!("Not a lambda set: {other:?}, {ptr:?}"),
+        other => panic!(
+            "Not a lambda set: {}, {ptr:?}",
+            layout_interner.dbg_repr(other)
+        ),
     }
 }
@@ -0,0 +1,565 @@
+//! How generated code manages heap memory.
+//!
+//! The mono IR tells us where values are shared (`Inc`), dropped (`Dec`, `DecRef`) and known to be
+//! dead (`Free`), and where a unique value may be reused (`Reset`). A [MemoryStrategy] decides what
+//! code to emit for each of these. Refcounting is the default; other strategies can be selected
+//! per build with [MemoryStrategyKind].
+use inkwell::module::Linkage;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue};
+use inkwell::IntPredicate;
+use roc_mono::layout::{InLayout, LayoutIds, LayoutRepr, STLayoutInterner};
+
+use super::build::{BuilderExt, Env};
+use super::refcounting::{
+    decref_layout, decrement_refcount_layout, free_layout, increment_n_refcount_layout,
+    increment_refcount_layout, PointerToRefcount,
+};
+
+pub trait MemoryStrategy {
+    /// `value` gains `inc_amount` new owners
+    fn increment<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: u64,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    );
+
+    /// Like [MemoryStrategy::increment], with an amount only known at runtime
+    fn increment_n<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: IntValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    );
+
+    /// One owner of `value` is done with it
+    fn decrement<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    );
+
+    /// One owner of the allocation behind `value` is done with it; its contents were already
+    /// taken care of
+    fn decref<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        parent: FunctionValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    );
+
+    /// A value that `Reset` could not reuse, because it is shared, is dropped
+    fn decrement_shared<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        refcount_ptr: PointerToRefcount<'ctx>,
+        layout: LayoutRepr<'a>,
+    );
+
+    /// The allocation behind `value` is known to be unique and dead
+    fn free<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    );
+
+    /// Called at the start of the default `roc_alloc` and `roc_dealloc`, which we only generate
+    /// when there is no host to provide them
+    fn count(&self, _env: &Env<'_, '_, '_>, _counter: MemoryCounter) {}
+
+    /// Called once all procedures are built, so every call to the allocator is in the module
+    fn finish(&self, _env: &Env<'_, '_, '_>) {}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryStrategyKind {
+    /// Free memory as soon as its refcount drops to zero
+    #[default]
+    Refcount,
+    /// Only free memory in the builtins; useful for short-lived programs
+    Arena,
+    /// Refcount, and count the memory traffic in [MEMORY_COUNTERS]; useful for benchmarks
+    Counting,
+    /// Refcount, and fill new memory with [POISON_BYTE]; useful for finding reads of uninitialized
+    /// memory
+    Poison,
+}
+
+impl MemoryStrategyKind {
+    pub fn strategy(self) -> &'static dyn MemoryStrategy {
+        match self {
+            MemoryStrategyKind::Refcount => &Refcounting,
+            MemoryStrategyKind::Arena => &Arena,
+            MemoryStrategyKind::Counting => &Counting,
+            MemoryStrategyKind::Poison => &Poison,
+        }
+    }
+}
+
+/// The global that [Counting] keeps its counters in, laid out like [MemoryCounters]
+pub const MEMORY_COUNTERS: &str = "roc_memory_counters";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryCounter {
+    Allocations,
+    Deallocations,
+    Increments,
+    Decrements,
+}
+
+/// The contents of [MEMORY_COUNTERS], for the host to read
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryCounters {
+    pub allocations: u64,
+    pub deallocations: u64,
+    pub increments: u64,
+    pub decrements: u64,
+}
+
+pub struct Refcounting;
+
+impl MemoryStrategy for Refcounting {
+    fn increment<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: u64,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        increment_refcount_layout(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn increment_n<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: IntValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        increment_n_refcount_layout(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn decrement<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        decrement_refcount_layout(env, layout_interner, layout_ids, value, layout);
+    }
+
+    fn decref<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        parent: FunctionValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        decref_layout(env, layout_interner, layout_ids, parent, value, layout);
+    }
+
+    fn decrement_shared<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        refcount_ptr: PointerToRefcount<'ctx>,
+        layout: LayoutRepr<'a>,
+    ) {
+        refcount_ptr.decrement(env, layout_interner, layout);
+    }
+
+    fn free<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        free_layout(env, layout_interner, value, layout);
+    }
+}
+
+/// Allocate as usual, but never free anything from compiled code. The zig builtins and the reset
+/// helpers still call `roc_dealloc` on memory they know is unused, like the old allocation of a
+/// list that grew. Refcounts are still incremented, so that a value that was ever shared is never
+/// considered unique and updated in place; without decrements the counts are an upper bound,
+/// which keeps in-place updates safe.
+pub struct Arena;
+
+impl MemoryStrategy for Arena {
+    fn increment<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: u64,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        increment_refcount_layout(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn increment_n<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: IntValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        increment_n_refcount_layout(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn decrement<'a, 'ctx>(
+        &self,
+        _env: &Env<'a, 'ctx, '_>,
+        _layout_interner: &STLayoutInterner<'a>,
+        _layout_ids: &mut LayoutIds<'a>,
+        _value: BasicValueEnum<'ctx>,
+        _layout: InLayout<'a>,
+    ) {
+    }
+
+    fn decref<'a, 'ctx>(
+        &self,
+        _env: &Env<'a, 'ctx, '_>,
+        _layout_interner: &STLayoutInterner<'a>,
+        _layout_ids: &mut LayoutIds<'a>,
+        _parent: FunctionValue<'ctx>,
+        _value: BasicValueEnum<'ctx>,
+        _layout: InLayout<'a>,
+    ) {
+    }
+
+    fn decrement_shared<'a, 'ctx>(
+        &self,
+        _env: &Env<'a, 'ctx, '_>,
+        _layout_interner: &STLayoutInterner<'a>,
+        _refcount_ptr: PointerToRefcount<'ctx>,
+        _layout: LayoutRepr<'a>,
+    ) {
+    }
+
+    fn free<'a, 'ctx>(
+        &self,
+        _env: &Env<'a, 'ctx, '_>,
+        _layout_interner: &STLayoutInterner<'a>,
+        _value: BasicValueEnum<'ctx>,
+        _layout: InLayout<'a>,
+    ) {
+    }
+}
+
+/// Refcount like [Refcounting], and count how often the generated code allocates, deallocates,
+/// increments and decrements. These are the `Inc` and `Dec` statements of the mono IR (and the
+/// refcount updates of builtins on list elements), not the updates of the values nested inside
+/// them, so the counts show what refcount elision saved. Allocations are only counted when there
+/// is no host, because the host provides `roc_alloc` and `roc_dealloc` otherwise.
+pub struct Counting;
+
+impl Counting {
+    fn bump<'ctx>(env: &Env<'_, 'ctx, '_>, counter: MemoryCounter, amount: IntValue<'ctx>) {
+        let i64_type = env.context.i64_type();
+        let counters_type = env.context.struct_type(&[i64_type.into(); 4], false);
+
+        let global = match env.module.get_global(MEMORY_COUNTERS) {
+            Some(global) => global,
+            None => {
+                let global = env.module.add_global(counters_type, None, MEMORY_COUNTERS);
+                global.set_initializer(&counters_type.const_zero());
+
+                global
+            }
+        };
+
+        let counter_ptr = env.builder.new_build_struct_gep(
+            counters_type,
+            global.as_pointer_value(),
+            counter as u32,
+            "counter_ptr",
+        );
+        let amount = env
+            .builder
+            .new_build_int_cast_sign_flag(amount, i64_type, false, "amount");
+        let old = env
+            .builder
+            .new_build_load(i64_type, counter_ptr, "counter")
+            .into_int_value();
+        let new = env.builder.new_build_int_add(old, amount, "counter");
+
+        env.builder.new_build_store(counter_ptr, new);
+    }
+
+    fn bump_one(env: &Env<'_, '_, '_>, counter: MemoryCounter) {
+        Self::bump(env, counter, env.context.i64_type().const_int(1, false));
+    }
+}
+
+impl MemoryStrategy for Counting {
+    fn increment<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: u64,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        let amount = env.context.i64_type().const_int(inc_amount, false);
+        Self::bump(env, MemoryCounter::Increments, amount);
+
+        Refcounting.increment(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn increment_n<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: IntValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Self::bump(env, MemoryCounter::Increments, inc_amount);
+
+        Refcounting.increment_n(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn decrement<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Self::bump_one(env, MemoryCounter::Decrements);
+
+        Refcounting.decrement(env, layout_interner, layout_ids, value, layout);
+    }
+
+    fn decref<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        parent: FunctionValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Self::bump_one(env, MemoryCounter::Decrements);
+
+        Refcounting.decref(env, layout_interner, layout_ids, parent, value, layout);
+    }
+
+    fn decrement_shared<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        refcount_ptr: PointerToRefcount<'ctx>,
+        layout: LayoutRepr<'a>,
+    ) {
+        Self::bump_one(env, MemoryCounter::Decrements);
+
+        Refcounting.decrement_shared(env, layout_interner, refcount_ptr, layout);
+    }
+
+    fn free<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        // the deallocation itself is counted by `roc_dealloc`
+        Refcounting.free(env, layout_interner, value, layout);
+    }
+
+    fn count(&self, env: &Env<'_, '_, '_>, counter: MemoryCounter) {
+        Self::bump_one(env, counter);
+    }
+}
+
+/// The byte that [Poison] fills new memory with. A pointer read from memory that was never written
+/// is then 0xAAAA...; no allocator hands out such an address, so using it faults right away.
+pub const POISON_BYTE: u8 = 0xAA;
+
+/// Refcount, and fill every new allocation with [POISON_BYTE]. A builtin fast path that reads a list
+/// element or string byte it never wrote then gets a recognizable value, instead of whatever the
+/// allocator happened to leave there.
+///
+/// This wraps the `roc_alloc` and `roc_realloc` that the builtins and the generated code call, so
+/// it also works when the host provides the allocator.
+pub struct Poison;
+
+impl Poison {
+    /// Make every call to the allocator `name` go through a wrapper that poisons the bytes
+    /// `start..end` of the memory it returns. `poisoned_range` computes those from the arguments.
+    fn wrap<'ctx>(
+        env: &Env<'_, 'ctx, '_>,
+        name: &str,
+        poisoned_range: impl Fn(&[BasicValueEnum<'ctx>]) -> (IntValue<'ctx>, IntValue<'ctx>),
+    ) {
+        let Some(allocator) = env.module.get_function(name) else {
+            return;
+        };
+
+        let wrapper = env.module.add_function(
+            &format!("{name}_poisoned"),
+            allocator.get_type(),
+            Some(Linkage::Internal),
+        );
+        wrapper.set_call_conventions(allocator.get_call_conventions());
+
+        // before the wrapper calls the allocator, or the wrapper would call itself
+        allocator.replace_all_uses_with(wrapper);
+
+        let builder = env.builder;
+        let entry = env.context.append_basic_block(wrapper, "entry");
+
+        builder.position_at_end(entry);
+
+        // the wrapper has no debug info, so it must not point at the last function we built
+        builder.unset_current_debug_location();
+
+        let arguments = wrapper.get_params();
+        let call_arguments: Vec<BasicMetadataValueEnum> =
+            Vec::from_iter(arguments.iter().map(|argument| (*argument).into()));
+        let call = builder.new_build_call(allocator, &call_arguments, "allocation");
+        call.set_call_convention(allocator.get_call_conventions());
+
+        let allocation = call
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+
+        // a realloc may shrink the allocation
+        let (start, end) = poisoned_range(&arguments);
+        let grows = builder.new_build_int_compare(IntPredicate::UGT, end, start, "grows");
+        let length = builder.new_build_int_sub(end, start, "length");
+        let length = builder
+            .new_build_select(grows, length, length.get_type().const_zero(), "length")
+            .into_int_value();
+
+        let i8_type = env.context.i8_type();
+        let new_bytes =
+            unsafe { builder.new_build_in_bounds_gep(i8_type, allocation, &[start], "new_bytes") };
+        let poison = i8_type.const_int(POISON_BYTE as u64, false);
+
+        env.call_memset(new_bytes, poison, length);
+
+        builder.new_build_return(Some(&allocation));
+    }
+}
+
+impl MemoryStrategy for Poison {
+    fn increment<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: u64,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Refcounting.increment(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn increment_n<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        inc_amount: IntValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Refcounting.increment_n(env, layout_interner, layout_ids, inc_amount, value, layout);
+    }
+
+    fn decrement<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Refcounting.decrement(env, layout_interner, layout_ids, value, layout);
+    }
+
+    fn decref<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        parent: FunctionValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Refcounting.decref(env, layout_interner, layout_ids, parent, value, layout);
+    }
+
+    fn decrement_shared<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        refcount_ptr: PointerToRefcount<'ctx>,
+        layout: LayoutRepr<'a>,
+    ) {
+        Refcounting.decrement_shared(env, layout_interner, refcount_ptr, layout);
+    }
+
+    fn free<'a, 'ctx>(
+        &self,
+        env: &Env<'a, 'ctx, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        value: BasicValueEnum<'ctx>,
+        layout: InLayout<'a>,
+    ) {
+        Refcounting.free(env, layout_interner, value, layout);
+    }
+
+    fn finish(&self, env: &Env<'_, '_, '_>) {
+        // roc_alloc(size, alignment)
+        Self::wrap(env, "roc_alloc", |arguments| {
+            let size = arguments[0].into_int_value();
+
+            (size.get_type().const_zero(), size)
+        });
+
+        // roc_realloc(ptr, new_size, old_size, alignment)
+        Self::wrap(env, "roc_realloc", |arguments| {
+            let new_size = arguments[1].into_int_value();
+            let old_size = arguments[2].into_int_value();
+
+            (old_size, new_size)
+        });
+    }
+}
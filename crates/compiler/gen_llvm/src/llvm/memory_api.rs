@@ -0,0 +1,162 @@
+//! A C API for hosts that hold on to Roc values across calls. Only built with `--memory-api`,
+//! because every one of these functions ends up in the binary.
+//!
+//! For every layout that crosses the host boundary, we expose five functions with C linkage:
+//!
+//! * `void roc__retain_<name>(T *value)` registers an additional owner of `value`
+//! * `void roc__release_<name>(T *value)` drops one owner of `value`, freeing it if that was the
+//!   last owner
+//! * `void roc__deepcopy_<name>(T *destination, const T *source)` writes a copy of `source` that
+//!   shares no heap allocations with it
+//! * `size_t roc__sizeof_<name>(void)` and `size_t roc__alignof_<name>(void)` return the size and
+//!   alignment of `T`, so hosts can store values whose layout they are not told about
+//!
+//! `<name>` is derived from the exposed symbol, so it is stable between compilations:
+//!
+//! * `<exposed>_arg<i>` and `<exposed>_result` for an exposed function, where `<exposed>` is the
+//!   name of its C wrapper without the `roc__` prefix (e.g. `mainForHost_1_exposed`)
+//! * `<def>_arg<i>`, `<def>_closure` and `<def>_result` for a lambda set exposed to the host,
+//!   where `<def>` is the name that its `roc__<def>_caller` function uses
+//!
+//! The `roc__memory_api_manifest` global is a NUL-terminated string listing every `<name>` that
+//! the module defines, one per line.
+use crate::debug_info_init;
+use crate::llvm::build::{add_func, load_roc_value, BuilderExt, CCReturn, Env, FunctionSpec};
+use crate::llvm::convert::basic_type_from_layout;
+use crate::llvm::deep_copy::deep_copy_layout;
+use inkwell::module::Linkage;
+use inkwell::types::{BasicType, BasicTypeEnum};
+use inkwell::values::FunctionValue;
+use inkwell::AddressSpace;
+use roc_mono::layout::{InLayout, LayoutIds, LayoutInterner, STLayoutInterner};
+
+const MANIFEST_NAME: &str = "roc__memory_api_manifest";
+
+#[derive(Default)]
+pub(crate) struct MemoryApi {
+    names: Vec<String>,
+}
+
+impl MemoryApi {
+    pub fn expose<'a>(
+        &mut self,
+        env: &Env<'a, '_, '_>,
+        layout_interner: &STLayoutInterner<'a>,
+        layout_ids: &mut LayoutIds<'a>,
+        name: String,
+        layout: InLayout<'a>,
+    ) {
+        let repr = layout_interner.get_repr(layout);
+        let ptr_type = basic_type_from_layout(env, layout_interner, repr)
+            .ptr_type(AddressSpace::default())
+            .as_basic_type_enum();
+
+        {
+            let function = build_void_header(env, &format!("roc__retain_{name}"), &[ptr_type]);
+            let value_ptr = function.get_nth_param(0).unwrap().into_pointer_value();
+            let value = load_roc_value(env, layout_interner, repr, value_ptr, "value");
+
+            env.memory_strategy
+                .increment(env, layout_interner, layout_ids, 1, value, layout);
+
+            env.builder.new_build_return(None);
+        }
+
+        {
+            let function = build_void_header(env, &format!("roc__release_{name}"), &[ptr_type]);
+            let value_ptr = function.get_nth_param(0).unwrap().into_pointer_value();
+            let value = load_roc_value(env, layout_interner, repr, value_ptr, "value");
+
+            env.memory_strategy
+                .decrement(env, layout_interner, layout_ids, value, layout);
+
+            env.builder.new_build_return(None);
+        }
+
+        {
+            let function =
+                build_void_header(env, &format!("roc__deepcopy_{name}"), &[ptr_type, ptr_type]);
+            let destination = function.get_nth_param(0).unwrap().into_pointer_value();
+            let source = function.get_nth_param(1).unwrap().into_pointer_value();
+
+            deep_copy_layout(
+                env,
+                layout_interner,
+                layout_ids,
+                layout,
+                destination,
+                source,
+            );
+
+            env.builder.new_build_return(None);
+        }
+
+        {
+            let (size, alignment) = layout_interner.stack_size_and_alignment(layout);
+
+            for (prefix, value) in [("sizeof", size), ("alignof", alignment)] {
+                let ptr_int = env.ptr_int();
+                let return_type = Some(ptr_int.as_basic_type_enum());
+                let function_spec = FunctionSpec::cconv(env, CCReturn::Return, return_type, &[]);
+
+                build_api_header(env, &format!("roc__{prefix}_{name}"), function_spec);
+
+                let value = ptr_int.const_int(value as u64, false);
+                env.builder.new_build_return(Some(&value));
+            }
+        }
+
+        self.names.push(name);
+    }
+
+    /// Add the manifest of all exposed functions to the module
+    pub fn finish(self, env: &Env<'_, '_, '_>) {
+        let manifest: String = self.names.iter().map(|name| format!("{name}\n")).collect();
+
+        let initializer = env.context.const_string(manifest.as_bytes(), true);
+
+        let global = env
+            .module
+            .add_global(initializer.get_type(), None, MANIFEST_NAME);
+
+        global.set_initializer(&initializer);
+        global.set_constant(true);
+        global.set_linkage(Linkage::External);
+    }
+}
+
+/// Add a C function returning void, and position the builder at its entry block
+fn build_void_header<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    name: &str,
+    arguments: &[BasicTypeEnum<'ctx>],
+) -> FunctionValue<'ctx> {
+    let function_spec = FunctionSpec::cconv(env, CCReturn::Void, None, arguments);
+
+    build_api_header(env, name, function_spec)
+}
+
+/// Add a C function, and position the builder at its entry block
+fn build_api_header<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    name: &str,
+    function_spec: FunctionSpec<'ctx>,
+) -> FunctionValue<'ctx> {
+    let function = add_func(
+        env.context,
+        env.module,
+        name,
+        function_spec,
+        Linkage::External,
+    );
+
+    let subprogram = env.new_subprogram(name);
+    function.set_subprogram(subprogram);
+
+    debug_info_init!(env, function);
+
+    let entry = env.context.append_basic_block(function, "entry");
+    env.builder.position_at_end(entry);
+
+    function
+}
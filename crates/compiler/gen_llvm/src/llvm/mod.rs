@@ -4,11 +4,21 @@ pub mod build_list;
 pub mod build_str;
 pub mod compare;
 pub mod convert;
+pub mod crash_dump;
+pub mod deep_copy;
+pub mod effect_mocks;
 mod expect;
 pub mod externs;
+pub mod heap_header;
+pub mod helper_cache;
 mod intrinsics;
 mod lowlevel;
+pub mod memory;
+mod memory_api;
 pub mod refcounting;
+pub mod sendable;
+pub mod symbol_map;
+pub mod totality;
 
 mod align;
 mod erased;
@@ -2,20 +2,25 @@ use crate::debug_info_init;
 use crate::llvm::bitcode::{build_dec_wrapper, call_void_bitcode_fn, call_void_list_bitcode_fn};
 use crate::llvm::build::BuilderExt;
 use crate::llvm::build::{
-    add_func, cast_basic_basic, get_tag_id, tag_pointer_clear_tag_id, Env, FAST_CALL_CONV,
+    add_func, get_tag_id, set_cold_successor, tag_pointer_clear_tag_id, ColdSuccessor, Env,
+    FAST_CALL_CONV,
 };
 use crate::llvm::build_list::{layout_refcounted, layout_width};
 use crate::llvm::build_str::str_allocation_ptr;
-use crate::llvm::convert::{basic_type_from_layout, zig_str_type, RocUnion};
+use crate::llvm::convert::{
+    basic_type_from_in_layout, basic_type_from_layout, zig_str_type, RocUnion,
+};
+use crate::llvm::heap_header::HeapHeader;
+use crate::llvm::helper_cache::HelperCache;
 use crate::llvm::struct_::RocStruct;
 use bumpalo::collections::Vec;
+use inkwell::attributes::{Attribute, AttributeLoc};
 use inkwell::basic_block::BasicBlock;
-use inkwell::module::Linkage;
+use inkwell::module::{Linkage, Module};
 use inkwell::types::{AnyTypeEnum, BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
 use inkwell::values::{BasicValueEnum, FunctionValue, InstructionValue, IntValue, PointerValue};
 use inkwell::{AddressSpace, IntPredicate};
 use roc_builtins::bitcode;
-use roc_module::symbol::Interns;
 use roc_module::symbol::Symbol;
 use roc_mono::ir::ErasedField;
 use roc_mono::layout::{
@@ -36,17 +41,9 @@ impl<'ctx> PointerToRefcount<'ctx> {
     /// the invariant is that the given pointer really points to the refcount,
     /// not the data, and only is the start of the allocated buffer if the
     /// alignment works out that way.
-    pub unsafe fn from_ptr<'a, 'env>(env: &Env<'a, 'ctx, 'env>, ptr: PointerValue<'ctx>) -> Self {
-        // must make sure it's a pointer to usize
-        let refcount_type = env.ptr_int();
-
-        let value = env.builder.new_build_pointer_cast(
-            ptr,
-            refcount_type.ptr_type(AddressSpace::default()),
-            "to_refcount_ptr",
-        );
-
-        Self { value }
+    pub unsafe fn from_ptr<'a, 'env>(_env: &Env<'a, 'ctx, 'env>, ptr: PointerValue<'ctx>) -> Self {
+        // pointers are opaque: the refcount type is given wherever we load or store through it
+        Self { value: ptr }
     }
 
     pub fn from_ptr_to_data<'a, 'env>(
@@ -54,20 +51,15 @@ impl<'ctx> PointerToRefcount<'ctx> {
         data_ptr: PointerValue<'ctx>,
     ) -> Self {
         let builder = env.builder;
-        // pointer to usize
-        let refcount_type = env.ptr_int();
-        let refcount_ptr_type = refcount_type.ptr_type(AddressSpace::default());
 
-        let ptr_as_usize_ptr =
-            builder.new_build_pointer_cast(data_ptr, refcount_ptr_type, "as_usize_ptr");
-
-        // get a pointer to index -1
-        let index_intvalue = refcount_type.const_int(-1_i64 as u64, false);
+        // whatever else is in the header, the refcount comes right before the data
+        let offset = HeapHeader::refcount_only(env.target).refcount_offset();
+        let offset_intvalue = env.ptr_int().const_int(-(offset as i64) as u64, false);
         let refcount_ptr = unsafe {
             builder.new_build_in_bounds_gep(
-                env.ptr_int(),
-                ptr_as_usize_ptr,
-                &[index_intvalue],
+                env.context.i8_type(),
+                data_ptr,
+                &[offset_intvalue],
                 "get_rc_ptr",
             )
         };
@@ -138,7 +130,11 @@ impl<'ctx> PointerToRefcount<'ctx> {
         let block = env.builder.get_insert_block().expect("to be in a function");
         let di_location = env.builder.get_current_debug_location().unwrap();
 
-        let fn_name = &format!("decrement_refcounted_ptr_{alignment}");
+        let fn_name = &if env.sendable_layouts.contains(layout) {
+            format!("decrement_refcounted_ptr_{alignment}_atomic")
+        } else {
+            format!("decrement_refcounted_ptr_{alignment}")
+        };
 
         let function = match env.module.get_function(fn_name) {
             Some(function_value) => function_value,
@@ -163,6 +159,7 @@ impl<'ctx> PointerToRefcount<'ctx> {
                 debug_info_init!(env, function_value);
 
                 Self::build_decrement_function_body(env, function_value, alignment, layout);
+                disable_tail_calls_for_profiling(env, function_value);
 
                 function_value
             }
@@ -218,6 +215,15 @@ fn debug_assert_not_list(layout: LayoutRepr<'_>) {
     debug_assert!(!matches!(layout, LayoutRepr::Builtin(Builtin::List(_))), "List are no longer safe to refcount through pointer alone. They must go through the zig bitcode functions");
 }
 
+/// What we refcount through a pointer is never a list, so the header is only a refcount
+fn elements_refcounted_arg<'ctx>(env: &Env<'_, 'ctx, '_>) -> IntValue<'ctx> {
+    let header = HeapHeader::refcount_only(env.target);
+
+    env.context
+        .bool_type()
+        .const_int(header.elements_refcounted() as u64, false)
+}
+
 fn incref_pointer<'ctx>(
     env: &Env<'_, 'ctx, '_>,
     pointer: PointerValue<'ctx>,
@@ -227,17 +233,12 @@ fn incref_pointer<'ctx>(
     debug_assert_not_list(layout);
     call_void_bitcode_fn(
         env,
-        &[
-            env.builder
-                .new_build_pointer_cast(
-                    pointer,
-                    env.ptr_int().ptr_type(AddressSpace::default()),
-                    "to_isize_ptr",
-                )
-                .into(),
-            amount.into(),
-        ],
-        roc_builtins::bitcode::UTILS_INCREF_RC_PTR,
+        &[pointer.into(), amount.into()],
+        if env.sendable_layouts.contains(layout) {
+            roc_builtins::bitcode::UTILS_INCREF_RC_PTR_ATOMIC
+        } else {
+            roc_builtins::bitcode::UTILS_INCREF_RC_PTR
+        },
     );
 }
 
@@ -252,15 +253,9 @@ fn free_pointer<'ctx>(
     call_void_bitcode_fn(
         env,
         &[
-            env.builder
-                .new_build_pointer_cast(
-                    pointer,
-                    env.ptr_int().ptr_type(AddressSpace::default()),
-                    "to_isize_ptr",
-                )
-                .into(),
+            pointer.into(),
             alignment.into(),
-            env.context.bool_type().const_int(0, false).into(),
+            elements_refcounted_arg(env).into(),
         ],
         roc_builtins::bitcode::UTILS_FREE_RC_PTR,
     )
@@ -277,17 +272,15 @@ fn decref_pointer<'ctx>(
     call_void_bitcode_fn(
         env,
         &[
-            env.builder
-                .new_build_pointer_cast(
-                    pointer,
-                    env.ptr_int().ptr_type(AddressSpace::default()),
-                    "to_isize_ptr",
-                )
-                .into(),
+            pointer.into(),
             alignment.into(),
-            env.context.bool_type().const_int(0, false).into(),
+            elements_refcounted_arg(env).into(),
         ],
-        roc_builtins::bitcode::UTILS_DECREF_RC_PTR,
+        if env.sendable_layouts.contains(layout) {
+            roc_builtins::bitcode::UTILS_DECREF_RC_PTR_ATOMIC
+        } else {
+            roc_builtins::bitcode::UTILS_DECREF_RC_PTR
+        },
     );
 }
 
@@ -303,17 +296,15 @@ pub fn decref_pointer_check_null<'ctx>(
     call_void_bitcode_fn(
         env,
         &[
-            env.builder
-                .new_build_pointer_cast(
-                    pointer,
-                    env.context.i8_type().ptr_type(AddressSpace::default()),
-                    "to_i8_ptr",
-                )
-                .into(),
+            pointer.into(),
             alignment.into(),
-            env.context.bool_type().const_int(0, false).into(),
+            elements_refcounted_arg(env).into(),
         ],
-        roc_builtins::bitcode::UTILS_DECREF_CHECK_NULL,
+        if env.sendable_layouts.contains(layout) {
+            roc_builtins::bitcode::UTILS_DECREF_CHECK_NULL_ATOMIC
+        } else {
+            roc_builtins::bitcode::UTILS_DECREF_CHECK_NULL
+        },
     );
 }
 
@@ -329,8 +320,9 @@ fn modify_refcount_struct<'a, 'ctx>(
     let di_location = env.builder.get_current_debug_location().unwrap();
 
     let (_, fn_name) = function_name_from_mode(
+        env,
+        layout_interner,
         layout_ids,
-        &env.interns,
         "increment_struct",
         "decrement_struct",
         layout_interner.get_repr(struct_layout),
@@ -428,8 +420,9 @@ fn modify_refcount_erased<'a, 'ctx>(
     let di_location = env.builder.get_current_debug_location().unwrap();
 
     let (_, fn_name) = function_name_from_mode(
+        env,
+        layout_interner,
         layout_ids,
-        &env.interns,
         "increment_erased",
         "decrement_erased",
         layout_interner.get_repr(Layout::ERASED),
@@ -554,6 +547,109 @@ pub fn decrement_refcount_layout<'a, 'ctx>(
     );
 }
 
+/// Decrement only the allocation that `value` points to, without touching its contents.
+pub fn decref_layout<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    parent: FunctionValue<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    layout: InLayout<'a>,
+) {
+    match layout_interner.runtime_representation(layout) {
+        LayoutRepr::Builtin(Builtin::Str) => todo!(),
+        list_layout @ LayoutRepr::Builtin(Builtin::List(element_layout)) => {
+            debug_assert!(value.is_struct_value());
+            let dec_element_fn =
+                build_dec_wrapper(env, layout_interner, layout_ids, element_layout);
+            call_void_list_bitcode_fn(
+                env,
+                &[value.into_struct_value()],
+                &[
+                    env.alignment_intvalue(layout_interner, element_layout),
+                    layout_width(env, layout_interner, element_layout),
+                    layout_refcounted(env, layout_interner, element_layout),
+                    dec_element_fn.as_global_value().as_pointer_value().into(),
+                ],
+                if env.sendable_layouts.contains(list_layout) {
+                    bitcode::LIST_DECREF_ATOMIC
+                } else {
+                    bitcode::LIST_DECREF
+                },
+            );
+        }
+
+        other_layout if other_layout.is_refcounted(layout_interner) => {
+            if value.is_pointer_value() {
+                let clear_tag_id = match other_layout {
+                    LayoutRepr::Union(union_layout) => {
+                        union_layout.stores_tag_id_in_pointer(env.target)
+                    }
+                    _ => false,
+                };
+
+                let value_ptr = if clear_tag_id {
+                    tag_pointer_clear_tag_id(env, value.into_pointer_value())
+                } else {
+                    value.into_pointer_value()
+                };
+
+                let then_block = env.context.append_basic_block(parent, "then");
+                let done_block = env.context.append_basic_block(parent, "done");
+
+                let condition = env
+                    .builder
+                    .new_build_is_not_null(value_ptr, "box_is_not_null");
+                env.builder
+                    .new_build_conditional_branch(condition, then_block, done_block);
+
+                {
+                    env.builder.position_at_end(then_block);
+                    let refcount_ptr = PointerToRefcount::from_ptr_to_data(env, value_ptr);
+                    refcount_ptr.decrement(env, layout_interner, layout_interner.get_repr(layout));
+
+                    env.builder.new_build_unconditional_branch(done_block);
+                }
+
+                env.builder.position_at_end(done_block);
+            } else {
+                eprint!("we're likely leaking memory; see issue #985 for details");
+            }
+        }
+        _ => {
+            // nothing to do
+        }
+    }
+}
+
+/// Unconditionally deallocate the allocation that `value` points to.
+pub fn free_layout<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    value: BasicValueEnum<'ctx>,
+    layout: InLayout<'a>,
+) {
+    let alignment = layout_interner.allocation_alignment_bytes(layout);
+
+    debug_assert!(value.is_pointer_value());
+    let value = value.into_pointer_value();
+
+    let runtime_layout = layout_interner.runtime_representation(layout);
+    let clear_tag_id = match runtime_layout {
+        LayoutRepr::Union(union) => union.stores_tag_id_in_pointer(env.target),
+        _ => false,
+    };
+
+    let ptr = if clear_tag_id {
+        tag_pointer_clear_tag_id(env, value)
+    } else {
+        value
+    };
+
+    let rc_ptr = PointerToRefcount::from_ptr_to_data(env, ptr);
+    rc_ptr.deallocate(env, alignment, runtime_layout);
+}
+
 fn modify_refcount_builtin<'a, 'ctx>(
     env: &Env<'a, 'ctx, '_>,
     layout_interner: &STLayoutInterner<'a>,
@@ -618,25 +714,7 @@ fn modify_refcount_layout_help<'a, 'ctx>(
             None => return,
         };
 
-    match layout_interner.get_repr(layout) {
-        LayoutRepr::RecursivePointer(rec_layout) => {
-            let layout = rec_layout;
-
-            let bt = basic_type_from_layout(env, layout_interner, layout_interner.get_repr(layout));
-
-            // cast the i64 pointer to a pointer to block of memory
-            let field_cast = env.builder.new_build_pointer_cast(
-                value.into_pointer_value(),
-                bt.into_pointer_type(),
-                "i64_to_opaque",
-            );
-
-            call_help(env, function, call_mode, field_cast.into());
-        }
-        _ => {
-            call_help(env, function, call_mode, value);
-        }
-    }
+    call_help(env, function, call_mode, value);
 }
 
 fn call_help<'ctx>(
@@ -672,6 +750,142 @@ fn modify_refcount_layout_build_function<'a, 'ctx>(
     layout_ids: &mut LayoutIds<'a>,
     mode: Mode,
     layout: InLayout<'a>,
+) -> Option<FunctionValue<'ctx>> {
+    let repr = layout_interner.get_repr(layout);
+
+    let function = match env.helper_cache {
+        Some(cache) if HelperCache::is_cacheable(repr) => {
+            let key = cache.key(layout_interner, &env.sendable_layouts, mode.into(), repr);
+
+            match cache.lookup(env, &key) {
+                Some(function) => function,
+                None => {
+                    let function = modify_refcount_layout_build_function_help(
+                        env,
+                        layout_interner,
+                        layout_ids,
+                        mode,
+                        layout,
+                    )?;
+
+                    cache.record(key, function);
+
+                    function
+                }
+            }
+        }
+        _ => modify_refcount_layout_build_function_help(
+            env,
+            layout_interner,
+            layout_ids,
+            mode,
+            layout,
+        )?,
+    };
+
+    disable_tail_calls_for_profiling(env, function);
+
+    // the recursion of recursive unions goes through their (noinline) free function, so their
+    // helpers can be inlined too
+    apply_rc_outlining(env, function);
+
+    Some(function)
+}
+
+/// A refcounting helper that ends in a tail call to another helper (or to `free`) disappears
+/// from the call stack. That is fine normally, but makes profiles attribute the time to the
+/// wrong caller.
+fn disable_tail_calls_for_profiling(env: &Env<'_, '_, '_>, function: FunctionValue<'_>) {
+    if env.profiling {
+        let attribute = env
+            .context
+            .create_string_attribute("disable-tail-calls", "true");
+
+        function.add_attribute(AttributeLoc::Function, attribute);
+    }
+}
+
+/// Whether calls to a refcounting helper should be inlined at the call site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RcOutlining {
+    /// Inline helpers with fewer than this many LLVM instructions, and outline the rest
+    Threshold(usize),
+    /// Inline all helpers
+    Inline,
+    /// Never inline helpers
+    Outline,
+}
+
+impl RcOutlining {
+    /// A helper for a single `Str` field is about this size
+    pub const DEFAULT_THRESHOLD: usize = 16;
+}
+
+impl Default for RcOutlining {
+    fn default() -> Self {
+        RcOutlining::Threshold(Self::DEFAULT_THRESHOLD)
+    }
+}
+
+/// The always inliner runs at every optimization level, so marking a helper `alwaysinline` is
+/// enough to inline it at all its call sites.
+fn apply_rc_outlining(env: &Env<'_, '_, '_>, function: FunctionValue<'_>) {
+    let always_inline = Attribute::get_named_enum_kind_id("alwaysinline");
+    let no_inline = Attribute::get_named_enum_kind_id("noinline");
+
+    let inline = match env.rc_outlining {
+        RcOutlining::Inline => true,
+        RcOutlining::Outline => false,
+        RcOutlining::Threshold(threshold) => {
+            let decided = function
+                .get_enum_attribute(AttributeLoc::Function, always_inline)
+                .is_some()
+                || function
+                    .get_enum_attribute(AttributeLoc::Function, no_inline)
+                    .is_some();
+
+            if decided {
+                return;
+            }
+
+            instruction_count(function) < threshold
+        }
+    };
+
+    let (add, remove) = if inline {
+        (always_inline, no_inline)
+    } else {
+        (no_inline, always_inline)
+    };
+
+    function.remove_enum_attribute(AttributeLoc::Function, remove);
+    function.add_attribute(
+        AttributeLoc::Function,
+        env.context.create_enum_attribute(add, 0),
+    );
+}
+
+fn instruction_count(function: FunctionValue<'_>) -> usize {
+    let mut count = 0;
+
+    for block in function.get_basic_blocks() {
+        let mut instruction = block.get_first_instruction();
+
+        while let Some(current) = instruction {
+            count += 1;
+            instruction = current.get_next_instruction();
+        }
+    }
+
+    count
+}
+
+fn modify_refcount_layout_build_function_help<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    mode: Mode,
+    layout: InLayout<'a>,
 ) -> Option<FunctionValue<'ctx>> {
     use LayoutRepr::*;
 
@@ -771,8 +985,9 @@ fn modify_refcount_list<'a, 'ctx>(
 
     let list_layout = LayoutRepr::Builtin(Builtin::List(element_layout));
     let (_, fn_name) = function_name_from_mode(
+        env,
+        layout_interner,
         layout_ids,
-        &env.interns,
         "increment_list",
         "decrement_list",
         list_layout,
@@ -838,6 +1053,10 @@ fn modify_refcount_list_help<'a, 'ctx>(
 
     let original_wrapper = arg_val.into_struct_value();
 
+    let is_atomic = env
+        .sendable_layouts
+        .contains(LayoutRepr::Builtin(Builtin::List(element_layout)));
+
     // List incrementing and decrementing is more complex now.
     // Always go through zig.
     match mode {
@@ -853,7 +1072,11 @@ fn modify_refcount_list_help<'a, 'ctx>(
                     layout_refcounted(env, layout_interner, element_layout),
                     dec_element_fn.as_global_value().as_pointer_value().into(),
                 ],
-                bitcode::LIST_DECREF,
+                if is_atomic {
+                    bitcode::LIST_DECREF_ATOMIC
+                } else {
+                    bitcode::LIST_DECREF
+                },
             )
         }
         Mode::Inc => {
@@ -869,7 +1092,11 @@ fn modify_refcount_list_help<'a, 'ctx>(
                     inc_amount_val.into_int_value().into(),
                     layout_refcounted(env, layout_interner, element_layout),
                 ],
-                bitcode::LIST_INCREF,
+                if is_atomic {
+                    bitcode::LIST_INCREF_ATOMIC
+                } else {
+                    bitcode::LIST_INCREF
+                },
             )
         }
     }
@@ -889,8 +1116,9 @@ fn modify_refcount_str<'a, 'ctx>(
     let di_location = env.builder.get_current_debug_location().unwrap();
 
     let (_, fn_name) = function_name_from_mode(
+        env,
+        layout_interner,
         layout_ids,
-        &env.interns,
         "increment_str",
         "decrement_str",
         layout_interner.get_repr(layout),
@@ -1048,16 +1276,92 @@ pub fn build_header_help<'ctx>(
         linkage,
     );
 
+    fn_val.add_attribute(
+        AttributeLoc::Function,
+        env.context.create_string_attribute(HELPER_ATTRIBUTE, ""),
+    );
+
     let subprogram = env.new_subprogram(fn_name);
     fn_val.set_subprogram(subprogram);
 
     debug_info_init!(env, fn_val);
 
-    env.dibuilder.finalize();
-
     fn_val
 }
 
+/// Marks the functions made by [build_header_help], so [remove_dead_helpers] can find them
+const HELPER_ATTRIBUTE: &str = "roc-helper";
+
+/// Delete the refcounting, equality and other helpers that nothing calls.
+///
+/// Helpers are generated when code needs them, but a later step (e.g. inlining, or the elision
+/// of a refcount operation) can remove every call. Global DCE only removes the private ones,
+/// and only at the start of the optimization pipeline; on Windows helpers are external, so
+/// nothing removes them at all. Run this just before the optimization pipeline.
+pub fn remove_dead_helpers(module: &Module<'_>) {
+    loop {
+        let dead: std::vec::Vec<_> = module
+            .get_functions()
+            .filter(|function| {
+                function
+                    .get_string_attribute(AttributeLoc::Function, HELPER_ATTRIBUTE)
+                    .is_some()
+                    && use_count(*function) == self_use_count(*function)
+            })
+            .collect();
+
+        if dead.is_empty() {
+            break;
+        }
+
+        // removing a helper can make the helpers it calls dead too
+        for function in dead {
+            unsafe { function.delete() };
+        }
+    }
+}
+
+fn use_count(function: FunctionValue<'_>) -> usize {
+    let mut count = 0;
+    let mut next = function
+        .as_global_value()
+        .as_pointer_value()
+        .get_first_use();
+
+    while let Some(current) = next {
+        count += 1;
+        next = current.get_next_use();
+    }
+
+    count
+}
+
+/// Recursive helpers use themselves, which does not keep them alive
+fn self_use_count(function: FunctionValue<'_>) -> usize {
+    let pointer = function.as_global_value().as_pointer_value();
+    let mut count = 0;
+
+    for block in function.get_basic_blocks() {
+        let mut instruction = block.get_first_instruction();
+
+        while let Some(current) = instruction {
+            for index in 0..current.get_num_operands() {
+                let operand = current
+                    .get_operand(index)
+                    .and_then(|operand| operand.left());
+
+                if operand == Some(pointer.into()) {
+                    count += 1;
+                }
+            }
+
+            instruction = current.get_next_instruction();
+        }
+    }
+
+    count
+}
+
 #[derive(Clone, Copy)]
 pub enum Mode {
     Inc,
@@ -1093,8 +1397,9 @@ fn build_rec_union<'a, 'ctx>(
     let layout = LayoutRepr::Union(union_layout);
 
     let (_, fn_name) = function_name_from_mode(
+        env,
+        layout_interner,
         layout_ids,
-        &env.interns,
         "increment_rec_union",
         "decrement_rec_union",
         layout,
@@ -1161,7 +1466,6 @@ fn build_rec_union_help<'a, 'ctx>(
     let parent = fn_val;
 
     debug_assert!(arg_val.is_pointer_value());
-    let current_tag_id = get_tag_id(env, layout_interner, fn_val, &union_layout, arg_val);
     let value_ptr = if union_layout.stores_tag_id_in_pointer(env.target) {
         tag_pointer_clear_tag_id(env, arg_val.into_pointer_value())
     } else {
@@ -1204,44 +1508,107 @@ fn build_rec_union_help<'a, 'ctx>(
         }
 
         Mode::Dec => {
-            let do_recurse_block = env.context.append_basic_block(parent, "do_recurse");
-            let no_recurse_block = env.context.append_basic_block(parent, "no_recurse");
+            let do_free_block = env.context.append_basic_block(parent, "do_free");
+            let no_free_block = env.context.append_basic_block(parent, "no_free");
 
-            builder.new_build_conditional_branch(
+            // freeing is the slow path
+            let branch = builder.new_build_conditional_branch(
                 refcount_ptr.is_1(env),
-                do_recurse_block,
-                no_recurse_block,
+                do_free_block,
+                no_free_block,
             );
+            set_cold_successor(env, branch, ColdSuccessor::Then);
 
             {
-                env.builder.position_at_end(no_recurse_block);
+                env.builder.position_at_end(no_free_block);
 
                 refcount_ptr.modify(call_mode, layout, env, layout_interner);
                 env.builder.new_build_return(None);
             }
 
             {
-                env.builder.position_at_end(do_recurse_block);
+                let free_fn =
+                    build_rec_union_free(env, layout_interner, layout_ids, union_layout, fn_val);
 
-                build_rec_union_recursive_decrement(
-                    env,
-                    layout_interner,
-                    layout_ids,
-                    parent,
-                    fn_val,
-                    union_layout,
-                    tags,
-                    value_ptr,
-                    current_tag_id,
-                    refcount_ptr,
-                    do_recurse_block,
-                    DecOrReuse::Dec,
-                )
+                env.builder.position_at_end(do_free_block);
+
+                let call = call_help(env, free_fn, CallMode::Dec, arg_val);
+                call.set_tail_call(true);
+
+                env.builder.new_build_return(None);
             }
         }
     }
 }
 
+/// The part of `decrement_fn` that runs when the cell is unique: decrement the children and free
+/// the cell. It lives in its own cold function, so that the decrement itself is small enough to
+/// inline at its call sites.
+fn build_rec_union_free<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    layout_ids: &mut LayoutIds<'a>,
+    union_layout: UnionLayout<'a>,
+    decrement_fn: FunctionValue<'ctx>,
+) -> FunctionValue<'ctx> {
+    let fn_name = format!("{}_free", decrement_fn.get_name().to_str().unwrap());
+
+    if let Some(function_value) = env.module.get_function(&fn_name) {
+        return function_value;
+    }
+
+    let block = env.builder.get_insert_block().expect("to be in a function");
+    let di_location = env.builder.get_current_debug_location().unwrap();
+
+    let layout = LayoutRepr::Union(union_layout);
+    let basic_type = basic_type_from_layout(env, layout_interner, layout);
+    let fn_val = build_header(env, basic_type, Mode::Dec, &fn_name);
+
+    for name in ["cold", "noinline"] {
+        let kind_id = Attribute::get_named_enum_kind_id(name);
+        debug_assert!(kind_id > 0);
+        let attr = env.context.create_enum_attribute(kind_id, 0);
+        fn_val.add_attribute(AttributeLoc::Function, attr);
+    }
+
+    let entry = env.context.append_basic_block(fn_val, "entry");
+    env.builder.position_at_end(entry);
+
+    debug_info_init!(env, fn_val);
+
+    let arg_val = fn_val.get_param_iter().next().unwrap();
+    arg_val.set_name(Symbol::ARG_1.as_str(&env.interns));
+
+    let value_ptr = if union_layout.stores_tag_id_in_pointer(env.target) {
+        tag_pointer_clear_tag_id(env, arg_val.into_pointer_value())
+    } else {
+        arg_val.into_pointer_value()
+    };
+
+    // the null case was handled by `decrement_fn`
+    let refcount_ptr = PointerToRefcount::from_ptr_to_data(env, value_ptr);
+
+    build_rec_union_recursive_decrement(
+        env,
+        layout_interner,
+        layout_ids,
+        fn_val,
+        decrement_fn,
+        union_layout,
+        union_layout_tags(env.arena, &union_layout),
+        arg_val,
+        value_ptr,
+        refcount_ptr,
+        entry,
+        DecOrReuse::Dec,
+    );
+
+    env.builder.position_at_end(block);
+    env.builder.set_current_debug_location(di_location);
+
+    fn_val
+}
+
 enum DecOrReuse {
     Dec,
     Reuse,
@@ -1263,8 +1630,8 @@ fn build_rec_union_recursive_decrement<'a, 'ctx>(
     decrement_fn: FunctionValue<'ctx>,
     union_layout: UnionLayout<'a>,
     tags: UnionLayoutTags<'a>,
+    arg_val: BasicValueEnum<'ctx>,
     value_ptr: PointerValue<'ctx>,
-    current_tag_id: IntValue<'ctx>,
     refcount_ptr: PointerToRefcount<'ctx>,
     match_block: BasicBlock<'ctx>,
     decrement_or_reuse: DecOrReuse,
@@ -1275,15 +1642,21 @@ fn build_rec_union_recursive_decrement<'a, 'ctx>(
 
     let UnionLayoutTags { nullable_id, tags } = tags;
 
+    // When every tag has its refcounted fields in the same place (e.g. AST-like unions where every
+    // variant stores a single recursive pointer first), the tag id does not matter: we don't read
+    // it, and emit the field modifications for all tags at once.
+    let uniform_fields = uniform_refcounted_fields(layout_interner, tags);
+    let tags: &[&[InLayout<'a>]] = match uniform_fields {
+        Some(fields) => env.arena.alloc([fields]),
+        None => tags,
+    };
+
     // next, make a jump table for all possible values of the tag_id
     let mut cases = Vec::with_capacity_in(tags.len(), env.arena);
 
-    let tag_id_int_type = basic_type_from_layout(
-        env,
-        layout_interner,
-        layout_interner.get_repr(union_layout.tag_id_layout()),
-    )
-    .into_int_type();
+    let tag_id_int_type =
+        basic_type_from_in_layout(env, layout_interner, union_layout.tag_id_layout())
+            .into_int_type();
 
     for (tag_id, field_layouts) in tags.iter().enumerate() {
         let tag_id = match nullable_id {
@@ -1317,13 +1690,6 @@ fn build_rec_union_recursive_decrement<'a, 'ctx>(
         let fields_struct = LayoutRepr::struct_(field_layouts);
         let wrapper_type = basic_type_from_layout(env, layout_interner, fields_struct);
 
-        // cast the opaque pointer to a pointer of the correct shape
-        let struct_ptr = env.builder.new_build_pointer_cast(
-            value_ptr,
-            wrapper_type.ptr_type(AddressSpace::default()),
-            "opaque_to_correct_recursive_decrement",
-        );
-
         // defer actually performing the refcount modifications until after the current cell has
         // been decremented, see below
         let mut deferred_rec = Vec::new_in(env.arena);
@@ -1331,32 +1697,27 @@ fn build_rec_union_recursive_decrement<'a, 'ctx>(
 
         for (i, field_layout) in field_layouts.iter().enumerate() {
             if let LayoutRepr::RecursivePointer(_) = layout_interner.get_repr(*field_layout) {
-                // this field has type `*i64`, but is really a pointer to the data we want
+                // this field is a pointer to the data we want
                 let elem_pointer = env.builder.new_build_struct_gep(
                     wrapper_type.into_struct_type(),
-                    struct_ptr,
+                    value_ptr,
                     i as u32,
                     "gep_recursive_pointer",
                 );
 
-                let ptr_as_i64_ptr = env.builder.new_build_load(
-                    env.context.i64_type().ptr_type(AddressSpace::default()),
-                    elem_pointer,
-                    "load_recursive_pointer",
-                );
-
-                debug_assert!(ptr_as_i64_ptr.is_pointer_value());
-
-                // therefore we must cast it to our desired type
                 let union_layout = LayoutRepr::Union(union_layout);
                 let union_type = basic_type_from_layout(env, layout_interner, union_layout);
-                let recursive_field_ptr = cast_basic_basic(env, ptr_as_i64_ptr, union_type);
+                let recursive_field_ptr =
+                    env.builder
+                        .new_build_load(union_type, elem_pointer, "load_recursive_pointer");
+
+                debug_assert!(recursive_field_ptr.is_pointer_value());
 
                 deferred_rec.push(recursive_field_ptr);
             } else if layout_interner.contains_refcounted(*field_layout) {
                 let elem_pointer = env.builder.new_build_struct_gep(
                     wrapper_type.into_struct_type(),
-                    struct_ptr,
+                    value_ptr,
                     i as u32,
                     "gep_recursive_pointer",
                 );
@@ -1412,10 +1773,12 @@ fn build_rec_union_recursive_decrement<'a, 'ctx>(
 
     cases.reverse();
 
-    if matches!(
-        union_layout,
-        UnionLayout::NullableUnwrapped { .. } | UnionLayout::NonNullableUnwrapped { .. }
-    ) {
+    if uniform_fields.is_some()
+        || matches!(
+            union_layout,
+            UnionLayout::NullableUnwrapped { .. } | UnionLayout::NonNullableUnwrapped { .. }
+        )
+    {
         debug_assert!(cases.len() <= 1, "{cases:?}");
 
         if cases.is_empty() {
@@ -1429,6 +1792,8 @@ fn build_rec_union_recursive_decrement<'a, 'ctx>(
     } else {
         let default_block = env.context.append_basic_block(parent, "switch_default");
 
+        let current_tag_id = get_tag_id(env, layout_interner, parent, &union_layout, arg_val);
+
         // switch on it
         env.builder
             .new_build_switch(current_tag_id, default_block, &cases);
@@ -1448,6 +1813,42 @@ fn build_rec_union_recursive_decrement<'a, 'ctx>(
     }
 }
 
+/// If all tags agree on which fields need refcounting, and on the layout of every field up to and
+/// including the last such field (so that those fields are at the same offsets), return that
+/// shared prefix of fields.
+fn uniform_refcounted_fields<'a>(
+    interner: &STLayoutInterner<'a>,
+    tags: &'a [&'a [InLayout<'a>]],
+) -> Option<&'a [InLayout<'a>]> {
+    if tags.len() < 2 {
+        return None;
+    }
+
+    let refcounted_prefix = |fields: &'a [InLayout<'a>]| {
+        let needs_refcounting = |field: &InLayout<'a>| {
+            matches!(interner.get_repr(*field), LayoutRepr::RecursivePointer(_))
+                || interner.contains_refcounted(*field)
+        };
+
+        match fields.iter().rposition(needs_refcounting) {
+            Some(last) => &fields[..=last],
+            None => &fields[..0],
+        }
+    };
+
+    let (first, rest) = tags.split_first()?;
+    let prefix = refcounted_prefix(first);
+
+    if rest
+        .iter()
+        .all(|fields| refcounted_prefix(fields) == prefix)
+    {
+        Some(prefix)
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 struct UnionLayoutTags<'a> {
     nullable_id: Option<u16>,
@@ -1502,6 +1903,7 @@ pub fn build_reset<'a, 'ctx>(
     let layout_id = layout_ids.get(Symbol::DEC, &union_layout_repr);
     let fn_name = layout_id.to_symbol_string(Symbol::DEC, &env.interns);
     let fn_name = format!("{fn_name}_reset");
+    env.record_helper(layout_interner, &fn_name, Symbol::DEC, union_layout_repr);
 
     let dec_function = build_rec_union(env, layout_interner, layout_ids, Mode::Dec, union_layout);
 
@@ -1566,7 +1968,6 @@ fn build_reuse_rec_union_help<'a, 'ctx>(
     let parent = reset_function;
 
     debug_assert!(arg_val.is_pointer_value());
-    let current_tag_id = get_tag_id(env, layout_interner, reset_function, &union_layout, arg_val);
     let value_ptr = tag_pointer_clear_tag_id(env, arg_val.into_pointer_value());
 
     // to increment/decrement the cons-cell itself
@@ -1624,8 +2025,8 @@ fn build_reuse_rec_union_help<'a, 'ctx>(
             dec_function,
             union_layout,
             tags,
+            arg_val,
             value_ptr,
-            current_tag_id,
             refcount_ptr,
             do_recurse_block,
             DecOrReuse::Reuse,
@@ -1634,8 +2035,9 @@ fn build_reuse_rec_union_help<'a, 'ctx>(
 }
 
 fn function_name_from_mode<'a>(
+    env: &Env<'a, '_, '_>,
+    layout_interner: &STLayoutInterner<'a>,
     layout_ids: &mut LayoutIds<'a>,
-    interns: &Interns,
     if_inc: &'static str,
     if_dec: &'static str,
     layout: LayoutRepr<'a>,
@@ -1647,10 +2049,15 @@ fn function_name_from_mode<'a>(
     // layout ids of the inc and dec versions to be different, which is
     // rather confusing, so now `inc_x` always corresponds to `dec_x`
     let layout_id = layout_ids.get(Symbol::DEC, &layout);
-    match mode {
-        Mode::Inc => (if_inc, layout_id.to_symbol_string(Symbol::INC, interns)),
-        Mode::Dec => (if_dec, layout_id.to_symbol_string(Symbol::DEC, interns)),
-    }
+    let (helper, symbol) = match mode {
+        Mode::Inc => (if_inc, Symbol::INC),
+        Mode::Dec => (if_dec, Symbol::DEC),
+    };
+
+    let fn_name = layout_id.to_symbol_string(symbol, &env.interns);
+    env.record_helper(layout_interner, &fn_name, symbol, layout);
+
+    (helper, fn_name)
 }
 
 fn modify_refcount_nonrecursive<'a, 'ctx>(
@@ -1667,8 +2074,9 @@ fn modify_refcount_nonrecursive<'a, 'ctx>(
     let di_location = env.builder.get_current_debug_location().unwrap();
 
     let (_, fn_name) = function_name_from_mode(
+        env,
+        layout_interner,
         layout_ids,
-        &env.interns,
         "increment_union",
         "decrement_union",
         layout,
@@ -1745,11 +2153,7 @@ fn modify_refcount_nonrecursive_help<'a, 'ctx>(
     let tag_id = env
         .builder
         .new_build_load(
-            basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(union_layout.tag_id_layout()),
-            ),
+            basic_type_from_in_layout(env, layout_interner, union_layout.tag_id_layout()),
             tag_id_ptr,
             "load_tag_id",
         )
@@ -1783,19 +2187,13 @@ fn modify_refcount_nonrecursive_help<'a, 'ctx>(
 
         debug_assert!(data_struct_type.is_struct_type());
         let data_struct_type = data_struct_type.into_struct_type();
-        let opaque_tag_data_ptr = env.builder.new_build_struct_gep(
+        let tag_data_ptr = env.builder.new_build_struct_gep(
             union_struct_type,
             arg_ptr,
             RocUnion::TAG_DATA_INDEX,
             "field_ptr",
         );
 
-        let cast_tag_data_pointer = env.builder.new_build_pointer_cast(
-            opaque_tag_data_ptr,
-            data_struct_type.ptr_type(AddressSpace::default()),
-            "cast_to_concrete_tag",
-        );
-
         for (i, field_layout) in field_layouts.iter().enumerate() {
             if let LayoutRepr::RecursivePointer(union_layout) =
                 layout_interner.get_repr(*field_layout)
@@ -1803,27 +2201,18 @@ fn modify_refcount_nonrecursive_help<'a, 'ctx>(
                 // This field is a pointer to the recursive pointer.
                 let field_ptr = env.builder.new_build_struct_gep(
                     data_struct_type,
-                    cast_tag_data_pointer,
+                    tag_data_ptr,
                     i as u32,
                     "modify_tag_field",
                 );
 
                 // This is the actual pointer to the recursive data.
-                let field_value = env.builder.new_build_load(
-                    env.context.i64_type().ptr_type(AddressSpace::default()),
-                    field_ptr,
-                    "load_recursive_pointer",
-                );
+                let union_type = basic_type_from_in_layout(env, layout_interner, union_layout);
+                let recursive_ptr_field_value =
+                    env.builder
+                        .new_build_load(union_type, field_ptr, "load_recursive_pointer");
 
-                debug_assert!(field_value.is_pointer_value());
-
-                // therefore we must cast it to our desired type
-                let union_type = basic_type_from_layout(
-                    env,
-                    layout_interner,
-                    layout_interner.get_repr(union_layout),
-                );
-                let recursive_ptr_field_value = cast_basic_basic(env, field_value, union_type);
+                debug_assert!(recursive_ptr_field_value.is_pointer_value());
 
                 modify_refcount_layout_help(
                     env,
@@ -1836,7 +2225,7 @@ fn modify_refcount_nonrecursive_help<'a, 'ctx>(
             } else if layout_interner.contains_refcounted(*field_layout) {
                 let field_ptr = env.builder.new_build_struct_gep(
                     data_struct_type,
-                    cast_tag_data_pointer,
+                    tag_data_ptr,
                     i as u32,
                     "modify_tag_field",
                 );
@@ -1845,11 +2234,7 @@ fn modify_refcount_nonrecursive_help<'a, 'ctx>(
                     field_ptr.into()
                 } else {
                     env.builder.new_build_load(
-                        basic_type_from_layout(
-                            env,
-                            layout_interner,
-                            layout_interner.get_repr(*field_layout),
-                        ),
+                        basic_type_from_in_layout(env, layout_interner, *field_layout),
                         field_ptr,
                         "field_value",
                     )
@@ -0,0 +1,250 @@
+//! Find the layouts of values that the host can share between threads.
+//!
+//! A value crosses the host boundary when it is an argument or the result of an exposed entry
+//! point, or when it is passed to or returned from an effect (a foreign call). The host may then
+//! keep it, and retain or release it from any thread, so every layout reachable from these
+//! values gets atomic refcount updates. All other layouts keep using plain arithmetic.
+//!
+//! `List.mapParallel` shares values between threads too: the elements it maps, their results,
+//! and whatever the mapping function captures. Those layouts are always sendable, see
+//! [SendableLayouts::insert_parallel_maps].
+//!
+//! The zig builtins update refcounts too (e.g. when `List.concat` consumes its argument), but
+//! cannot tell which values are sendable. As soon as any layout is sendable, all of their refcount
+//! updates are atomic, see [SendableLayouts::configure_builtins].
+use inkwell::module::{Linkage, Module};
+use roc_builtins::bitcode;
+use roc_collections::{MutMap, MutSet};
+use roc_error_macros::internal_error;
+use roc_module::symbol::Symbol;
+use roc_mono::ir::{Call, CallType, Expr, Proc, ProcLayout, Stmt};
+use roc_mono::layout::{
+    Builtin, InLayout, LayoutInterner, LayoutRepr, STLayoutInterner, UnionLayout,
+};
+use roc_mono::low_level::HigherOrder;
+
+#[derive(Debug, Default)]
+pub struct SendableLayouts<'a> {
+    /// set when an erased closure crosses the boundary: we cannot know what it captures
+    everything: bool,
+    reprs: MutSet<LayoutRepr<'a>>,
+}
+
+impl<'a> SendableLayouts<'a> {
+    /// Nothing is sendable; all refcounts are updated non-atomically.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn from_host_interface<'p>(
+        layout_interner: &STLayoutInterner<'a>,
+        entry_points: impl IntoIterator<Item = ProcLayout<'a>>,
+        procedures: impl IntoIterator<Item = &'p Proc<'a>>,
+    ) -> Self
+    where
+        'a: 'p,
+    {
+        let mut sendable = Self::none();
+
+        for proc_layout in entry_points {
+            for argument in proc_layout.arguments {
+                sendable.insert(layout_interner, *argument);
+            }
+
+            sendable.insert(layout_interner, proc_layout.result);
+        }
+
+        for proc in procedures {
+            let mut symbol_layouts = MutMap::default();
+            let mut foreign_calls = Vec::new();
+
+            for (layout, symbol) in proc.args {
+                symbol_layouts.insert(*symbol, *layout);
+            }
+
+            for_each_call(&proc.body, &mut symbol_layouts, &mut |call| {
+                if let CallType::Foreign { ret_layout, .. } = call.call_type {
+                    foreign_calls.push((call.arguments, ret_layout));
+                }
+            });
+
+            for (arguments, ret_layout) in foreign_calls {
+                for argument in arguments {
+                    if let Some(layout) = symbol_layouts.get(argument) {
+                        sendable.insert(layout_interner, *layout);
+                    }
+                }
+
+                sendable.insert(layout_interner, ret_layout);
+            }
+        }
+
+        sendable
+    }
+
+    /// Add the layouts that `List.mapParallel` hands to the threads of the host. Their refcounts
+    /// must be updated atomically whether or not the host interface is sendable.
+    pub fn insert_parallel_maps<'p>(
+        &mut self,
+        layout_interner: &STLayoutInterner<'a>,
+        procedures: impl IntoIterator<Item = &'p Proc<'a>>,
+    ) where
+        'a: 'p,
+    {
+        let mut parallel_maps = Vec::new();
+
+        for proc in procedures {
+            for_each_call(&proc.body, &mut MutMap::default(), &mut |call| {
+                if let CallType::HigherOrder(higher_order) = call.call_type {
+                    if let HigherOrder::ListMapParallel { .. } = higher_order.op {
+                        parallel_maps.push(higher_order);
+                    }
+                }
+            });
+        }
+
+        for higher_order in parallel_maps {
+            let passed_function = &higher_order.passed_function;
+
+            for argument in passed_function.argument_layouts {
+                self.insert(layout_interner, *argument);
+            }
+
+            self.insert(layout_interner, passed_function.return_layout);
+
+            if let Some(closure_env_layout) = higher_order.closure_env_layout {
+                self.insert(layout_interner, closure_env_layout);
+            }
+        }
+    }
+
+    /// Make the refcount updates inside the zig builtins atomic when any layout is sendable.
+    /// The flag becomes a private constant either way, so the builtins of programs that share
+    /// nothing with other threads keep their plain arithmetic after optimization, and the host
+    /// cannot change it.
+    pub fn configure_builtins(&self, module: &Module) {
+        let is_atomic = self.everything || !self.reprs.is_empty();
+
+        let global = module
+            .get_global(bitcode::UTILS_ATOMIC_REFCOUNTS)
+            .unwrap_or_else(|| {
+                internal_error!("{} is not in the builtins", bitcode::UTILS_ATOMIC_REFCOUNTS)
+            });
+
+        // a `u8` on the zig side
+        let flag = module
+            .get_context()
+            .i8_type()
+            .const_int(is_atomic as u64, false);
+        global.set_initializer(&flag);
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+    }
+
+    pub fn contains(&self, repr: LayoutRepr<'a>) -> bool {
+        self.everything || self.reprs.contains(&repr)
+    }
+
+    fn insert(&mut self, layout_interner: &STLayoutInterner<'a>, layout: InLayout<'a>) {
+        let repr = layout_interner.get_repr(layout);
+
+        if !self.reprs.insert(repr) {
+            return;
+        }
+
+        match repr {
+            LayoutRepr::Builtin(Builtin::List(element)) => self.insert(layout_interner, element),
+            LayoutRepr::Builtin(_) => {}
+            LayoutRepr::Struct(fields) => {
+                for field in fields {
+                    self.insert(layout_interner, *field);
+                }
+            }
+            LayoutRepr::Ptr(inner) => self.insert(layout_interner, inner),
+            LayoutRepr::Union(union_layout) => match union_layout {
+                UnionLayout::NonRecursive(tags)
+                | UnionLayout::Recursive(tags)
+                | UnionLayout::NullableWrapped {
+                    other_tags: tags, ..
+                } => {
+                    for field in tags.iter().flat_map(|tag| tag.iter()) {
+                        self.insert(layout_interner, *field);
+                    }
+                }
+                UnionLayout::NonNullableUnwrapped(fields)
+                | UnionLayout::NullableUnwrapped {
+                    other_fields: fields,
+                    ..
+                } => {
+                    for field in fields {
+                        self.insert(layout_interner, *field);
+                    }
+                }
+            },
+            LayoutRepr::LambdaSet(lambda_set) => {
+                self.insert(layout_interner, lambda_set.runtime_representation())
+            }
+            LayoutRepr::RecursivePointer(union_layout) => {
+                self.insert(layout_interner, union_layout)
+            }
+            LayoutRepr::FunctionPointer(_) => {}
+            LayoutRepr::Erased(_) => self.everything = true,
+        }
+    }
+}
+
+/// Call `on_call` for every call in `stmt`, while recording the layout of every symbol defined
+/// along the way in `symbol_layouts`
+fn for_each_call<'a>(
+    stmt: &Stmt<'a>,
+    symbol_layouts: &mut MutMap<Symbol, InLayout<'a>>,
+    on_call: &mut impl FnMut(&Call<'a>),
+) {
+    use Stmt::*;
+
+    let mut stmt = stmt;
+
+    loop {
+        match stmt {
+            Let(symbol, expr, layout, cont) => {
+                symbol_layouts.insert(*symbol, *layout);
+
+                if let Expr::Call(call) = expr {
+                    on_call(call);
+                }
+
+                stmt = cont;
+            }
+            Switch {
+                branches,
+                default_branch,
+                ..
+            } => {
+                for (_, _, branch) in branches.iter() {
+                    for_each_call(branch, symbol_layouts, on_call);
+                }
+
+                stmt = default_branch.1;
+            }
+            Join {
+                parameters,
+                body,
+                remainder,
+                ..
+            } => {
+                for param in parameters.iter() {
+                    symbol_layouts.insert(param.symbol, param.layout);
+                }
+
+                for_each_call(body, symbol_layouts, on_call);
+
+                stmt = remainder;
+            }
+            Refcounting(_, cont) => stmt = cont,
+            Expect { remainder, .. } | ExpectFx { remainder, .. } | Dbg { remainder, .. } => {
+                stmt = remainder
+            }
+            Ret(_) | Jump(_, _) | Crash(_, _) => return,
+        }
+    }
+}
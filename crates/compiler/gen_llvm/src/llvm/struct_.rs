@@ -12,7 +12,7 @@ use crate::llvm::build::{load_roc_value, use_roc_value};
 
 use super::{
     build::{create_entry_block_alloca, store_roc_value, BuilderExt, Env},
-    convert::basic_type_from_layout,
+    convert::{basic_type_from_in_layout, basic_type_from_layout},
     scope::Scope,
 };
 
@@ -196,11 +196,7 @@ fn build_struct_value_helper<'a, 'ctx>(
             .get_repr(field_layout)
             .is_dropped_because_empty()
         {
-            let field_type = basic_type_from_layout(
-                env,
-                layout_interner,
-                layout_interner.get_repr(field_layout),
-            );
+            let field_type = basic_type_from_in_layout(env, layout_interner, field_layout);
             field_types.push(field_type);
 
             if layout_interner.is_passed_by_reference(field_layout) {
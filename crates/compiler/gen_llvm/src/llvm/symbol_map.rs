@@ -0,0 +1,98 @@
+//! Where each function in the generated code comes from, for tools that only see symbol names:
+//! demanglers, profilers, and binary size analysis.
+//!
+//! Procedures are recorded when their header is built, helpers (refcounting, equality, copying)
+//! when we pick their name. Many of them are inlined later, so [SymbolMap::entries] only returns
+//! the ones that are still in the module.
+use std::cell::RefCell;
+
+use inkwell::module::Module;
+use roc_collections::all::MutMap;
+use roc_error_macros::internal_error;
+use roc_module::symbol::{Interns, Symbol};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolOrigin {
+    /// A specialization of a Roc definition
+    Proc {
+        /// e.g. `List.map`
+        definition: String,
+        arguments: Vec<String>,
+        result: String,
+    },
+    /// A helper that the compiler generates for one layout
+    Helper {
+        /// e.g. `#dec` or `#list_eq`
+        helper: String,
+        kind: HelperKind,
+        layout: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HelperKind {
+    Refcounting,
+    Equality,
+    Copying,
+}
+
+impl HelperKind {
+    pub fn of(helper: Symbol) -> Self {
+        match helper {
+            Symbol::INC | Symbol::DEC | Symbol::GENERIC_RC_REF => HelperKind::Refcounting,
+            Symbol::LIST_EQ | Symbol::GENERIC_EQ | Symbol::GENERIC_EQ_REF => HelperKind::Equality,
+            Symbol::GENERIC_COPY_REF | Symbol::CLONE => HelperKind::Copying,
+            _ => internal_error!("{helper:?} is not a helper"),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HelperKind::Refcounting => "refcounting",
+            HelperKind::Equality => "equality",
+            HelperKind::Copying => "copying",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolMap {
+    origins: RefCell<MutMap<String, SymbolOrigin>>,
+}
+
+impl SymbolMap {
+    /// `origin` is only computed the first time we see `fn_name`
+    pub fn record(&self, fn_name: &str, origin: impl FnOnce() -> SymbolOrigin) {
+        let mut origins = self.origins.borrow_mut();
+
+        if !origins.contains_key(fn_name) {
+            origins.insert(fn_name.to_string(), origin());
+        }
+    }
+
+    pub fn contains(&self, fn_name: &str) -> bool {
+        self.origins.borrow().contains_key(fn_name)
+    }
+
+    /// The functions that are still defined in `module`, sorted by symbol
+    pub fn entries(&self, module: &Module<'_>) -> Vec<(String, SymbolOrigin)> {
+        let origins = self.origins.borrow();
+
+        let mut entries = Vec::from_iter(origins.iter().filter_map(|(fn_name, origin)| {
+            let function = module.get_function(fn_name)?;
+
+            (function.count_basic_blocks() > 0).then(|| (fn_name.clone(), origin.clone()))
+        }));
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        entries
+    }
+}
+
+/// The name of a Roc definition the way users write it, e.g. `List.map`
+pub fn roc_name(interns: &Interns, symbol: Symbol) -> String {
+    let module_name = interns.module_ids.get_name(symbol.module_id()).unwrap();
+
+    format!("{}.{}", module_name, symbol.as_str(interns))
+}
@@ -0,0 +1,130 @@
+//! Check that the refcounting and equality helpers cannot crash. Hosts call them on whatever
+//! valid values they hold, through the exposed functions and the `roc__` refcounting API, so a
+//! helper that can reach `roc_panic` or a trap is a bug in code gen, even when no test triggers
+//! it. (There are no hashing helpers to check: `Hash` is implemented in Roc.)
+//!
+//! We follow the functions that a helper calls, or passes to a builtin like
+//! `roc_builtins.list.decref`, as long as they are procs or helpers recorded in the
+//! [SymbolMap]. The builtins themselves are not followed; they have their own tests.
+use std::collections::VecDeque;
+
+use inkwell::module::Module;
+use inkwell::values::{BasicValueEnum, FunctionValue};
+use roc_collections::all::MutMap;
+
+use crate::llvm::symbol_map::{HelperKind, SymbolMap, SymbolOrigin};
+
+/// Calling any of these means crashing
+const CRASHING_FUNCTIONS: &[&str] = &["roc_panic", "llvm.trap", "llvm.debugtrap", "abort"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashingHelper {
+    pub kind: HelperKind,
+    /// From the helper to the function that crashes
+    pub path: Vec<String>,
+}
+
+impl std::fmt::Display for CrashingHelper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the {} helper {} can crash: {}",
+            self.kind.as_str(),
+            self.path[0],
+            self.path.join(" -> ")
+        )
+    }
+}
+
+/// The refcounting and equality helpers in `module` that can crash. Run this before the
+/// optimization pipeline, which inlines most helpers.
+pub fn crashing_helpers(module: &Module<'_>, symbol_map: &SymbolMap) -> Vec<CrashingHelper> {
+    symbol_map
+        .entries(module)
+        .into_iter()
+        .filter_map(|(fn_name, origin)| match origin {
+            SymbolOrigin::Helper {
+                kind: kind @ (HelperKind::Refcounting | HelperKind::Equality),
+                ..
+            } => {
+                let path = path_to_crash(module, symbol_map, &fn_name)?;
+
+                Some(CrashingHelper { kind, path })
+            }
+            SymbolOrigin::Helper { .. } | SymbolOrigin::Proc { .. } => None,
+        })
+        .collect()
+}
+
+/// Breadth first, so the path we report is a shortest one
+fn path_to_crash(module: &Module<'_>, symbol_map: &SymbolMap, helper: &str) -> Option<Vec<String>> {
+    let mut callers: MutMap<String, String> = MutMap::default();
+    let mut queue = VecDeque::from([helper.to_string()]);
+
+    while let Some(fn_name) = queue.pop_front() {
+        let function = module.get_function(&fn_name)?;
+
+        for used in used_functions(module, function) {
+            let used_name = used.get_name().to_string_lossy().into_owned();
+
+            if used_name == helper || callers.contains_key(&used_name) {
+                continue;
+            }
+
+            callers.insert(used_name.clone(), fn_name.clone());
+
+            if CRASHING_FUNCTIONS.contains(&used_name.as_str()) {
+                let mut path = vec![used_name];
+
+                while let Some(caller) = callers.get(path.last().unwrap()) {
+                    path.push(caller.clone());
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            if symbol_map.contains(&used_name) {
+                queue.push_back(used_name);
+            }
+        }
+    }
+
+    None
+}
+
+/// The functions that the instructions of `function` call or take a pointer to
+pub fn used_functions<'ctx>(
+    module: &Module<'ctx>,
+    function: FunctionValue<'ctx>,
+) -> Vec<FunctionValue<'ctx>> {
+    let mut used = Vec::new();
+
+    for block in function.get_basic_blocks() {
+        let mut instruction = block.get_first_instruction();
+
+        while let Some(current) = instruction {
+            for index in 0..current.get_num_operands() {
+                let Some(BasicValueEnum::PointerValue(pointer)) = current
+                    .get_operand(index)
+                    .and_then(|operand| operand.left())
+                else {
+                    continue;
+                };
+
+                // a local value can have the same name as a function
+                let name = pointer.get_name().to_string_lossy();
+                let used_function = module.get_function(&name).filter(|used_function| {
+                    used_function.as_global_value().as_pointer_value() == pointer
+                });
+
+                used.extend(used_function);
+            }
+
+            instruction = current.get_next_instruction();
+        }
+    }
+
+    used
+}
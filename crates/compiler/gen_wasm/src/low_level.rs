@@ -185,6 +185,9 @@ impl<'a> LowLevelCall<'a> {
         match self.lowlevel {
             // Str
             StrConcat => self.load_args_and_call_zig(backend, bitcode::STR_CONCAT),
+            StrAppendUnchecked => {
+                self.load_args_and_call_zig(backend, bitcode::STR_APPEND_UNCHECKED)
+            }
             StrGetUnsafe => self.load_args_and_call_zig(backend, bitcode::STR_GET_UNSAFE),
             StrJoinWith => self.load_args_and_call_zig(backend, bitcode::STR_JOIN_WITH),
             StrIsEmpty => match backend.storage.get(&self.arguments[0]) {
@@ -377,7 +380,7 @@ impl<'a> LowLevelCall<'a> {
                 backend.call_host_fn_after_loading_args(bitcode::LIST_DECREF);
             }
 
-            ListSortWith => {
+            ListSortWith | ListMapParallel => {
                 internal_error!("HigherOrder lowlevels should not be handled here")
             }
 
@@ -1765,68 +1768,36 @@ impl<'a> LowLevelCall<'a> {
                 }
                 _ => panic_ret_type(),
             },
-            NumRound => {
-                self.load_args(backend);
+            NumRound | NumCeiling | NumFloor => {
                 let arg_type = CodeGenNumType::for_symbol(backend, self.arguments[0]);
-                let ret_type = CodeGenNumType::from(self.ret_layout);
 
-                let width = match ret_type {
-                    CodeGenNumType::I32 => IntWidth::I32,
-                    CodeGenNumType::I64 => IntWidth::I64,
-                    CodeGenNumType::I128 => todo!("{:?} for I128", self.lowlevel),
-                    _ => internal_error!("Invalid return type for round: {:?}", ret_type),
+                // the Zig builtins saturate at the bounds of the integer type, like the LLVM backend
+                let width = match self.ret_layout_raw {
+                    LayoutRepr::Builtin(Builtin::Int(IntWidth::I128 | IntWidth::U128)) => {
+                        todo!("{:?} for I128", self.lowlevel)
+                    }
+                    LayoutRepr::Builtin(Builtin::Int(width)) => width,
+                    _ => panic_ret_type(),
                 };
 
-                match arg_type {
-                    F32 => self.load_args_and_call_zig(backend, &bitcode::NUM_ROUND_F32[width]),
-                    F64 => self.load_args_and_call_zig(backend, &bitcode::NUM_ROUND_F64[width]),
-                    Decimal => self.load_args_and_call_zig(backend, &bitcode::DEC_ROUND[width]),
-                    _ => internal_error!("Invalid argument type for round: {:?}", arg_type),
-                }
-            }
-            NumCeiling | NumFloor => {
-                self.load_args(backend);
-                let arg_type = CodeGenNumType::for_symbol(backend, self.arguments[0]);
-                let ret_type = CodeGenNumType::from(self.ret_layout);
-
-                let width = match ret_type {
-                    CodeGenNumType::I32 => IntWidth::I32,
-                    CodeGenNumType::I64 => IntWidth::I64,
-                    CodeGenNumType::I128 => todo!("{:?} for I128", self.lowlevel),
-                    _ => internal_error!("Invalid return type for round: {:?}", ret_type),
+                let name = match (arg_type, self.lowlevel) {
+                    (F32, NumRound) => &bitcode::NUM_ROUND_F32[width],
+                    (F64, NumRound) => &bitcode::NUM_ROUND_F64[width],
+                    (Decimal, NumRound) => &bitcode::DEC_ROUND[width],
+                    (F32, NumCeiling) => &bitcode::NUM_CEILING_F32[width],
+                    (F64, NumCeiling) => &bitcode::NUM_CEILING_F64[width],
+                    (Decimal, NumCeiling) => &bitcode::DEC_CEILING[width],
+                    (F32, NumFloor) => &bitcode::NUM_FLOOR_F32[width],
+                    (F64, NumFloor) => &bitcode::NUM_FLOOR_F64[width],
+                    (Decimal, NumFloor) => &bitcode::DEC_FLOOR[width],
+                    _ => internal_error!(
+                        "Invalid argument type for {:?}: {:?}",
+                        self.lowlevel,
+                        arg_type
+                    ),
                 };
 
-                match (arg_type, self.lowlevel) {
-                    (F32, NumCeiling) => {
-                        backend.code_builder.f32_ceil();
-                    }
-                    (F64, NumCeiling) => {
-                        backend.code_builder.f64_ceil();
-                    }
-                    (Decimal, NumCeiling) => {
-                        return self.load_args_and_call_zig(backend, &bitcode::DEC_CEILING[width]);
-                    }
-                    (F32, NumFloor) => {
-                        backend.code_builder.f32_floor();
-                    }
-                    (F64, NumFloor) => {
-                        backend.code_builder.f64_floor();
-                    }
-                    (Decimal, NumFloor) => {
-                        return self.load_args_and_call_zig(backend, &bitcode::DEC_FLOOR[width]);
-                    }
-                    _ => internal_error!("Invalid argument type for ceiling: {:?}", arg_type),
-                }
-
-                match (ret_type, arg_type) {
-                    // TODO: unsigned truncation
-                    (I32, F32) => backend.code_builder.i32_trunc_s_f32(),
-                    (I32, F64) => backend.code_builder.i32_trunc_s_f64(),
-                    (I64, F32) => backend.code_builder.i64_trunc_s_f32(),
-                    (I64, F64) => backend.code_builder.i64_trunc_s_f64(),
-                    (I128, _) => todo!("{:?} for I128", self.lowlevel),
-                    _ => panic_ret_type(),
-                }
+                self.load_args_and_call_zig(backend, name);
             }
             NumPowInt => {
                 self.load_args(backend);
@@ -2240,7 +2211,7 @@ impl<'a> LowLevelCall<'a> {
 
             Eq | NotEq => self.eq_or_neq(backend),
 
-            BoxExpr | UnboxExpr => {
+            BoxExpr | UnboxExpr | SizeOf | AlignOf => {
                 unreachable!("The {:?} operation is turned into mono Expr", self.lowlevel)
             }
 
@@ -2731,6 +2702,7 @@ pub fn call_higher_order_lowlevel<'a>(
             .unwrap();
         match op {
             ListSortWith { .. } => ProcSource::HigherOrderCompare(passed_proc_index),
+            ListMapParallel { .. } => ProcSource::HigherOrderMapper(passed_proc_index),
         }
     };
     let wrapper_sym = backend.create_symbol(&format!("#wrap#{fn_name:?}"));
@@ -2755,6 +2727,19 @@ pub fn call_higher_order_lowlevel<'a>(
         wrapper_arg_layouts.extend(boxed_closure_arg_layouts);
 
         match helper_proc_source {
+            ProcSource::HigherOrderMapper(_) => {
+                // Our convention is that the last arg of the wrapper is the heap return pointer
+                wrapper_arg_layouts.push(
+                    backend
+                        .layout_interner
+                        .insert_direct_no_semantic(LayoutRepr::Ptr(*result_layout)),
+                );
+                ProcLayout {
+                    arguments: wrapper_arg_layouts.into_bump_slice(),
+                    result: Layout::UNIT,
+                    niche: fn_name.niche(),
+                }
+            }
             ProcSource::HigherOrderCompare(_) => ProcLayout {
                 arguments: wrapper_arg_layouts.into_bump_slice(),
                 result: *result_layout,
@@ -2835,6 +2820,71 @@ pub fn call_higher_order_lowlevel<'a>(
 
             backend.call_host_fn_after_loading_args(bitcode::LIST_SORT_WITH);
         }
+        // There are no threads to hand the chunks to here, so map the list sequentially
+        ListMapParallel { xs } => {
+            let old_elem_in_layout = unwrap_list_elem_layout(
+                backend
+                    .layout_interner
+                    .get_repr(backend.storage.symbol_layouts[xs]),
+            );
+            let (old_elem_width, old_alignment) = backend
+                .layout_interner
+                .stack_size_and_alignment(old_elem_in_layout);
+            let old_elem_refcounted = backend
+                .layout_interner
+                .contains_refcounted(old_elem_in_layout);
+
+            let (new_elem_width, new_alignment) = backend
+                .layout_interner
+                .stack_size_and_alignment(*result_layout);
+            let new_elem_refcounted = backend.layout_interner.contains_refcounted(*result_layout);
+
+            let inc_fn_ptr =
+                build_refcount_element_fn(backend, old_elem_in_layout, HelperOp::IndirectInc);
+            let dec_fn_ptr =
+                build_refcount_element_fn(backend, old_elem_in_layout, HelperOp::IndirectDec);
+
+            let cb = &mut backend.code_builder;
+
+            // (return pointer)                 i32
+            // input: RocList,                  i32
+            // caller: Caller1,                 i32
+            // data: Opaque,                    i32
+            // inc_n_data: IncN,                i32
+            // data_is_owned: bool,             i32
+            // old_alignment: u32,              i32
+            // old_element_width: usize,        i32
+            // old_elements_refcounted: bool,   i32
+            // inc: Inc,                        i32
+            // dec: Dec,                        i32
+            // new_alignment: u32,              i32
+            // new_element_width: usize,        i32
+            // new_elements_refcounted: bool    i32
+
+            backend.storage.load_symbols(cb, &[return_sym, *xs]);
+            cb.i32_const(wrapper_fn_ptr);
+            if closure_data_exists {
+                backend
+                    .storage
+                    .load_symbols(cb, &[wrapped_captured_environment]);
+            } else {
+                // load_symbols assumes that a zero-size arg should be eliminated in code gen,
+                // but that's a specialization that our Zig code doesn't have! Pass a null pointer.
+                cb.i32_const(0);
+            }
+            cb.i32_const(inc_n_fn_ptr);
+            cb.i32_const(*owns_captured_environment as i32);
+            cb.i32_const(old_alignment as i32);
+            cb.i32_const(old_elem_width as i32);
+            cb.i32_const(old_elem_refcounted as i32);
+            cb.i32_const(inc_fn_ptr);
+            cb.i32_const(dec_fn_ptr);
+            cb.i32_const(new_alignment as i32);
+            cb.i32_const(new_elem_width as i32);
+            cb.i32_const(new_elem_refcounted as i32);
+
+            backend.call_host_fn_after_loading_args(bitcode::LIST_MAP);
+        }
     }
 }
 
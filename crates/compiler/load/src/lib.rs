@@ -24,6 +24,7 @@ pub use roc_load_internal::file::{
 pub use roc_load_internal::module::{
     CheckedModule, EntryPoint, Expectations, ExposedToHost, LoadedModule, MonomorphizedModule,
 };
+pub use roc_load_internal::mono_cache::MonoCache;
 pub use roc_solve::FunctionKind;
 
 #[allow(clippy::too_many_arguments)]
@@ -146,6 +147,35 @@ pub fn load_and_monomorphize<'a>(
     }
 }
 
+/// Like [load_and_monomorphize], but reuses the mono IR of an earlier load when none of the
+/// modules of the program changed since, and stores the mono IR for later loads otherwise.
+pub fn load_and_monomorphize_cached<'a>(
+    arena: &'a Bump,
+    filename: PathBuf,
+    opt_main_path: Option<PathBuf>,
+    roc_cache_dir: RocCacheDir<'_>,
+    load_config: LoadConfig,
+    mono_cache: &MonoCache,
+) -> Result<MonomorphizedModule<'a>, LoadMonomorphizedError<'a>> {
+    let opt_main = opt_main_path.as_deref();
+
+    if let Some(module) = mono_cache.load(arena, &filename, opt_main, &load_config) {
+        return Ok(module);
+    }
+
+    let module = load_and_monomorphize(
+        arena,
+        filename.clone(),
+        opt_main_path.clone(),
+        roc_cache_dir,
+        load_config.clone(),
+    )?;
+
+    mono_cache.store(&filename, opt_main_path.as_deref(), &load_config, &module);
+
+    Ok(module)
+}
+
 pub fn load_and_typecheck<'a>(
     arena: &'a Bump,
     filename: PathBuf,
@@ -106,7 +106,7 @@ macro_rules! log {
     ($($arg:tt)*) => (dbg_do!(ROC_PRINT_LOAD_LOG, println!($($arg)*)))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LoadConfig {
     pub target: Target,
     pub render: RenderTarget,
@@ -2849,6 +2849,7 @@ fn update<'a>(
                         arena,
                         &layout_interner,
                         &mut state.procedures,
+                        &state.host_exposed_lambda_sets,
                     );
 
                     debug_print_ir!(state, &layout_interner, ROC_PRINT_IR_AFTER_REFCOUNT);
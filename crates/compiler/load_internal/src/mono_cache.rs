@@ -0,0 +1,523 @@
+//! An on-disk cache of the mono IR of a program, so that building, running or testing code that
+//! did not change skips everything up to code gen.
+//!
+//! An entry is keyed by the root module, the load options that change the mono IR, and a
+//! fingerprint of the compiler binary. It stores the source of every module the program was made
+//! of, and is only used when all of those are still the same on disk. Programs with problems are
+//! never stored, because the reports of those problems would be lost.
+//!
+//! The mono IR is written field by field, see [roc_mono::serialize]. Subs are written in the
+//! in-memory format of [Subs::serialize], so an entry is only ever read back by the compiler
+//! binary that wrote it.
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use roc_can::expr::{DbgLookup, ExpectLookup};
+use roc_collections::small_string_interner::SmallStringInterner;
+use roc_collections::{MutMap, VecMap};
+use roc_module::ident::ModuleName;
+use roc_module::symbol::{IdentIds, IdentIdsByModule, Interns, ModuleId, ModuleIds, Symbol};
+use roc_mono::ir::LambdaSetId;
+use roc_mono::layout::STLayoutInterner;
+use roc_mono::serialize::{Reader, Serial, Writer};
+use roc_region::all::Region;
+use roc_types::subs::{Subs, Variable};
+
+use crate::file::LoadConfig;
+use crate::module::{
+    EntryPoint, Expectations, ExposedToHost, MonomorphizedModule, ToplevelExpects,
+};
+
+const EXTENSION: &str = "mono";
+
+pub struct MonoCache {
+    dir: PathBuf,
+    fingerprint: String,
+}
+
+impl MonoCache {
+    /// Returns `None` if the cache directory cannot be created; loading then proceeds
+    /// without caching.
+    pub fn new(dir: PathBuf) -> Option<Self> {
+        std::fs::create_dir_all(&dir).ok()?;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+
+        // Entries contain subs in their in-memory format, so they can only be read by the
+        // compiler binary that wrote them.
+        if let Ok(metadata) = std::env::current_exe().and_then(std::fs::metadata) {
+            hasher.update(&metadata.len().to_le_bytes());
+
+            if let Ok(modified) = metadata.modified() {
+                hasher.update(format!("{modified:?}").as_bytes());
+            }
+        }
+
+        Some(Self {
+            dir,
+            fingerprint: hasher.finalize().to_hex().to_string(),
+        })
+    }
+
+    fn path(&self, root: &Path, opt_main_path: Option<&Path>, load_config: &LoadConfig) -> PathBuf {
+        let LoadConfig {
+            target,
+            exec_mode,
+            function_kind,
+            ..
+        } = load_config;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.fingerprint.as_bytes());
+        hasher.update(format!("{target:?} {exec_mode:?} {function_kind:?}").as_bytes());
+
+        for path in std::iter::once(root).chain(opt_main_path) {
+            let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            hasher.update(path.as_os_str().as_encoded_bytes());
+            hasher.update(&[0]);
+        }
+
+        self.dir
+            .join(hasher.finalize().to_hex().as_str())
+            .with_extension(EXTENSION)
+    }
+
+    /// The mono IR of an earlier load with the same options, if none of its modules changed since
+    pub fn load<'a>(
+        &self,
+        arena: &'a Bump,
+        root: &Path,
+        opt_main_path: Option<&Path>,
+        load_config: &LoadConfig,
+    ) -> Option<MonomorphizedModule<'a>> {
+        let bytes = std::fs::read(self.path(root, opt_main_path, load_config)).ok()?;
+        let mut reader = Reader::new(arena, &bytes);
+
+        if reader.str() != self.fingerprint {
+            return None;
+        }
+
+        let sources = read_sources(&mut reader);
+
+        for (module_id, (path, source)) in sources.iter() {
+            if !module_id.is_builtin() && std::fs::read_to_string(path).ok()? != **source {
+                return None;
+            }
+        }
+
+        let module_id = reader.value();
+        let interns = read_interns(&mut reader);
+        let subs = read_subs(&mut reader);
+        let layout_interner = STLayoutInterner::read_from(&mut reader);
+
+        let procedures = (0..reader.length())
+            .map(|_| {
+                let symbol = reader.value();
+                let proc_layout = reader.proc_layout();
+
+                ((symbol, proc_layout), reader.proc())
+            })
+            .collect();
+
+        let host_exposed_lambda_sets = reader.host_exposed_lambda_sets();
+
+        let toplevel_expects = (0..reader.length())
+            .map(|_| {
+                let module_id = reader.value();
+                let pure = read_vec_map(&mut reader);
+                let fx = read_vec_map(&mut reader);
+
+                (module_id, ToplevelExpects { pure, fx })
+            })
+            .collect();
+
+        let entry_point = read_entry_point(&mut reader);
+        let exposed_to_host = read_exposed_to_host(&mut reader);
+        let expectations = read_expectations(&mut reader);
+        let needs_prebuilt_host = reader.value();
+        let glue_layouts = reader.glue_layouts();
+
+        debug_assert!(reader.is_at_end());
+
+        Some(MonomorphizedModule {
+            module_id,
+            interns,
+            subs,
+            layout_interner,
+            can_problems: MutMap::default(),
+            type_problems: MutMap::default(),
+            procedures,
+            host_exposed_lambda_sets,
+            toplevel_expects,
+            entry_point,
+            exposed_to_host,
+            sources,
+            timings: MutMap::default(),
+            expectations,
+            needs_prebuilt_host,
+            glue_layouts,
+        })
+    }
+
+    /// Stores the mono IR of a program, unless it has problems or reads files other than its
+    /// modules. Failing to store is not an error; the next load just misses.
+    pub fn store(
+        &self,
+        root: &Path,
+        opt_main_path: Option<&Path>,
+        load_config: &LoadConfig,
+        loaded: &MonomorphizedModule<'_>,
+    ) {
+        if has_problems(&loaded.can_problems) || has_problems(&loaded.type_problems) {
+            return;
+        }
+
+        // the contents of ingested files are not in the sources, so we could not tell
+        // when they change
+        if loaded
+            .sources
+            .values()
+            .any(|(_, source)| ingests_files(source))
+        {
+            return;
+        }
+
+        let mut writer = Writer::new();
+        writer.str(&self.fingerprint);
+
+        if write_sources(&mut writer, &loaded.sources).is_none() {
+            return;
+        }
+
+        writer.value(loaded.module_id);
+        write_interns(&mut writer, &loaded.interns);
+        write_subs(&mut writer, &loaded.subs);
+        loaded.layout_interner.write_to(&mut writer);
+
+        writer.length(loaded.procedures.len());
+        for ((symbol, proc_layout), proc) in loaded.procedures.iter() {
+            writer.value(*symbol);
+            writer.proc_layout(*proc_layout);
+            writer.proc(proc);
+        }
+
+        writer.host_exposed_lambda_sets(&loaded.host_exposed_lambda_sets);
+
+        writer.length(loaded.toplevel_expects.len());
+        for (module_id, ToplevelExpects { pure, fx }) in loaded.toplevel_expects.iter() {
+            writer.value(*module_id);
+            write_vec_map(&mut writer, pure);
+            write_vec_map(&mut writer, fx);
+        }
+
+        if write_entry_point(&mut writer, &loaded.entry_point).is_none() {
+            return;
+        }
+
+        write_exposed_to_host(&mut writer, &loaded.exposed_to_host);
+
+        if write_expectations(&mut writer, &loaded.expectations).is_none() {
+            return;
+        }
+
+        writer.value(loaded.needs_prebuilt_host);
+        writer.glue_layouts(&loaded.glue_layouts);
+
+        // write the entry in one go, so a concurrent load never sees half of it
+        let path = self.path(root, opt_main_path, load_config);
+        let _ = tempfile::NamedTempFile::new_in(&self.dir).and_then(|mut file| {
+            file.write_all(&writer.finish())?;
+            file.persist(path)?;
+
+            Ok(())
+        });
+    }
+}
+
+fn has_problems<P>(problems: &MutMap<ModuleId, Vec<P>>) -> bool {
+    problems.values().any(|problems| !problems.is_empty())
+}
+
+/// Whether a module has an `import "some/file" as ...`
+fn ingests_files(source: &str) -> bool {
+    source.lines().any(|line| {
+        line.trim_start()
+            .strip_prefix("import")
+            .is_some_and(|rest| rest.trim_start().starts_with('"'))
+    })
+}
+
+fn write_path(writer: &mut Writer, path: &Path) -> Option<()> {
+    writer.str(path.to_str()?);
+
+    Some(())
+}
+
+fn read_path(reader: &mut Reader<'_, '_>) -> PathBuf {
+    PathBuf::from(reader.str())
+}
+
+fn write_sources(
+    writer: &mut Writer,
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+) -> Option<()> {
+    writer.length(sources.len());
+    for (module_id, (path, source)) in sources.iter() {
+        writer.value(*module_id);
+        write_path(writer, path)?;
+        writer.str(source);
+    }
+
+    Some(())
+}
+
+fn read_sources(reader: &mut Reader<'_, '_>) -> MutMap<ModuleId, (PathBuf, Box<str>)> {
+    (0..reader.length())
+        .map(|_| {
+            let module_id = reader.value();
+            let path = read_path(reader);
+
+            (module_id, (path, reader.str().into()))
+        })
+        .collect()
+}
+
+fn write_ident_ids(writer: &mut Writer, ident_ids: &IdentIds) {
+    let (buffer, lengths, offsets) = ident_ids.interner.raw_parts();
+
+    writer.values(buffer);
+    writer.values(lengths);
+    writer.values(offsets);
+}
+
+fn read_ident_ids(reader: &mut Reader<'_, '_>) -> IdentIds {
+    let buffer = reader.value_vec();
+    let lengths = reader.value_vec();
+    let offsets = reader.value_vec();
+
+    // safety: the parts were written by `write_ident_ids`
+    let interner = unsafe { SmallStringInterner::from_raw_parts(buffer, lengths, offsets) };
+
+    IdentIds { interner }
+}
+
+fn write_interns(writer: &mut Writer, interns: &Interns) {
+    let module_ids = &interns.module_ids;
+
+    writer.length(module_ids.iter().len());
+    for (_, name) in module_ids.iter() {
+        writer.str(name.as_str());
+    }
+
+    writer.length(interns.all_ident_ids.len());
+    for module_id in interns.all_ident_ids.keys() {
+        writer.value(*module_id);
+        write_ident_ids(writer, interns.all_ident_ids.get(module_id).unwrap());
+    }
+}
+
+fn read_interns(reader: &mut Reader<'_, '_>) -> Interns {
+    let mut module_ids = ModuleIds::default();
+
+    // module ids are handed out in order, so this gives every module its old id back
+    for _ in 0..reader.length() {
+        module_ids.get_or_insert(&ModuleName::from(reader.str()));
+    }
+
+    let mut all_ident_ids = IdentIdsByModule::default();
+
+    for _ in 0..reader.length() {
+        let module_id = reader.value();
+        all_ident_ids.insert(module_id, read_ident_ids(reader));
+    }
+
+    Interns {
+        module_ids,
+        all_ident_ids,
+    }
+}
+
+fn write_subs(writer: &mut Writer, subs: &Subs) {
+    let mut bytes = Vec::new();
+
+    // writing to a Vec cannot fail
+    subs.serialize(&[], &mut bytes).unwrap();
+
+    writer.values(&bytes);
+}
+
+fn read_subs(reader: &mut Reader<'_, '_>) -> Subs {
+    let bytes: &[u8] = reader.values();
+
+    // Subs are read in place, so they need the alignment they were written with
+    let mut aligned = vec![0u64; bytes.len().div_ceil(8)];
+    let aligned_bytes =
+        unsafe { std::slice::from_raw_parts_mut(aligned.as_mut_ptr().cast::<u8>(), bytes.len()) };
+    aligned_bytes.copy_from_slice(bytes);
+
+    let ((subs, _), _) = Subs::deserialize(aligned_bytes);
+
+    subs
+}
+
+fn write_vec_map<'a, K: Serial<'a>, V: Serial<'a>>(writer: &mut Writer, map: &VecMap<K, V>) {
+    writer.length(map.len());
+    for (key, value) in map.iter() {
+        writer.value(*key);
+        writer.value(*value);
+    }
+}
+
+fn read_vec_map<'a, K: Serial<'a> + PartialEq, V: Serial<'a>>(
+    reader: &mut Reader<'a, '_>,
+) -> VecMap<K, V> {
+    let mut map = VecMap::default();
+
+    for _ in 0..reader.length() {
+        let key = reader.value();
+        map.insert(key, reader.value());
+    }
+
+    map
+}
+
+fn write_entry_point(writer: &mut Writer, entry_point: &EntryPoint<'_>) -> Option<()> {
+    match entry_point {
+        EntryPoint::Executable {
+            exposed_to_host,
+            platform_path,
+        } => {
+            writer.plain(0u8);
+
+            writer.length(exposed_to_host.len());
+            for (name, symbol, proc_layout) in exposed_to_host.iter() {
+                writer.str(name);
+                writer.value(*symbol);
+                writer.proc_layout(*proc_layout);
+            }
+
+            write_path(writer, platform_path)?;
+        }
+        EntryPoint::Test => writer.plain(1u8),
+    }
+
+    Some(())
+}
+
+fn read_entry_point<'a>(reader: &mut Reader<'a, '_>) -> EntryPoint<'a> {
+    match reader.plain::<u8>() {
+        0 => {
+            let arena = reader.arena();
+            let exposed_to_host = (0..reader.length()).map(|_| {
+                let name = reader.arena_str();
+                let symbol = reader.value();
+
+                (name, symbol, reader.proc_layout())
+            });
+            let exposed_to_host = arena.alloc_slice_fill_iter(exposed_to_host);
+
+            EntryPoint::Executable {
+                exposed_to_host,
+                platform_path: read_path(reader),
+            }
+        }
+        _ => EntryPoint::Test,
+    }
+}
+
+fn write_exposed_to_host(writer: &mut Writer, exposed_to_host: &ExposedToHost) {
+    let ExposedToHost {
+        top_level_values,
+        closure_types,
+        lambda_sets,
+        getters,
+    } = exposed_to_host;
+
+    let top_level_values: Vec<(Symbol, Variable)> =
+        top_level_values.iter().map(|(k, v)| (*k, *v)).collect();
+
+    writer.values(&top_level_values);
+    writer.values(closure_types);
+    writer.values(lambda_sets);
+    writer.values(getters);
+}
+
+fn read_exposed_to_host(reader: &mut Reader<'_, '_>) -> ExposedToHost {
+    let top_level_values: Vec<(Symbol, Variable)> = reader.value_vec();
+    let closure_types = reader.value_vec();
+    let lambda_sets: Vec<(Symbol, LambdaSetId)> = reader.value_vec();
+    let getters = reader.value_vec();
+
+    ExposedToHost {
+        top_level_values: top_level_values.into_iter().collect(),
+        closure_types,
+        lambda_sets,
+        getters,
+    }
+}
+
+fn write_expectations(
+    writer: &mut Writer,
+    expectations: &VecMap<ModuleId, Expectations>,
+) -> Option<()> {
+    writer.length(expectations.len());
+    for (module_id, module_expectations) in expectations.iter() {
+        let Expectations {
+            subs,
+            path,
+            expectations,
+            dbgs,
+            ident_ids,
+        } = module_expectations;
+
+        writer.value(*module_id);
+        write_subs(writer, subs);
+        write_path(writer, path)?;
+
+        writer.length(expectations.len());
+        for (region, lookups) in expectations.iter() {
+            writer.value(*region);
+            writer.values(lookups);
+        }
+
+        write_vec_map(writer, dbgs);
+        write_ident_ids(writer, ident_ids);
+    }
+
+    Some(())
+}
+
+fn read_expectations(reader: &mut Reader<'_, '_>) -> VecMap<ModuleId, Expectations> {
+    let mut all_expectations = VecMap::default();
+
+    for _ in 0..reader.length() {
+        let module_id: ModuleId = reader.value();
+        let subs = read_subs(reader);
+        let path = read_path(reader);
+
+        let mut expectations = VecMap::default();
+        for _ in 0..reader.length() {
+            let region: Region = reader.value();
+            let lookups: Vec<ExpectLookup> = reader.value_vec();
+            expectations.insert(region, lookups);
+        }
+
+        let dbgs: VecMap<Symbol, DbgLookup> = read_vec_map(reader);
+        let ident_ids = read_ident_ids(reader);
+
+        all_expectations.insert(
+            module_id,
+            Expectations {
+                subs,
+                path,
+                expectations,
+                dbgs,
+                ident_ids,
+            },
+        );
+    }
+
+    all_expectations
+}
@@ -21,7 +21,8 @@ use roc_load_internal::docs::DocDef;
 use roc_load_internal::file::{
     ExecutionMode, LoadConfig, LoadResult, LoadStart, LoadingProblem, Threading,
 };
-use roc_load_internal::module::LoadedModule;
+use roc_load_internal::module::{LoadedModule, MonomorphizedModule};
+use roc_load_internal::mono_cache::MonoCache;
 use roc_module::ident::ModuleName;
 use roc_module::symbol::{Interns, ModuleId};
 use roc_packaging::cache::RocCacheDir;
@@ -2135,3 +2136,92 @@ fn roc_file_no_extension() {
 
     assert_eq!(err, expected, "\n{}", err);
 }
+
+fn load_and_monomorphize<'a>(
+    arena: &'a Bump,
+    filename: PathBuf,
+    load_config: LoadConfig,
+) -> MonomorphizedModule<'a> {
+    use LoadResult::*;
+
+    let load_start = LoadStart::from_path(
+        arena,
+        filename,
+        None,
+        RenderTarget::Generic,
+        RocCacheDir::Disallowed,
+        DEFAULT_PALETTE,
+    )
+    .unwrap_or_else(|_| panic!("the module did not parse"));
+
+    match roc_load_internal::file::load(
+        arena,
+        load_start,
+        Default::default(),
+        Default::default(), // these tests will re-compile the builtins
+        RocCacheDir::Disallowed,
+        load_config,
+    ) {
+        Ok(Monomorphized(module)) => module,
+        _ => panic!("the module did not monomorphize"),
+    }
+}
+
+#[test]
+fn mono_cache_misses_after_source_change() {
+    let dir = TmpDir::new("tmp/mono_cache_misses_after_source_change");
+    let filename = dir.path().join("Cached.roc");
+    let cache = MonoCache::new(dir.path().join("cache")).unwrap();
+
+    let load_config = LoadConfig {
+        target: TARGET,
+        function_kind: FunctionKind::LambdaSet,
+        render: RenderTarget::Generic,
+        palette: DEFAULT_PALETTE,
+        threading: Threading::Single,
+        exec_mode: ExecutionMode::Test,
+    };
+
+    std::fs::write(
+        &filename,
+        indoc!(
+            r"
+            module [double]
+
+            double = \x -> x * 2
+
+            expect double 2 == 4
+            "
+        ),
+    )
+    .unwrap();
+
+    let arena = Bump::new();
+    let module = load_and_monomorphize(&arena, filename.clone(), load_config.clone());
+    cache.store(&filename, None, &load_config, &module);
+
+    let cached = cache
+        .load(&arena, &filename, None, &load_config)
+        .expect("the unchanged module did not hit the cache");
+
+    assert_eq!(cached.procedures.len(), module.procedures.len());
+    for (key, proc) in module.procedures.iter() {
+        assert_eq!(cached.procedures.get(key), Some(proc));
+    }
+
+    std::fs::write(
+        &filename,
+        indoc!(
+            r"
+            module [double]
+
+            double = \x -> x + x
+
+            expect double 2 == 4
+            "
+        ),
+    )
+    .unwrap();
+
+    assert!(cache.load(&arena, &filename, None, &load_config).is_none());
+}
@@ -4,6 +4,7 @@ use crate::symbol::Symbol;
 /// These are always wrapped when exposed to end users, and can only make it
 /// into an Expr when added directly by can::builtins
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
 pub enum LowLevel {
     StrConcat,
     StrJoinWith,
@@ -26,6 +27,7 @@ pub enum LowLevel {
     StrReserve,
     StrWithCapacity,
     StrReleaseExcessCapacity,
+    StrAppendUnchecked,
     ListLenUsize,
     ListLenU64,
     ListWithCapacity,
@@ -37,6 +39,7 @@ pub enum LowLevel {
     ListConcat,
     ListPrepend,
     ListSortWith,
+    ListMapParallel,
     ListSublist,
     ListDropAt,
     ListSwap,
@@ -124,20 +127,34 @@ pub enum LowLevel {
     RefCountIsUnique,
     BoxExpr,
     UnboxExpr,
+    SizeOf,
+    AlignOf,
     Unreachable,
     DictPseudoSeed,
     SetJmp,
     LongJmp,
+    // keep this last, `LowLevel::from_u8` relies on it
     SetLongJmpBuffer,
 }
 
 macro_rules! higher_order {
     () => {
-        ListSortWith
+        ListSortWith | ListMapParallel
     };
 }
 
 impl LowLevel {
+    /// The low-level whose discriminant is `raw`, if there is one
+    pub fn from_u8(raw: u8) -> Option<Self> {
+        if raw <= LowLevel::SetLongJmpBuffer as u8 {
+            // Safety: LowLevel is `repr(u8)` and its fieldless variants are numbered from zero up
+            // to `SetLongJmpBuffer`
+            Some(unsafe { std::mem::transmute::<u8, LowLevel>(raw) })
+        } else {
+            None
+        }
+    }
+
     /// is one of the arguments always a function?
     /// An example is List.map.
     pub fn is_higher_order(&self) -> bool {
@@ -151,6 +168,7 @@ impl LowLevel {
 
         match self {
             ListSortWith => 1,
+            ListMapParallel => 1,
             _ => unreachable!(),
         }
     }
@@ -206,11 +224,16 @@ macro_rules! map_symbol_to_lowlevel {
                 // these are higher-order lowlevels. these need the surrounding
                 // function to provide enough type information for code generation
                 LowLevel::ListSortWith => unreachable!(),
+                LowLevel::ListMapParallel => unreachable!(),
 
                 // (un)boxing is handled in a custom way
                 LowLevel::BoxExpr => unreachable!(),
                 LowLevel::UnboxExpr => unreachable!(),
 
+                // these become constants once the layout of their argument is known
+                LowLevel::SizeOf => unreachable!(),
+                LowLevel::AlignOf => unreachable!(),
+
                 // these functions return polymorphic values
                 LowLevel::NumIntCast => unreachable!(),
                 LowLevel::NumToFloatCast => unreachable!(),
@@ -267,6 +290,7 @@ map_symbol_to_lowlevel! {
     StrToNum <= STR_TO_NUM;
     StrWithCapacity <= STR_WITH_CAPACITY;
     StrReleaseExcessCapacity <= STR_RELEASE_EXCESS_CAPACITY;
+    StrAppendUnchecked <= STR_APPEND_UNCHECKED;
     ListLenU64 <= LIST_LEN_U64;
     ListLenUsize <= LIST_LEN_USIZE;
     ListGetCapacity <= LIST_CAPACITY;
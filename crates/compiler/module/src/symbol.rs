@@ -164,6 +164,20 @@ impl Symbol {
         u64::from_ne_bytes(self.to_ne_bytes())
     }
 
+    /// The symbol that [Symbol::as_u64] gave `raw`, or `None` if no symbol gives `raw`
+    pub const fn from_u64(raw: u64) -> Option<Symbol> {
+        let [a, b, c, d, e, f, g, h] = raw.to_ne_bytes();
+
+        // the same order as in `to_ne_bytes`
+        match NonZeroU32::new(u32::from_ne_bytes([e, f, g, h])) {
+            Some(module_id) => Some(Self {
+                ident_id: u32::from_ne_bytes([a, b, c, d]),
+                module_id,
+            }),
+            None => None,
+        }
+    }
+
     pub fn fully_qualified(self, interns: &Interns, home: ModuleId) -> ModuleName {
         let module_id = self.module_id();
 
@@ -385,6 +399,18 @@ impl ModuleId {
         (self.0.get() - 1) as usize
     }
 
+    pub const fn as_u32(self) -> u32 {
+        self.0.get()
+    }
+
+    /// The module id that [ModuleId::as_u32] gave `raw`, or `None` if no module id gives `raw`
+    pub const fn from_u32(raw: u32) -> Option<ModuleId> {
+        match NonZeroU32::new(raw) {
+            Some(id) => Some(ModuleId(id)),
+            None => None,
+        }
+    }
+
     #[cfg(any(debug_assertions, feature = "debug-symbols"))]
     pub fn register_debug_idents(self, ident_ids: &IdentIds) {
         let mut all = DEBUG_IDENT_IDS_BY_MODULE_ID.lock().expect("Failed to acquire lock for Debug interning into DEBUG_MODULE_ID_NAMES, presumably because a thread panicked.");
@@ -1349,6 +1375,11 @@ define_builtins! {
         166 NUM_NAN_F64: "nanF64"
         167 NUM_INFINITY_F32: "infinityF32"
         168 NUM_INFINITY_F64: "infinityF64"
+        169 NUM_RANDOM_STATE: "RandomState" // the Num.RandomState opaque type
+        170 NUM_SPLIT_MIX_64: "splitMix64"
+        171 NUM_RANDOM_SEED: "randomSeed"
+        172 NUM_RANDOM_U64: "randomU64"
+        173 NUM_RANDOM_F64: "randomF64"
     }
     4 BOOL: "Bool" => {
         0 BOOL_BOOL: "Bool" exposed_type=true // the Bool.Bool type alias
@@ -1417,6 +1448,7 @@ define_builtins! {
         48 STR_RELEASE_EXCESS_CAPACITY: "releaseExcessCapacity"
         49 STR_DROP_PREFIX: "dropPrefix"
         50 STR_DROP_SUFFIX: "dropSuffix"
+        51 STR_APPEND_UNCHECKED: "appendUnchecked"
     }
     6 LIST: "List" => {
         0 LIST_LIST: "List" exposed_apply_type=true // the List.List type alias
@@ -1511,6 +1543,7 @@ define_builtins! {
         89 LIST_CONCAT_UTF8: "concatUtf8"
         90 LIST_FOR_EACH_FX: "forEach!"
         91 LIST_FOR_EACH_TRY_FX: "forEachTry!"
+        92 LIST_MAP_PARALLEL: "mapParallel"
     }
     7 RESULT: "Result" => {
         0 RESULT_RESULT: "Result" exposed_type=true // the Result.Result type alias
@@ -1593,6 +1626,8 @@ define_builtins! {
         0 BOX_BOX_TYPE: "Box" exposed_apply_type=true // the Box.Box opaque type
         1 BOX_BOX_FUNCTION: "box" // Box.box
         2 BOX_UNBOX: "unbox"
+        3 BOX_SIZE_OF: "sizeOf"
+        4 BOX_ALIGN_OF: "alignOf"
     }
     11 ENCODE: "Encode" => {
         0 ENCODE_ENCODER: "Encoder" exposed_type=true
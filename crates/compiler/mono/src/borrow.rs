@@ -2,7 +2,7 @@ use bumpalo::{
     collections::{CollectIn, Vec},
     Bump,
 };
-use roc_collections::{MutMap, ReferenceMatrix};
+use roc_collections::{MutMap, MutSet, ReferenceMatrix};
 use roc_module::symbol::Symbol;
 
 use crate::{
@@ -33,6 +33,16 @@ impl BorrowSignature {
         Self(len as _)
     }
 
+    fn owned(len: usize) -> Self {
+        let mut signature = BorrowSignature::new(len);
+
+        for i in 0..len {
+            signature.set(i, Ownership::Owned);
+        }
+
+        signature
+    }
+
     fn from_layouts<'a>(
         interner: &impl LayoutInterner<'a>,
         layouts: impl ExactSizeIterator<Item = &'a InLayout<'a>>,
@@ -103,13 +113,18 @@ pub(crate) fn infer_borrow_signatures<'a>(
     arena: &'a Bump,
     interner: &impl LayoutInterner<'a>,
     procs: &MutMap<(Symbol, ProcLayout<'a>), Proc<'a>>,
+    host_exposed_procs: &MutSet<Symbol>,
 ) -> BorrowSignatures<'a> {
     let mut borrow_signatures: BorrowSignatures = BorrowSignatures {
         procs: procs
             .iter()
             .map(|(_key, proc)| {
                 let key = (proc.name.name(), proc.proc_layout(arena));
-                let signature = BorrowSignature::from_layouts(interner, key.1.arguments.iter());
+                let signature = if host_exposed_procs.contains(&key.0) {
+                    BorrowSignature::owned(key.1.arguments.len())
+                } else {
+                    BorrowSignature::from_layouts(interner, key.1.arguments.iter())
+                };
                 (key, signature)
             })
             .collect(),
@@ -146,7 +161,8 @@ pub(crate) fn infer_borrow_signatures<'a>(
                 let (_, proc) = procs.iter().nth(index).unwrap();
                 let key = (proc.name.name(), proc.proc_layout(arena));
 
-                if proc.args.is_empty() {
+                // The host always passes ownership of the arguments, so nothing can be borrowed
+                if proc.args.is_empty() || host_exposed_procs.contains(&key.0) {
                     continue;
                 }
 
@@ -157,6 +173,8 @@ pub(crate) fn infer_borrow_signatures<'a>(
                     borrow_signature: *borrow_signatures.procs.get(&key).unwrap(),
                     join_point_stack,
                     join_points: proc_join_points,
+                    captures: MutMap::default(),
+                    environments: MutMap::default(),
                     modified: false,
                 };
 
@@ -211,12 +229,17 @@ fn infer_borrow_signature<'a>(
 }
 
 struct State<'state, 'arena> {
-    /// Argument symbols with a layout of `List *` or `Str`, i.e. the layouts
-    /// for which borrow inference might decide to pass as borrowed
+    /// Argument symbols with a layout of `List *`, `Str` or a closure environment, i.e. the
+    /// layouts for which borrow inference might decide to pass as borrowed
     args: &'state [(InLayout<'arena>, Symbol)],
     borrow_signature: BorrowSignature,
     join_point_stack: Vec<'arena, (JoinPointId, &'state [Param<'arena>])>,
     join_points: MutMap<JoinPointId, BorrowSignature>,
+    /// Refcounted values loaded from an argument, e.g. the captures of a closure environment.
+    /// A closure that needs to own one of its captures must own its whole environment.
+    captures: MutMap<Symbol, Symbol>,
+    /// Structs that contain such captures, e.g. the environment of a nested closure.
+    environments: MutMap<Symbol, &'arena [Symbol]>,
     modified: bool,
 }
 
@@ -227,9 +250,12 @@ fn layout_to_ownership<'a>(
     match interner.get_repr(in_layout) {
         LayoutRepr::Builtin(Builtin::Str) => Ownership::Borrowed,
         LayoutRepr::Builtin(Builtin::List(_)) => Ownership::Borrowed,
-        LayoutRepr::LambdaSet(inner) => {
-            layout_to_ownership(inner.runtime_representation(), interner)
-        }
+        LayoutRepr::LambdaSet(inner) => match interner.get_repr(inner.runtime_representation()) {
+            // The captured environment of a closure is usually only read by the closure, so it
+            // can borrow the captures from whoever created the closure
+            LayoutRepr::Struct(_) => Ownership::Borrowed,
+            _ => layout_to_ownership(inner.runtime_representation(), interner),
+        },
         _ => Ownership::Owned,
     }
 }
@@ -254,6 +280,8 @@ impl<'state, 'a> State<'state, 'a> {
             borrow_signature: *borrow_signature,
             join_point_stack: Vec::new_in(arena),
             join_points: MutMap::default(),
+            captures: MutMap::default(),
+            environments: MutMap::default(),
             modified: false,
         }
     }
@@ -262,6 +290,16 @@ impl<'state, 'a> State<'state, 'a> {
     ///
     /// Currently argument symbols participate if `layout_to_ownership` returns `Borrowed` for their layout.
     fn mark_owned(&mut self, symbol: Symbol) {
+        if let Some(structure) = self.captures.get(&symbol) {
+            self.mark_owned(*structure);
+        }
+
+        if let Some(fields) = self.environments.get(&symbol) {
+            for field in fields.iter() {
+                self.mark_owned(*field);
+            }
+        }
+
         if let Some(index) = self.args.iter().position(|(_, s)| *s == symbol) {
             self.modified |= self.borrow_signature.set(index, Ownership::Owned);
         }
@@ -286,7 +324,26 @@ impl<'state, 'a> State<'state, 'a> {
         stmt: &Stmt<'a>,
     ) {
         match stmt {
-            Stmt::Let(_, expr, _, stmt) => {
+            Stmt::Let(binding, expr, layout, stmt) => {
+                match expr {
+                    Expr::StructAtIndex { structure, .. } => {
+                        if interner.contains_refcounted(*layout) {
+                            self.captures.insert(*binding, *structure);
+                        }
+                    }
+                    Expr::Struct(fields) => {
+                        let is_capture = |field: &Symbol| {
+                            self.captures.contains_key(field)
+                                || self.environments.contains_key(field)
+                        };
+
+                        if fields.iter().any(is_capture) {
+                            self.environments.insert(*binding, fields);
+                        }
+                    }
+                    _ => {}
+                }
+
                 self.inspect_expr(borrow_signatures, expr);
                 self.inspect_stmt(interner, borrow_signatures, stmt);
             }
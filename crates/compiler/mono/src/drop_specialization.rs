@@ -1556,7 +1556,7 @@ fn low_level_no_rc(lowlevel: &LowLevel) -> RC {
         ListReplaceUnsafe => RC::Rc,
         StrGetUnsafe | ListGetUnsafe => RC::NoRc,
         ListConcat => RC::Rc,
-        StrConcat => RC::Rc,
+        StrConcat | StrAppendUnchecked => RC::Rc,
         ListConcatUtf8 => RC::Rc,
         StrSubstringUnsafe => RC::Rc,
         StrReserve => RC::Rc,
@@ -1568,6 +1568,7 @@ fn low_level_no_rc(lowlevel: &LowLevel) -> RC {
         ListPrepend => RC::Rc,
         StrJoinWith => RC::NoRc,
         ListSortWith => RC::Rc,
+        ListMapParallel => RC::Rc,
 
         ListAppendUnsafe
         | ListReserve
@@ -1630,7 +1631,7 @@ fn low_level_no_rc(lowlevel: &LowLevel) -> RC {
         ListIsUnique => RC::Rc,
         ListClone => RC::Rc,
 
-        BoxExpr | UnboxExpr => {
+        BoxExpr | UnboxExpr | SizeOf | AlignOf => {
             unreachable!("These lowlevel operations are turned into mono Expr's")
         }
 
@@ -17,10 +17,10 @@ use roc_module::{low_level::LowLevelWrapperType, symbol::Symbol};
 use crate::ir::ErasedField;
 use crate::{
     ir::{
-        BranchInfo, Call, CallType, Expr, HigherOrderLowLevel, JoinPointId, ListLiteralElement,
-        ModifyRc, Param, Proc, ProcLayout, Stmt,
+        BranchInfo, Call, CallType, Expr, HigherOrderLowLevel, HostExposedLambdaSets, JoinPointId,
+        ListLiteralElement, ModifyRc, Param, PassedFunction, Proc, ProcLayout, Stmt,
     },
-    layout::{InLayout, LayoutInterner, Niche, STLayoutInterner},
+    layout::{InLayout, LayoutInterner, LayoutRepr, Niche, STLayoutInterner},
     low_level::HigherOrder,
 };
 
@@ -31,9 +31,20 @@ pub fn insert_inc_dec_operations<'a>(
     arena: &'a Bump,
     layout_interner: &STLayoutInterner<'a>,
     procedures: &mut HashMap<(Symbol, ProcLayout<'a>), Proc<'a>, BuildHasherDefault<WyHash>>,
+    host_exposed_lambda_sets: &HostExposedLambdaSets<'a>,
 ) {
-    let borrow_signatures =
-        crate::borrow::infer_borrow_signatures(arena, layout_interner, procedures);
+    // The host hands over its references when it calls one of these.
+    let host_exposed_procs = host_exposed_lambda_sets
+        .iter()
+        .map(|(_, _, hels)| hels.symbol)
+        .collect::<MutSet<_>>();
+
+    let borrow_signatures = crate::borrow::infer_borrow_signatures(
+        arena,
+        layout_interner,
+        procedures,
+        &host_exposed_procs,
+    );
     let borrow_signatures = arena.alloc(borrow_signatures);
 
     // All calls to lowlevels are wrapped in another function to help with type inference and return/parameter layouts.
@@ -247,6 +258,158 @@ Type containing data about the symbols consumption of a join point.
 */
 type JoinPointConsumption = MutSet<Symbol>;
 
+/**
+Bindings that never own a reference, because another value keeps their contents alive.
+*/
+#[derive(Default)]
+struct BorrowedBindings<'a> {
+    // Closure environments that are only passed to borrowed parameters, with their captures.
+    // Such an environment borrows its captures, so those must stay alive until its last use.
+    environments: MutMap<Symbol, &'a [Symbol]>,
+    // Captures loaded from a borrowed closure environment argument, which outlives them.
+    captures: MutSet<Symbol>,
+}
+
+impl<'a> BorrowedBindings<'a> {
+    /**
+    Find the borrowed bindings in a procedure, given the borrow signature of its arguments.
+    */
+    fn from_proc(
+        layout_interner: &STLayoutInterner<'a>,
+        borrow_signatures: &crate::borrow::BorrowSignatures<'a>,
+        proc: &Proc<'a>,
+        borrow_signature: &crate::borrow::BorrowSignature,
+    ) -> Self {
+        let borrowed_arguments = proc
+            .args
+            .iter()
+            .zip(borrow_signature.iter())
+            .filter_map(|((_, symbol), ownership)| ownership.is_borrowed().then_some(*symbol))
+            .collect::<MutSet<_>>();
+
+        let mut borrowed_bindings = BorrowedBindings::default();
+        // Symbols that are used in a position that needs an owned reference.
+        let mut owned_usages = MutSet::default();
+
+        let mut stack = vec![&proc.body];
+        while let Some(stmt) = stack.pop() {
+            match stmt {
+                Stmt::Let(binding, expr, layout, continuation) => {
+                    match expr {
+                        Expr::Struct(arguments) => {
+                            owned_usages.extend(arguments.iter().copied());
+
+                            if matches!(layout_interner.get_repr(*layout), LayoutRepr::LambdaSet(_))
+                            {
+                                borrowed_bindings.environments.insert(*binding, arguments);
+                            }
+                        }
+                        Expr::StructAtIndex { structure, .. } => {
+                            if borrowed_arguments.contains(structure) {
+                                borrowed_bindings.captures.insert(*binding);
+                            }
+                        }
+                        Expr::Call(Call {
+                            arguments,
+                            call_type,
+                        }) => match call_type.clone().replace_lowlevel_wrapper() {
+                            CallType::ByName {
+                                name,
+                                arg_layouts,
+                                ret_layout,
+                                ..
+                            } => {
+                                let proc_layout = ProcLayout {
+                                    arguments: arg_layouts,
+                                    result: ret_layout,
+                                    niche: Niche::NONE,
+                                };
+
+                                let borrow_signature = match borrow_signatures
+                                    .procs
+                                    .get(&(name.name(), proc_layout))
+                                {
+                                    Some(s) => s,
+                                    None => unreachable!("no borrow signature for {name:?} layout"),
+                                };
+
+                                owned_usages.extend(
+                                    arguments.iter().zip(borrow_signature.iter()).filter_map(
+                                        |(symbol, ownership)| {
+                                            ownership.is_owned().then_some(*symbol)
+                                        },
+                                    ),
+                                );
+                            }
+                            _ => owned_usages.extend(arguments.iter().copied()),
+                        },
+                        Expr::Tag { arguments, .. } => {
+                            owned_usages.extend(arguments.iter().copied());
+                        }
+                        Expr::Array { elems, .. } => {
+                            owned_usages.extend(elems.iter().filter_map(|element| match element {
+                                ListLiteralElement::Literal(_) => None,
+                                ListLiteralElement::Symbol(symbol) => Some(*symbol),
+                            }));
+                        }
+                        Expr::ErasedMake { value, .. } => owned_usages.extend(*value),
+                        Expr::ErasedLoad { symbol, .. } => {
+                            owned_usages.insert(*symbol);
+                        }
+                        Expr::Alloca { initializer, .. } => owned_usages.extend(*initializer),
+                        // These only borrow the structure they look into.
+                        Expr::GetTagId { .. }
+                        | Expr::UnionAtIndex { .. }
+                        | Expr::GetElementPointer { .. } => {}
+                        Expr::Literal(_)
+                        | Expr::NullPointer
+                        | Expr::FunctionPointer { .. }
+                        | Expr::EmptyArray
+                        | Expr::RuntimeErrorFunction(_)
+                        | Expr::Reset { .. }
+                        | Expr::ResetRef { .. } => {}
+                    }
+
+                    stack.push(continuation);
+                }
+                Stmt::Switch {
+                    branches,
+                    default_branch,
+                    ..
+                } => {
+                    stack.extend(branches.iter().map(|(_, _, branch)| branch));
+                    stack.push(default_branch.1);
+                }
+                Stmt::Ret(symbol) | Stmt::Crash(symbol, _) => {
+                    owned_usages.insert(*symbol);
+                }
+                Stmt::Refcounting(_, _) => unreachable!("refcounting should not be in the AST yet"),
+                // Expects and dbg only borrow the symbols they look at.
+                Stmt::Expect { remainder, .. }
+                | Stmt::ExpectFx { remainder, .. }
+                | Stmt::Dbg { remainder, .. } => stack.push(remainder),
+                Stmt::Join {
+                    body, remainder, ..
+                } => {
+                    stack.push(body);
+                    stack.push(remainder);
+                }
+                Stmt::Jump(_, arguments) => owned_usages.extend(arguments.iter().copied()),
+            }
+        }
+
+        borrowed_bindings
+            .environments
+            .retain(|symbol, _| !owned_usages.contains(symbol));
+
+        borrowed_bindings
+    }
+
+    fn contains(&self, symbol: &Symbol) -> bool {
+        self.environments.contains_key(symbol) || self.captures.contains(symbol)
+    }
+}
+
 /**
 The environment for the reference counting pass.
 Contains the symbols rc types and the ownership.
@@ -260,6 +423,8 @@ struct RefcountEnvironment<'v> {
     jointpoint_closures: MutMap<JoinPointId, JoinPointConsumption>,
     // inferred borrow signatures of roc functions
     borrow_signatures: &'v crate::borrow::BorrowSignatures<'v>,
+    // bindings in the current procedure that never own a reference
+    borrowed_bindings: &'v BorrowedBindings<'v>,
 }
 
 impl<'v> RefcountEnvironment<'v> {
@@ -387,10 +552,21 @@ impl<'v> RefcountEnvironment<'v> {
 
     /**
     Filter the given symbols to only contain reference counted symbols.
+    A borrowed closure environment also uses its captures, as it borrows them.
     */
     fn borrowed_usages(&self, symbols: impl IntoIterator<Item = Symbol>) -> MutSet<Symbol> {
         symbols
             .into_iter()
+            .flat_map(|symbol| {
+                let captures = self
+                    .borrowed_bindings
+                    .environments
+                    .get(&symbol)
+                    .copied()
+                    .unwrap_or_default();
+
+                std::iter::once(symbol).chain(captures.iter().copied())
+            })
             .filter(|symbol| {
                 // If the symbol is reference counted, we need to increment the usage count.
                 // If the symbol is not reference counted, we don't need to do anything.
@@ -418,18 +594,27 @@ fn insert_inc_dec_operations_proc<'a>(
     // As the symbols should be limited in scope for the current proc.
     symbol_rc_types_env.insert_symbols_rc_type_proc(proc);
 
+    let borrow_signature = borrow_signatures
+        .procs
+        .get(&(proc.name.name(), proc.proc_layout(arena)))
+        .unwrap();
+
+    let borrowed_bindings = BorrowedBindings::from_proc(
+        symbol_rc_types_env.layout_interner,
+        borrow_signatures,
+        proc,
+        borrow_signature,
+    );
+
     let mut environment = RefcountEnvironment {
         symbols_rc_types: &symbol_rc_types_env.symbols_rc_type,
         symbols_ownership: MutMap::default(),
         jointpoint_closures: MutMap::default(),
         borrow_signatures,
+        borrowed_bindings: &borrowed_bindings,
     };
 
     // Add all arguments to the environment (if they are reference counted)
-    let borrow_signature = borrow_signatures
-        .procs
-        .get(&(proc.name.name(), proc.proc_layout(arena)))
-        .unwrap();
     for ((_, symbol), ownership) in proc.args.iter().zip(borrow_signature.iter()) {
         environment.add_symbol_with(*symbol, ownership);
     }
@@ -491,7 +676,13 @@ fn insert_refcount_operations_stmt<'v, 'a>(
             );
 
             for (binding, _, _) in triples.iter() {
-                environment.add_symbol(**binding); // Add the bound symbol to the environment. As it can be used in the continuation.
+                // Add the bound symbol to the environment. As it can be used in the continuation.
+                if environment.borrowed_bindings.contains(binding) {
+                    // A borrowed binding is never consumed, and thus never dropped.
+                    environment.add_symbol_with(**binding, Ownership::Borrowed);
+                } else {
+                    environment.add_symbol(**binding);
+                }
             }
 
             triples
@@ -916,6 +1107,16 @@ fn insert_refcount_operations_binding<'a>(
             new_let!(stmt)
         }
 
+        // A borrowed closure environment borrows its captures, which stay alive until its last use.
+        Expr::Struct(_)
+            if environment
+                .borrowed_bindings
+                .environments
+                .contains_key(binding) =>
+        {
+            new_let!(stmt)
+        }
+
         Expr::Tag { arguments, .. } | Expr::Struct(arguments) => {
             let new_let = new_let!(stmt);
 
@@ -941,6 +1142,11 @@ fn insert_refcount_operations_binding<'a>(
             }
         }
 
+        // The closure environment is borrowed for the whole procedure, which keeps this capture alive.
+        Expr::StructAtIndex { .. } if environment.borrowed_bindings.captures.contains(binding) => {
+            new_let!(stmt)
+        }
+
         Expr::GetTagId { structure, .. }
         | Expr::StructAtIndex { structure, .. }
         | Expr::UnionAtIndex { structure, .. }
@@ -1087,32 +1293,60 @@ fn insert_refcount_operations_binding<'a>(
                         inc_owned!(owned_arguments, new_let)
                     }
                 },
-                CallType::HigherOrder(HigherOrderLowLevel {
-                    op: operator,
-
-                    closure_env_layout: _,
-
-                    // update mode of the higher order lowlevel itself
-                    update_mode: _,
-
-                    passed_function,
-                }) => {
-                    // Functions always take their arguments as owned.
-                    // (Except lowlevels, but those are wrapped in functions that take their arguments as owned and perform rc.)
+                CallType::HigherOrder(higher_order) => {
+                    let HigherOrderLowLevel {
+                        op: operator,
+                        closure_env_layout,
+                        passed_function,
+                        ..
+                    } = higher_order;
+
+                    // Functions take their arguments as owned, unless borrow inference decided
+                    // that the passed function can borrow its captured environment. In that case
+                    // the lowlevel doesn't have to hand every call its own reference.
+                    let owns_captured_environment = match closure_env_layout {
+                        None => true,
+                        Some(_) => {
+                            let proc_layout = ProcLayout {
+                                arguments: passed_function.argument_layouts,
+                                result: passed_function.return_layout,
+                                niche: Niche::NONE,
+                            };
+
+                            match environment
+                                .borrow_signatures
+                                .procs
+                                .get(&(passed_function.name.name(), proc_layout))
+                            {
+                                // The captured environment is the last argument.
+                                Some(s) => s.iter().last().map_or(true, |o| o.is_owned()),
+                                None => true,
+                            }
+                        }
+                    };
 
-                    // This should always be true, not sure where this could be set to false.
-                    debug_assert!(passed_function.owns_captured_environment);
+                    let new_expr = Expr::Call(Call {
+                        arguments: *arguments,
+                        call_type: CallType::HigherOrder(arena.alloc(HigherOrderLowLevel {
+                            passed_function: PassedFunction {
+                                owns_captured_environment,
+                                ..*passed_function
+                            },
+                            ..higher_order.clone()
+                        })),
+                    });
 
                     match operator {
-                        HigherOrder::ListSortWith { xs } => {
+                        HigherOrder::ListSortWith { xs } | HigherOrder::ListMapParallel { xs } => {
                             // TODO if non-unique, elements have been consumed, must still consume the list itself
                             if let [_xs_symbol, _function_symbol, closure_symbol] = &arguments {
                                 let new_stmt = dec_borrowed!([*closure_symbol], stmt);
-                                let new_let = new_let!(new_stmt);
+                                let new_let =
+                                    arena.alloc(Stmt::Let(*binding, new_expr, *layout, new_stmt));
 
                                 inc_owned!([*xs].into_iter(), new_let)
                             } else {
-                                panic!("ListSortWith should have 3 arguments");
+                                panic!("{operator:?} should have 3 arguments");
                             }
                         }
                     }
@@ -1267,7 +1501,7 @@ pub(crate) fn lowlevel_borrow_signature(op: LowLevel) -> &'static [Ownership] {
         ListReplaceUnsafe => &[OWNED, IRRELEVANT, IRRELEVANT],
         StrGetUnsafe | ListGetUnsafe => &[BORROWED, IRRELEVANT],
         ListConcat => &[OWNED, OWNED],
-        StrConcat => &[OWNED, BORROWED],
+        StrConcat | StrAppendUnchecked => &[OWNED, BORROWED],
         ListConcatUtf8 => &[OWNED, BORROWED],
         StrSubstringUnsafe => &[OWNED, IRRELEVANT, IRRELEVANT],
         StrReserve => &[OWNED, IRRELEVANT],
@@ -1279,6 +1513,7 @@ pub(crate) fn lowlevel_borrow_signature(op: LowLevel) -> &'static [Ownership] {
         ListPrepend => &[OWNED, OWNED],
         StrJoinWith => &[BORROWED, BORROWED],
         ListSortWith => &[OWNED, FUNCTION, CLOSURE_DATA],
+        ListMapParallel => &[OWNED, FUNCTION, CLOSURE_DATA],
         ListAppendUnsafe => &[OWNED, OWNED],
         ListReserve => &[OWNED, IRRELEVANT],
         ListSublist => &[OWNED, IRRELEVANT, IRRELEVANT],
@@ -1340,7 +1575,7 @@ pub(crate) fn lowlevel_borrow_signature(op: LowLevel) -> &'static [Ownership] {
         ListIsUnique => &[BORROWED],
         ListClone => &[OWNED],
 
-        BoxExpr | UnboxExpr => {
+        BoxExpr | UnboxExpr | SizeOf | AlignOf => {
             unreachable!("These lowlevel operations are turned into mono Expr's")
         }
 
@@ -916,6 +916,8 @@ pub struct Procs<'a> {
     pending_specializations: PendingSpecializations<'a>,
     specialized: Specialized<'a>,
     host_exposed_lambda_sets: HostExposedLambdaSets<'a>,
+    /// Continuations passed to hosted functions, see [expose_continuation_to_host]
+    host_exposed_continuations: MutMap<(ForeignSymbol, InLayout<'a>), LambdaSetId>,
     pub runtime_errors: BumpMap<Symbol, &'a str>,
     pub externals_we_need: BumpMap<ModuleId, ExternalSpecializations<'a>>,
     symbol_specializations: SymbolSpecializations<'a>,
@@ -936,6 +938,7 @@ impl<'a> Procs<'a> {
             runtime_errors: BumpMap::new_in(arena),
             externals_we_need: BumpMap::new_in(arena),
             host_exposed_lambda_sets: std::vec::Vec::new(),
+            host_exposed_continuations: MutMap::default(),
             symbol_specializations: Default::default(),
             specialization_stack: SpecializationStack(Vec::with_capacity_in(16, arena)),
 
@@ -1738,7 +1741,7 @@ impl<'a> Call<'a> {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct CallSpecId {
-    id: u32,
+    pub(crate) id: u32,
 }
 
 impl CallSpecId {
@@ -1753,7 +1756,7 @@ impl CallSpecId {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct UpdateModeId {
-    id: u32,
+    pub(crate) id: u32,
 }
 
 impl UpdateModeId {
@@ -3193,6 +3196,67 @@ fn specialize_host_specializations<'a>(
     }
 }
 
+/// A hosted function can take a function argument that the host calls once an effect has
+/// completed, e.g. `readFile! : Str, (Result Str Str => {}) => {}`. The host receives the
+/// captures of that continuation, and needs a caller for them, just like for the functions in the
+/// type of an entry point.
+///
+/// A hosted function gets a new specialization for every lambda set it is called with, so the
+/// host can receive continuations of several layouts. Each gets its own caller, numbered in the
+/// order they are found, e.g. `roc__fx_readFile_0_caller`; the returned id tells them apart.
+fn expose_continuation_to_host<'a>(
+    env: &mut Env<'a, '_>,
+    procs: &mut Procs<'a>,
+    layout_cache: &mut LayoutCache<'a>,
+    foreign_symbol: &ForeignSymbol,
+    raw_function_layout: RawFunctionLayout<'a>,
+    lambda_set_layout: InLayout<'a>,
+) -> LambdaSetId {
+    let key = (foreign_symbol.clone(), lambda_set_layout);
+
+    if let Some(id) = procs.host_exposed_continuations.get(&key) {
+        return *id;
+    }
+
+    let exposed = procs
+        .host_exposed_continuations
+        .keys()
+        .filter(|(symbol, _)| symbol == foreign_symbol)
+        .count();
+    let id = LambdaSetId(exposed as u32);
+
+    let symbol = env.unique_symbol();
+    let (key_symbol, (top_level, proc)) = generate_host_exposed_function(
+        env,
+        procs,
+        layout_cache,
+        LambdaName::no_niche(symbol),
+        raw_function_layout,
+    );
+
+    procs
+        .specialized
+        .insert_specialized(symbol, top_level, proc);
+
+    let hels = HostExposedLambdaSet {
+        id,
+        symbol,
+        proc_layout: top_level,
+        raw_function_layout,
+    };
+
+    // e.g. `roc_fx_readFile` exposes `roc__fx_readFile_0_caller`
+    let name = foreign_symbol.as_str().trim_start_matches("roc_");
+    let name = LambdaName::no_niche(env.named_unique_symbol(name));
+
+    procs
+        .host_exposed_lambda_sets
+        .push((name, key_symbol, hels));
+    procs.host_exposed_continuations.insert(key, id);
+
+    id
+}
+
 fn specialize_external_specializations<'a>(
     env: &mut Env<'a, '_>,
     procs: &mut Procs<'a>,
@@ -5748,15 +5812,57 @@ pub fn with_hole<'a>(
                 "ForeignCall"
             );
 
+            // A continuation is passed to the host in a box, so every lambda set has the same
+            // representation there, followed by the id of its caller. The host can only use the
+            // box during the call; to resume the continuation later, it copies the captures out
+            // and retains them.
+            let mut call_arguments = Vec::with_capacity_in(arg_symbols.len(), env.arena);
+            let mut continuations = Vec::new_in(env.arena);
+
+            for ((var, _), symbol) in args.iter().zip(arg_symbols.iter()) {
+                match layout_cache.raw_from_var(env.arena, *var, env.subs) {
+                    Ok(raw @ RawFunctionLayout::Function(_, lambda_set, _)) => {
+                        let id = expose_continuation_to_host(
+                            env,
+                            procs,
+                            layout_cache,
+                            &foreign_symbol,
+                            raw,
+                            lambda_set.full_layout,
+                        );
+
+                        let boxed = env.unique_symbol();
+                        let id_symbol = env.unique_symbol();
+
+                        call_arguments.push(boxed);
+                        call_arguments.push(id_symbol);
+                        continuations.push((*symbol, lambda_set.full_layout, boxed, id_symbol, id));
+                    }
+                    _ => call_arguments.push(*symbol),
+                }
+            }
+
             let call = self::Call {
                 call_type: CallType::Foreign {
                     foreign_symbol,
                     ret_layout: layout,
                 },
-                arguments: arg_symbols,
+                arguments: call_arguments.into_bump_slice(),
             };
 
-            let result = build_call(env, call, assigned, layout, hole);
+            let mut result = build_call(env, call, assigned, layout, hole);
+
+            for (symbol, closure_layout, boxed, id_symbol, id) in continuations.into_iter().rev() {
+                let id_literal = Expr::Literal(Literal::Int((id.0 as i128).to_ne_bytes()));
+                result = Stmt::Let(id_symbol, id_literal, Layout::U32, env.arena.alloc(result));
+
+                let closure_layout = &*env.arena.alloc(closure_layout);
+                let box_layout = layout_cache.put_in_direct_no_semantic(LayoutRepr::Union(
+                    UnionLayout::NonNullableUnwrapped(std::slice::from_ref(closure_layout)),
+                ));
+                let expr = boxed::box_(env.arena.alloc(symbol), closure_layout);
+                result = Stmt::Let(boxed, expr, box_layout, env.arena.alloc(result));
+            }
 
             let iter = args
                 .into_iter()
@@ -5854,6 +5960,11 @@ pub fn with_hole<'a>(
                     let xs = arg_symbols[0];
                     match_on_closure_argument!(ListSortWith, [xs])
                 }
+                ListMapParallel => {
+                    debug_assert_eq!(arg_symbols.len(), 2);
+                    let xs = arg_symbols[0];
+                    match_on_closure_argument!(ListMapParallel, [xs])
+                }
                 BoxExpr => {
                     debug_assert_eq!(arg_symbols.len(), 1);
                     let x = arg_symbols[0];
@@ -5875,6 +5986,25 @@ pub fn with_hole<'a>(
 
                     Stmt::Let(assigned, expr, layout, hole)
                 }
+                SizeOf | AlignOf => {
+                    debug_assert_eq!(args.len(), 1);
+
+                    // the argument is only there for its type, whose layout we know by now
+                    let arg_layout = return_on_layout_error!(
+                        env,
+                        layout_cache.from_var(env.arena, args[0].0, env.subs),
+                        "RunLowLevel"
+                    );
+
+                    let bytes = match op {
+                        SizeOf => layout_cache.interner.stack_size(arg_layout),
+                        _ => layout_cache.interner.alignment_bytes(arg_layout),
+                    };
+
+                    let expr = Expr::Literal(Literal::Int((bytes as i128).to_ne_bytes()));
+
+                    Stmt::Let(assigned, expr, layout, hole)
+                }
                 _ => {
                     let call = self::Call {
                         call_type: CallType::LowLevel {
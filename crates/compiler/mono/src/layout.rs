@@ -1,5 +1,6 @@
 use crate::ir::Parens;
 use crate::layout::intern::NeedsRecursionPointerFixup;
+use crate::serialize::{Reader, Writer};
 use bitvec::vec::BitVec;
 use bumpalo::collections::Vec;
 use bumpalo::Bump;
@@ -1337,6 +1338,15 @@ impl<'a> Niche<'a> {
         }
     }
 
+    pub(crate) fn write_to(self, writer: &mut Writer) {
+        let NichePriv::Captures(captures) = self.0;
+        writer.values(captures);
+    }
+
+    pub(crate) fn read_from(reader: &mut Reader<'a, '_>) -> Self {
+        Niche(NichePriv::Captures(reader.values()))
+    }
+
     pub fn dbg_deep<'r, I: LayoutInterner<'a>>(
         &'r self,
         interner: &'r I,
@@ -1397,6 +1407,18 @@ impl<'a> LambdaName<'a> {
     pub(crate) fn replace_name(&self, name: Symbol) -> Self {
         Self { name, ..*self }
     }
+
+    pub(crate) fn write_to(self, writer: &mut Writer) {
+        writer.value(self.name);
+        self.niche.write_to(writer);
+    }
+
+    pub(crate) fn read_from(reader: &mut Reader<'a, '_>) -> Self {
+        Self {
+            name: reader.value(),
+            niche: Niche::read_from(reader),
+        }
+    }
 }
 
 /// Closure data for a function
@@ -1724,7 +1746,10 @@ impl<'a> LambdaSet<'a> {
                 let enum_repr = match layout {
                     LayoutRepr::Builtin(Builtin::Bool) => EnumDispatch::Bool,
                     LayoutRepr::Builtin(Builtin::Int(IntWidth::U8)) => EnumDispatch::U8,
-                    other => internal_error!("Invalid layout for enum dispatch: {:?}", other),
+                    other => internal_error!(
+                        "Invalid layout for enum dispatch: {}",
+                        interner.dbg_repr(other)
+                    ),
                 };
                 ClosureRepresentation::EnumDispatch(enum_repr)
             }
@@ -1766,7 +1791,10 @@ impl<'a> LambdaSet<'a> {
                 let enum_repr = match layout {
                     LayoutRepr::Builtin(Builtin::Bool) => EnumDispatch::Bool,
                     LayoutRepr::Builtin(Builtin::Int(IntWidth::U8)) => EnumDispatch::U8,
-                    other => internal_error!("Invalid layout for enum dispatch: {:?}", other),
+                    other => internal_error!(
+                        "Invalid layout for enum dispatch: {}",
+                        interner.dbg_repr(other)
+                    ),
                 };
                 ClosureCallOptions::EnumDispatch(enum_repr)
             }
@@ -1838,7 +1866,10 @@ impl<'a> LambdaSet<'a> {
         match result.map(|l| env.cache.interner.chase_recursive(l)) {
             Ok(LayoutRepr::LambdaSet(lambda_set)) => Cacheable(Ok(lambda_set), criteria),
             Err(err) => Cacheable(Err(err), criteria),
-            Ok(layout) => internal_error!("other layout found for lambda set: {:?}", layout),
+            Ok(layout) => internal_error!(
+                "other layout found for lambda set: {}",
+                env.cache.interner.dbg_repr(layout)
+            ),
         }
     }
 
@@ -2430,6 +2461,33 @@ impl<'a> Layout<'a> {
         }
     }
 
+    pub(crate) fn write_to(self, writer: &mut Writer) {
+        match self.repr {
+            LayoutWrapper::Direct(repr) => {
+                writer.plain(0u8);
+                writer.layout_repr(repr);
+            }
+            LayoutWrapper::Newtype(real) => {
+                writer.plain(1u8);
+                writer.value(real);
+            }
+        }
+
+        self.semantic.write_to(writer);
+    }
+
+    pub(crate) fn read_from(reader: &mut Reader<'a, '_>) -> Self {
+        let repr = match reader.plain::<u8>() {
+            0 => LayoutWrapper::Direct(reader.layout_repr()),
+            _ => LayoutWrapper::Newtype(reader.value()),
+        };
+
+        Self {
+            repr,
+            semantic: SemanticRepr::read_from(reader),
+        }
+    }
+
     pub(crate) fn repr<I>(&self, interner: &I) -> LayoutRepr<'a>
     where
         I: LayoutInterner<'a>,
@@ -8,6 +8,7 @@ use roc_module::symbol::Symbol;
 use roc_target::Target;
 
 use crate::layout::LayoutRepr;
+use crate::serialize::{Reader, Writer};
 
 use super::{LambdaSet, Layout, LayoutWrapper, SeenRecPtrs, SemanticRepr, UnionLayout};
 
@@ -313,6 +314,35 @@ pub trait LayoutInterner<'a>: Sized {
         seen_rec: &mut SeenRecPtrs<'a>,
         parens: crate::ir::Parens,
     ) -> ven_pretty::DocBuilder<'b, D, A>
+    where
+        D: ven_pretty::DocAllocator<'b, A>,
+        D::Doc: Clone,
+        A: Clone,
+    {
+        match self.get_repr(layout) {
+            LayoutRepr::Union(union_layout) => {
+                let is_recursive = !matches!(union_layout, UnionLayout::NonRecursive(..));
+                if is_recursive {
+                    seen_rec.insert(layout);
+                }
+                let doc = union_layout.to_doc(alloc, self, seen_rec, parens);
+                if is_recursive {
+                    seen_rec.remove(&layout);
+                }
+                doc
+            }
+            repr => self.repr_to_doc(repr, alloc, seen_rec, parens),
+        }
+    }
+
+    /// Like [`to_doc`][LayoutInterner::to_doc], for a layout that was already looked up
+    fn repr_to_doc<'b, D, A>(
+        &self,
+        repr: LayoutRepr<'a>,
+        alloc: &'b D,
+        seen_rec: &mut SeenRecPtrs<'a>,
+        parens: crate::ir::Parens,
+    ) -> ven_pretty::DocBuilder<'b, D, A>
     where
         D: ven_pretty::DocAllocator<'b, A>,
         D::Doc: Clone,
@@ -320,7 +350,7 @@ pub trait LayoutInterner<'a>: Sized {
     {
         use LayoutRepr::*;
 
-        match self.get_repr(layout) {
+        match repr {
             Builtin(builtin) => builtin.to_doc(alloc, self, seen_rec, parens),
             Struct(field_layouts) => {
                 let fields_doc = field_layouts
@@ -332,17 +362,7 @@ pub trait LayoutInterner<'a>: Sized {
                     .append(alloc.intersperse(fields_doc, ", "))
                     .append(alloc.text("}"))
             }
-            Union(union_layout) => {
-                let is_recursive = !matches!(union_layout, UnionLayout::NonRecursive(..));
-                if is_recursive {
-                    seen_rec.insert(layout);
-                }
-                let doc = union_layout.to_doc(alloc, self, seen_rec, parens);
-                if is_recursive {
-                    seen_rec.remove(&layout);
-                }
-                doc
-            }
+            Union(union_layout) => union_layout.to_doc(alloc, self, seen_rec, parens),
             LambdaSet(lambda_set) => {
                 self.to_doc(lambda_set.runtime_representation(), alloc, seen_rec, parens)
             }
@@ -387,6 +407,19 @@ pub trait LayoutInterner<'a>: Sized {
         doc.1.pretty(80).to_string()
     }
 
+    /// Pretty-print a layout that was already looked up, e.g. the one that a panic message
+    /// complains about.
+    fn dbg_repr(&self, repr: LayoutRepr<'a>) -> String {
+        let alloc: ven_pretty::Arena<()> = ven_pretty::Arena::new();
+        let doc = self.repr_to_doc(
+            repr,
+            &alloc,
+            &mut Default::default(),
+            crate::ir::Parens::NotNeeded,
+        );
+        doc.1.pretty(80).to_string()
+    }
+
     /// Yields a debug representation of a layout, traversing its entire nested structure and
     /// debug-printing all intermediate interned layouts.
     ///
@@ -961,6 +994,56 @@ impl<'a> STLayoutInterner<'a> {
     pub fn is_empty(&self) -> bool {
         self.vec.is_empty()
     }
+
+    /// Writes every layout of the interner, so that [STLayoutInterner::read_from] can restore it
+    /// with every layout at the same index.
+    pub fn write_to(&self, writer: &mut Writer) {
+        writer.value(self.target);
+
+        writer.length(self.vec.len());
+        for layout in &self.vec {
+            layout.write_to(writer);
+        }
+
+        writer.length(self.map.len());
+        for (layout, interned) in &self.map {
+            layout.write_to(writer);
+            writer.value(*interned);
+        }
+
+        writer.length(self.normalized_lambda_set_map.len());
+        for (normalized, lambda_set) in &self.normalized_lambda_set_map {
+            writer.lambda_set(*normalized);
+            writer.lambda_set(*lambda_set);
+        }
+    }
+
+    pub fn read_from(reader: &mut Reader<'a, '_>) -> Self {
+        let target = reader.value();
+
+        let length = reader.length();
+        let vec = (0..length).map(|_| Layout::read_from(reader)).collect();
+
+        let length = reader.length();
+        let mut map = BumpMap::with_capacity_and_hasher(length, default_hasher());
+        for _ in 0..length {
+            map.insert(Layout::read_from(reader), reader.value());
+        }
+
+        let length = reader.length();
+        let mut normalized_lambda_set_map =
+            BumpMap::with_capacity_and_hasher(length, default_hasher());
+        for _ in 0..length {
+            normalized_lambda_set_map.insert(reader.lambda_set(), reader.lambda_set());
+        }
+
+        Self {
+            map,
+            normalized_lambda_set_map,
+            vec,
+            target,
+        }
+    }
 }
 
 macro_rules! st_impl {
@@ -2,6 +2,8 @@
 
 use roc_module::symbol::Symbol;
 
+use crate::serialize::{Reader, Writer};
+
 /// A semantic representation of a memory layout.
 /// Semantic representations describe the shape of a type a [Layout][super::Layout] is generated
 /// for. Semantic representations disambiguate types that have the same runtime memory layout, but
@@ -62,6 +64,38 @@ impl<'a> SemanticRepr<'a> {
     pub(super) fn lambdas(lambdas: &'a [Symbol]) -> Self {
         Self(Inner::Lambdas(SemaLambdas { lambdas }))
     }
+
+    pub(super) fn write_to(self, writer: &mut Writer) {
+        match self.0 {
+            Inner::None => writer.plain(0u8),
+            Inner::Record(SemaRecord { fields }) => {
+                writer.plain(1u8);
+                writer.strs(fields);
+            }
+            Inner::Tuple(SemaTuple { size }) => {
+                writer.plain(2u8);
+                writer.length(size);
+            }
+            Inner::TagUnion(SemaTagUnion { tags }) => {
+                writer.plain(3u8);
+                writer.strs(tags);
+            }
+            Inner::Lambdas(SemaLambdas { lambdas }) => {
+                writer.plain(4u8);
+                writer.values(lambdas);
+            }
+        }
+    }
+
+    pub(super) fn read_from(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => Self::NONE,
+            1 => Self::record(reader.arena_strs()),
+            2 => Self::tuple(reader.length()),
+            3 => Self::tag_union(reader.arena_strs()),
+            _ => Self::lambdas(reader.values()),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -17,6 +17,8 @@ pub mod ir;
 pub mod layout;
 pub mod low_level;
 pub mod reset_reuse;
+pub mod serialize;
+pub mod stack_size;
 pub mod tail_recursion;
 
 pub mod debug;
@@ -3,12 +3,14 @@ use roc_module::symbol::Symbol;
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum HigherOrder {
     ListSortWith { xs: Symbol },
+    ListMapParallel { xs: Symbol },
 }
 
 impl HigherOrder {
     pub fn function_arity(&self) -> usize {
         match self {
             HigherOrder::ListSortWith { .. } => 2,
+            HigherOrder::ListMapParallel { .. } => 1,
         }
     }
 
@@ -19,6 +21,7 @@ impl HigherOrder {
 
         match self {
             ListSortWith { .. } => 2,
+            ListMapParallel { .. } => 2,
         }
     }
 
@@ -0,0 +1,1622 @@
+//! Writes the mono IR of a program to bytes and reads it back, so that `roc_load` can cache the
+//! result of everything up to and including specialization on disk.
+//!
+//! Only integers are written as bytes, in little-endian order. Everything else, down to a single
+//! symbol or low-level op, implements [Serial] and writes its fields one at a time, so the bytes
+//! never depend on how the compiler lays out its own types. Everything that borrows from an arena
+//! is allocated in the arena again when it is read.
+//!
+//! Types whose representation is private to their module, like [crate::layout::Layout], write and
+//! read themselves with `write_to` and `read_from`.
+use bumpalo::collections::Vec;
+use bumpalo::Bump;
+use roc_builtins::bitcode::{FloatWidth, IntWidth};
+use roc_can::abilities::SpecializationId;
+use roc_can::expr::{DbgLookup, ExpectLookup};
+use roc_module::ident::ForeignSymbol;
+use roc_module::low_level::LowLevel;
+use roc_module::symbol::{ModuleId, Symbol};
+use roc_region::all::{Position, Region};
+use roc_target::Target;
+use roc_types::subs::Variable;
+
+use crate::ir::{
+    BranchInfo, Call, CallSpecId, CallType, CrashTag, ErasedField, Expr, GlueLayouts,
+    HigherOrderLowLevel, HostExposedLambdaSet, HostExposedLambdaSets, JoinPointId, LambdaSetId,
+    ListLiteralElement, Literal, ModifyRc, Param, PassedFunction, Proc, ProcLayout, ReuseToken,
+    SelfRecursive, Stmt, UpdateModeId,
+};
+use crate::layout::{
+    Builtin, Erased, FunctionPointer, InLayout, LambdaName, LambdaSet, LayoutRepr, Niche,
+    RawFunctionLayout, UnionLayout,
+};
+use crate::low_level::HigherOrder;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// The primitive integers, the only values that [Writer::plain] writes as bytes
+pub trait Int: Copy + private::Sealed {
+    const SIZE: usize;
+
+    fn extend(self, bytes: &mut std::vec::Vec<u8>);
+
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! int {
+    ($($ty:ty),*) => {$(
+        impl private::Sealed for $ty {}
+
+        impl Int for $ty {
+            const SIZE: usize = std::mem::size_of::<$ty>();
+
+            fn extend(self, bytes: &mut std::vec::Vec<u8>) {
+                bytes.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn from_bytes(bytes: &[u8]) -> Self {
+                Self::from_le_bytes(bytes.try_into().unwrap())
+            }
+        }
+
+        impl<'a> Serial<'a> for $ty {
+            fn write(self, writer: &mut Writer) {
+                writer.plain(self);
+            }
+
+            fn read(reader: &mut Reader<'a, '_>) -> Self {
+                reader.plain()
+            }
+        }
+    )*};
+}
+
+int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// A value that writes itself to a [Writer] one field at a time, and reads itself back from a
+/// [Reader].
+pub trait Serial<'a>: Copy {
+    fn write(self, writer: &mut Writer);
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self;
+}
+
+#[derive(Debug, Default)]
+pub struct Writer {
+    bytes: std::vec::Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn finish(self) -> std::vec::Vec<u8> {
+        self.bytes
+    }
+
+    pub fn plain<T: Int>(&mut self, value: T) {
+        value.extend(&mut self.bytes);
+    }
+
+    pub fn length(&mut self, length: usize) {
+        self.plain(length as u64);
+    }
+
+    pub fn value<'a, T: Serial<'a>>(&mut self, value: T) {
+        value.write(self);
+    }
+
+    pub fn values<'a, T: Serial<'a>>(&mut self, values: &[T]) {
+        self.length(values.len());
+
+        for value in values {
+            value.write(self);
+        }
+    }
+
+    pub fn str(&mut self, string: &str) {
+        self.length(string.len());
+        self.bytes.extend_from_slice(string.as_bytes());
+    }
+
+    pub fn strs(&mut self, strings: &[&str]) {
+        self.length(strings.len());
+
+        for string in strings {
+            self.str(string);
+        }
+    }
+
+    pub fn proc(&mut self, proc: &Proc<'_>) {
+        proc.name.write_to(self);
+        self.values(proc.args);
+        self.stmt(&proc.body);
+        self.value(proc.closure_data_layout);
+        self.value(proc.ret_layout);
+        self.value(proc.is_self_recursive);
+        self.value(proc.is_erased);
+    }
+
+    pub fn proc_layout(&mut self, proc_layout: ProcLayout<'_>) {
+        self.values(proc_layout.arguments);
+        self.value(proc_layout.result);
+        proc_layout.niche.write_to(self);
+    }
+
+    pub fn host_exposed_lambda_sets(&mut self, lambda_sets: &HostExposedLambdaSets<'_>) {
+        self.length(lambda_sets.len());
+
+        for (lambda_name, symbol, lambda_set) in lambda_sets {
+            lambda_name.write_to(self);
+            self.value(*symbol);
+
+            let HostExposedLambdaSet {
+                id,
+                symbol,
+                proc_layout,
+                raw_function_layout,
+            } = lambda_set;
+
+            self.value(*id);
+            self.value(*symbol);
+            self.proc_layout(*proc_layout);
+            self.raw_function_layout(*raw_function_layout);
+        }
+    }
+
+    pub fn glue_layouts(&mut self, glue_layouts: &GlueLayouts<'_>) {
+        self.length(glue_layouts.getters.len());
+
+        for (symbol, proc_layout) in &glue_layouts.getters {
+            self.value(*symbol);
+            self.proc_layout(*proc_layout);
+        }
+    }
+
+    fn raw_function_layout(&mut self, layout: RawFunctionLayout<'_>) {
+        match layout {
+            RawFunctionLayout::Function(arguments, lambda_set, result) => {
+                self.plain(0u8);
+                self.values(arguments);
+                self.lambda_set(lambda_set);
+                self.value(result);
+            }
+            RawFunctionLayout::ErasedFunction(arguments, result) => {
+                self.plain(1u8);
+                self.values(arguments);
+                self.value(result);
+            }
+            RawFunctionLayout::ZeroArgumentThunk(result) => {
+                self.plain(2u8);
+                self.value(result);
+            }
+        }
+    }
+
+    pub(crate) fn layout_repr(&mut self, repr: LayoutRepr<'_>) {
+        match repr {
+            LayoutRepr::Builtin(builtin) => {
+                self.plain(0u8);
+                self.value(builtin);
+            }
+            LayoutRepr::Struct(fields) => {
+                self.plain(1u8);
+                self.values(fields);
+            }
+            LayoutRepr::Ptr(pointee) => {
+                self.plain(2u8);
+                self.value(pointee);
+            }
+            LayoutRepr::Union(union_layout) => {
+                self.plain(3u8);
+                self.union_layout(union_layout);
+            }
+            LayoutRepr::LambdaSet(lambda_set) => {
+                self.plain(4u8);
+                self.lambda_set(lambda_set);
+            }
+            LayoutRepr::RecursivePointer(union_layout) => {
+                self.plain(5u8);
+                self.value(union_layout);
+            }
+            LayoutRepr::FunctionPointer(FunctionPointer { args, ret }) => {
+                self.plain(6u8);
+                self.values(args);
+                self.value(ret);
+            }
+            LayoutRepr::Erased(Erased) => {
+                self.plain(7u8);
+            }
+        }
+    }
+
+    fn union_layout(&mut self, union_layout: UnionLayout<'_>) {
+        match union_layout {
+            UnionLayout::NonRecursive(tags) => {
+                self.plain(0u8);
+                self.tags(tags);
+            }
+            UnionLayout::Recursive(tags) => {
+                self.plain(1u8);
+                self.tags(tags);
+            }
+            UnionLayout::NonNullableUnwrapped(fields) => {
+                self.plain(2u8);
+                self.values(fields);
+            }
+            UnionLayout::NullableWrapped {
+                nullable_id,
+                other_tags,
+            } => {
+                self.plain(3u8);
+                self.value(nullable_id);
+                self.tags(other_tags);
+            }
+            UnionLayout::NullableUnwrapped {
+                nullable_id,
+                other_fields,
+            } => {
+                self.plain(4u8);
+                self.value(nullable_id);
+                self.values(other_fields);
+            }
+        }
+    }
+
+    fn tags(&mut self, tags: &[&[InLayout<'_>]]) {
+        self.length(tags.len());
+
+        for fields in tags {
+            self.values(fields);
+        }
+    }
+
+    pub(crate) fn lambda_set(&mut self, lambda_set: LambdaSet<'_>) {
+        self.values(lambda_set.args);
+        self.value(lambda_set.ret);
+
+        self.length(lambda_set.set.len());
+        for (symbol, captures) in lambda_set.set.iter() {
+            self.value(*symbol);
+            self.values(captures);
+        }
+
+        self.value(lambda_set.representation);
+        self.value(lambda_set.full_layout);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'_>) {
+        match stmt {
+            Stmt::Let(symbol, expr, layout, continuation) => {
+                self.plain(0u8);
+                self.value(*symbol);
+                self.expr(expr);
+                self.value(*layout);
+                self.stmt(continuation);
+            }
+            Stmt::Switch {
+                cond_symbol,
+                cond_layout,
+                branches,
+                default_branch,
+                ret_layout,
+            } => {
+                self.plain(1u8);
+                self.value(*cond_symbol);
+                self.value(*cond_layout);
+
+                self.length(branches.len());
+                for (value, info, branch) in branches.iter() {
+                    self.value(*value);
+                    self.branch_info(info);
+                    self.stmt(branch);
+                }
+
+                self.branch_info(&default_branch.0);
+                self.stmt(default_branch.1);
+                self.value(*ret_layout);
+            }
+            Stmt::Ret(symbol) => {
+                self.plain(2u8);
+                self.value(*symbol);
+            }
+            Stmt::Refcounting(modify, continuation) => {
+                self.plain(3u8);
+                self.value(*modify);
+                self.stmt(continuation);
+            }
+            Stmt::Expect {
+                condition,
+                region,
+                lookups,
+                variables,
+                remainder,
+            } => {
+                self.plain(4u8);
+                self.value(*condition);
+                self.value(*region);
+                self.values(lookups);
+                self.values(variables);
+                self.stmt(remainder);
+            }
+            Stmt::ExpectFx {
+                condition,
+                region,
+                lookups,
+                variables,
+                remainder,
+            } => {
+                self.plain(5u8);
+                self.value(*condition);
+                self.value(*region);
+                self.values(lookups);
+                self.values(variables);
+                self.stmt(remainder);
+            }
+            Stmt::Dbg {
+                source_location,
+                source,
+                symbol,
+                variable,
+                remainder,
+            } => {
+                self.plain(6u8);
+                self.str(source_location);
+                self.str(source);
+                self.value(*symbol);
+                self.value(*variable);
+                self.stmt(remainder);
+            }
+            Stmt::Join {
+                id,
+                parameters,
+                body,
+                remainder,
+            } => {
+                self.plain(7u8);
+                self.value(*id);
+                self.values(parameters);
+                self.stmt(body);
+                self.stmt(remainder);
+            }
+            Stmt::Jump(id, arguments) => {
+                self.plain(8u8);
+                self.value(*id);
+                self.values(arguments);
+            }
+            Stmt::Crash(symbol, tag) => {
+                self.plain(9u8);
+                self.value(*symbol);
+                self.value(*tag);
+            }
+        }
+    }
+
+    fn branch_info(&mut self, info: &BranchInfo<'_>) {
+        match info {
+            BranchInfo::None => {
+                self.plain(0u8);
+            }
+            BranchInfo::Constructor {
+                scrutinee,
+                layout,
+                tag_id,
+            } => {
+                self.plain(1u8);
+                self.value(*scrutinee);
+                self.value(*layout);
+                self.value(*tag_id);
+            }
+            BranchInfo::List { scrutinee, len } => {
+                self.plain(2u8);
+                self.value(*scrutinee);
+                self.value(*len);
+            }
+            BranchInfo::Unique { scrutinee, unique } => {
+                self.plain(3u8);
+                self.value(*scrutinee);
+                self.value(*unique);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<'_>) {
+        match expr {
+            Expr::Literal(literal) => {
+                self.plain(0u8);
+                self.literal(literal);
+            }
+            Expr::Call(call) => {
+                self.plain(1u8);
+                self.call(call);
+            }
+            Expr::Tag {
+                tag_layout,
+                tag_id,
+                arguments,
+                reuse,
+            } => {
+                self.plain(2u8);
+                self.union_layout(*tag_layout);
+                self.value(*tag_id);
+                self.values(arguments);
+                self.value(*reuse);
+            }
+            Expr::Struct(fields) => {
+                self.plain(3u8);
+                self.values(fields);
+            }
+            Expr::NullPointer => {
+                self.plain(4u8);
+            }
+            Expr::StructAtIndex {
+                index,
+                field_layouts,
+                structure,
+            } => {
+                self.plain(5u8);
+                self.value(*index);
+                self.values(field_layouts);
+                self.value(*structure);
+            }
+            Expr::GetTagId {
+                structure,
+                union_layout,
+            } => {
+                self.plain(6u8);
+                self.value(*structure);
+                self.union_layout(*union_layout);
+            }
+            Expr::UnionAtIndex {
+                structure,
+                tag_id,
+                union_layout,
+                index,
+            } => {
+                self.plain(7u8);
+                self.value(*structure);
+                self.value(*tag_id);
+                self.union_layout(*union_layout);
+                self.value(*index);
+            }
+            Expr::GetElementPointer {
+                structure,
+                union_layout,
+                indices,
+            } => {
+                self.plain(8u8);
+                self.value(*structure);
+                self.union_layout(*union_layout);
+                self.values(indices);
+            }
+            Expr::Array { elem_layout, elems } => {
+                self.plain(9u8);
+                self.value(*elem_layout);
+
+                self.length(elems.len());
+                for elem in elems.iter() {
+                    match elem {
+                        ListLiteralElement::Literal(literal) => {
+                            self.plain(0u8);
+                            self.literal(literal);
+                        }
+                        ListLiteralElement::Symbol(symbol) => {
+                            self.plain(1u8);
+                            self.value(*symbol);
+                        }
+                    }
+                }
+            }
+            Expr::EmptyArray => {
+                self.plain(10u8);
+            }
+            Expr::ErasedMake { value, callee } => {
+                self.plain(11u8);
+                self.value(*value);
+                self.value(*callee);
+            }
+            Expr::ErasedLoad { symbol, field } => {
+                self.plain(12u8);
+                self.value(*symbol);
+                self.value(*field);
+            }
+            Expr::FunctionPointer { lambda_name } => {
+                self.plain(13u8);
+                lambda_name.write_to(self);
+            }
+            Expr::Alloca {
+                element_layout,
+                initializer,
+            } => {
+                self.plain(14u8);
+                self.value(*element_layout);
+                self.value(*initializer);
+            }
+            Expr::Reset {
+                symbol,
+                update_mode,
+            } => {
+                self.plain(15u8);
+                self.value(*symbol);
+                self.value(*update_mode);
+            }
+            Expr::ResetRef {
+                symbol,
+                update_mode,
+            } => {
+                self.plain(16u8);
+                self.value(*symbol);
+                self.value(*update_mode);
+            }
+            Expr::RuntimeErrorFunction(message) => {
+                self.plain(17u8);
+                self.str(message);
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal<'_>) {
+        match literal {
+            Literal::Int(bytes) => {
+                self.plain(0u8);
+                self.value(*bytes);
+            }
+            Literal::U128(bytes) => {
+                self.plain(1u8);
+                self.value(*bytes);
+            }
+            Literal::Float(float) => {
+                self.plain(2u8);
+                self.value(*float);
+            }
+            Literal::Decimal(bytes) => {
+                self.plain(3u8);
+                self.value(*bytes);
+            }
+            Literal::Str(string) => {
+                self.plain(4u8);
+                self.str(string);
+            }
+            Literal::Bool(bool) => {
+                self.plain(5u8);
+                self.value(*bool);
+            }
+            Literal::Byte(byte) => {
+                self.plain(6u8);
+                self.value(*byte);
+            }
+        }
+    }
+
+    fn call(&mut self, call: &Call<'_>) {
+        match &call.call_type {
+            CallType::ByName {
+                name,
+                ret_layout,
+                arg_layouts,
+                specialization_id,
+            } => {
+                self.plain(0u8);
+                name.write_to(self);
+                self.value(*ret_layout);
+                self.values(arg_layouts);
+                self.value(*specialization_id);
+            }
+            CallType::ByPointer {
+                pointer,
+                ret_layout,
+                arg_layouts,
+            } => {
+                self.plain(1u8);
+                self.value(*pointer);
+                self.value(*ret_layout);
+                self.values(arg_layouts);
+            }
+            CallType::Foreign {
+                foreign_symbol,
+                ret_layout,
+            } => {
+                self.plain(2u8);
+                self.str(foreign_symbol.as_str());
+                self.value(*ret_layout);
+            }
+            CallType::LowLevel { op, update_mode } => {
+                self.plain(3u8);
+                self.value(*op);
+                self.value(*update_mode);
+            }
+            CallType::HigherOrder(higher_order) => {
+                let HigherOrderLowLevel {
+                    op,
+                    closure_env_layout,
+                    update_mode,
+                    passed_function,
+                } = higher_order;
+
+                self.plain(4u8);
+                self.value(*op);
+                self.value(*closure_env_layout);
+                self.value(*update_mode);
+
+                passed_function.name.write_to(self);
+                self.values(passed_function.argument_layouts);
+                self.value(passed_function.return_layout);
+                self.value(passed_function.specialization_id);
+                self.value(passed_function.captured_environment);
+                self.value(passed_function.owns_captured_environment);
+            }
+        }
+
+        self.values(call.arguments);
+    }
+}
+
+pub struct Reader<'a, 'b> {
+    arena: &'a Bump,
+    bytes: &'b [u8],
+    offset: usize,
+}
+
+impl<'a, 'b> Reader<'a, 'b> {
+    pub fn new(arena: &'a Bump, bytes: &'b [u8]) -> Self {
+        Self {
+            arena,
+            bytes,
+            offset: 0,
+        }
+    }
+
+    pub fn arena(&self) -> &'a Bump {
+        self.arena
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+
+    fn take(&mut self, length: usize) -> &'b [u8] {
+        let bytes = &self.bytes[self.offset..][..length];
+        self.offset += length;
+
+        bytes
+    }
+
+    /// Read an integer that [Writer::plain] wrote
+    pub fn plain<T: Int>(&mut self) -> T {
+        T::from_bytes(self.take(T::SIZE))
+    }
+
+    pub fn length(&mut self) -> usize {
+        self.plain::<u64>() as usize
+    }
+
+    pub fn value<T: Serial<'a>>(&mut self) -> T {
+        T::read(self)
+    }
+
+    /// Read the values that [Writer::values] wrote into the arena
+    pub fn values<T: Serial<'a>>(&mut self) -> &'a [T] {
+        let arena = self.arena;
+        let length = self.length();
+
+        arena.alloc_slice_fill_with(length, |_| self.value())
+    }
+
+    pub fn value_vec<T: Serial<'a>>(&mut self) -> std::vec::Vec<T> {
+        let length = self.length();
+
+        (0..length).map(|_| self.value()).collect()
+    }
+
+    /// A string that only lives as long as the bytes
+    pub fn str(&mut self) -> &'b str {
+        let length = self.length();
+
+        std::str::from_utf8(self.take(length)).unwrap()
+    }
+
+    /// A string in the arena
+    pub fn arena_str(&mut self) -> &'a str {
+        let string = self.str();
+
+        self.arena.alloc_str(string)
+    }
+
+    pub fn arena_strs(&mut self) -> &'a [&'a str] {
+        let arena = self.arena;
+        let length = self.length();
+
+        arena.alloc_slice_fill_with(length, |_| self.arena_str())
+    }
+
+    pub fn proc(&mut self) -> Proc<'a> {
+        Proc {
+            name: LambdaName::read_from(self),
+            args: self.values(),
+            body: self.stmt(),
+            closure_data_layout: self.value(),
+            ret_layout: self.value(),
+            is_self_recursive: self.value(),
+            is_erased: self.value(),
+        }
+    }
+
+    pub fn proc_layout(&mut self) -> ProcLayout<'a> {
+        ProcLayout {
+            arguments: self.values(),
+            result: self.value(),
+            niche: Niche::read_from(self),
+        }
+    }
+
+    pub fn host_exposed_lambda_sets(&mut self) -> HostExposedLambdaSets<'a> {
+        let length = self.length();
+
+        (0..length)
+            .map(|_| {
+                let lambda_name = LambdaName::read_from(self);
+                let symbol = self.value();
+
+                let lambda_set = HostExposedLambdaSet {
+                    id: self.value(),
+                    symbol: self.value(),
+                    proc_layout: self.proc_layout(),
+                    raw_function_layout: self.raw_function_layout(),
+                };
+
+                (lambda_name, symbol, lambda_set)
+            })
+            .collect()
+    }
+
+    pub fn glue_layouts(&mut self) -> GlueLayouts<'a> {
+        let length = self.length();
+        let getters = (0..length)
+            .map(|_| (self.value(), self.proc_layout()))
+            .collect();
+
+        GlueLayouts { getters }
+    }
+
+    fn raw_function_layout(&mut self) -> RawFunctionLayout<'a> {
+        match self.plain::<u8>() {
+            0 => RawFunctionLayout::Function(self.values(), self.lambda_set(), self.value()),
+            1 => RawFunctionLayout::ErasedFunction(self.values(), self.value()),
+            _ => RawFunctionLayout::ZeroArgumentThunk(self.value()),
+        }
+    }
+
+    pub(crate) fn layout_repr(&mut self) -> LayoutRepr<'a> {
+        match self.plain::<u8>() {
+            0 => LayoutRepr::Builtin(self.value()),
+            1 => LayoutRepr::Struct(self.values()),
+            2 => LayoutRepr::Ptr(self.value()),
+            3 => LayoutRepr::Union(self.union_layout()),
+            4 => LayoutRepr::LambdaSet(self.lambda_set()),
+            5 => LayoutRepr::RecursivePointer(self.value()),
+            6 => LayoutRepr::FunctionPointer(FunctionPointer {
+                args: self.values(),
+                ret: self.value(),
+            }),
+            _ => LayoutRepr::Erased(Erased),
+        }
+    }
+
+    fn union_layout(&mut self) -> UnionLayout<'a> {
+        match self.plain::<u8>() {
+            0 => UnionLayout::NonRecursive(self.tags()),
+            1 => UnionLayout::Recursive(self.tags()),
+            2 => UnionLayout::NonNullableUnwrapped(self.values()),
+            3 => UnionLayout::NullableWrapped {
+                nullable_id: self.value(),
+                other_tags: self.tags(),
+            },
+            _ => UnionLayout::NullableUnwrapped {
+                nullable_id: self.value(),
+                other_fields: self.values(),
+            },
+        }
+    }
+
+    fn tags(&mut self) -> &'a [&'a [InLayout<'a>]] {
+        let arena = self.arena;
+        let length = self.length();
+
+        arena.alloc_slice_fill_with(length, |_| self.values())
+    }
+
+    pub(crate) fn lambda_set(&mut self) -> LambdaSet<'a> {
+        let arena = self.arena;
+
+        let args = arena.alloc(self.values());
+        let ret = self.value();
+
+        let length = self.length();
+        let set = arena.alloc_slice_fill_with(length, |_| (self.value(), self.values()));
+
+        LambdaSet {
+            args,
+            ret,
+            set: arena.alloc(&*set),
+            representation: self.value(),
+            full_layout: self.value(),
+        }
+    }
+
+    fn stmt(&mut self) -> Stmt<'a> {
+        let arena = self.arena;
+
+        match self.plain::<u8>() {
+            0 => Stmt::Let(
+                self.value(),
+                self.expr(),
+                self.value(),
+                arena.alloc(self.stmt()),
+            ),
+            1 => {
+                let cond_symbol = self.value();
+                let cond_layout = self.value();
+
+                let length = self.length();
+                let mut branches = Vec::with_capacity_in(length, arena);
+                for _ in 0..length {
+                    branches.push((self.value(), self.branch_info(), self.stmt()));
+                }
+
+                let default_branch = (self.branch_info(), &*arena.alloc(self.stmt()));
+
+                Stmt::Switch {
+                    cond_symbol,
+                    cond_layout,
+                    branches: branches.into_bump_slice(),
+                    default_branch,
+                    ret_layout: self.value(),
+                }
+            }
+            2 => Stmt::Ret(self.value()),
+            3 => Stmt::Refcounting(self.value(), arena.alloc(self.stmt())),
+            4 => Stmt::Expect {
+                condition: self.value(),
+                region: self.value(),
+                lookups: self.values(),
+                variables: self.values(),
+                remainder: arena.alloc(self.stmt()),
+            },
+            5 => Stmt::ExpectFx {
+                condition: self.value(),
+                region: self.value(),
+                lookups: self.values(),
+                variables: self.values(),
+                remainder: arena.alloc(self.stmt()),
+            },
+            6 => Stmt::Dbg {
+                source_location: self.arena_str(),
+                source: self.arena_str(),
+                symbol: self.value(),
+                variable: self.value(),
+                remainder: arena.alloc(self.stmt()),
+            },
+            7 => Stmt::Join {
+                id: self.value(),
+                parameters: self.values(),
+                body: arena.alloc(self.stmt()),
+                remainder: arena.alloc(self.stmt()),
+            },
+            8 => Stmt::Jump(self.value(), self.values()),
+            _ => Stmt::Crash(self.value(), self.value()),
+        }
+    }
+
+    fn branch_info(&mut self) -> BranchInfo<'a> {
+        match self.plain::<u8>() {
+            0 => BranchInfo::None,
+            1 => BranchInfo::Constructor {
+                scrutinee: self.value(),
+                layout: self.value(),
+                tag_id: self.value(),
+            },
+            2 => BranchInfo::List {
+                scrutinee: self.value(),
+                len: self.value(),
+            },
+            _ => BranchInfo::Unique {
+                scrutinee: self.value(),
+                unique: self.value(),
+            },
+        }
+    }
+
+    fn expr(&mut self) -> Expr<'a> {
+        let arena = self.arena;
+
+        match self.plain::<u8>() {
+            0 => Expr::Literal(self.literal()),
+            1 => Expr::Call(self.call()),
+            2 => Expr::Tag {
+                tag_layout: self.union_layout(),
+                tag_id: self.value(),
+                arguments: self.values(),
+                reuse: self.value(),
+            },
+            3 => Expr::Struct(self.values()),
+            4 => Expr::NullPointer,
+            5 => Expr::StructAtIndex {
+                index: self.value(),
+                field_layouts: self.values(),
+                structure: self.value(),
+            },
+            6 => Expr::GetTagId {
+                structure: self.value(),
+                union_layout: self.union_layout(),
+            },
+            7 => Expr::UnionAtIndex {
+                structure: self.value(),
+                tag_id: self.value(),
+                union_layout: self.union_layout(),
+                index: self.value(),
+            },
+            8 => Expr::GetElementPointer {
+                structure: self.value(),
+                union_layout: self.union_layout(),
+                indices: self.values(),
+            },
+            9 => {
+                let elem_layout = self.value();
+
+                let length = self.length();
+                let elems = arena.alloc_slice_fill_with(length, |_| match self.plain::<u8>() {
+                    0 => ListLiteralElement::Literal(self.literal()),
+                    _ => ListLiteralElement::Symbol(self.value()),
+                });
+
+                Expr::Array { elem_layout, elems }
+            }
+            10 => Expr::EmptyArray,
+            11 => Expr::ErasedMake {
+                value: self.value(),
+                callee: self.value(),
+            },
+            12 => Expr::ErasedLoad {
+                symbol: self.value(),
+                field: self.value(),
+            },
+            13 => Expr::FunctionPointer {
+                lambda_name: LambdaName::read_from(self),
+            },
+            14 => Expr::Alloca {
+                element_layout: self.value(),
+                initializer: self.value(),
+            },
+            15 => Expr::Reset {
+                symbol: self.value(),
+                update_mode: self.value(),
+            },
+            16 => Expr::ResetRef {
+                symbol: self.value(),
+                update_mode: self.value(),
+            },
+            _ => Expr::RuntimeErrorFunction(self.arena_str()),
+        }
+    }
+
+    fn literal(&mut self) -> Literal<'a> {
+        match self.plain::<u8>() {
+            0 => Literal::Int(self.value()),
+            1 => Literal::U128(self.value()),
+            2 => Literal::Float(self.value()),
+            3 => Literal::Decimal(self.value()),
+            4 => Literal::Str(self.arena_str()),
+            5 => Literal::Bool(self.value()),
+            _ => Literal::Byte(self.value()),
+        }
+    }
+
+    fn call(&mut self) -> Call<'a> {
+        let call_type = match self.plain::<u8>() {
+            0 => CallType::ByName {
+                name: LambdaName::read_from(self),
+                ret_layout: self.value(),
+                arg_layouts: self.values(),
+                specialization_id: self.value(),
+            },
+            1 => CallType::ByPointer {
+                pointer: self.value(),
+                ret_layout: self.value(),
+                arg_layouts: self.values(),
+            },
+            2 => CallType::Foreign {
+                foreign_symbol: ForeignSymbol::from(self.str()),
+                ret_layout: self.value(),
+            },
+            3 => CallType::LowLevel {
+                op: self.value(),
+                update_mode: self.value(),
+            },
+            _ => {
+                let higher_order = HigherOrderLowLevel {
+                    op: self.value(),
+                    closure_env_layout: self.value(),
+                    update_mode: self.value(),
+                    passed_function: PassedFunction {
+                        name: LambdaName::read_from(self),
+                        argument_layouts: self.values(),
+                        return_layout: self.value(),
+                        specialization_id: self.value(),
+                        captured_environment: self.value(),
+                        owns_captured_environment: self.value(),
+                    },
+                };
+
+                CallType::HigherOrder(self.arena.alloc(higher_order))
+            }
+        };
+
+        Call {
+            call_type,
+            arguments: self.values(),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for bool {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self as u8);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        reader.plain::<u8>() != 0
+    }
+}
+
+impl<'a> Serial<'a> for f64 {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.to_bits());
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        f64::from_bits(reader.plain())
+    }
+}
+
+impl<'a> Serial<'a> for [u8; 16] {
+    fn write(self, writer: &mut Writer) {
+        writer.bytes.extend_from_slice(&self);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        reader.take(16).try_into().unwrap()
+    }
+}
+
+impl<'a, T: Serial<'a>> Serial<'a> for Option<T> {
+    fn write(self, writer: &mut Writer) {
+        match self {
+            None => writer.plain(0u8),
+            Some(value) => {
+                writer.plain(1u8);
+                writer.value(value);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => None,
+            _ => Some(reader.value()),
+        }
+    }
+}
+
+impl<'a, A: Serial<'a>, B: Serial<'a>> Serial<'a> for (A, B) {
+    fn write(self, writer: &mut Writer) {
+        writer.value(self.0);
+        writer.value(self.1);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        (reader.value(), reader.value())
+    }
+}
+
+impl<'a> Serial<'a> for Symbol {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.as_u64());
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        Symbol::from_u64(reader.plain()).expect("a symbol with a module id of 0")
+    }
+}
+
+impl<'a> Serial<'a> for ModuleId {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.as_u32());
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        ModuleId::from_u32(reader.plain()).expect("a module id of 0")
+    }
+}
+
+impl<'a> Serial<'a> for Variable {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.index());
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        // Safety: the variable indexes the same subs it was written for
+        unsafe { Variable::from_index(reader.plain()) }
+    }
+}
+
+impl<'a> Serial<'a> for Region {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.start().offset);
+        writer.plain(self.end().offset);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        let start = Position::new(reader.plain());
+        let end = Position::new(reader.plain());
+
+        Region::new(start, end)
+    }
+}
+
+impl<'a> Serial<'a> for SpecializationId {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.to_u32());
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        SpecializationId::from_u32(reader.plain()).expect("a specialization id of 0")
+    }
+}
+
+impl<'a> Serial<'a> for ExpectLookup {
+    fn write(self, writer: &mut Writer) {
+        writer.value(self.symbol);
+        writer.value(self.var);
+        writer.value(self.ability_info);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        ExpectLookup {
+            symbol: reader.value(),
+            var: reader.value(),
+            ability_info: reader.value(),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for DbgLookup {
+    fn write(self, writer: &mut Writer) {
+        writer.value(self.symbol);
+        writer.value(self.var);
+        writer.value(self.region);
+        writer.value(self.ability_info);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        DbgLookup {
+            symbol: reader.value(),
+            var: reader.value(),
+            region: reader.value(),
+            ability_info: reader.value(),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for Target {
+    fn write(self, writer: &mut Writer) {
+        let tag: u8 = match self {
+            Target::LinuxX32 => 0,
+            Target::LinuxX64 => 1,
+            Target::LinuxArm64 => 2,
+            Target::LinuxRiscv64 => 3,
+            Target::MacX64 => 4,
+            Target::MacArm64 => 5,
+            Target::WinX32 => 6,
+            Target::WinX64 => 7,
+            Target::WinArm64 => 8,
+            Target::Wasm32 => 9,
+        };
+
+        writer.plain(tag);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => Target::LinuxX32,
+            1 => Target::LinuxX64,
+            2 => Target::LinuxArm64,
+            3 => Target::LinuxRiscv64,
+            4 => Target::MacX64,
+            5 => Target::MacArm64,
+            6 => Target::WinX32,
+            7 => Target::WinX64,
+            8 => Target::WinArm64,
+            _ => Target::Wasm32,
+        }
+    }
+}
+
+impl<'a> Serial<'a> for LowLevel {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self as u8);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        LowLevel::from_u8(reader.plain()).expect("an unknown low-level")
+    }
+}
+
+impl<'a> Serial<'a> for HigherOrder {
+    fn write(self, writer: &mut Writer) {
+        match self {
+            HigherOrder::ListSortWith { xs } => {
+                writer.plain(0u8);
+                writer.value(xs);
+            }
+            HigherOrder::ListMapParallel { xs } => {
+                writer.plain(1u8);
+                writer.value(xs);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => HigherOrder::ListSortWith { xs: reader.value() },
+            _ => HigherOrder::ListMapParallel { xs: reader.value() },
+        }
+    }
+}
+
+impl<'a> Serial<'a> for InLayout<'a> {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.index() as u64);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        // Safety: the layout indexes the interner that is read along with it
+        unsafe { InLayout::from_index(reader.plain::<u64>() as usize) }
+    }
+}
+
+impl<'a> Serial<'a> for IntWidth {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self as u8);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => IntWidth::U8,
+            1 => IntWidth::U16,
+            2 => IntWidth::U32,
+            3 => IntWidth::U64,
+            4 => IntWidth::U128,
+            5 => IntWidth::I8,
+            6 => IntWidth::I16,
+            7 => IntWidth::I32,
+            8 => IntWidth::I64,
+            _ => IntWidth::I128,
+        }
+    }
+}
+
+impl<'a> Serial<'a> for FloatWidth {
+    fn write(self, writer: &mut Writer) {
+        match self {
+            FloatWidth::F32 => writer.plain(0u8),
+            FloatWidth::F64 => writer.plain(1u8),
+        }
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => FloatWidth::F32,
+            _ => FloatWidth::F64,
+        }
+    }
+}
+
+impl<'a> Serial<'a> for Builtin<'a> {
+    fn write(self, writer: &mut Writer) {
+        match self {
+            Builtin::Int(width) => {
+                writer.plain(0u8);
+                writer.value(width);
+            }
+            Builtin::Float(width) => {
+                writer.plain(1u8);
+                writer.value(width);
+            }
+            Builtin::Bool => writer.plain(2u8),
+            Builtin::Decimal => writer.plain(3u8),
+            Builtin::Str => writer.plain(4u8),
+            Builtin::List(element) => {
+                writer.plain(5u8);
+                writer.value(element);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => Builtin::Int(reader.value()),
+            1 => Builtin::Float(reader.value()),
+            2 => Builtin::Bool,
+            3 => Builtin::Decimal,
+            4 => Builtin::Str,
+            _ => Builtin::List(reader.value()),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for JoinPointId {
+    fn write(self, writer: &mut Writer) {
+        writer.value(self.0);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        JoinPointId(reader.value())
+    }
+}
+
+impl<'a> Serial<'a> for Param<'a> {
+    fn write(self, writer: &mut Writer) {
+        writer.value(self.symbol);
+        writer.value(self.layout);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        Param {
+            symbol: reader.value(),
+            layout: reader.value(),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for SelfRecursive {
+    fn write(self, writer: &mut Writer) {
+        match self {
+            SelfRecursive::NotSelfRecursive => writer.plain(0u8),
+            SelfRecursive::SelfRecursive(id) => {
+                writer.plain(1u8);
+                writer.value(id);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => SelfRecursive::NotSelfRecursive,
+            _ => SelfRecursive::SelfRecursive(reader.value()),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for ModifyRc {
+    fn write(self, writer: &mut Writer) {
+        match self {
+            ModifyRc::Inc(symbol, amount) => {
+                writer.plain(0u8);
+                writer.value(symbol);
+                writer.plain(amount);
+            }
+            ModifyRc::Dec(symbol) => {
+                writer.plain(1u8);
+                writer.value(symbol);
+            }
+            ModifyRc::DecRef(symbol) => {
+                writer.plain(2u8);
+                writer.value(symbol);
+            }
+            ModifyRc::Free(symbol) => {
+                writer.plain(3u8);
+                writer.value(symbol);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => ModifyRc::Inc(reader.value(), reader.plain()),
+            1 => ModifyRc::Dec(reader.value()),
+            2 => ModifyRc::DecRef(reader.value()),
+            _ => ModifyRc::Free(reader.value()),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for CrashTag {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self as u32);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        CrashTag::try_from(reader.plain::<u32>()).expect("an unknown crash tag")
+    }
+}
+
+impl<'a> Serial<'a> for ErasedField {
+    fn write(self, writer: &mut Writer) {
+        match self {
+            ErasedField::Value => writer.plain(0u8),
+            ErasedField::ValuePtr => writer.plain(1u8),
+            ErasedField::Callee => writer.plain(2u8),
+        }
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        match reader.plain::<u8>() {
+            0 => ErasedField::Value,
+            1 => ErasedField::ValuePtr,
+            _ => ErasedField::Callee,
+        }
+    }
+}
+
+impl<'a> Serial<'a> for ReuseToken {
+    fn write(self, writer: &mut Writer) {
+        writer.value(self.symbol);
+        writer.value(self.update_tag_id);
+        writer.value(self.update_mode);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        ReuseToken {
+            symbol: reader.value(),
+            update_tag_id: reader.value(),
+            update_mode: reader.value(),
+        }
+    }
+}
+
+impl<'a> Serial<'a> for UpdateModeId {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.id);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        UpdateModeId { id: reader.plain() }
+    }
+}
+
+impl<'a> Serial<'a> for CallSpecId {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.id);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        CallSpecId { id: reader.plain() }
+    }
+}
+
+impl<'a> Serial<'a> for LambdaSetId {
+    fn write(self, writer: &mut Writer) {
+        writer.plain(self.0);
+    }
+
+    fn read(reader: &mut Reader<'a, '_>) -> Self {
+        LambdaSetId(reader.plain())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+    use roc_module::low_level::LowLevel;
+    use roc_module::symbol::Symbol;
+    use roc_target::Target;
+
+    use super::{Reader, Writer};
+    use crate::ir::{
+        BranchInfo, Call, CallType, CrashTag, Expr, Literal, ModifyRc, Proc, ReuseToken,
+        SelfRecursive, Stmt, UpdateModeId,
+    };
+    use crate::layout::{
+        Builtin, InLayout, LambdaName, Layout, LayoutInterner, LayoutRepr, STLayoutInterner,
+        UnionLayout,
+    };
+
+    #[test]
+    fn proc_and_interner_round_trip() {
+        let arena = Bump::new();
+        let mut interner = STLayoutInterner::with_capacity(4, Target::LinuxX64);
+
+        let list_of_f64 =
+            interner.insert_direct_no_semantic(LayoutRepr::Builtin(Builtin::List(Layout::F64)));
+        let tags: &[&[InLayout]] =
+            arena.alloc([&[Layout::I64] as &[_], arena.alloc([list_of_f64]) as &[_]]);
+        let union_layout = UnionLayout::NonRecursive(tags);
+        let union = interner.insert_direct_no_semantic(LayoutRepr::Union(union_layout));
+
+        let [x, y, tag] = [Symbol::ARG_1, Symbol::ARG_2, Symbol::ARG_3];
+
+        let switch = Stmt::Switch {
+            cond_symbol: x,
+            cond_layout: Layout::F64,
+            branches: arena.alloc([(1, BranchInfo::None, Stmt::Ret(tag))]),
+            default_branch: (
+                BranchInfo::Unique {
+                    scrutinee: tag,
+                    unique: true,
+                },
+                &*arena.alloc(Stmt::Crash(y, CrashTag::User)),
+            ),
+            ret_layout: union,
+        };
+
+        let body = Stmt::Let(
+            x,
+            Expr::Literal(Literal::Float(1.5)),
+            Layout::F64,
+            arena.alloc(Stmt::Let(
+                y,
+                Expr::Call(Call {
+                    call_type: CallType::LowLevel {
+                        op: LowLevel::NumAdd,
+                        update_mode: UpdateModeId::BACKEND_DUMMY,
+                    },
+                    arguments: arena.alloc([x, x]),
+                }),
+                Layout::F64,
+                arena.alloc(Stmt::Let(
+                    tag,
+                    Expr::Tag {
+                        tag_layout: union_layout,
+                        tag_id: 1,
+                        arguments: arena.alloc([y]),
+                        reuse: Some(ReuseToken {
+                            symbol: x,
+                            update_tag_id: true,
+                            update_mode: UpdateModeId::BACKEND_DUMMY,
+                        }),
+                    },
+                    union,
+                    arena.alloc(Stmt::Refcounting(
+                        ModifyRc::Inc(tag, 2),
+                        arena.alloc(switch),
+                    )),
+                )),
+            )),
+        );
+
+        let proc = Proc {
+            name: LambdaName::no_niche(Symbol::ATTR_ATTR),
+            args: arena.alloc([(Layout::F64, x)]),
+            body,
+            closure_data_layout: None,
+            ret_layout: union,
+            is_self_recursive: SelfRecursive::NotSelfRecursive,
+            is_erased: false,
+        };
+
+        let mut writer = Writer::new();
+        interner.write_to(&mut writer);
+        writer.proc(&proc);
+        let bytes = writer.finish();
+
+        let read_arena = Bump::new();
+        let mut reader = Reader::new(&read_arena, &bytes);
+        let mut read_interner = STLayoutInterner::read_from(&mut reader);
+        let read_proc = reader.proc();
+        assert!(reader.is_at_end());
+
+        assert_eq!(read_proc, proc);
+
+        assert_eq!(read_interner.target(), interner.target());
+        for layout in [Layout::F64, list_of_f64, union] {
+            assert_eq!(read_interner.get(layout), interner.get(layout));
+        }
+
+        // the map of the interner is read too, so interning a layout again finds it
+        let union_again = read_interner.insert_direct_no_semantic(LayoutRepr::Union(union_layout));
+        assert_eq!(union_again, union);
+    }
+}
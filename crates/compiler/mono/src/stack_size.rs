@@ -0,0 +1,215 @@
+//! Find values that are too large to live on the stack.
+//!
+//! Values are passed by value and bound on the stack no matter how large their layout is, so a
+//! 64 KB record makes every procedure that touches it copy 64 KB around, and a few of those in a
+//! recursive procedure overflow the stack. Nothing goes wrong at compile time, so we look for such
+//! values once all procedures are specialized, and warn about them.
+use crate::ir::{Proc, ProcLayout, Stmt};
+use crate::layout::{InLayout, LayoutInterner, STLayoutInterner};
+use roc_collections::MutMap;
+use roc_module::symbol::Symbol;
+
+/// Warn about values larger than this many bytes, unless the user picks another limit
+pub const DEFAULT_STACK_VALUE_LIMIT: u32 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackValuePosition {
+    /// The argument with this (zero-based) index
+    Argument(usize),
+    Return,
+    /// A value bound in the body of the procedure
+    Local(Symbol),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LargeStackValue<'a> {
+    pub proc: Symbol,
+    pub position: StackValuePosition,
+    pub layout: InLayout<'a>,
+    pub size: u32,
+}
+
+/// The values of `procedures` whose layout is larger than `limit` bytes, largest first
+pub fn find_large_stack_values<'a>(
+    interner: &STLayoutInterner<'a>,
+    procedures: &MutMap<(Symbol, ProcLayout<'a>), Proc<'a>>,
+    limit: u32,
+) -> Vec<LargeStackValue<'a>> {
+    let mut large_values = Vec::new();
+
+    for proc in procedures.values() {
+        let proc_name = proc.name.name();
+        let mut check = |position, layout| {
+            let size = interner.stack_size(layout);
+            let reported =
+                |value: &LargeStackValue| value.proc == proc_name && value.layout == layout;
+
+            // one warning per layout and procedure is enough to find the culprit
+            if size > limit && !large_values.iter().any(reported) {
+                large_values.push(LargeStackValue {
+                    proc: proc_name,
+                    position,
+                    layout,
+                    size,
+                });
+            }
+        };
+
+        for (index, (layout, _)) in proc.args.iter().enumerate() {
+            check(StackValuePosition::Argument(index), *layout);
+        }
+
+        check(StackValuePosition::Return, proc.ret_layout);
+
+        let mut stack = vec![&proc.body];
+
+        while let Some(stmt) = stack.pop() {
+            match stmt {
+                Stmt::Let(symbol, _, layout, cont) => {
+                    // a large return value was reported already
+                    if !matches!(cont, Stmt::Ret(returned) if returned == symbol) {
+                        check(StackValuePosition::Local(*symbol), *layout);
+                    }
+
+                    stack.push(cont);
+                }
+                Stmt::Switch {
+                    branches,
+                    default_branch,
+                    ..
+                } => {
+                    stack.extend(branches.iter().map(|(_, _, branch)| branch));
+                    stack.push(default_branch.1);
+                }
+                Stmt::Join {
+                    parameters,
+                    body,
+                    remainder,
+                    ..
+                } => {
+                    for param in parameters.iter() {
+                        check(StackValuePosition::Local(param.symbol), param.layout);
+                    }
+
+                    stack.push(body);
+                    stack.push(remainder);
+                }
+                Stmt::Refcounting(_, cont) => stack.push(cont),
+                Stmt::Expect { remainder, .. }
+                | Stmt::ExpectFx { remainder, .. }
+                | Stmt::Dbg { remainder, .. } => stack.push(remainder),
+                Stmt::Ret(_) | Stmt::Jump(..) | Stmt::Crash(..) => {}
+            }
+        }
+    }
+
+    large_values.sort_by_key(|value| std::cmp::Reverse(value.size));
+
+    large_values
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+    use roc_collections::MutMap;
+    use roc_module::symbol::Symbol;
+    use roc_target::Target;
+
+    use super::{find_large_stack_values, StackValuePosition, DEFAULT_STACK_VALUE_LIMIT};
+    use crate::ir::{Proc, ProcLayout, SelfRecursive, Stmt};
+    use crate::layout::{
+        InLayout, LambdaName, Layout, LayoutInterner, LayoutRepr, Niche, STLayoutInterner,
+    };
+
+    /// A record of `size` bytes
+    fn bytes<'a>(arena: &'a Bump, interner: &mut STLayoutInterner<'a>, size: u32) -> InLayout<'a> {
+        let fields = arena.alloc_slice_fill_copy(size as usize, Layout::U8);
+        interner.insert_direct_no_semantic(LayoutRepr::Struct(fields))
+    }
+
+    /// The identity function on `layout`
+    fn identity<'a>(
+        arena: &'a Bump,
+        procedures: &mut MutMap<(Symbol, ProcLayout<'a>), Proc<'a>>,
+        name: Symbol,
+        layout: InLayout<'a>,
+    ) {
+        let proc_layout = ProcLayout {
+            arguments: arena.alloc([layout]),
+            result: layout,
+            niche: Niche::NONE,
+        };
+
+        let proc = Proc {
+            name: LambdaName::no_niche(name),
+            args: arena.alloc([(layout, Symbol::ARG_1)]),
+            body: Stmt::Ret(Symbol::ARG_1),
+            closure_data_layout: None,
+            ret_layout: layout,
+            is_self_recursive: SelfRecursive::NotSelfRecursive,
+            is_erased: false,
+        };
+
+        procedures.insert((name, proc_layout), proc);
+    }
+
+    #[test]
+    fn values_larger_than_the_limit() {
+        let arena = Bump::new();
+        let mut interner = STLayoutInterner::with_capacity(4, Target::LinuxX64);
+        let mut procedures = MutMap::default();
+
+        let at_limit = bytes(&arena, &mut interner, DEFAULT_STACK_VALUE_LIMIT);
+        let above_limit = bytes(&arena, &mut interner, DEFAULT_STACK_VALUE_LIMIT + 1);
+
+        identity(&arena, &mut procedures, Symbol::LIST_MAP, at_limit);
+        identity(&arena, &mut procedures, Symbol::LIST_MAP2, above_limit);
+
+        let large_values =
+            find_large_stack_values(&interner, &procedures, DEFAULT_STACK_VALUE_LIMIT);
+
+        // the return value has the same layout as the argument, so it is not reported again
+        assert_eq!(large_values.len(), 1);
+        assert_eq!(large_values[0].proc, Symbol::LIST_MAP2);
+        assert_eq!(large_values[0].position, StackValuePosition::Argument(0));
+        assert_eq!(large_values[0].layout, above_limit);
+        assert_eq!(large_values[0].size, DEFAULT_STACK_VALUE_LIMIT + 1);
+    }
+
+    #[test]
+    fn largest_values_first() {
+        let arena = Bump::new();
+        let mut interner = STLayoutInterner::with_capacity(4, Target::LinuxX64);
+        let mut procedures = MutMap::default();
+
+        let sizes = [
+            DEFAULT_STACK_VALUE_LIMIT + 1,
+            4 * DEFAULT_STACK_VALUE_LIMIT,
+            2 * DEFAULT_STACK_VALUE_LIMIT,
+        ];
+
+        for (name, size) in [Symbol::ARG_2, Symbol::ARG_3, Symbol::ARG_4]
+            .into_iter()
+            .zip(sizes)
+        {
+            let layout = bytes(&arena, &mut interner, size);
+            identity(&arena, &mut procedures, name, layout);
+        }
+
+        let large_values =
+            find_large_stack_values(&interner, &procedures, DEFAULT_STACK_VALUE_LIMIT);
+        let found: Vec<_> = large_values
+            .iter()
+            .map(|value| (value.proc, value.size))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                (Symbol::ARG_3, 4 * DEFAULT_STACK_VALUE_LIMIT),
+                (Symbol::ARG_4, 2 * DEFAULT_STACK_VALUE_LIMIT),
+                (Symbol::ARG_2, DEFAULT_STACK_VALUE_LIMIT + 1),
+            ]
+        );
+    }
+}
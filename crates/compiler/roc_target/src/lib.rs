@@ -41,6 +41,7 @@ pub enum PtrWidth {
 pub enum Architecture {
     Aarch32,
     Aarch64,
+    Riscv64,
     Wasm32,
     X86_32,
     X86_64,
@@ -51,6 +52,7 @@ impl std::fmt::Display for Architecture {
         let arch_str = match self {
             Architecture::Aarch32 => "arm",
             Architecture::Aarch64 => "arm64",
+            Architecture::Riscv64 => "riscv64",
             Architecture::Wasm32 => "wasm32",
             Architecture::X86_32 => "x86_32",
             Architecture::X86_64 => "x86_64",
@@ -64,7 +66,7 @@ impl Architecture {
         use Architecture::*;
 
         match self {
-            X86_64 | Aarch64 => PtrWidth::Bytes8,
+            X86_64 | Aarch64 | Riscv64 => PtrWidth::Bytes8,
             X86_32 | Aarch32 | Wasm32 => PtrWidth::Bytes4,
         }
     }
@@ -79,6 +81,7 @@ pub enum Target {
     LinuxX32,
     LinuxX64,
     LinuxArm64,
+    LinuxRiscv64,
     MacX64,
     MacArm64,
     WinX32,
@@ -100,6 +103,7 @@ impl Target {
             LinuxX32 | WinX32 => Architecture::X86_32,
             LinuxX64 | WinX64 | MacX64 => Architecture::X86_64,
             LinuxArm64 | WinArm64 | MacArm64 => Architecture::Aarch64,
+            LinuxRiscv64 => Architecture::Riscv64,
             Wasm32 => Architecture::Wasm32,
         }
     }
@@ -107,7 +111,7 @@ impl Target {
     pub const fn operating_system(&self) -> OperatingSystem {
         use Target::*;
         match self {
-            LinuxX32 | LinuxX64 | LinuxArm64 => OperatingSystem::Linux,
+            LinuxX32 | LinuxX64 | LinuxArm64 | LinuxRiscv64 => OperatingSystem::Linux,
             MacX64 | MacArm64 => OperatingSystem::Mac,
             WinX32 | WinX64 | WinArm64 => OperatingSystem::Windows,
             Wasm32 => OperatingSystem::Freestanding,
@@ -145,7 +149,7 @@ impl Target {
     pub const fn object_file_ext(&self) -> &str {
         use Target::*;
         match self {
-            LinuxX32 | LinuxX64 | LinuxArm64 | MacX64 | MacArm64 => "o",
+            LinuxX32 | LinuxX64 | LinuxArm64 | LinuxRiscv64 | MacX64 | MacArm64 => "o",
             WinX32 | WinX64 | WinArm64 => "obj",
             Wasm32 => "wasm",
         }
@@ -155,7 +159,7 @@ impl Target {
     pub const fn static_library_file_ext(&self) -> &str {
         use Target::*;
         match self {
-            LinuxX32 | LinuxX64 | LinuxArm64 | MacX64 | MacArm64 => "a",
+            LinuxX32 | LinuxX64 | LinuxArm64 | LinuxRiscv64 | MacX64 | MacArm64 => "a",
             WinX32 | WinX64 | WinArm64 => "lib",
             Wasm32 => "wasm",
         }
@@ -165,7 +169,7 @@ impl Target {
     pub const fn dynamic_library_file_ext(&self) -> &str {
         use Target::*;
         match self {
-            LinuxX32 | LinuxX64 | LinuxArm64 => "so",
+            LinuxX32 | LinuxX64 | LinuxArm64 | LinuxRiscv64 => "so",
             MacX64 | MacArm64 => "dylib",
             WinX32 | WinX64 | WinArm64 => "dll",
             Wasm32 => "wasm",
@@ -176,7 +180,7 @@ impl Target {
     pub const fn executable_file_ext(&self) -> Option<&str> {
         use Target::*;
         match self {
-            LinuxX32 | LinuxX64 | LinuxArm64 | MacX64 | MacArm64 => None,
+            LinuxX32 | LinuxX64 | LinuxArm64 | LinuxRiscv64 | MacX64 | MacArm64 => None,
             WinX32 | WinX64 | WinArm64 => Some("exe"),
             Wasm32 => Some("wasm"),
         }
@@ -187,7 +191,7 @@ impl Target {
     pub fn prebuilt_static_object(&self) -> String {
         use Target::*;
         match self {
-            LinuxX32 | LinuxX64 | LinuxArm64 | MacX64 | MacArm64 | Wasm32 => {
+            LinuxX32 | LinuxX64 | LinuxArm64 | LinuxRiscv64 | MacX64 | MacArm64 | Wasm32 => {
                 format!("{}.o", self)
             }
             WinX32 | WinX64 | WinArm64 => {
@@ -201,7 +205,7 @@ impl Target {
     pub fn prebuilt_static_library(&self) -> String {
         use Target::*;
         match self {
-            LinuxX32 | LinuxX64 | LinuxArm64 | MacX64 | MacArm64 | Wasm32 => {
+            LinuxX32 | LinuxX64 | LinuxArm64 | LinuxRiscv64 | MacX64 | MacArm64 | Wasm32 => {
                 format!("{}.a", self)
             }
             WinX32 | WinX64 | WinArm64 => {
@@ -222,6 +226,11 @@ impl Target {
         format!("metadata_{}.rm", self) // short for roc metadata
     }
 
+    // file name for the LLVM bitcode of functions that a platform wants inlined into the app
+    pub fn platform_intrinsics(&self) -> String {
+        format!("intrinsics_{}.bc", self)
+    }
+
     // file name for a stubbed app dynamic library file
     pub fn stub_app_lib_file_name(&self) -> String {
         format!("libapp.{}", self.dynamic_library_file_ext())
@@ -254,6 +263,14 @@ impl Target {
         }
     }
 
+    /// Search for intrinsics in the platform main directory. Bitcode is specific to a target, so
+    /// unlike hosts there is no generic fallback.
+    pub fn find_platform_intrinsics(&self, platform_main_roc: &Path) -> Option<PathBuf> {
+        let intrinsics_path = platform_main_roc.with_file_name(self.platform_intrinsics());
+
+        intrinsics_path.exists().then_some(intrinsics_path)
+    }
+
     /// Search for a prebuilt surgical host in the platform main directory.
     pub fn find_surgical_host(
         &self,
@@ -328,6 +345,7 @@ impl FromStr for Target {
             "linux-x32" => Ok(LinuxX32),
             "linux-x64" => Ok(LinuxX64),
             "linux-arm64" => Ok(LinuxArm64),
+            "linux-riscv64" => Ok(LinuxRiscv64),
             // TODO: Can we change these to just `mac`.
             // Currently, we need to keep it as `macos` to match platform naming.
             "macos-x64" => Ok(MacX64),
@@ -354,6 +372,7 @@ impl From<&Target> for &'static str {
             LinuxX32 => "linux-x32",
             LinuxX64 => "linux-x64",
             LinuxArm64 => "linux-arm64",
+            LinuxRiscv64 => "linux-riscv64",
             // TODO: Can we change these to just `mac`.
             // Currently, we need to keep it as `macos` to match platform naming.
             MacX64 => "macos-x64",
@@ -391,6 +410,11 @@ impl From<&Triple> for Target {
                 operating_system: OperatingSystem::Linux,
                 ..
             } => Target::LinuxArm64,
+            Triple {
+                architecture: Architecture::Riscv64(_),
+                operating_system: OperatingSystem::Linux,
+                ..
+            } => Target::LinuxRiscv64,
             Triple {
                 architecture: Architecture::X86_32(_),
                 operating_system: OperatingSystem::Windows,
@@ -446,6 +470,7 @@ impl TryFrom<(Architecture, OperatingSystem)> for Target {
             (Architecture::X86_32, OperatingSystem::Linux) => Ok(Target::LinuxX32),
             (Architecture::X86_64, OperatingSystem::Linux) => Ok(Target::LinuxX64),
             (Architecture::Aarch64, OperatingSystem::Linux) => Ok(Target::LinuxArm64),
+            (Architecture::Riscv64, OperatingSystem::Linux) => Ok(Target::LinuxRiscv64),
             (Architecture::X86_32, OperatingSystem::Windows) => Ok(Target::WinX32),
             (Architecture::X86_64, OperatingSystem::Windows) => Ok(Target::WinX64),
             (Architecture::Aarch64, OperatingSystem::Windows) => Ok(Target::WinArm64),
@@ -462,3 +487,28 @@ impl std::fmt::Display for Target {
         write!(f, "{}", Into::<&'static str>::into(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn target_names_round_trip() {
+        for target in Target::iter() {
+            let name: &str = target.into();
+
+            assert!(
+                matches!(Target::from_str(name), Ok(parsed) if parsed == target),
+                "{name} does not parse back to {target:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn target_arch_os_round_trip() {
+        for target in Target::iter() {
+            assert_eq!(Target::try_from(target.arch_os()), Ok(target));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+#[path = "../src/helpers/mod.rs"]
+mod helpers;
+
+// defines roc_alloc and friends
+pub use helpers::platform_functions::*;
+
+use bumpalo::Bump;
+use criterion::black_box;
+use helpers::llvm::{bench_lib_function, BenchReport};
+use roc_gen_llvm::llvm::memory::MemoryStrategyKind;
+use roc_gen_llvm::{run_roc::RocCallResult, run_roc_dylib};
+use roc_mono::ir::OptLevel;
+use roc_std::RocList;
+
+// Reports the allocations, frees and refcount updates per call next to the wall time, so changes
+// to refcount elision can be measured. Run with `cargo bench --bench memory_traffic`.
+
+type Input = RocList<i64>;
+type Output = RocList<i64>;
+
+const ITERATIONS: u32 = 10_000;
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "list map",
+        indoc::indoc!(
+            r#"
+            app "bench" provides [main] to "./platform"
+
+            main : List I64 -> List I64
+            main = \list -> List.map list (\x -> x + 2)
+            "#
+        ),
+    ),
+    (
+        "list map shared",
+        indoc::indoc!(
+            r#"
+            app "bench" provides [main] to "./platform"
+
+            main : List I64 -> List I64
+            main = \list ->
+                doubled = List.map list (\x -> x * 2)
+
+                List.concat doubled list
+            "#
+        ),
+    ),
+    (
+        "list walk append",
+        indoc::indoc!(
+            r#"
+            app "bench" provides [main] to "./platform"
+
+            main : List I64 -> List I64
+            main = \list ->
+                List.walk list [] \state, x -> List.append state (x + 1)
+            "#
+        ),
+    ),
+];
+
+fn bench_program(arena: &Bump, source: &str, input: &Input) -> BenchReport {
+    let config = helpers::llvm::HelperConfig {
+        opt_level: OptLevel::Optimize,
+        memory_strategy: MemoryStrategyKind::Counting,
+        ..helpers::llvm::HelperConfig::default()
+    };
+
+    let context = inkwell::context::Context::create();
+    let (main_fn_name, errors, lib) = helpers::llvm::helper(
+        arena,
+        config,
+        source,
+        arena.alloc(context),
+        roc_load::FunctionKind::LambdaSet,
+    );
+
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    let roc_main = run_roc_dylib!(&lib, main_fn_name, &Input, Output);
+
+    bench_lib_function(&lib, ITERATIONS, || unsafe {
+        let mut main_result = RocCallResult::default();
+
+        // the roc code will dec this list, so inc it first so it is not free'd
+        std::mem::forget(input.clone());
+
+        roc_main(black_box(input), &mut main_result);
+
+        // frees the output list on the host side, so it is not counted
+        let _ = Result::<Output, _>::from(main_result);
+    })
+}
+
+fn main() {
+    let arena = Bump::new();
+    let numbers = Vec::from_iter(0..1_000);
+    let input = RocList::from_slice(&numbers);
+
+    for (name, source) in PROGRAMS {
+        let report = bench_program(&arena, source, &input);
+
+        println!("{name:<20} {report}");
+    }
+}
@@ -6,7 +6,7 @@ pub use helpers::platform_functions::*;
 
 use bumpalo::Bump;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use roc_gen_llvm::{llvm::build::LlvmBackendMode, run_roc::RocCallResult, run_roc_dylib};
+use roc_gen_llvm::{run_roc::RocCallResult, run_roc_dylib};
 use roc_mono::ir::OptLevel;
 use roc_std::RocList;
 
@@ -84,10 +84,9 @@ fn roc_function<'a>(
     source: &str,
 ) -> libloading::Symbol<'a, Main<*mut Input, Output>> {
     let config = helpers::llvm::HelperConfig {
-        mode: LlvmBackendMode::GenTest,
-        ignore_problems: false,
         emit_debug_info: true,
         opt_level: OptLevel::Optimize,
+        ..helpers::llvm::HelperConfig::default()
     };
 
     let context = inkwell::context::Context::create();
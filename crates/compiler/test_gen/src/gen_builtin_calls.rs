@@ -0,0 +1,140 @@
+//! Tests of the code gen of single builtins, which run without loading any Roc source. See
+//! [crate::helpers::builtin].
+#![cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+
+use crate::helpers::builtin::assert_builtin_evals_to;
+
+use roc_module::low_level::LowLevel;
+use roc_std::{RocList, RocStr};
+
+#[test]
+fn str_concat() {
+    assert_builtin_evals_to!(
+        LowLevel::StrConcat,
+        [
+            RocStr::from("A string that is too long to be small, "),
+            RocStr::from("and then some")
+        ],
+        RocStr::from("A string that is too long to be small, and then some")
+    );
+}
+
+#[test]
+fn str_repeat() {
+    assert_builtin_evals_to!(
+        LowLevel::StrRepeat,
+        [RocStr::from("ab"), 3u64],
+        RocStr::from("ababab")
+    );
+}
+
+#[test]
+fn str_split() {
+    assert_builtin_evals_to!(
+        LowLevel::StrSplit,
+        [RocStr::from("first, second, third"), RocStr::from(", ")],
+        RocList::from_slice(&[
+            RocStr::from("first"),
+            RocStr::from("second"),
+            RocStr::from("third"),
+        ])
+    );
+}
+
+#[test]
+fn str_count_utf8_bytes() {
+    assert_builtin_evals_to!(LowLevel::StrCountUtf8Bytes, [RocStr::from("héllo")], 6u64);
+}
+
+#[test]
+fn str_starts_with() {
+    assert_builtin_evals_to!(
+        LowLevel::StrStartsWith,
+        [RocStr::from("hello world"), RocStr::from("hello")],
+        true
+    );
+}
+
+#[test]
+fn list_len() {
+    assert_builtin_evals_to!(
+        LowLevel::ListLenU64,
+        [RocList::from_slice(&[1i64, 2, 3])],
+        3u64
+    );
+}
+
+#[test]
+fn list_concat() {
+    assert_builtin_evals_to!(
+        LowLevel::ListConcat,
+        [
+            RocList::from_slice(&[1i64, 2]),
+            RocList::from_slice(&[3i64, 4, 5])
+        ],
+        RocList::from_slice(&[1i64, 2, 3, 4, 5])
+    );
+}
+
+#[test]
+fn list_concat_empty() {
+    assert_builtin_evals_to!(
+        LowLevel::ListConcat,
+        [RocList::<u8>::empty(), RocList::from_slice(&[7u8])],
+        RocList::from_slice(&[7u8])
+    );
+}
+
+#[test]
+fn list_concat_of_strs() {
+    assert_builtin_evals_to!(
+        LowLevel::ListConcat,
+        [
+            RocList::from_slice(&[RocStr::from("A string that is too long to be small")]),
+            RocList::from_slice(&[RocStr::from("short")])
+        ],
+        RocList::from_slice(&[
+            RocStr::from("A string that is too long to be small"),
+            RocStr::from("short"),
+        ])
+    );
+}
+
+#[test]
+fn list_sublist() {
+    assert_builtin_evals_to!(
+        LowLevel::ListSublist,
+        [RocList::from_slice(&[10i64, 20, 30, 40]), 1u64, 2u64],
+        RocList::from_slice(&[20i64, 30])
+    );
+}
+
+#[test]
+fn list_sublist_past_the_end() {
+    assert_builtin_evals_to!(
+        LowLevel::ListSublist,
+        [RocList::from_slice(&[10i64, 20, 30, 40]), 3u64, 10u64],
+        RocList::from_slice(&[40i64])
+    );
+}
+
+#[test]
+fn list_drop_at() {
+    assert_builtin_evals_to!(
+        LowLevel::ListDropAt,
+        [RocList::from_slice(&[1i64, 2, 3]), 1u64],
+        RocList::from_slice(&[1i64, 3])
+    );
+}
+
+#[test]
+fn list_of_lists_concat() {
+    assert_builtin_evals_to!(
+        LowLevel::ListConcat,
+        [
+            RocList::from_slice(&[RocList::from_slice(&[1u8, 2])]),
+            RocList::from_slice(&[RocList::<u8>::empty()])
+        ],
+        RocList::from_slice(&[RocList::from_slice(&[1u8, 2]), RocList::empty()])
+    );
+}
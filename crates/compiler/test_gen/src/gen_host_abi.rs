@@ -0,0 +1,380 @@
+//! Call the wrappers that a host calls, like `roc__main_1_exposed`, the way a host does: with
+//! `#[repr(C)]` mirrors of the layouts of the arguments and the result. The other tests call a test
+//! wrapper instead, so they keep passing when a change to the layouts breaks the host ABI.
+//!
+//! Besides the values that come back, these check that `main` releases exactly the references it
+//! was given, and that the only memory it keeps is the memory it returns.
+#[allow(unused_imports)]
+use indoc::indoc;
+
+#[allow(unused_imports)]
+use roc_std::{RocList, RocStr};
+
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn host_abi_config() -> crate::helpers::llvm::HelperConfig {
+    use crate::helpers::llvm::HelperConfig;
+    use roc_gen_llvm::llvm::build::LlvmBackendMode;
+    use roc_gen_llvm::llvm::memory::MemoryStrategyKind;
+
+    HelperConfig {
+        mode: LlvmBackendMode::BinaryGlue,
+        memory_strategy: MemoryStrategyKind::Counting,
+        ..HelperConfig::default()
+    }
+}
+
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn build_with_host_abi(src: &str) -> libloading::Library {
+    build_with_config(src, host_abi_config())
+}
+
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn build_with_config(src: &str, config: crate::helpers::llvm::HelperConfig) -> libloading::Library {
+    use crate::helpers::llvm::helper;
+
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+
+    let (_, errors, lib) = helper(
+        &arena,
+        config,
+        src,
+        &context,
+        roc_load::FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    lib
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn str_argument_and_result() {
+    use crate::helpers::llvm::{memory_counters, HOST_ENTRY_POINT};
+
+    let lib = build_with_host_abi(indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : Str -> Str
+        main = \s -> Str.concat s ", and back again"
+        "#
+    ));
+
+    type Main = unsafe extern "C" fn(RocStr) -> RocStr;
+    let main: Main = unsafe { *lib.get::<Main>(HOST_ENTRY_POINT.as_bytes()).unwrap() };
+
+    let input = RocStr::from("There and back, in a string too long to be small");
+    let kept = input.clone();
+
+    let output = unsafe { main(input.clone()) };
+
+    assert_eq!(
+        output.as_str(),
+        "There and back, in a string too long to be small, and back again"
+    );
+    assert!(output.is_unique());
+
+    // `main` released the one reference it was given
+    assert!(!kept.is_unique());
+    drop(input);
+    assert!(kept.is_unique());
+
+    let counters = memory_counters(&lib);
+    assert_eq!((counters.allocations, counters.deallocations), (1, 0));
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn record_argument_and_result() {
+    use crate::helpers::llvm::{memory_counters, HOST_ENTRY_POINT};
+
+    // fields are sorted by alignment, and then by name
+    #[repr(C)]
+    struct Account {
+        balance: u64,
+        owner: RocStr,
+        active: bool,
+    }
+
+    let lib = build_with_host_abi(indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : { active : Bool, balance : U64, owner : Str } -> { active : Bool, balance : U64, owner : Str }
+        main = \account -> { account & active: !account.active, balance: account.balance + 100 }
+        "#
+    ));
+
+    type Main = unsafe extern "C" fn(Account) -> Account;
+    let main: Main = unsafe { *lib.get::<Main>(HOST_ENTRY_POINT.as_bytes()).unwrap() };
+
+    let owner = RocStr::from("An owner whose name is too long to be small");
+    let input = Account {
+        balance: 42,
+        owner: owner.clone(),
+        active: true,
+    };
+
+    let output = unsafe { main(input) };
+
+    assert_eq!(output.balance, 142);
+    assert_eq!(output.owner, owner);
+    assert!(!output.active);
+
+    // the owner is passed through, so `main` neither retains nor releases it
+    assert!(!owner.is_unique());
+    drop(output);
+    assert!(owner.is_unique());
+
+    let counters = memory_counters(&lib);
+    assert_eq!((counters.allocations, counters.deallocations), (0, 0));
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn list_of_str_argument_and_result() {
+    use crate::helpers::llvm::{memory_counters, HOST_ENTRY_POINT};
+
+    let lib = build_with_host_abi(indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : List Str -> List Str
+        main = \strings -> List.reverse strings
+        "#
+    ));
+
+    type Main = unsafe extern "C" fn(RocList<RocStr>) -> RocList<RocStr>;
+    let main: Main = unsafe { *lib.get::<Main>(HOST_ENTRY_POINT.as_bytes()).unwrap() };
+
+    let input = RocList::from_slice(&[
+        RocStr::from("The first string, too long to be small"),
+        RocStr::from("The second string, too long to be small"),
+        RocStr::from("The third string, too long to be small"),
+    ]);
+
+    let output = unsafe { main(input.clone()) };
+
+    let strings = |list: &RocList<RocStr>| list.iter().map(RocStr::as_str).collect::<Vec<_>>();
+    let mut expected = strings(&input);
+    expected.reverse();
+    assert_eq!(strings(&output), expected);
+
+    // the list is shared, so `main` reverses a copy: it releases the list it was given, and
+    // retains every string once for the copy
+    assert!(input.is_unique());
+    assert!(input.iter().all(|string| !string.is_unique()));
+    drop(output);
+    assert!(input.iter().all(RocStr::is_unique));
+
+    let counters = memory_counters(&lib);
+    assert_eq!((counters.allocations, counters.deallocations), (1, 0));
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn debug_info_of_host_functions_is_valid() {
+    use crate::helpers::llvm::{create_llvm_module, HelperConfig};
+    use roc_gen_llvm::llvm::build::LlvmBackendMode;
+
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+    let config = HelperConfig {
+        mode: LlvmBackendMode::BinaryGlue,
+        emit_debug_info: true,
+        memory_api: true,
+        ..HelperConfig::default()
+    };
+
+    // building the module verifies it, and the verifier rejects debug info that is not finalized
+    let (_, errors, module) = create_llvm_module(
+        &arena,
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main : List Str -> Str
+            main = \strings -> Str.joinWith strings ", "
+            "#
+        ),
+        config,
+        &context,
+        target_lexicon::Triple::host().into(),
+        roc_load::FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    // the exposed wrappers and the memory API are built after the procedures
+    let host_functions = Vec::from_iter(module.get_functions().filter(|function| {
+        let name = function.get_name().to_str().unwrap();
+        name.starts_with("roc__") && function.count_basic_blocks() > 0
+    }));
+
+    let name = |function: &inkwell::values::FunctionValue| {
+        function.get_name().to_str().unwrap().to_string()
+    };
+
+    assert!(host_functions
+        .iter()
+        .any(|function| name(function).starts_with("roc__retain_")));
+
+    for function in host_functions {
+        assert!(
+            function.get_subprogram().is_some(),
+            "{} has no debug info",
+            name(&function)
+        );
+    }
+}
+
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+const STR_IDENTITY: &str = indoc!(
+    r#"
+    app "test" provides [main] to "./platform"
+
+    main : Str -> Str
+    main = \s -> s
+    "#
+);
+
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+type MemoryApiFn = unsafe extern "C" fn(*const RocStr);
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn memory_api_retains_and_releases() {
+    use crate::helpers::llvm::HelperConfig;
+
+    let config = HelperConfig {
+        memory_api: true,
+        ..host_abi_config()
+    };
+    let lib = build_with_config(STR_IDENTITY, config);
+
+    let get = |name: &str| unsafe { *lib.get::<MemoryApiFn>(name.as_bytes()).unwrap() };
+    let retain = get("roc__retain_main_1_exposed_arg0");
+    let release = get("roc__release_main_1_exposed_arg0");
+
+    let value = RocStr::from("A string that is too long to be small");
+
+    unsafe { retain(&value) };
+    assert!(!value.is_unique());
+
+    unsafe { release(&value) };
+    assert!(value.is_unique());
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn memory_api_is_only_built_when_asked_for() {
+    let lib = build_with_host_abi(STR_IDENTITY);
+
+    let retain = unsafe { lib.get::<MemoryApiFn>(b"roc__retain_main_1_exposed_arg0") };
+    assert!(retain.is_err());
+}
+
+/// The LLVM type of `main`, built for Windows x64, when it takes and returns a `record`
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn win_x64_main_signature(record: &str) -> String {
+    use crate::helpers::llvm::{create_llvm_module, HOST_ENTRY_POINT};
+    use indoc::formatdoc;
+
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+    let src = formatdoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : {record} -> {record}
+        main = \r -> r
+        "#
+    );
+
+    let (_, errors, module) = create_llvm_module(
+        &arena,
+        &src,
+        host_abi_config(),
+        &context,
+        roc_target::Target::WinX64,
+        roc_load::FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    let main = module.get_function(HOST_ENTRY_POINT).unwrap();
+    main.get_type().print_to_string().to_string()
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn win_x64_small_records_are_passed_as_integers() {
+    assert_eq!(win_x64_main_signature("{ a : U8 }"), "i8 (i8)");
+    assert_eq!(win_x64_main_signature("{ a : U8, b : U8 }"), "i16 (i16)");
+    assert_eq!(win_x64_main_signature("{ a : U16, b : U16 }"), "i32 (i32)");
+    assert_eq!(win_x64_main_signature("{ a : U32, b : U32 }"), "i64 (i64)");
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn win_x64_other_records_are_passed_by_reference() {
+    // returned through an output pointer, and passed by reference without `byval`
+    assert_eq!(
+        win_x64_main_signature("{ a : U32, b : U32, c : U32 }"),
+        "void (ptr, ptr)"
+    );
+}
+
+#[test]
+#[cfg(all(
+    feature = "gen-llvm",
+    not(feature = "gen-llvm-wasm"),
+    windows,
+    target_arch = "x86_64"
+))]
+fn win_x64_records_round_trip() {
+    use crate::helpers::llvm::HOST_ENTRY_POINT;
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Pair {
+        a: u32,
+        b: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Triple {
+        a: u32,
+        b: u32,
+        c: u32,
+    }
+
+    let lib = build_with_host_abi(indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : { a : U32, b : U32 } -> { a : U32, b : U32 }
+        main = \{ a, b } -> { a: b, b: a }
+        "#
+    ));
+
+    type PairMain = unsafe extern "C" fn(Pair) -> Pair;
+    let main: PairMain = unsafe { *lib.get::<PairMain>(HOST_ENTRY_POINT.as_bytes()).unwrap() };
+    assert_eq!(unsafe { main(Pair { a: 1, b: 2 }) }, Pair { a: 2, b: 1 });
+
+    let lib = build_with_host_abi(indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : { a : U32, b : U32, c : U32 } -> { a : U32, b : U32, c : U32 }
+        main = \{ a, b, c } -> { a: c, b, c: a }
+        "#
+    ));
+
+    type TripleMain = unsafe extern "C" fn(Triple) -> Triple;
+    let main: TripleMain = unsafe { *lib.get::<TripleMain>(HOST_ENTRY_POINT.as_bytes()).unwrap() };
+    assert_eq!(
+        unsafe { main(Triple { a: 1, b: 2, c: 3 }) },
+        Triple { a: 3, b: 2, c: 1 }
+    );
+}
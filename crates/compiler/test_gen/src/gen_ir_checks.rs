@@ -0,0 +1,407 @@
+//! Tests that lock in optimizations by checking the LLVM IR that a program compiles to. See
+//! [crate::helpers::ir_check].
+#![cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+
+use crate::helpers::ir_check::{
+    assert_ir_matches, check_ir, function_ir, llvm_ir, llvm_ir_with_rc_outlining,
+};
+
+use indoc::indoc;
+use roc_gen_llvm::llvm::refcounting::RcOutlining;
+use roc_mono::ir::OptLevel;
+
+#[test]
+fn borrowed_list_is_not_refcounted() {
+    assert_ir_matches!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            count : List I64 -> U64
+            count = \list -> List.len list
+
+            main = count [1, 2, 3]
+            "#
+        ),
+        "count",
+        indoc!(
+            r#"
+            CHECK-NOT: #Attr_#inc
+            CHECK-NOT: #Attr_#dec
+            "#
+        )
+    );
+}
+
+#[test]
+fn list_used_twice_is_incremented() {
+    assert_ir_matches!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            twice : List I64 -> List I64
+            twice = \list -> List.concat list list
+
+            main = twice [1, 2, 3]
+            "#
+        ),
+        "twice",
+        indoc!(
+            r#"
+            CHECK: call {{.*}}#Attr_#inc
+            CHECK: call {{.*}}roc_builtins.list.concat
+            "#
+        )
+    );
+}
+
+#[test]
+fn tail_call_is_a_loop() {
+    assert_ir_matches!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            sum : List I64, U64, I64 -> I64
+            sum = \list, index, total ->
+                when List.get list index is
+                    Ok n -> sum list (index + 1) (total + n)
+                    Err _ -> total
+
+            main = sum [1, 2, 3] 0 0
+            "#
+        ),
+        "sum",
+        indoc!(
+            r##"
+            the list is passed along unchanged, so each iteration borrows it
+            CHECK-NOT: call {{.*}}@"#UserApp_sum_
+            CHECK-NOT: #Attr_#inc
+            CHECK-NOT: #Attr_#dec
+            "##
+        )
+    );
+}
+
+#[test]
+fn thunk_that_calls_functions_is_cached() {
+    let ir = llvm_ir(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            fib : U64 -> U64
+            fib = \n -> if n < 2 then n else fib (n - 1) + fib (n - 2)
+
+            value : U64
+            value = fib 10
+
+            main = value + value
+            "#
+        ),
+        OptLevel::Development,
+    );
+
+    check_ir(
+        &ir,
+        indoc!(
+            r#"
+            CHECK: #UserApp_value_{{.*}}_cache_state
+            CHECK: define {{.*}}#UserApp_value_{{.*}}_uncached
+            "#
+        ),
+    );
+}
+
+#[test]
+fn thunk_whose_callee_runs_dbg_is_not_cached() {
+    let ir = llvm_ir(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            noisy : U64 -> U64
+            noisy = \n ->
+                dbg n
+
+                n + 1
+
+            value : U64
+            value = noisy 10
+
+            main = value + value
+            "#
+        ),
+        OptLevel::Development,
+    );
+
+    check_ir(
+        &ir,
+        indoc!(
+            r#"
+            the dbg in noisy has to report at every use of value
+            CHECK-NOT: _cache_state
+            "#
+        ),
+    );
+}
+
+#[test]
+fn thunk_with_list_value_is_cached() {
+    let ir = llvm_ir(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            value : List U64
+            value = List.map [1, 2, 3] \n -> n * 2
+
+            main = List.concat value value
+            "#
+        ),
+        OptLevel::Development,
+    );
+
+    check_ir(
+        &ir,
+        indoc!(
+            r#"
+            the list gets the refcount of constants before it goes into the cache
+            CHECK: define {{.*}}#UserApp_value_
+            CHECK: make_static:
+            CHECK-NEXT: getelementptr
+            CHECK-NEXT: store i64 0
+            "#
+        ),
+    );
+}
+
+#[test]
+fn thunk_with_nested_refcounted_value_is_not_cached() {
+    let ir = llvm_ir(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            value : List Str
+            value = List.map [1, 2, 3] Num.toStr
+
+            main = List.concat value value
+            "#
+        ),
+        OptLevel::Development,
+    );
+
+    check_ir(
+        &ir,
+        indoc!(
+            r#"
+            callers could update the refcounts of the strings in the list from several threads
+            CHECK-NOT: _cache_state
+            "#
+        ),
+    );
+}
+
+const LIST_USED_TWICE: &str = indoc!(
+    r#"
+    app "test" provides [main] to "./platform"
+
+    twice : List I64 -> List I64
+    twice = \list -> List.concat list list
+
+    main = twice [1, 2, 3]
+    "#
+);
+
+/// The attribute group of the definition of the first function whose name contains `name`
+fn attributes_of<'a>(ir: &'a str, name: &str) -> &'a str {
+    let define = ir
+        .lines()
+        .find(|line| line.starts_with("define ") && line.contains(name))
+        .unwrap_or_else(|| panic!("There is no definition of `{name}`"));
+
+    let group = define
+        .trim_end_matches('{')
+        .split_whitespace()
+        .last()
+        .filter(|group| group.starts_with('#'))
+        .unwrap_or_else(|| panic!("`{define}` has no attributes"));
+
+    ir.lines()
+        .find(|line| line.starts_with(&format!("attributes {group} = ")))
+        .unwrap_or_else(|| panic!("There is no attribute group {group}"))
+}
+
+#[test]
+fn small_rc_helper_is_inlined() {
+    // a list helper only calls into zig, so it is well below the default threshold
+    for rc_outlining in [RcOutlining::default(), RcOutlining::Inline] {
+        let ir = llvm_ir_with_rc_outlining(LIST_USED_TWICE, OptLevel::Development, rc_outlining);
+
+        check_ir(
+            function_ir(&ir, "twice"),
+            indoc!(
+                r#"
+                CHECK-NOT: #Attr_#inc
+                "#
+            ),
+        );
+    }
+}
+
+#[test]
+fn small_rc_helper_is_not_inlined_when_outlining() {
+    let ir =
+        llvm_ir_with_rc_outlining(LIST_USED_TWICE, OptLevel::Development, RcOutlining::Outline);
+
+    check_ir(
+        function_ir(&ir, "twice"),
+        indoc!(
+            r#"
+            CHECK: call {{.*}}#Attr_#inc
+            "#
+        ),
+    );
+
+    let attributes = attributes_of(&ir, "#Attr_#inc");
+    assert!(attributes.contains("noinline"), "{attributes}");
+    assert!(!attributes.contains("alwaysinline"), "{attributes}");
+}
+
+#[test]
+fn large_rc_helper_is_outlined_at_the_default_threshold() {
+    // the helper of this record updates ten strings, which takes more instructions than the
+    // default threshold
+    let src = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        Strings : { a : Str, b : Str, c : Str, d : Str, e : Str, f : Str, g : Str, h : Str, i : Str, j : Str }
+
+        twice : Strings -> List Strings
+        twice = \strings -> [strings, strings]
+
+        main = twice { a: "a", b: "b", c: "c", d: "d", e: "e", f: "f", g: "g", h: "h", i: "i", j: "j" }
+        "#
+    );
+
+    let ir = llvm_ir_with_rc_outlining(src, OptLevel::Development, RcOutlining::default());
+
+    check_ir(
+        function_ir(&ir, "twice"),
+        indoc!(
+            r#"
+            CHECK: call {{.*}}#Attr_#inc
+            "#
+        ),
+    );
+
+    // but it is inlined when asked to
+    let ir = llvm_ir_with_rc_outlining(src, OptLevel::Development, RcOutlining::Inline);
+
+    check_ir(
+        function_ir(&ir, "twice"),
+        indoc!(
+            r#"
+            CHECK-NOT: call {{.*}}#Attr_#inc
+            "#
+        ),
+    );
+}
+
+const IR: &str = indoc!(
+    r##"
+    define i64 @f(i64 %0) {
+    entry:
+      %1 = add i64 %0, 1
+      call void @"#Attr_#dec_3"(ptr %list)
+      ret i64 %1
+    }
+    "##
+);
+
+#[test]
+fn check_matches_in_order() {
+    check_ir(
+        IR,
+        indoc!(
+            r#"
+            CHECK: define
+            CHECK: add i64 {{%[0-9]+}}, 1
+            CHECK: ret i64
+            "#
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "`CHECK: define` matched no line")]
+fn check_does_not_match_earlier_lines() {
+    check_ir(
+        IR,
+        indoc!(
+            r#"
+            CHECK: ret i64
+            CHECK: define
+            "#
+        ),
+    );
+}
+
+#[test]
+fn check_next_matches_the_next_line() {
+    check_ir(
+        IR,
+        indoc!(
+            r#"
+            CHECK: entry:
+            CHECK-NEXT: add i64
+            CHECK-NEXT: #Attr_#dec
+            "#
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "`CHECK-NEXT: ret i64` did not match the next line")]
+fn check_next_skips_no_lines() {
+    check_ir(
+        IR,
+        indoc!(
+            r#"
+            CHECK: add i64
+            CHECK-NEXT: ret i64
+            "#
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "`CHECK-NOT: #Attr_#dec` matched line 4")]
+fn check_not_between_matches() {
+    check_ir(
+        IR,
+        indoc!(
+            r#"
+            CHECK: add i64
+            CHECK-NOT: #Attr_#dec
+            CHECK: ret i64
+            "#
+        ),
+    );
+}
+
+#[test]
+fn check_not_after_the_last_match() {
+    check_ir(
+        IR,
+        indoc!(
+            r#"
+            CHECK: #Attr_#dec
+            CHECK-NOT: call
+            "#
+        ),
+    );
+}
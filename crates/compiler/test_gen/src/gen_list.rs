@@ -1490,6 +1490,61 @@ fn list_map_closure_string() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn list_map_parallel_changes_element_width() {
+    // the dev and wasm backends map sequentially, which must give the same list as List.map
+    assert_evals_to!(
+        indoc!(
+            r"
+            list : List U8
+            list = [1, 2, 3]
+
+            List.mapParallel list (\x -> Num.toI64 x * 1000) == List.map list (\x -> Num.toI64 x * 1000)
+            "
+        ),
+        true,
+        bool
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn list_map_parallel_closure_string() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            one : Str
+            one = "one "
+
+            List.mapParallel ["pear", "apple"] (\x -> Str.concat one x)
+            "#
+        ),
+        RocList::from_slice(&[RocStr::from("one pear"), RocStr::from("one apple")]),
+        RocList<RocStr>
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn list_map_parallel_keeps_order() {
+    // long enough to be split into several chunks
+    assert_evals_to!(
+        indoc!(
+            r"
+            offset : I64
+            offset = 10
+
+            List.range { start: At 0, end: Length 2000 }
+            |> List.mapParallel (\x -> x * 2 + offset)
+            |> List.walkWithIndex Bool.true (\ok, x, i -> ok && x == Num.toI64 i * 2 + offset)
+            "
+        ),
+        true,
+        bool
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn list_map4_group() {
@@ -1890,6 +1890,45 @@ fn floor_dec() {
     assert_evals_to!("Num.floor -0.5dec", -1, i64);
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn rounding_f32() {
+    assert_evals_to!("Num.round -2.5f32", -3, i64);
+    assert_evals_to!("Num.floor -2.5f32", -3, i64);
+    assert_evals_to!("Num.ceiling -2.5f32", -2, i64);
+    assert_evals_to!("Num.sqrt 16f32", 4.0, f32);
+    assert_evals_to!("Num.pow 2f32 10f32", 1024.0, f32);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn rounding_saturates() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            x : I8
+            x = Num.floor 1000.5f64
+
+            x
+            "
+        ),
+        127,
+        i8
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            x : U8
+            x = Num.ceiling -3.5f32
+
+            x
+            "
+        ),
+        0,
+        u8
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn pow_int() {
@@ -3924,3 +3963,37 @@ fn infinity_f32() {
 fn infinity_f64() {
     assert_evals_to!(r"Num.infinityF64", f64::INFINITY, f64);
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn split_mix_64() {
+    assert_evals_to!(r"(Num.splitMix64 0).value", 16294208416658607535, u64);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn random_u64_sequence() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            { value: a, state } = Num.randomU64 (Num.randomSeed 42)
+            { value: b } = Num.randomU64 state
+
+            (a, b)
+            "
+        ),
+        (1546998764402558742, 6990951692964543102),
+        (u64, u64)
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn random_f64_in_unit_interval() {
+    assert_evals_to!(
+        r"(Num.randomF64 (Num.randomSeed 7)).value",
+        true,
+        f64,
+        |f: f64| (0.0..1.0).contains(&f)
+    );
+}
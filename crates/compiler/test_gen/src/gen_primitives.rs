@@ -575,6 +575,72 @@ fn top_level_constant() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn top_level_thunk_used_twice() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            fib : U64 -> U64
+            fib = \n -> if n < 2 then n else fib (n - 1) + fib (n - 2)
+
+            value : U64
+            value = fib 10
+
+            main =
+                value + value
+                "#
+        ),
+        110,
+        u64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn top_level_refcounted_thunk_used_twice() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            value : List U64
+            value = List.map [1, 2, 3] \n -> n * 2
+
+            main =
+                List.concat value value
+                "#
+        ),
+        RocList::from_slice(&[2, 4, 6, 2, 4, 6]),
+        RocList<u64>
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn top_level_string_thunk_used_twice() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            greet : Str -> Str
+            greet = \name -> Str.concat "Hello to everyone who reads this, " name
+
+            value : Str
+            value = greet "and goodbye"
+
+            main =
+                Str.concat value (Str.concat ", " value)
+                "#
+        ),
+        RocStr::from("Hello to everyone who reads this, and goodbye, Hello to everyone who reads this, and goodbye"),
+        RocStr
+    );
+}
+
 #[test]
 #[ignore]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
@@ -3445,6 +3511,22 @@ fn box_and_unbox_tag_union() {
     )
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn box_size_of_and_align_of() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            header = { tag: 0u8, length: 0u32, id: 0u64 }
+
+            (Box.sizeOf header, Box.alignOf header, Box.sizeOf 0u16, Box.alignOf 0u16)
+            "
+        ),
+        (16, 8, 2, 2),
+        (u64, u64, u64, u64)
+    )
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn closure_called_in_its_defining_scope() {
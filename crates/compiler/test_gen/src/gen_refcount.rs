@@ -317,6 +317,56 @@ fn list_map_dealloc() {
     );
 }
 
+#[test]
+#[cfg(feature = "gen-wasm")]
+fn list_map_borrowed_captures() {
+    assert_refcounts!(
+        indoc!(
+            r#"
+                s = Str.concat "A long enough string " "to be heap-allocated"
+                list = [0x111, 0x222, 0x333]
+                lengths = List.map [1, 2] \n -> n + Str.countUtf8Bytes s + List.len list
+
+                { lengths, list, s }
+            "#
+        ),
+        (RocList<u64>, RocList<i64>, RocStr),
+        &[
+            (StandardRC, Live(1)),     // s
+            (StandardRC, Live(1)),     // list
+            (StandardRC, Deallocated), // [1, 2]
+            (StandardRC, Live(1)),     // lengths
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-wasm")]
+fn list_map_escaping_closure_captures() {
+    assert_refcounts!(
+        indoc!(
+            r#"
+                makeCounter = \prefix, suffix ->
+                    \n -> n + Str.countUtf8Bytes prefix + Str.countUtf8Bytes suffix
+
+                s = Str.concat "A long enough string " "to be heap-allocated"
+                t = Str.concat "Another long enough string " "to be heap-allocated"
+                counter = makeCounter s t
+                counts = List.map [1, 2] counter
+
+                { counts, s }
+            "#
+        ),
+        (RocList<u64>, RocStr),
+        &[
+            (StandardRC, Live(1)),     // s
+            (StandardRC, Deallocated), // t
+            (StandardRC, Deallocated), // [1, 2]
+            (StandardRC, Live(1)),     // counts
+        ]
+    );
+}
+
 #[test]
 #[cfg(feature = "gen-wasm")]
 fn list_map2_dealloc_tail() {
@@ -857,3 +907,333 @@ fn basic_cli_parser() {
         ]
     );
 }
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn atomic_refcount_list_shared_between_threads() {
+    use crate::helpers::llvm::{helper, HelperConfig};
+    use roc_gen_llvm::run_roc::RocCallResult;
+    use std::sync::atomic::{AtomicIsize, Ordering};
+
+    const THREADS: usize = 8;
+    const CALLS_PER_THREAD: usize = 10_000;
+
+    // the list crosses the host boundary, so `main` decrements it atomically when it is done
+    let src = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : List I64 -> U64
+        main = \list -> List.len list
+        "#
+    );
+
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+    let config = HelperConfig {
+        atomic_refcounts: true,
+        ..HelperConfig::default()
+    };
+
+    let (main_fn_name, errors, lib) = helper(
+        &arena,
+        config,
+        src,
+        &context,
+        roc_load::FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    type Main = unsafe extern "C" fn(*const RocList<i64>, *mut RocCallResult<u64>);
+    let main: Main = unsafe { *lib.get::<Main>(main_fn_name.as_bytes()).unwrap() };
+
+    let list = RocList::from_slice(&[1i64, 2, 3]);
+
+    // RocList is not Send, so the threads share its address
+    let list_addr = &list as *const RocList<i64> as usize;
+    let refcount_addr = unsafe { (list.as_ptr() as *const isize).sub(1) } as usize;
+
+    std::thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(move || {
+                let refcount = unsafe { AtomicIsize::from_ptr(refcount_addr as *mut isize) };
+
+                for _ in 0..CALLS_PER_THREAD {
+                    // hand a new reference to `main`, which gives it up again
+                    refcount.fetch_add(1, Ordering::Relaxed);
+
+                    let mut result = RocCallResult::default();
+                    unsafe { main(list_addr as *const RocList<i64>, &mut result) };
+
+                    let result: Result<u64, _> = result.into();
+                    assert!(matches!(result, Ok(3)));
+                }
+            });
+        }
+    });
+
+    // with a lost update, the list would be freed early or never become unique again
+    assert!(list.is_unique());
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn poison_fills_new_memory() {
+    use crate::helpers::llvm::{helper, HelperConfig};
+    use roc_gen_llvm::llvm::memory::{MemoryStrategyKind, POISON_BYTE};
+    use roc_gen_llvm::run_roc::RocCallResult;
+
+    // the list has capacity for 16 elements, but only the first one is ever written
+    let src = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : List U8 -> List U8
+        main = \list -> List.reserve list 16 |> List.append 42
+        "#
+    );
+
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+    let config = HelperConfig {
+        memory_strategy: MemoryStrategyKind::Poison,
+        ..HelperConfig::default()
+    };
+
+    let (main_fn_name, errors, lib) = helper(
+        &arena,
+        config,
+        src,
+        &context,
+        roc_load::FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    type Main = unsafe extern "C" fn(*const RocList<u8>, *mut RocCallResult<RocList<u8>>);
+    let main: Main = unsafe { *lib.get::<Main>(main_fn_name.as_bytes()).unwrap() };
+
+    let input = RocList::empty();
+    let mut result = RocCallResult::default();
+    unsafe { main(&input, &mut result) };
+
+    let list = Result::<RocList<u8>, _>::from(result).unwrap();
+    let spare = unsafe { std::slice::from_raw_parts(list.as_ptr(), list.capacity()) };
+
+    assert_eq!(spare[0], 42);
+    assert!(spare[1..].iter().all(|byte| *byte == POISON_BYTE));
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn arena_does_not_update_shared_values_in_place() {
+    use crate::helpers::llvm::{helper, HelperConfig};
+    use roc_gen_llvm::llvm::memory::MemoryStrategyKind;
+    use roc_gen_llvm::run_roc::RocCallResult;
+
+    let src = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : List U8 -> List U8
+        main = \list -> List.set list 0 99
+        "#
+    );
+
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+    let config = HelperConfig {
+        memory_strategy: MemoryStrategyKind::Arena,
+        ..HelperConfig::default()
+    };
+
+    let (main_fn_name, errors, lib) = helper(
+        &arena,
+        config,
+        src,
+        &context,
+        roc_load::FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    type Main = unsafe extern "C" fn(*const RocList<u8>, *mut RocCallResult<RocList<u8>>);
+    let main: Main = unsafe { *lib.get::<Main>(main_fn_name.as_bytes()).unwrap() };
+
+    // `main` owns its argument, and we keep a reference of our own
+    let input = RocList::from_slice(&[1, 2, 3]);
+    let kept = input.clone();
+
+    let mut result = RocCallResult::default();
+    unsafe { main(&input, &mut result) };
+    std::mem::forget(input);
+
+    let list = Result::<RocList<u8>, _>::from(result).unwrap();
+
+    assert_eq!(list.as_slice(), &[99, 2, 3]);
+    assert_eq!(kept.as_slice(), &[1, 2, 3]);
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn crashing_helpers_are_reported() {
+    use inkwell::AddressSpace;
+    use roc_gen_llvm::llvm::symbol_map::{HelperKind, SymbolMap, SymbolOrigin};
+    use roc_gen_llvm::llvm::totality::{crashing_helpers, CrashingHelper};
+
+    // the code gen tests only check that the helpers they build do not crash, so build a module
+    // with helpers that do: `#Attr_#dec_1` crashes in a helper that it calls
+    let context = inkwell::context::Context::create();
+    let module = context.create_module("helpers");
+    let builder = context.create_builder();
+
+    let ptr_type = context.i8_type().ptr_type(AddressSpace::default());
+    let panic_type = context
+        .void_type()
+        .fn_type(&[ptr_type.into(), context.i32_type().into()], false);
+    let helper_type = context.void_type().fn_type(&[ptr_type.into()], false);
+
+    let roc_panic = module.add_function("roc_panic", panic_type, None);
+    let symbol_map = SymbolMap::default();
+
+    let helpers = ["#Attr_#dec_1", "#Attr_#dec_2", "#Attr_#dec_3"].map(|name| {
+        symbol_map.record(name, || SymbolOrigin::Helper {
+            helper: "#dec".to_string(),
+            kind: HelperKind::Refcounting,
+            layout: "Str".to_string(),
+        });
+
+        module.add_function(name, helper_type, None)
+    });
+
+    let [dec_1, dec_2, dec_3] = helpers;
+    let tag = context.i32_type().const_zero();
+
+    builder.position_at_end(context.append_basic_block(dec_1, "entry"));
+    let arg = dec_1.get_first_param().unwrap();
+    builder.build_call(dec_2, &[arg.into()], "").unwrap();
+    builder.build_return(None).unwrap();
+
+    builder.position_at_end(context.append_basic_block(dec_2, "entry"));
+    let arg = dec_2.get_first_param().unwrap();
+    builder
+        .build_call(roc_panic, &[arg.into(), tag.into()], "")
+        .unwrap();
+    builder.build_unreachable().unwrap();
+
+    builder.position_at_end(context.append_basic_block(dec_3, "entry"));
+    builder.build_return(None).unwrap();
+
+    assert_eq!(
+        crashing_helpers(&module, &symbol_map),
+        [
+            CrashingHelper {
+                kind: HelperKind::Refcounting,
+                path: vec![
+                    "#Attr_#dec_1".to_string(),
+                    "#Attr_#dec_2".to_string(),
+                    "roc_panic".to_string(),
+                ],
+            },
+            CrashingHelper {
+                kind: HelperKind::Refcounting,
+                path: vec!["#Attr_#dec_2".to_string(), "roc_panic".to_string()],
+            },
+        ]
+    );
+}
+
+/// Build `src` with its refcounting and equality helpers cached in `dir`, and return the names of
+/// the functions of the module
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn build_with_helper_cache(src: &str, dir: &std::path::Path) -> Vec<String> {
+    use crate::helpers::llvm::{create_llvm_module, HelperConfig};
+    use roc_gen_llvm::llvm::refcounting::RcOutlining;
+
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+    let config = HelperConfig {
+        // keep the helpers around, so the module shows which ones were linked in
+        rc_outlining: RcOutlining::Outline,
+        helper_cache_dir: Some(Box::leak(dir.into())),
+        ..HelperConfig::default()
+    };
+
+    let (_, errors, module) = create_llvm_module(
+        &arena,
+        src,
+        config,
+        &context,
+        target_lexicon::Triple::host().into(),
+        roc_load::FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    Vec::from_iter(
+        module
+            .get_functions()
+            .map(|function| function.get_name().to_str().unwrap().to_string()),
+    )
+}
+
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn is_generated_helper(name: &str) -> bool {
+    ["#inc", "#dec", "#generic_eq", "#list_eq"]
+        .iter()
+        .any(|helper| name.contains(helper))
+}
+
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn is_cached_helper(name: &str) -> bool {
+    name.starts_with("roc_cached_helper.")
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn helper_cache_misses_then_hits() {
+    let src = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : List { name : Str, id : I64 } -> Bool
+        main = \records -> records == List.reverse records
+        "#
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+
+    let cold = build_with_helper_cache(src, dir.path());
+    assert!(cold.iter().any(|name| name.contains("#generic_eq")));
+    assert!(cold.iter().any(|name| name.contains("#list_eq")));
+    assert!(!cold.iter().any(|name| is_cached_helper(name)));
+
+    // every helper comes from the cache now, the equality helpers too
+    let warm = build_with_helper_cache(src, dir.path());
+    assert!(warm.iter().any(|name| is_cached_helper(name)));
+    assert!(
+        !warm.iter().any(|name| is_generated_helper(name)),
+        "these helpers were generated again: {:?}",
+        Vec::from_iter(warm.iter().filter(|name| is_generated_helper(name)))
+    );
+}
+
+#[test]
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+fn helper_cache_misses_after_layout_change() {
+    let src = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main : List { name : Str, id : ID } -> Bool
+        main = \records -> records == List.reverse records
+        "#
+    );
+    let with_layout = |id_type| src.replace("ID", id_type);
+
+    let dir = tempfile::tempdir().unwrap();
+
+    build_with_helper_cache(&with_layout("I64"), dir.path());
+
+    // the helpers of the records, and of the lists of them, are different now
+    let changed = build_with_helper_cache(&with_layout("Str"), dir.path());
+    assert!(changed.iter().any(|name| name.contains("#generic_eq")));
+    assert!(changed.iter().any(|name| name.contains("#list_eq")));
+}
@@ -1814,6 +1814,22 @@ fn with_capacity_concat() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn with_capacity_append_unchecked() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            Str.withCapacity 40
+            |> Str.appendUnchecked "The answer to the ultimate question"
+            |> Str.appendUnchecked " is forty-two"
+            "#
+        ),
+        RocStr::from("The answer to the ultimate question is forty-two"),
+        RocStr
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn str_with_prefix() {
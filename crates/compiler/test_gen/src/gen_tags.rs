@@ -2327,3 +2327,41 @@ fn recursive_tag_id_in_allocation_eq() {
         bool
     );
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn recursive_union_uniform_refcounted_fields() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            Expr : [Neg Expr Str, Not Expr Str, Leaf]
+
+            depth : Expr -> I64
+            depth = \expr ->
+                when expr is
+                    Neg inner _ -> 1 + depth inner
+                    Not inner _ -> 1 + depth inner
+                    Leaf -> 0
+
+            build : I64, Expr -> Expr
+            build = \n, acc ->
+                if n == 0 then
+                    acc
+                else if n % 2 == 0 then
+                    build (n - 1) (Neg acc "a long string that is heap-allocated")
+                else
+                    build (n - 1) (Not acc "another long string that is heap-allocated")
+
+            main =
+                expr = build 10 Leaf
+                copy = expr
+
+                depth expr + depth copy
+            "#
+        ),
+        20,
+        i64
+    );
+}
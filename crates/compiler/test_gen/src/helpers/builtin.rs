@@ -0,0 +1,283 @@
+//! Run one builtin, like `ListConcat` or `StrSplit`, on literal arguments. The call is written
+//! straight into the mono IR of a `main`, so no Roc source is loaded: a test of the code gen in
+//! `build_list.rs` or `build_str.rs` only pays for building one tiny module.
+//!
+//! Arguments and the result are Rust values with the representation of a Roc value, like
+//! `RocList<i64>` for a `List I64`.
+use std::path::PathBuf;
+
+use bumpalo::Bump;
+use roc_build::link::llvm_module_to_dylib;
+use roc_collections::all::{MutMap, MutSet};
+use roc_load::EntryPoint;
+use roc_module::low_level::LowLevel;
+use roc_module::symbol::{IdentIds, IdentIdsByModule, Interns, ModuleId, ModuleIds, Symbol};
+use roc_mono::ir::{
+    Call, CallType, CrashTag, Expr, ListLiteralElement, Literal, Proc, ProcLayout, SelfRecursive,
+    Stmt, UpdateModeId,
+};
+use roc_mono::layout::{
+    Builtin, InLayout, LambdaName, Layout, LayoutInterner, LayoutRepr, Niche, STLayoutInterner,
+};
+use roc_std::{RocList, RocStr};
+use roc_target::Target;
+
+use crate::helpers::llvm::{module_from_procedures, try_run_lib_function, HelperConfig};
+
+/// A Rust value that can be an argument or the result of a builtin
+pub trait BuiltinValue {
+    fn layout<'a>(layout_interner: &mut STLayoutInterner<'a>) -> InLayout<'a>
+    where
+        Self: Sized;
+
+    /// Binds this value in `main`, and returns the symbol it is bound to
+    fn bind<'a>(&self, main: &mut MainBuilder<'a>) -> Symbol;
+}
+
+/// The `main` that calls the builtin, and the values it binds before the call
+pub struct MainBuilder<'a> {
+    arena: &'a Bump,
+    home: ModuleId,
+    ident_ids: IdentIds,
+    layout_interner: STLayoutInterner<'a>,
+    lets: Vec<(Symbol, Expr<'a>, InLayout<'a>)>,
+}
+
+impl<'a> MainBuilder<'a> {
+    fn new_symbol(&mut self) -> Symbol {
+        Symbol::new(self.home, self.ident_ids.gen_unique())
+    }
+
+    fn bind(&mut self, expr: Expr<'a>, layout: InLayout<'a>) -> Symbol {
+        let symbol = self.new_symbol();
+        self.lets.push((symbol, expr, layout));
+
+        symbol
+    }
+
+    fn bind_literal<T: BuiltinValue>(&mut self, literal: Literal<'a>) -> Symbol {
+        let layout = T::layout(&mut self.layout_interner);
+
+        self.bind(Expr::Literal(literal), layout)
+    }
+}
+
+macro_rules! int_values {
+    ($($ty:ty => $layout:expr,)*) => {
+        $(
+            impl BuiltinValue for $ty {
+                fn layout<'a>(_: &mut STLayoutInterner<'a>) -> InLayout<'a> {
+                    $layout
+                }
+
+                fn bind<'a>(&self, main: &mut MainBuilder<'a>) -> Symbol {
+                    main.bind_literal::<Self>(Literal::Int((*self as i128).to_ne_bytes()))
+                }
+            }
+        )*
+    };
+}
+
+int_values! {
+    u8 => Layout::U8,
+    u16 => Layout::U16,
+    u32 => Layout::U32,
+    u64 => Layout::U64,
+    i8 => Layout::I8,
+    i16 => Layout::I16,
+    i32 => Layout::I32,
+    i64 => Layout::I64,
+}
+
+impl BuiltinValue for f32 {
+    fn layout<'a>(_: &mut STLayoutInterner<'a>) -> InLayout<'a> {
+        Layout::F32
+    }
+
+    fn bind<'a>(&self, main: &mut MainBuilder<'a>) -> Symbol {
+        main.bind_literal::<Self>(Literal::Float(*self as f64))
+    }
+}
+
+impl BuiltinValue for f64 {
+    fn layout<'a>(_: &mut STLayoutInterner<'a>) -> InLayout<'a> {
+        Layout::F64
+    }
+
+    fn bind<'a>(&self, main: &mut MainBuilder<'a>) -> Symbol {
+        main.bind_literal::<Self>(Literal::Float(*self))
+    }
+}
+
+impl BuiltinValue for bool {
+    fn layout<'a>(_: &mut STLayoutInterner<'a>) -> InLayout<'a> {
+        Layout::BOOL
+    }
+
+    fn bind<'a>(&self, main: &mut MainBuilder<'a>) -> Symbol {
+        main.bind_literal::<Self>(Literal::Bool(*self))
+    }
+}
+
+impl BuiltinValue for RocStr {
+    fn layout<'a>(_: &mut STLayoutInterner<'a>) -> InLayout<'a> {
+        Layout::STR
+    }
+
+    fn bind<'a>(&self, main: &mut MainBuilder<'a>) -> Symbol {
+        let string = main.arena.alloc_str(self.as_str());
+
+        main.bind_literal::<Self>(Literal::Str(string))
+    }
+}
+
+impl<T: BuiltinValue> BuiltinValue for RocList<T> {
+    fn layout<'a>(layout_interner: &mut STLayoutInterner<'a>) -> InLayout<'a> {
+        let element_layout = T::layout(layout_interner);
+
+        layout_interner
+            .insert_direct_no_semantic(LayoutRepr::Builtin(Builtin::List(element_layout)))
+    }
+
+    fn bind<'a>(&self, main: &mut MainBuilder<'a>) -> Symbol {
+        let elems = self
+            .iter()
+            .map(|element| ListLiteralElement::Symbol(element.bind(main)))
+            .collect::<Vec<_>>();
+
+        let expr = Expr::Array {
+            elem_layout: T::layout(&mut main.layout_interner),
+            elems: main.arena.alloc_slice_copy(&elems),
+        };
+        let layout = Self::layout(&mut main.layout_interner);
+
+        main.bind(expr, layout)
+    }
+}
+
+/// Call `op` with `arguments` in a `main` of its own, and compare what it returns to `expected`
+pub fn builtin_evals_to<T>(op: LowLevel, arguments: &[&dyn BuiltinValue], expected: T)
+where
+    T: BuiltinValue + PartialEq + std::fmt::Debug,
+{
+    let arena = Bump::new();
+    let context = inkwell::context::Context::create();
+    let target: Target = target_lexicon::Triple::host().into();
+
+    let mut module_ids = ModuleIds::default();
+    let home = module_ids.get_or_insert(&"Test".into());
+
+    let mut main = MainBuilder {
+        arena: &arena,
+        home,
+        ident_ids: IdentIds::default(),
+        layout_interner: STLayoutInterner::with_capacity(4, target),
+        lets: Vec::new(),
+    };
+
+    let arguments: Vec<_> = arguments.iter().map(|arg| arg.bind(&mut main)).collect();
+    let ret_layout = T::layout(&mut main.layout_interner);
+    let result = main.new_symbol();
+    let main_symbol = Symbol::new(home, main.ident_ids.add_str("main"));
+
+    let MainBuilder {
+        ident_ids,
+        layout_interner,
+        lets,
+        ..
+    } = main;
+
+    let call = Expr::Call(Call {
+        call_type: CallType::LowLevel {
+            op,
+            update_mode: UpdateModeId::BACKEND_DUMMY,
+        },
+        arguments: arena.alloc_slice_copy(&arguments),
+    });
+
+    let mut body = Stmt::Let(result, call, ret_layout, arena.alloc(Stmt::Ret(result)));
+
+    for (symbol, expr, layout) in lets.into_iter().rev() {
+        body = Stmt::Let(symbol, expr, layout, arena.alloc(body));
+    }
+
+    let proc_layout = ProcLayout {
+        arguments: &[],
+        result: ret_layout,
+        niche: Niche::NONE,
+    };
+    let proc = Proc {
+        name: LambdaName::no_niche(main_symbol),
+        args: &[],
+        body,
+        closure_data_layout: None,
+        ret_layout,
+        is_self_recursive: SelfRecursive::NotSelfRecursive,
+        is_erased: false,
+    };
+
+    let mut procedures = MutMap::default();
+    procedures.insert((main_symbol, proc_layout), proc);
+
+    // the frontend does this for every specialized proc
+    roc_mono::inc_dec::insert_inc_dec_operations(
+        &arena,
+        &layout_interner,
+        &mut procedures,
+        &Default::default(),
+    );
+
+    let mut all_ident_ids = IdentIdsByModule::default();
+    all_ident_ids.insert(home, ident_ids);
+
+    let interns = Interns {
+        module_ids,
+        all_ident_ids,
+    };
+
+    let config = HelperConfig::default();
+
+    let entry_point = EntryPoint::Executable {
+        exposed_to_host: arena.alloc([("main", main_symbol, proc_layout)]),
+        platform_path: PathBuf::new(),
+    };
+
+    let (main_fn_name, module) = module_from_procedures(
+        &arena,
+        &context,
+        config,
+        target,
+        interns,
+        &layout_interner,
+        procedures,
+        Default::default(),
+        entry_point,
+        MutSet::default(),
+    );
+
+    module.strip_debug_info();
+
+    let lib = llvm_module_to_dylib(module, target, config.opt_level)
+        .expect("Error loading compiled dylib for test");
+
+    match try_run_lib_function::<T>(main_fn_name, &lib) {
+        Ok(given) => assert_eq!(given, expected, "{op:?} returned the wrong value"),
+        Err((msg, CrashTag::Roc)) => panic!(r#"Roc failed with message: "{msg}""#),
+        Err((msg, CrashTag::User)) => panic!(r#"User crash with message: "{msg}""#),
+    }
+}
+
+/// `assert_builtin_evals_to!(LowLevel::ListConcat, [list1, list2], expected)`
+#[allow(unused_macros)]
+macro_rules! assert_builtin_evals_to {
+    ($op:expr, [$($argument:expr),* $(,)?], $expected:expr) => {
+        $crate::helpers::builtin::builtin_evals_to(
+            $op,
+            &[$(&$argument as &dyn $crate::helpers::builtin::BuiltinValue),*],
+            $expected,
+        )
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use assert_builtin_evals_to;
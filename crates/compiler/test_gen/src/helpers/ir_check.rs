@@ -0,0 +1,262 @@
+//! FileCheck-style assertions on the LLVM IR of a test, to keep optimizations from regressing
+//! silently. A test names one function of its app and lists what its IR must contain:
+//!
+//! ```text
+//! CHECK:      matches a line after the previous match
+//! CHECK-NEXT: matches the line right after the previous match
+//! CHECK-NOT:  matches no line between the previous match and the next one, or the end
+//! ```
+//!
+//! Patterns are literal text, except that `{{...}}` is a regex. Lines of the checks that do not
+//! start with `CHECK` are comments.
+//!
+//! Refcounting helpers are never inlined in these builds (unless a test asks for it with
+//! [llvm_ir_with_rc_outlining]), so every refcount update is a call to a helper like
+//! `#Attr_#inc_3` or `#Attr_#dec_3`.
+use regex::Regex;
+use roc_gen_llvm::llvm::refcounting::RcOutlining;
+use roc_load::FunctionKind;
+use roc_module::ident::ModuleName;
+use roc_mono::ir::OptLevel;
+
+use crate::helpers::llvm::{create_llvm_module, HelperConfig};
+
+/// The IR of the module of a test app, after optimizing it at `opt_level`. Above
+/// [OptLevel::Normal], most functions are inlined into `$Test.main`.
+pub fn llvm_ir(src: &str, opt_level: OptLevel) -> String {
+    llvm_ir_with_rc_outlining(src, opt_level, RcOutlining::Outline)
+}
+
+/// Like [llvm_ir], but refcounting helpers are inlined according to `rc_outlining`
+pub fn llvm_ir_with_rc_outlining(
+    src: &str,
+    opt_level: OptLevel,
+    rc_outlining: RcOutlining,
+) -> String {
+    let arena = bumpalo::Bump::new();
+    let context = inkwell::context::Context::create();
+    let target = target_lexicon::Triple::host().into();
+
+    let config = HelperConfig {
+        opt_level,
+        rc_outlining,
+        ..HelperConfig::default()
+    };
+
+    let (_, errors, module) = create_llvm_module(
+        &arena,
+        src,
+        config,
+        &context,
+        target,
+        FunctionKind::LambdaSet,
+    );
+    assert!(errors.is_empty(), "Encountered errors:\n{errors}");
+
+    module.strip_debug_info();
+    module.print_to_string().to_string()
+}
+
+/// The definition of a function in `module_ir`. `name` is the name of a top-level definition of
+/// the app, like `sum`, or the full name of a function, like `$Test.main`.
+pub fn function_ir<'a>(module_ir: &'a str, name: &str) -> &'a str {
+    let app_prefix = format!("{}_{name}_", ModuleName::APP);
+
+    let mut defined = Vec::new();
+    let mut found = Vec::new();
+    let mut offset = 0;
+
+    for line in module_ir.split_inclusive('\n') {
+        if let Some(fn_name) = line.strip_prefix("define ").and_then(defined_name) {
+            if fn_name == name || fn_name.starts_with(&app_prefix) {
+                let end = module_ir[offset..]
+                    .find("\n}\n")
+                    .map_or(module_ir.len(), |end| offset + end + 3);
+
+                found.push(&module_ir[offset..end]);
+            }
+
+            defined.push(fn_name);
+        }
+
+        offset += line.len();
+    }
+
+    match found.as_slice() {
+        [function] => function,
+        [] => panic!(
+            "There is no function `{name}`; these are defined:\n\n{}",
+            defined.join("\n")
+        ),
+        _ => panic!("`{name}` has several specializations; check one of them by its full name"),
+    }
+}
+
+/// The name in `@name(` or `@"name"(`
+fn defined_name(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once('@')?;
+
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split_once('"').map(|(name, _)| name),
+        None => rest.split_once('(').map(|(name, _)| name),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    Check,
+    Next,
+    Not,
+}
+
+struct Check<'a> {
+    directive: Directive,
+    /// As written in the checks, for error messages
+    source: &'a str,
+    pattern: Regex,
+}
+
+fn parse_checks(checks: &str) -> Vec<Check<'_>> {
+    let mut parsed = Vec::new();
+
+    for source in checks.lines().map(str::trim) {
+        if !source.starts_with("CHECK") {
+            continue;
+        }
+
+        let (directive, pattern) = if let Some(pattern) = source.strip_prefix("CHECK:") {
+            (Directive::Check, pattern)
+        } else if let Some(pattern) = source.strip_prefix("CHECK-NEXT:") {
+            (Directive::Next, pattern)
+        } else if let Some(pattern) = source.strip_prefix("CHECK-NOT:") {
+            (Directive::Not, pattern)
+        } else {
+            panic!("Unknown directive in `{source}`; use CHECK:, CHECK-NEXT: or CHECK-NOT:")
+        };
+
+        parsed.push(Check {
+            directive,
+            source,
+            pattern: pattern_to_regex(pattern.trim()),
+        });
+    }
+
+    parsed
+}
+
+/// Literal text, except for regexes in `{{...}}`
+fn pattern_to_regex(pattern: &str) -> Regex {
+    let mut regex = String::new();
+    let mut rest = pattern;
+
+    while let Some((literal, after)) = rest.split_once("{{") {
+        let (inner, after) = after
+            .split_once("}}")
+            .unwrap_or_else(|| panic!("Unclosed `{{{{` in `{pattern}`"));
+
+        regex.push_str(&regex::escape(literal));
+        regex.push_str(&format!("(?:{inner})"));
+        rest = after;
+    }
+
+    regex.push_str(&regex::escape(rest));
+
+    Regex::new(&regex).unwrap_or_else(|error| panic!("Invalid pattern `{pattern}`: {error}"))
+}
+
+/// Panics with an explanation when `ir` does not satisfy `checks`
+pub fn check_ir(ir: &str, checks: &str) {
+    let lines: Vec<&str> = ir.lines().collect();
+
+    let fail = |check: &Check, from: usize, problem: &str| -> ! {
+        let listing: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .skip(from)
+            .map(|(index, line)| format!("{:>4} {line}", index + 1))
+            .collect();
+
+        panic!(
+            "`{}` {problem}, searching from line {}:\n\n{}",
+            check.source,
+            from + 1,
+            listing.join("\n")
+        )
+    };
+
+    let check_nots = |nots: &[&Check], range: std::ops::Range<usize>| {
+        for not in nots {
+            if let Some(index) = range
+                .clone()
+                .find(|index| not.pattern.is_match(lines[*index]))
+            {
+                fail(not, range.start, &format!("matched line {}", index + 1));
+            }
+        }
+    };
+
+    // the first line that a later match may be on
+    let mut cursor = 0;
+    let mut has_matched = false;
+    let mut nots = Vec::new();
+
+    for check in parse_checks(checks).iter() {
+        match check.directive {
+            Directive::Not => nots.push(check),
+            Directive::Check => {
+                let index = (cursor..lines.len())
+                    .find(|index| check.pattern.is_match(lines[*index]))
+                    .unwrap_or_else(|| fail(check, cursor, "matched no line"));
+
+                check_nots(&nots, cursor..index);
+                nots.clear();
+
+                cursor = index + 1;
+                has_matched = true;
+            }
+            Directive::Next => {
+                if !has_matched {
+                    fail(check, cursor, "has no previous match to follow");
+                }
+
+                if !nots.is_empty() {
+                    fail(nots[0], cursor, "cannot come right before a CHECK-NEXT");
+                }
+
+                match lines.get(cursor) {
+                    Some(line) if check.pattern.is_match(line) => cursor += 1,
+                    _ => fail(check, cursor, "did not match the next line"),
+                }
+            }
+        }
+    }
+
+    check_nots(&nots, cursor..lines.len());
+}
+
+/// Compile `src`, and check the IR of its function called `name`, see [function_ir]
+pub fn ir_matches(src: &str, name: &str, checks: &str, opt_level: OptLevel) {
+    let module_ir = llvm_ir(src, opt_level);
+
+    check_ir(function_ir(&module_ir, name), checks);
+}
+
+/// `assert_ir_matches!(src, "function", checks)`, optionally followed by an [OptLevel]
+#[allow(unused_macros)]
+macro_rules! assert_ir_matches {
+    ($src:expr, $function:expr, $checks:expr) => {
+        $crate::helpers::ir_check::assert_ir_matches!(
+            $src,
+            $function,
+            $checks,
+            roc_mono::ir::OptLevel::Normal
+        )
+    };
+
+    ($src:expr, $function:expr, $checks:expr, $opt_level:expr) => {
+        $crate::helpers::ir_check::ir_matches($src, $function, $checks, $opt_level)
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use assert_ir_matches;
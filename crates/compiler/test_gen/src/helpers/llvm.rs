@@ -1,18 +1,29 @@
 use std::mem::MaybeUninit;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use inkwell::module::Module;
 use libloading::Library;
 use roc_build::link::llvm_module_to_dylib;
-use roc_collections::all::MutSet;
+use roc_collections::all::{MutMap, MutSet};
 use roc_command_utils::zig;
 use roc_gen_llvm::llvm::externs::add_default_roc_externs;
+use roc_gen_llvm::llvm::helper_cache::HelperCache;
+use roc_gen_llvm::llvm::memory::{MemoryCounters, MemoryStrategyKind, MEMORY_COUNTERS};
+use roc_gen_llvm::llvm::refcounting::RcOutlining;
+use roc_gen_llvm::llvm::sendable::SendableLayouts;
+use roc_gen_llvm::llvm::symbol_map::SymbolMap;
+use roc_gen_llvm::llvm::totality::crashing_helpers;
 use roc_gen_llvm::{llvm::build::LlvmBackendMode, run_roc::RocCallResult};
 use roc_load::{
     EntryPoint, ExecutionMode, FunctionKind, LoadConfig, LoadMonomorphizedError, Threading,
 };
-use roc_mono::ir::{CrashTag, OptLevel, SingleEntryPoint};
+use roc_module::symbol::{Interns, Symbol};
+use roc_mono::ir::{
+    CrashTag, GlueLayouts, HostExposedLambdaSets, OptLevel, Proc, ProcLayout, SingleEntryPoint,
+};
+use roc_mono::layout::STLayoutInterner;
 use roc_packaging::cache::RocCacheDir;
 use roc_region::all::LineInfo;
 use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE};
@@ -27,6 +38,17 @@ use roc_gen_wasm::wasm32_result::Wasm32Result;
 #[cfg(feature = "gen-llvm-wasm")]
 const TEST_WRAPPER_NAME: &str = "test_wrapper";
 
+/// The C-ABI wrapper of `main` that a host calls, built in [LlvmBackendMode::BinaryGlue]
+#[allow(dead_code)]
+pub const HOST_ENTRY_POINT: &str = "roc__main_1_exposed";
+
+/// A target for each pointer width, see [check_other_ptr_widths]
+const PTR_WIDTH_TARGETS: [Target; 2] = [Target::LinuxX32, Target::LinuxX64];
+
+/// The tasks of List.mapParallel run in a shuffled order in tests, so that results which end up in
+/// the wrong place in the output list are caught
+const SHUFFLE_PARALLEL_SEED: u64 = 0x5eed;
+
 #[allow(dead_code)]
 pub const OPT_LEVEL: OptLevel = if cfg!(debug_assertions) {
     OptLevel::Normal
@@ -47,7 +69,7 @@ fn promote_expr_to_module(src: &str) -> String {
     buffer
 }
 
-fn create_llvm_module<'a>(
+pub(crate) fn create_llvm_module<'a>(
     arena: &'a bumpalo::Bump,
     src: &str,
     config: HelperConfig,
@@ -181,6 +203,53 @@ fn create_llvm_module<'a>(
         }
     }
 
+    let exposed_to_host = match config.mode {
+        // build the wrappers that a host calls, so tests can check their ABI
+        LlvmBackendMode::BinaryGlue => loaded
+            .exposed_to_host
+            .top_level_values
+            .keys()
+            .copied()
+            .collect(),
+        // important! we don't want any procedures to get the C calling convention
+        _ => MutSet::default(),
+    };
+
+    let (main_fn_name, module) = module_from_procedures(
+        arena,
+        context,
+        config,
+        target,
+        interns,
+        &layout_interner,
+        procedures,
+        host_exposed_lambda_sets,
+        loaded.entry_point,
+        exposed_to_host,
+    );
+
+    let delayed_errors = if config.ignore_problems {
+        String::new()
+    } else {
+        delayed_errors.join("\n")
+    };
+    (main_fn_name, delayed_errors, module)
+}
+
+/// Generate the LLVM module of mono IR, which usually comes from loading a test, and verify it
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn module_from_procedures<'a>(
+    arena: &'a bumpalo::Bump,
+    context: &'a inkwell::context::Context,
+    config: HelperConfig,
+    target: Target,
+    interns: Interns,
+    layout_interner: &STLayoutInterner<'a>,
+    procedures: MutMap<(Symbol, ProcLayout<'a>), Proc<'a>>,
+    host_exposed_lambda_sets: HostExposedLambdaSets<'a>,
+    entry_point: EntryPoint<'a>,
+    exposed_to_host: MutSet<Symbol>,
+) -> (&'static str, &'a Module<'a>) {
     let builder = context.create_builder();
     let module = roc_gen_llvm::llvm::build::module_from_builtins(target, context, "app");
 
@@ -217,6 +286,26 @@ fn create_llvm_module<'a>(
         }
     }
 
+    let mut sendable_layouts = match &entry_point {
+        EntryPoint::Executable {
+            exposed_to_host, ..
+        } if config.atomic_refcounts => SendableLayouts::from_host_interface(
+            layout_interner,
+            exposed_to_host.iter().map(|(_, _, layout)| *layout),
+            procedures.values(),
+        ),
+        _ => SendableLayouts::none(),
+    };
+
+    sendable_layouts.insert_parallel_maps(layout_interner, procedures.values());
+
+    // to check that the helpers cannot crash
+    let symbol_map = SymbolMap::default();
+
+    let helper_cache = config
+        .helper_cache_dir
+        .and_then(|dir| HelperCache::new(dir.to_path_buf(), target, config.opt_level));
+
     // Compile and add all the Procs before adding main
     let env = roc_gen_llvm::llvm::build::Env {
         arena,
@@ -228,15 +317,24 @@ fn create_llvm_module<'a>(
         module,
         target,
         mode: config.mode,
-        // important! we don't want any procedures to get the C calling convention
-        exposed_to_host: MutSet::default(),
+        exposed_to_host,
+        helper_cache: helper_cache.as_ref(),
+        rc_outlining: config.rc_outlining,
+        memory_strategy: config.memory_strategy.strategy(),
+        sendable_layouts,
+        profiling: false,
+        crash_dumps: false,
+        memory_api: config.memory_api,
+        shuffle_parallel_tasks: Some(SHUFFLE_PARALLEL_SEED),
+        symbol_map: Some(&symbol_map),
+        basic_types: Default::default(),
     };
 
     // Add roc_alloc, roc_realloc, and roc_dealloc, since the repl has no
     // platform to provide them.
     add_default_roc_externs(&env);
 
-    let entry_point = match loaded.entry_point {
+    let entry_point = match entry_point {
         EntryPoint::Executable {
             exposed_to_host,
             platform_path: _,
@@ -258,18 +356,36 @@ fn create_llvm_module<'a>(
     let (main_fn_name, main_fn) = match config.mode {
         LlvmBackendMode::Binary => unreachable!(),
         LlvmBackendMode::BinaryDev => unreachable!(),
-        LlvmBackendMode::BinaryGlue => unreachable!(),
+        LlvmBackendMode::BinaryGlue => {
+            roc_gen_llvm::llvm::build::build_procedures(
+                &env,
+                layout_interner,
+                config.opt_level,
+                procedures,
+                host_exposed_lambda_sets,
+                roc_mono::ir::EntryPoint::Program(arena.alloc([entry_point])),
+                None,
+                &GlueLayouts::default(),
+            );
+
+            let main_fn = env
+                .module
+                .get_function(HOST_ENTRY_POINT)
+                .expect("the app should provide `main`");
+
+            (HOST_ENTRY_POINT, main_fn)
+        }
         LlvmBackendMode::CliTest => unreachable!(),
         LlvmBackendMode::WasmGenTest => roc_gen_llvm::llvm::build::build_wasm_test_wrapper(
             &env,
-            &layout_interner,
+            layout_interner,
             config.opt_level,
             procedures,
             entry_point,
         ),
         LlvmBackendMode::GenTest => roc_gen_llvm::llvm::build::build_procedures_return_main(
             &env,
-            &layout_interner,
+            layout_interner,
             config.opt_level,
             procedures,
             host_exposed_lambda_sets,
@@ -277,7 +393,9 @@ fn create_llvm_module<'a>(
         ),
     };
 
-    env.dibuilder.finalize();
+    if let Some(helper_cache) = env.helper_cache {
+        helper_cache.finish(&env);
+    }
 
     // Uncomment this to see the module's un-optimized LLVM instruction output:
     // env.module.print_to_stderr();
@@ -296,6 +414,15 @@ fn create_llvm_module<'a>(
         panic_bad_llvm(main_fn_name);
     }
 
+    roc_gen_llvm::llvm::refcounting::remove_dead_helpers(env.module);
+
+    let crashing = crashing_helpers(env.module, &symbol_map);
+    assert!(
+        crashing.is_empty(),
+        "Refcounting and equality helpers must not crash, but:\n\n{}",
+        Vec::from_iter(crashing.iter().map(|helper| helper.to_string())).join("\n")
+    );
+
     module_pass.run_on(env.module);
 
     // Verify the module
@@ -310,12 +437,7 @@ fn create_llvm_module<'a>(
     // Uncomment this to see the module's optimized LLVM instruction output:
     // env.module.print_to_stderr();
 
-    let delayed_errors = if config.ignore_problems {
-        String::new()
-    } else {
-        delayed_errors.join("\n")
-    };
-    (main_fn_name, delayed_errors, env.module)
+    (main_fn_name, env.module)
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -324,6 +446,32 @@ pub struct HelperConfig {
     pub ignore_problems: bool,
     pub emit_debug_info: bool,
     pub opt_level: OptLevel,
+    pub memory_strategy: MemoryStrategyKind,
+    /// Update the refcounts of values that cross the host boundary atomically
+    pub atomic_refcounts: bool,
+    /// When to inline refcounting helpers at their call sites
+    pub rc_outlining: RcOutlining,
+    /// Cache the refcounting and equality helpers in this directory
+    pub helper_cache_dir: Option<&'static Path>,
+    /// Expose the memory API of the values that cross the host boundary
+    pub memory_api: bool,
+}
+
+impl Default for HelperConfig {
+    /// The config of most tests; tests that need something else override only those fields
+    fn default() -> Self {
+        Self {
+            mode: LlvmBackendMode::GenTest,
+            ignore_problems: false,
+            emit_debug_info: false,
+            opt_level: OPT_LEVEL,
+            memory_strategy: MemoryStrategyKind::default(),
+            atomic_refcounts: false,
+            rc_outlining: RcOutlining::default(),
+            helper_cache_dir: None,
+            memory_api: false,
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -353,6 +501,35 @@ pub fn helper<'a>(
     (main_fn_name, delayed_errors, lib)
 }
 
+/// With ROC_CHECK_ALL_PTR_WIDTHS, also build the module of a test for the pointer widths that it
+/// does not run with. Building verifies the module, which catches code gen that assumes the width
+/// of the host, like an `i64` where a pointer-sized integer belongs. The header in front of
+/// refcounted data depends on the pointer width too, so this covers its layout as well.
+#[allow(dead_code)]
+fn check_other_ptr_widths(
+    src: &str,
+    config: HelperConfig,
+    run_target: Target,
+    function_kind: FunctionKind,
+) {
+    use roc_debug_flags::{dbg_set, ROC_CHECK_ALL_PTR_WIDTHS};
+
+    if !dbg_set!(ROC_CHECK_ALL_PTR_WIDTHS) {
+        return;
+    }
+
+    for target in PTR_WIDTH_TARGETS {
+        if target.ptr_width() == run_target.ptr_width() {
+            continue;
+        }
+
+        let arena = bumpalo::Bump::new();
+        let context = inkwell::context::Context::create();
+
+        create_llvm_module(&arena, src, config, &context, target, function_kind);
+    }
+}
+
 #[allow(dead_code)]
 fn write_final_wasm() -> bool {
     #[allow(unused_imports)]
@@ -481,13 +658,14 @@ where
 
     let config = HelperConfig {
         mode: LlvmBackendMode::WasmGenTest,
-        emit_debug_info: false,
         ignore_problems,
-        opt_level: OPT_LEVEL,
+        ..HelperConfig::default()
     };
 
     let wasm_bytes = compile_to_wasm_bytes(&arena, config, src, &context, function_kind);
 
+    check_other_ptr_widths(src, config, Target::Wasm32, function_kind);
+
     crate::helpers::wasm::run_wasm_test_bytes::<T>(TEST_WRAPPER_NAME, wasm_bytes)
 }
 
@@ -540,6 +718,71 @@ pub fn try_run_lib_function<T>(
     }
 }
 
+/// Wall time and memory traffic of calling a Roc function a number of times
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct BenchReport {
+    pub iterations: u32,
+    pub elapsed: Duration,
+    /// Totals over all iterations
+    pub counters: MemoryCounters,
+}
+
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let per_iteration = |total: u64| total as f64 / self.iterations as f64;
+        let counters = &self.counters;
+
+        write!(
+            f,
+            "{:?} per iteration, {} allocations, {} frees, {} increments, {} decrements",
+            self.elapsed / self.iterations,
+            per_iteration(counters.allocations),
+            per_iteration(counters.deallocations),
+            per_iteration(counters.increments),
+            per_iteration(counters.decrements),
+        )
+    }
+}
+
+/// Run `call` (which calls into `lib`) `iterations` times. `lib` must be built with
+/// [MemoryStrategyKind::Counting] to see its memory traffic; otherwise all counters are zero.
+#[allow(dead_code)]
+pub fn bench_lib_function(lib: &Library, iterations: u32, mut call: impl FnMut()) -> BenchReport {
+    let before = memory_counters(lib);
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        call();
+    }
+
+    let elapsed = start.elapsed();
+    let after = memory_counters(lib);
+
+    BenchReport {
+        iterations,
+        elapsed,
+        counters: MemoryCounters {
+            allocations: after.allocations - before.allocations,
+            deallocations: after.deallocations - before.deallocations,
+            increments: after.increments - before.increments,
+            decrements: after.decrements - before.decrements,
+        },
+    }
+}
+
+/// The counters of a library built with [MemoryStrategyKind::Counting]
+#[allow(dead_code)]
+pub fn memory_counters(lib: &Library) -> MemoryCounters {
+    unsafe {
+        match lib.get::<*const MemoryCounters>(MEMORY_COUNTERS.as_bytes()) {
+            Ok(counters) => **counters,
+            // the program never counted anything, or was built with another strategy
+            Err(_) => MemoryCounters::default(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 // only used in tests
 pub(crate) fn llvm_evals_to<T, U, F>(
@@ -559,15 +802,16 @@ pub(crate) fn llvm_evals_to<T, U, F>(
     let context = Context::create();
 
     let config = crate::helpers::llvm::HelperConfig {
-        mode: LlvmBackendMode::GenTest,
-        emit_debug_info: false,
         ignore_problems,
-        opt_level: crate::helpers::llvm::OPT_LEVEL,
+        ..crate::helpers::llvm::HelperConfig::default()
     };
 
     let (main_fn_name, errors, lib) =
         crate::helpers::llvm::helper(&arena, config, src, &context, function_kind);
 
+    let host = target_lexicon::Triple::host().into();
+    check_other_ptr_widths(src, config, host, function_kind);
+
     let result = crate::helpers::llvm::try_run_lib_function::<T>(main_fn_name, &lib);
 
     match result {
@@ -2,9 +2,14 @@ extern crate bumpalo;
 
 pub mod platform_functions;
 
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+pub mod builtin;
+
 #[cfg(feature = "gen-dev")]
 pub mod dev;
 pub mod from_wasm32_memory;
+#[cfg(all(feature = "gen-llvm", not(feature = "gen-llvm-wasm")))]
+pub mod ir_check;
 #[cfg(feature = "gen-llvm")]
 pub mod llvm;
 #[cfg(any(feature = "gen-wasm", feature = "gen-llvm-wasm"))]
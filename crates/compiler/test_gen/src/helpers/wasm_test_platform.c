@@ -135,6 +135,13 @@ void roc_panic(void* msg, unsigned int panic_tag)
 // TODO: add a way to send dbg to rust.
 void roc_debug(void* loc, void* msg) {}
 
+// wasm has no threads, so List.mapParallel runs its tasks in order
+void roc_parallel_for(void *context, size_t task_count, void (*task)(void *, size_t))
+{
+    for (size_t i = 0; i < task_count; ++i)
+        task(context, i);
+}
+
 //--------------------------
 
 void *roc_memset(void *str, int c, size_t n)
@@ -5,10 +5,13 @@
 #![allow(clippy::float_cmp)]
 
 pub mod gen_abilities;
+pub mod gen_builtin_calls;
 pub mod gen_compare;
 pub mod gen_definitions;
 pub mod gen_dict;
 pub mod gen_erased;
+pub mod gen_host_abi;
+pub mod gen_ir_checks;
 pub mod gen_list;
 pub mod gen_num;
 pub mod gen_panic;
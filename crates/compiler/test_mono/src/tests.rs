@@ -1580,6 +1580,23 @@ fn list_map_closure_owns() {
     )
 }
 
+#[mono_test]
+fn list_map_closure_borrows_captures() {
+    // the closure environment is only read by the closure, so `addAffixes` passes its arguments
+    // along without an inc, and the closure reads the captures without an inc or dec
+    indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        addAffixes : List Str, Str, Str -> List Str
+        addAffixes = \list, separator, suffix ->
+            List.map list \string -> Str.concat (Str.concat string separator) suffix
+
+        main = addAffixes ["a", "b"] ": " "!"
+        "#
+    )
+}
+
 #[mono_test]
 fn list_sort_asc() {
     indoc!(
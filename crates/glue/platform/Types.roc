@@ -1,4 +1,4 @@
-module [Types, shape, size, alignment, target, walkShapes, entryPoints]
+module [Types, shape, size, alignment, target, walkShapes, entryPoints, isOpaque]
 
 import Shape exposing [Shape]
 import TypeId exposing [TypeId, typeIDfromU64, typeIDtoU64]
@@ -24,6 +24,11 @@ Types := {
 
     ## Names and types of the entry points of the program (e.g. mainForHost)
     entrypoints : List Tuple1,
+
+    ## Opaque types. Their values should only cross the host boundary as handles that the host
+    ## manages through the memory API of the app, so that the platform's ABI does not depend on
+    ## how the app represents them.
+    opaques : List TypeId,
     target : Target,
 }
     implements [Inspect, Encoding]
@@ -34,12 +39,21 @@ target = \@Types types -> types.target
 entryPoints : Types -> List Tuple1
 entryPoints = \@Types { entrypoints } -> entrypoints
 
+## Walk the shapes of all types. The shape of an opaque type is the shape of the type it wraps,
+## so opaque types are skipped here: their shape was already visited under that type's id.
 walkShapes : Types, state, (state, Shape, TypeId -> state) -> state
-walkShapes = \@Types { types: shapes }, originalState, update ->
+walkShapes = \@Types { types: shapes, opaques }, originalState, update ->
     List.walkWithIndex shapes originalState \state, elem, index ->
         id = typeIDfromU64 index
 
-        update state elem id
+        if List.contains opaques id then
+            state
+        else
+            update state elem id
+
+isOpaque : Types, TypeId -> Bool
+isOpaque = \@Types { opaques }, id ->
+    List.contains opaques id
 
 shape : Types, TypeId -> Shape
 shape = \@Types types, id ->
@@ -0,0 +1,487 @@
+app [makeGlue] { pf: platform "../platform/main.roc" }
+
+import pf.Types exposing [Types]
+import pf.Shape exposing [Shape, RocFn]
+import pf.File exposing [File]
+import pf.TypeId exposing [TypeId]
+
+## Generates a C++17 header with RAII wrappers for the values that cross the host boundary.
+##
+## The wrappers are built on the memory API of the compiled app: `roc__retain_<name>`,
+## `roc__release_<name>` and `roc__deepcopy_<name>`. Copying a wrapper retains the value,
+## destroying it releases the value. The wrappers store the value as raw bytes; they do not
+## describe its fields.
+##
+## Values of opaque types are wrapped in a `roc::Handle` instead, which does not bake the size
+## or alignment of the value into the header: it asks the app through `roc__sizeof_<name>` and
+## `roc__alignof_<name>`, so the app can change how it represents an opaque type without
+## breaking hosts compiled against an older header.
+makeGlue : List Types -> Result (List File) Str
+makeGlue = \typesByArch ->
+    archSections =
+        List.walkWithIndex typesByArch "" \buf, types, index ->
+            directive = if index == 0 then "#if" else "#elif"
+            arch = (Types.target types).architecture
+
+            """
+            $(buf)$(directive) $(archCondition arch)
+
+            $(generateArch types)
+
+            """
+
+    content =
+        """
+        $(fileHeader)
+        $(archSections)#else
+        #error "roc_app.hpp was not generated for this architecture"
+        #endif
+
+        """
+
+    Ok [{ name: "roc_app.hpp", content }]
+
+generateArch : Types -> Str
+generateArch = \types ->
+    lambdaSets =
+        Types.walkShapes types { buf: "", seen: Set.empty {} } \state, shape, _id ->
+            when shape is
+                Function rocFn if !rocFn.isToplevel && !(Set.contains state.seen rocFn.externName) ->
+                    {
+                        buf: generateLambdaSet state.buf types rocFn,
+                        seen: Set.insert state.seen rocFn.externName,
+                    }
+
+                _ ->
+                    state
+
+    List.walk (Types.entryPoints types) lambdaSets.buf \buf, T name id ->
+        generateEntryPoint buf types name id
+
+generateEntryPoint : Str, Types, Str, TypeId -> Str
+generateEntryPoint = \buf, types, name, id ->
+    apiName = "$(name)_1_exposed"
+    className = capitalize name
+
+    { args, ret } =
+        when Types.shape types id is
+            Function rocFn -> { args: rocFn.args, ret: rocFn.ret }
+            _ -> { args: [], ret: id }
+
+    buf
+    |> generateArgumentValues types apiName className args
+    |> generateValue types "$(apiName)_result" "$(className)Result" ret
+    |> generateEntryPointCall types name className args ret
+
+## A function that calls the entry point and wraps its result. We can only pass arguments that
+## have the same representation in C++ and in the C calling convention of the app, so entry points
+## with other arguments get no such function; the host calls `roc__<name>_1_exposed_generic`
+## itself and uses `adopt` on the result.
+generateEntryPointCall : Str, Types, Str, Str, List TypeId, TypeId -> Str
+generateEntryPointCall = \buf, types, name, className, args, ret ->
+    argTypes =
+        List.walkUntil args (Ok []) \state, argId ->
+            when (state, Types.shape types argId) is
+                (Ok list, Unit) -> Continue (Ok list)
+                (Ok list, shape) ->
+                    if Types.isOpaque types argId then
+                        Break (Err NotScalar)
+                    else
+                        when scalarType shape is
+                            Ok type -> Continue (Ok (List.append list type))
+                            Err NotScalar -> Break (Err NotScalar)
+
+                (Err NotScalar, _) -> Break state
+
+    returnShape = Types.shape types ret
+
+    when argTypes is
+        Ok cTypes if hasWrapper returnShape || isUnit returnShape ->
+            parameters =
+                cTypes
+                |> List.mapWithIndex \type, index -> "$(type) arg$(Num.toStr index)"
+                |> Str.joinWith ", "
+
+            arguments =
+                List.mapWithIndex cTypes \_, index -> ", arg$(Num.toStr index)"
+                |> Str.joinWith ""
+
+            externParameters = if Str.isEmpty parameters then "void *output" else "void *output, $(parameters)"
+
+            if isUnit returnShape then
+                """
+                $(buf)
+                extern "C" void roc__$(name)_1_exposed_generic($(externParameters));
+
+                inline void $(name)($(parameters)) {
+                    unsigned char output[1];
+                    roc__$(name)_1_exposed_generic(output$(arguments));
+                }
+
+                """
+            else
+                """
+                $(buf)
+                extern "C" void roc__$(name)_1_exposed_generic($(externParameters));
+
+                inline $(className)Result $(name)($(parameters)) {
+                    return $(className)Result::from_output([&](void *output) {
+                        roc__$(name)_1_exposed_generic(output$(arguments));
+                    });
+                }
+
+                """
+
+        _ ->
+            buf
+
+generateLambdaSet : Str, Types, RocFn -> Str
+generateLambdaSet = \buf, types, rocFn ->
+    defName =
+        rocFn.externName
+        |> Str.dropPrefix "roc__"
+        |> Str.dropSuffix "_caller"
+    className = rocFn.functionName
+
+    buf
+    |> generateArgumentValues types defName className rocFn.args
+    |> generateValue types "$(defName)_closure" "$(className)Closure" rocFn.lambdaSet
+    |> generateValue types "$(defName)_result" "$(className)Result" rocFn.ret
+
+generateArgumentValues : Str, Types, Str, Str, List TypeId -> Str
+generateArgumentValues = \buf, types, apiName, className, args ->
+    List.walkWithIndex args buf \accum, argId, index ->
+        indexStr = Num.toStr index
+
+        generateValue accum types "$(apiName)_arg$(indexStr)" "$(className)Arg$(indexStr)" argId
+
+## Declare the memory API for `apiName`, and an RAII wrapper named `className` around it
+generateValue : Str, Types, Str, Str, TypeId -> Str
+generateValue = \buf, types, apiName, className, id ->
+    shape = Types.shape types id
+
+    if hasWrapper shape && Types.isOpaque types id then
+        """
+        $(buf)
+        extern "C" void roc__retain_$(apiName)(void *value);
+        extern "C" void roc__release_$(apiName)(void *value);
+        extern "C" void roc__deepcopy_$(apiName)(void *destination, const void *source);
+        extern "C" std::size_t roc__sizeof_$(apiName)(void);
+        extern "C" std::size_t roc__alignof_$(apiName)(void);
+
+        using $(className) = roc::Handle<roc__retain_$(apiName), roc__release_$(apiName), roc__deepcopy_$(apiName), roc__sizeof_$(apiName), roc__alignof_$(apiName)>;
+
+        """
+    else if hasWrapper shape then
+        size = Num.toStr (Types.size types id)
+        alignment = Num.toStr (Types.alignment types id)
+
+        """
+        $(buf)
+        extern "C" void roc__retain_$(apiName)(void *value);
+        extern "C" void roc__release_$(apiName)(void *value);
+        extern "C" void roc__deepcopy_$(apiName)(void *destination, const void *source);
+
+        using $(className) = roc::Value<$(size), $(alignment), roc__retain_$(apiName), roc__release_$(apiName), roc__deepcopy_$(apiName)>;
+
+        """
+    else
+        when shape is
+            Unit | EmptyTagUnion ->
+                buf
+
+            _ ->
+                """
+                $(buf)
+                // No wrapper for $(apiName): glue does not know the size of closures yet.
+
+                """
+
+## Whether glue knows enough about values of this shape to wrap them
+hasWrapper : Shape -> Bool
+hasWrapper = \shape ->
+    when shape is
+        Unit | EmptyTagUnion | Unsized | Function _ -> Bool.false
+        _ -> Bool.true
+
+isUnit : Shape -> Bool
+isUnit = \shape ->
+    when shape is
+        Unit -> Bool.true
+        _ -> Bool.false
+
+scalarType : Shape -> Result Str [NotScalar]
+scalarType = \shape ->
+    when shape is
+        Bool -> Ok "bool"
+        Num U8 -> Ok "std::uint8_t"
+        Num U16 -> Ok "std::uint16_t"
+        Num U32 -> Ok "std::uint32_t"
+        Num U64 -> Ok "std::uint64_t"
+        Num I8 -> Ok "std::int8_t"
+        Num I16 -> Ok "std::int16_t"
+        Num I32 -> Ok "std::int32_t"
+        Num I64 -> Ok "std::int64_t"
+        Num F32 -> Ok "float"
+        Num F64 -> Ok "double"
+        # 128-bit integers and Dec are passed differently on each architecture
+        _ -> Err NotScalar
+
+capitalize : Str -> Str
+capitalize = \name ->
+    when Str.toUtf8 name is
+        [first, .. as rest] if first >= 'a' && first <= 'z' ->
+            List.prepend rest (first - 32)
+            |> Str.fromUtf8
+            |> Result.withDefault name
+
+        _ ->
+            name
+
+archCondition = \arch ->
+    when arch is
+        Aarch32 ->
+            "defined(__arm__) || defined(_M_ARM)"
+
+        Aarch64 ->
+            "defined(__aarch64__) || defined(_M_ARM64)"
+
+        Riscv64 ->
+            "defined(__riscv) && __riscv_xlen == 64"
+
+        Wasm32 ->
+            "defined(__wasm32__)"
+
+        X86x32 ->
+            "defined(__i386__) || defined(_M_IX86)"
+
+        X86x64 ->
+            "defined(__x86_64__) || defined(_M_X64)"
+
+fileHeader =
+    """
+    // ⚠️ GENERATED CODE ⚠️ - this entire file was generated by the `roc glue` CLI command
+    //
+    // Every value that crosses the host boundary is wrapped in a roc::Value, which owns one
+    // reference to it. Copying a roc::Value retains the value, destroying it releases the value,
+    // and `deep_copy` makes a copy that shares no heap allocations with the original.
+    //
+    // Values of opaque types are wrapped in a roc::Handle, which behaves the same, but only holds a
+    // pointer to the value. Its size and alignment come from the app at runtime, so this header
+    // does not depend on how the app represents the value.
+
+    #pragma once
+
+    #include <cstddef>
+    #include <cstdint>
+    #include <cstring>
+    #include <new>
+    #include <utility>
+
+    namespace roc {
+
+    template <std::size_t Size, std::size_t Align, void (*Retain)(void *), void (*Release)(void *),
+              void (*DeepCopy)(void *, const void *)>
+    class Value {
+      public:
+        static constexpr std::size_t size = Size;
+        static constexpr std::size_t alignment = Align;
+        static constexpr std::size_t storage_size = Size == 0 ? 1 : Size;
+
+        /// Take over the reference that the Roc value at `bytes` holds, e.g. one that a Roc
+        /// function wrote to its output pointer
+        static Value adopt(const void *bytes) noexcept {
+            Value value;
+            std::memcpy(value.bytes_, bytes, Size);
+            value.owned_ = true;
+
+            return value;
+        }
+
+        /// Let `write` store a Roc value in the new wrapper, e.g. by passing the pointer it gets
+        /// as the output pointer of a Roc function
+        template <typename Write> static Value from_output(Write write) {
+            Value value;
+            write(static_cast<void *>(value.bytes_));
+            value.owned_ = true;
+
+            return value;
+        }
+
+        Value(const Value &other) noexcept : owned_(other.owned_) {
+            std::memcpy(bytes_, other.bytes_, Size);
+
+            if (owned_) {
+                Retain(bytes_);
+            }
+        }
+
+        Value(Value &&other) noexcept : owned_(std::exchange(other.owned_, false)) {
+            std::memcpy(bytes_, other.bytes_, Size);
+        }
+
+        Value &operator=(const Value &other) noexcept {
+            if (this != &other) {
+                *this = Value(other);
+            }
+
+            return *this;
+        }
+
+        Value &operator=(Value &&other) noexcept {
+            if (this != &other) {
+                reset();
+                std::memcpy(bytes_, other.bytes_, Size);
+                owned_ = std::exchange(other.owned_, false);
+            }
+
+            return *this;
+        }
+
+        ~Value() { reset(); }
+
+        /// A copy that shares no heap allocations with this value, e.g. to hand to another thread
+        Value deep_copy() const noexcept {
+            Value copy;
+
+            if (owned_) {
+                DeepCopy(copy.bytes_, bytes_);
+                copy.owned_ = true;
+            }
+
+            return copy;
+        }
+
+        /// Give up the reference without releasing it, e.g. to pass the value to a Roc function,
+        /// which consumes its arguments
+        void *into_raw() noexcept {
+            owned_ = false;
+
+            return bytes_;
+        }
+
+        /// False for a value that was moved from, or given up with `into_raw`
+        bool has_value() const noexcept { return owned_; }
+
+        const void *data() const noexcept { return bytes_; }
+
+      private:
+        Value() noexcept = default;
+
+        void reset() noexcept {
+            if (std::exchange(owned_, false)) {
+                Release(bytes_);
+            }
+        }
+
+        alignas(Align) unsigned char bytes_[storage_size] = {};
+        bool owned_ = false;
+    };
+
+    template <void (*Retain)(void *), void (*Release)(void *), void (*DeepCopy)(void *, const void *),
+              std::size_t (*SizeOf)(), std::size_t (*AlignOf)()>
+    class Handle {
+      public:
+        /// Take over the reference that the Roc value at `bytes` holds, e.g. one that a Roc
+        /// function wrote to its output pointer
+        static Handle adopt(const void *bytes) {
+            Handle handle(allocate());
+            std::memcpy(handle.bytes_, bytes, SizeOf());
+            handle.owned_ = true;
+
+            return handle;
+        }
+
+        /// Let `write` store a Roc value in the new handle, e.g. by passing the pointer it gets
+        /// as the output pointer of a Roc function
+        template <typename Write> static Handle from_output(Write write) {
+            Handle handle(allocate());
+            write(handle.bytes_);
+            handle.owned_ = true;
+
+            return handle;
+        }
+
+        Handle(const Handle &other) : bytes_(nullptr), owned_(other.owned_) {
+            if (owned_) {
+                bytes_ = allocate();
+                std::memcpy(bytes_, other.bytes_, SizeOf());
+                Retain(bytes_);
+            }
+        }
+
+        Handle(Handle &&other) noexcept
+            : bytes_(std::exchange(other.bytes_, nullptr)), owned_(std::exchange(other.owned_, false)) {}
+
+        Handle &operator=(const Handle &other) {
+            if (this != &other) {
+                *this = Handle(other);
+            }
+
+            return *this;
+        }
+
+        Handle &operator=(Handle &&other) noexcept {
+            if (this != &other) {
+                reset();
+                bytes_ = std::exchange(other.bytes_, nullptr);
+                owned_ = std::exchange(other.owned_, false);
+            }
+
+            return *this;
+        }
+
+        ~Handle() { reset(); }
+
+        /// A copy that shares no heap allocations with this value, e.g. to hand to another thread
+        Handle deep_copy() const {
+            if (!owned_) {
+                return Handle(nullptr);
+            }
+
+            Handle copy(allocate());
+            DeepCopy(copy.bytes_, bytes_);
+            copy.owned_ = true;
+
+            return copy;
+        }
+
+        /// Give up the reference without releasing it, e.g. to pass the value to a Roc function,
+        /// which consumes its arguments. The pointer stays valid until the handle is destroyed.
+        void *into_raw() noexcept {
+            owned_ = false;
+
+            return bytes_;
+        }
+
+        /// False for a value that was moved from, or given up with `into_raw`
+        bool has_value() const noexcept { return owned_; }
+
+        const void *data() const noexcept { return bytes_; }
+
+      private:
+        explicit Handle(void *bytes) noexcept : bytes_(bytes) {}
+
+        static void *allocate() {
+            std::size_t size = SizeOf();
+
+            return ::operator new(size == 0 ? 1 : size, std::align_val_t(AlignOf()));
+        }
+
+        void reset() noexcept {
+            if (std::exchange(owned_, false)) {
+                Release(bytes_);
+            }
+
+            if (bytes_ != nullptr) {
+                ::operator delete(std::exchange(bytes_, nullptr), std::align_val_t(AlignOf()));
+            }
+        }
+
+        void *bytes_;
+        bool owned_ = false;
+    };
+
+    } // namespace roc
+
+    """
@@ -0,0 +1,199 @@
+app [makeGlue] { pf: platform "../platform/main.roc" }
+
+import pf.Types exposing [Types]
+import pf.Shape exposing [Shape, RocStructFields, RocTagUnion, RocSingleTagPayload]
+import pf.File exposing [File]
+import pf.TypeId exposing [TypeId, typeIDtoU64]
+
+## Describes the types of a platform as JSON, for binding generators that are not written in Roc,
+## and for checking in CI that the API of a platform did not change by accident.
+##
+## Types refer to each other by their `id`, so recursive types need no special treatment. The ids
+## are only meaningful within one target.
+makeGlue : List Types -> Result (List File) Str
+makeGlue = \typesByArch ->
+    targets = array (List.map typesByArch describeTarget)
+
+    Ok [{ name: "types.json", content: "$(object [field "targets" targets])\n" }]
+
+describeTarget : Types -> Str
+describeTarget = \types ->
+    target = Types.target types
+
+    describeEntryPoint = \T name id ->
+        object [field "name" (string name), field "type" (typeId id)]
+
+    shapes =
+        Types.walkShapes types [] \list, shape, id ->
+            description = object [
+                field "id" (typeId id),
+                field "size" (Num.toStr (Types.size types id)),
+                field "alignment" (Num.toStr (Types.alignment types id)),
+                field "shape" (describeShape shape),
+            ]
+
+            List.append list description
+
+    object [
+        field "architecture" (string (Inspect.toStr target.architecture)),
+        field "operatingSystem" (string (Inspect.toStr target.operatingSystem)),
+        field "entryPoints" (array (List.map (Types.entryPoints types) describeEntryPoint)),
+        field "types" (array shapes),
+    ]
+
+describeShape : Shape -> Str
+describeShape = \shape ->
+    when shape is
+        RocStr -> kind "Str" []
+        Bool -> kind "Bool" []
+        RocResult ok err -> kind "Result" [field "ok" (typeId ok), field "err" (typeId err)]
+        Num num -> kind "Num" [field "type" (string (Inspect.toStr num))]
+        RocList elem -> kind "List" [field "element" (typeId elem)]
+        RocDict key value -> kind "Dict" [field "key" (typeId key), field "value" (typeId value)]
+        RocSet elem -> kind "Set" [field "element" (typeId elem)]
+        RocBox elem -> kind "Box" [field "element" (typeId elem)]
+        TagUnion union -> kind "TagUnion" (describeTagUnion union)
+        EmptyTagUnion -> kind "EmptyTagUnion" []
+        Struct { name, fields } ->
+            kind "Struct" [field "name" (string name), field "fields" (describeFields fields)]
+
+        TagUnionPayload { name, fields } ->
+            kind "TagUnionPayload" [field "name" (string name), field "fields" (describeFields fields)]
+
+        RecursivePointer id -> kind "RecursivePointer" [field "type" (typeId id)]
+        Function rocFn ->
+            kind "Function" [
+                field "name" (string rocFn.functionName),
+                field "externName" (string rocFn.externName),
+                field "args" (array (List.map rocFn.args typeId)),
+                field "lambdaSet" (typeId rocFn.lambdaSet),
+                field "ret" (typeId rocFn.ret),
+                field "isToplevel" (if rocFn.isToplevel then "true" else "false"),
+            ]
+
+        Unit -> kind "Unit" []
+        Unsized -> kind "Unsized" []
+
+## The fields of a tag union, next to its `kind`. The `representation` says how its values are
+## stored; see `RocTagUnion` in the glue platform.
+describeTagUnion : RocTagUnion -> List Str
+describeTagUnion = \union ->
+    when union is
+        Enumeration { name, tags, size } -> [
+                representation "Enumeration",
+                field "name" (string name),
+                field "tags" (array (List.map tags string)),
+                field "size" (Num.toStr size),
+            ]
+
+        NonRecursive { name, tags, discriminantSize, discriminantOffset } -> [
+                representation "NonRecursive",
+                field "name" (string name),
+                field "tags" (describeTags tags),
+                field "discriminantSize" (Num.toStr discriminantSize),
+                field "discriminantOffset" (Num.toStr discriminantOffset),
+            ]
+
+        Recursive { name, tags, discriminantSize, discriminantOffset } -> [
+                representation "Recursive",
+                field "name" (string name),
+                field "tags" (describeTags tags),
+                field "discriminantSize" (Num.toStr discriminantSize),
+                field "discriminantOffset" (Num.toStr discriminantOffset),
+            ]
+
+        NullableWrapped { name, indexOfNullTag, tags, discriminantSize, discriminantOffset } -> [
+                representation "NullableWrapped",
+                field "name" (string name),
+                field "indexOfNullTag" (Num.toStr indexOfNullTag),
+                field "tags" (describeTags tags),
+                field "discriminantSize" (Num.toStr discriminantSize),
+                field "discriminantOffset" (Num.toStr discriminantOffset),
+            ]
+
+        NonNullableUnwrapped { name, tagName, payload } -> [
+                representation "NonNullableUnwrapped",
+                field "name" (string name),
+                field "tag" (string tagName),
+                field "payload" (typeId payload),
+            ]
+
+        SingleTagStruct { name, tagName, payload } -> [
+                representation "SingleTagStruct",
+                field "name" (string name),
+                field "tag" (string tagName),
+                field "payload" (describeSingleTagPayload payload),
+            ]
+
+        NullableUnwrapped { name, nullTag, nonNullTag, nonNullPayload } -> [
+                representation "NullableUnwrapped",
+                field "name" (string name),
+                field "nullTag" (string nullTag),
+                field "nonNullTag" (string nonNullTag),
+                field "nonNullPayload" (typeId nonNullPayload),
+            ]
+
+describeTags : List { name : Str, payload : [Some TypeId, None] } -> Str
+describeTags = \tags ->
+    describeTag = \{ name, payload } ->
+        when payload is
+            Some id -> object [field "name" (string name), field "payload" (typeId id)]
+            None -> object [field "name" (string name), field "payload" "null"]
+
+    array (List.map tags describeTag)
+
+describeFields : RocStructFields -> Str
+describeFields = \fields ->
+    when fields is
+        HasNoClosure list ->
+            describeField = \{ name, id } ->
+                object [field "name" (string name), field "type" (typeId id)]
+
+            array (List.map list describeField)
+
+        HasClosure list ->
+            describeField = \{ name, id, accessors } ->
+                object [
+                    field "name" (string name),
+                    field "type" (typeId id),
+                    field "getter" (string accessors.getter),
+                ]
+
+            array (List.map list describeField)
+
+describeSingleTagPayload : RocSingleTagPayload -> Str
+describeSingleTagPayload = \payload ->
+    when payload is
+        HasNoClosure list ->
+            array (List.map list \{ id } -> object [field "type" (typeId id)])
+
+        HasClosure list ->
+            describeField = \{ name, id } ->
+                object [field "name" (string name), field "type" (typeId id)]
+
+            array (List.map list describeField)
+
+kind : Str, List Str -> Str
+kind = \name, fields ->
+    object (List.prepend fields (field "kind" (string name)))
+
+representation : Str -> Str
+representation = \name -> field "representation" (string name)
+
+typeId : TypeId -> Str
+typeId = \id -> Num.toStr (typeIDtoU64 id)
+
+# JSON
+
+object : List Str -> Str
+object = \fields -> "{ $(Str.joinWith fields ", ") }"
+
+field : Str, Str -> Str
+field = \name, value -> "$(string name): $(value)"
+
+array : List Str -> Str
+array = \elements -> "[$(Str.joinWith elements ", ")]"
+
+## Names of types, tags and fields are Roc identifiers, so there is nothing to escape
+string : Str -> Str
+string = \str -> "\"$(str)\""
@@ -59,6 +59,23 @@ pub fn generate(
                 emit_debug_info: false,
                 emit_llvm_ir: false,
                 fuzz: false,
+                cache_helpers: false,
+                cache_mono: false,
+                rc_outlining: Default::default(),
+                memory_strategy: Default::default(),
+                atomic_refcounts: false,
+                freestanding: false,
+                static_heap_bytes: None,
+                profiling: false,
+                crash_dumps: false,
+                memory_api: false,
+                shuffle_parallel_tasks: None,
+                emit_symbol_map: false,
+                emit_call_graph: None,
+                size_report: false,
+                pgo: None,
+                emit_entry_manifest: false,
+                stack_value_limit: None,
             };
 
             let load_config = standard_load_config(
@@ -63,6 +63,7 @@ pub struct Types {
     pub aligns: roc_std::RocList<u32>,
     pub deps: roc_std::RocList<Tuple2>,
     pub entrypoints: roc_std::RocList<Tuple1>,
+    pub opaques: roc_std::RocList<u64>,
     pub sizes: roc_std::RocList<u32>,
     pub types: roc_std::RocList<RocType>,
     pub typesByName: roc_std::RocList<Tuple1>,
@@ -74,6 +75,7 @@ impl RocRefcounted for Types {
         self.aligns.inc();
         self.deps.inc();
         self.entrypoints.inc();
+        self.opaques.inc();
         self.sizes.inc();
         self.types.inc();
         self.typesByName.inc();
@@ -84,6 +86,7 @@ impl RocRefcounted for Types {
         self.aligns.dec();
         self.deps.dec();
         self.entrypoints.dec();
+        self.opaques.dec();
         self.sizes.dec();
         self.types.dec();
         self.typesByName.dec();
@@ -286,9 +289,10 @@ impl core::fmt::Debug for OperatingSystem {
 pub enum Architecture {
     Aarch32 = 0,
     Aarch64 = 1,
-    Wasm32 = 2,
-    X86x32 = 3,
-    X86x64 = 4,
+    Riscv64 = 2,
+    Wasm32 = 3,
+    X86x32 = 4,
+    X86x64 = 5,
 }
 roc_refcounted_noop_impl!(Architecture);
 
@@ -297,6 +301,7 @@ impl core::fmt::Debug for Architecture {
         match self {
             Self::Aarch32 => f.write_str("Architecture::Aarch32"),
             Self::Aarch64 => f.write_str("Architecture::Aarch64"),
+            Self::Riscv64 => f.write_str("Architecture::Riscv64"),
             Self::Wasm32 => f.write_str("Architecture::Wasm32"),
             Self::X86x32 => f.write_str("Architecture::X86x32"),
             Self::X86x64 => f.write_str("Architecture::X86x64"),
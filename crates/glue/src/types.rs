@@ -65,6 +65,11 @@ pub struct Types {
     /// This is important for declaration order in C; we need to output a
     /// type declaration earlier in the file than where it gets referenced by another type.
     deps: VecMap<TypeId, Vec<TypeId>>,
+
+    /// Opaque types, which hosts should only handle through the memory API of the app, so
+    /// that their layout can change without changing the platform's ABI. Maps the type that an
+    /// opaque type wraps to the id of the opaque type.
+    opaques: VecMap<TypeId, TypeId>,
     target: Target,
 }
 
@@ -88,6 +93,7 @@ impl Types {
             types_by_name: FnvHashMap::with_capacity_and_hasher(10, Default::default()),
             entry_points: Vec::new(),
             deps: VecMap::with_capacity(cap),
+            opaques: VecMap::default(),
         }
     }
 
@@ -540,7 +546,7 @@ impl Types {
         layout: InLayout<'a>,
     ) -> TypeId {
         for (id, existing_type) in self.types.iter().enumerate() {
-            if self.is_equivalent(&typ, existing_type) {
+            if self.is_equivalent(&typ, existing_type) && !self.is_opaque(TypeId(id)) {
                 return TypeId(id);
             }
         }
@@ -562,6 +568,29 @@ impl Types {
         id
     }
 
+    /// Add an opaque type whose values are represented like those of `repr`. Unlike other types,
+    /// every opaque type gets its own id, so glue can tell it apart from the type it wraps.
+    pub fn add_opaque(&mut self, repr: TypeId) -> TypeId {
+        if let Some(id) = self.opaques.get(&repr) {
+            return *id;
+        }
+
+        let id = TypeId(self.types.len());
+
+        assert!(id.0 <= TypeId::MAX.0);
+
+        self.types.push(self.get_type(repr).clone());
+        self.sizes.push(self.size_ignoring_alignment(repr));
+        self.aligns.push(self.align(repr));
+        self.opaques.insert(repr, id);
+
+        id
+    }
+
+    pub fn is_opaque(&self, id: TypeId) -> bool {
+        self.opaques.values().any(|opaque| *opaque == id)
+    }
+
     pub fn depends(&mut self, id: TypeId, depends_on: TypeId) {
         self.deps.get_or_insert(id, Vec::new).push(depends_on);
     }
@@ -670,6 +699,7 @@ impl From<&Types> for roc_type::Types {
             sizes: types.sizes.as_slice().into(),
             types: types.types.iter().map(roc_type::RocType::from).collect(),
             typesByName: types_by_name,
+            opaques: types.opaques.values().map(|id| id.0 as _).collect(),
             target: types.target.into(),
         }
     }
@@ -919,6 +949,7 @@ impl From<Architecture> for roc_type::Architecture {
         match arch {
             Architecture::Aarch32 => roc_type::Architecture::Aarch32,
             Architecture::Aarch64 => roc_type::Architecture::Aarch64,
+            Architecture::Riscv64 => roc_type::Architecture::Riscv64,
             Architecture::Wasm32 => roc_type::Architecture::Wasm32,
             Architecture::X86_32 => roc_type::Architecture::X86x32,
             Architecture::X86_64 => roc_type::Architecture::X86x64,
@@ -1428,7 +1459,7 @@ fn add_type_help<'a>(
         Content::Structure(FlatType::EmptyTagUnion) => {
             types.add_anonymous(&env.layout_cache.interner, RocType::EmptyTagUnion, layout)
         }
-        Content::Alias(name, alias_vars, real_var, _) => {
+        Content::Alias(name, alias_vars, real_var, kind) => {
             if name.is_builtin() {
                 match env.layout_cache.get_repr(layout) {
                     LayoutRepr::Builtin(builtin) => {
@@ -1569,7 +1600,12 @@ fn add_type_help<'a>(
             } else {
                 // If this was a non-builtin type alias, we can use that alias name
                 // in the generated bindings.
-                add_type_help(env, layout, *real_var, Some(*name), types)
+                let type_id = add_type_help(env, layout, *real_var, Some(*name), types);
+
+                match kind {
+                    AliasKind::Opaque => types.add_opaque(type_id),
+                    AliasKind::Structural => type_id,
+                }
             }
         }
         Content::RangedNumber(_) => todo!(),
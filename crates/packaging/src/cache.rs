@@ -267,3 +267,15 @@ pub fn roc_cache_packages_dir() -> PathBuf {
     const PACKAGES_DIR_NAME: &str = "packages";
     roc_cache_dir().join(PACKAGES_DIR_NAME)
 }
+
+/// Returns a path of the form roc_cache_dir().join("helpers")
+pub fn roc_cache_helpers_dir() -> PathBuf {
+    const HELPERS_DIR_NAME: &str = "helpers";
+    roc_cache_dir().join(HELPERS_DIR_NAME)
+}
+
+/// Returns a path of the form roc_cache_dir().join("mono")
+pub fn roc_cache_mono_dir() -> PathBuf {
+    const MONO_DIR_NAME: &str = "mono";
+    roc_cache_dir().join(MONO_DIR_NAME)
+}
@@ -6,6 +6,8 @@ use roc_collections::all::MutSet;
 use roc_error_macros::internal_error;
 use roc_gen_llvm::llvm::build::LlvmBackendMode;
 use roc_gen_llvm::llvm::externs::add_default_roc_externs;
+use roc_gen_llvm::llvm::memory::MemoryStrategyKind;
+use roc_gen_llvm::llvm::sendable::SendableLayouts;
 use roc_gen_llvm::{run_jit_function, run_jit_function_dynamic_type};
 use roc_load::{EntryPoint, MonomorphizedModule};
 use roc_mono::ir::OptLevel;
@@ -13,6 +15,7 @@ use roc_mono::layout::STLayoutInterner;
 use roc_parse::ast::Expr;
 use roc_repl_eval::eval::jit_to_ast;
 use roc_repl_eval::gen::{format_answer, ReplOutput};
+use roc_repl_eval::sharing::sharing_report;
 use roc_repl_eval::{ReplApp, ReplAppMemory};
 use roc_std::RocStr;
 use roc_target::Target;
@@ -52,21 +55,8 @@ pub fn eval_llvm(
 
     let interns = loaded.interns.clone();
 
-    #[cfg(not(all(
-        any(target_os = "linux", target_os = "macos"),
-        any(target_arch = "x86_64", target_arch = "aarch64")
-    )))]
-    let (lib, main_fn_name, subs, layout_interner) =
-        mono_module_to_dylib_llvm(&arena, target, loaded, opt_level)
-            .expect("we produce a valid Dylib");
-
-    #[cfg(all(
-        any(target_os = "linux", target_os = "macos"),
-        any(target_arch = "x86_64", target_arch = "aarch64")
-    ))]
     let (lib, main_fn_name, subs, layout_interner) =
-        mono_module_to_dylib_asm(&arena, target, loaded, opt_level)
-            .expect("We failed to produce a valid Dylib.\nTIP: if you're on macos, try this:\n\t<https://github.com/roc-lang/roc/issues/5797#issuecomment-1786105269>");
+        mono_module_to_dylib(&arena, target, loaded, opt_level);
 
     let mut app = CliApp { lib };
 
@@ -90,6 +80,58 @@ pub fn eval_llvm(
     })
 }
 
+/// Report how many of the heap allocations of the expression's value are shared, for `:sharing`
+pub fn sharing_llvm(
+    loaded: MonomorphizedModule<'_>,
+    target: Target,
+    opt_level: OptLevel,
+) -> Option<String> {
+    let arena = Bump::new();
+
+    debug_assert_eq!(loaded.exposed_to_host.top_level_values.len(), 1);
+    let main_fn_symbol = *loaded.exposed_to_host.top_level_values.keys().next()?;
+
+    let (_, main_fn_layout) = *loaded
+        .procedures
+        .keys()
+        .find(|(s, _)| *s == main_fn_symbol)?;
+
+    let (lib, main_fn_name, _, layout_interner) =
+        mono_module_to_dylib(&arena, target, loaded, opt_level);
+
+    let mut app = CliApp { lib };
+    let size = layout_interner.stack_size(main_fn_layout.result) as usize;
+
+    let report = app.call_function_dynamic_size(main_fn_name, size, |mem, addr| {
+        sharing_report(mem, &layout_interner, target, addr, main_fn_layout.result)
+    })?;
+
+    Some(report.to_string())
+}
+
+fn mono_module_to_dylib<'a>(
+    arena: &'a Bump,
+    target: Target,
+    loaded: MonomorphizedModule<'a>,
+    opt_level: OptLevel,
+) -> (Library, &'a str, Subs, STLayoutInterner<'a>) {
+    #[cfg(not(all(
+        any(target_os = "linux", target_os = "macos"),
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )))]
+    let dylib = mono_module_to_dylib_llvm(arena, target, loaded, opt_level)
+        .expect("we produce a valid Dylib");
+
+    #[cfg(all(
+        any(target_os = "linux", target_os = "macos"),
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    let dylib = mono_module_to_dylib_asm(arena, target, loaded, opt_level)
+        .expect("We failed to produce a valid Dylib.\nTIP: if you're on macos, try this:\n\t<https://github.com/roc-lang/roc/issues/5797#issuecomment-1786105269>");
+
+    dylib
+}
+
 struct CliApp {
     lib: Library,
 }
@@ -217,6 +259,16 @@ fn mono_module_to_dylib_llvm<'a>(
         mode: LlvmBackendMode::GenTest, // so roc_panic is generated
         // important! we don't want any procedures to get the C calling convention
         exposed_to_host: MutSet::default(),
+        helper_cache: None,
+        rc_outlining: Default::default(),
+        memory_strategy: MemoryStrategyKind::default().strategy(),
+        sendable_layouts: SendableLayouts::none(),
+        profiling: false,
+        crash_dumps: false,
+        memory_api: false,
+        shuffle_parallel_tasks: None,
+        symbol_map: None,
+        basic_types: Default::default(),
     };
 
     // Add roc_alloc, roc_realloc, and roc_dealloc, since the repl has no
@@ -252,8 +304,6 @@ fn mono_module_to_dylib_llvm<'a>(
         entry_point,
     );
 
-    env.dibuilder.finalize();
-
     // Uncomment this to see the module's un-optimized LLVM instruction output:
     // env.module.print_to_stderr();
 
@@ -263,6 +313,8 @@ fn mono_module_to_dylib_llvm<'a>(
         internal_error!("Main function {main_fn_name} failed LLVM verification in build. Uncomment things nearby to see more details.", );
     }
 
+    roc_gen_llvm::llvm::refcounting::remove_dead_helpers(env.module);
+
     module_pass.run_on(env.module);
 
     // Uncomment this to see the module's optimized LLVM instruction output:
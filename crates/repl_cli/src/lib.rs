@@ -20,7 +20,7 @@ use rustyline_derive::{Completer, Helper, Hinter};
 use std::borrow::Cow;
 use target_lexicon::Triple;
 
-use crate::cli_gen::eval_llvm;
+use crate::cli_gen::{eval_llvm, sharing_llvm};
 
 pub const WELCOME_MESSAGE: &str = concatcp!(
     "\n  The rockin' ",
@@ -87,13 +87,36 @@ pub fn main(has_color: bool, has_header: bool) -> i32 {
                 arena.reset();
                 match repl_state.step(&arena, line, target, DEFAULT_PALETTE) {
                     ReplAction::Eval { opt_mono, problems } => {
-                        let output = evaluate(opt_mono, problems, target);
+                        let output = evaluate_and_remember(repl_state, opt_mono, problems, target);
                         // If there was no output, don't print a blank line!
                         // (This happens for something like a type annotation.)
                         if !output.is_empty() {
                             println!("{}", strip_colors_if_necessary(&output));
                         }
                     }
+                    ReplAction::Cached(output) => {
+                        let output =
+                            format_output(ANSI_STYLE_CODES, Some(output), Problems::default());
+
+                        println!("{}", strip_colors_if_necessary(&output));
+                    }
+                    ReplAction::TypeOf {
+                        opt_output,
+                        problems,
+                    } => {
+                        let output = format_output(ANSI_STYLE_CODES, opt_output, problems);
+
+                        if !output.is_empty() {
+                            println!("{}", strip_colors_if_necessary(&output));
+                        }
+                    }
+                    ReplAction::Sharing { opt_mono, problems } => {
+                        let output = sharing(opt_mono, problems, target);
+
+                        if !output.is_empty() {
+                            println!("{}", strip_colors_if_necessary(&output));
+                        }
+                    }
                     ReplAction::Exit => {
                         return 0;
                     }
@@ -138,6 +161,44 @@ pub fn evaluate(
     format_output(ANSI_STYLE_CODES, opt_output, problems)
 }
 
+/// Like [`evaluate`], but lets `repl_state` cache the output, so evaluating the same input again
+/// doesn't need to compile anything
+pub fn evaluate_and_remember(
+    repl_state: &mut ReplState,
+    opt_mono: Option<MonomorphizedModule<'_>>,
+    problems: Problems,
+    target: Target,
+) -> String {
+    let opt_output = opt_mono.and_then(|mono| eval_llvm(mono, target, OptLevel::Normal));
+
+    repl_state.remember_output(opt_output.as_ref(), &problems);
+
+    format_output(ANSI_STYLE_CODES, opt_output, problems)
+}
+
+/// Like [`evaluate`], but reports which heap allocations of the value are shared instead of
+/// printing it
+pub fn sharing(
+    opt_mono: Option<MonomorphizedModule<'_>>,
+    problems: Problems,
+    target: Target,
+) -> String {
+    let has_errors = !problems.errors.is_empty();
+    let mut output = format_output(ANSI_STYLE_CODES, None, problems);
+
+    // like `evaluate`, don't run anything if there was a compile-time error
+    if has_errors {
+        return output;
+    }
+
+    if let Some(report) = opt_mono.and_then(|mono| sharing_llvm(mono, target, OptLevel::Normal)) {
+        output.push('\n');
+        output.push_str(&report);
+    }
+
+    output
+}
+
 #[derive(Default)]
 struct InputValidator {}
 
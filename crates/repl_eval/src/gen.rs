@@ -5,16 +5,20 @@ use roc_problem::Severity;
 use roc_reporting::report::Palette;
 use std::path::PathBuf;
 
+use roc_collections::MutMap;
 use roc_fmt::annotation::Formattable;
 use roc_fmt::annotation::{Newlines, Parens};
-use roc_load::{LoadingProblem, MonomorphizedModule};
+use roc_load::{LoadedModule, LoadingProblem, MonomorphizedModule};
+use roc_module::symbol::{Interns, ModuleId};
 use roc_parse::ast::Expr;
 use roc_region::all::LineInfo;
 use roc_reporting::report::{can_problem, type_problem, RocDocAllocator};
 use roc_solve::FunctionKind;
+use roc_solve_problem::TypeError;
 use roc_target::Target;
+use roc_types::pretty_print::{name_and_print_var, DebugPrint};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ReplOutput {
     pub expr: String,
     pub expr_type: String,
@@ -102,6 +106,106 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
         ..
     } = &mut loaded;
 
+    let problems = report_problems(
+        module_src,
+        bytes_before_expr,
+        sources,
+        interns,
+        can_problems,
+        type_problems,
+        palette,
+    );
+
+    (Some(loaded), problems)
+}
+
+/// Infer the type of `expr` without specializing it or generating any code for it, which is
+/// much faster than evaluating it
+pub fn compile_to_type<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    defs: I,
+    expr: &str,
+    target: Target,
+    palette: Palette,
+) -> (Option<ReplOutput>, Problems) {
+    let filename = PathBuf::from("replfile.roc");
+    let src_dir = PathBuf::from(".");
+    let (bytes_before_expr, module_src) = promote_expr_to_module(arena, defs, expr);
+    let loaded = roc_load::load_and_typecheck_str(
+        arena,
+        filename,
+        module_src,
+        src_dir,
+        None,
+        target,
+        FunctionKind::LambdaSet,
+        roc_reporting::report::RenderTarget::ColorTerminal,
+        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
+        palette,
+    );
+
+    let mut loaded = match loaded {
+        Ok(v) => v,
+        Err(LoadingProblem::FormattedReport(report)) => {
+            return (
+                None,
+                Problems {
+                    errors: vec![report],
+                    warnings: Vec::new(),
+                },
+            );
+        }
+        Err(e) => {
+            todo!("error while loading module: {:?}", e)
+        }
+    };
+
+    let LoadedModule {
+        module_id,
+        interns,
+        solved,
+        sources,
+        can_problems,
+        type_problems,
+        exposed_to_host,
+        ..
+    } = &mut loaded;
+
+    let problems = report_problems(
+        module_src,
+        bytes_before_expr,
+        sources,
+        interns,
+        can_problems,
+        type_problems,
+        palette,
+    );
+
+    debug_assert_eq!(exposed_to_host.len(), 1);
+    let opt_output = exposed_to_host.values().next().map(|var| ReplOutput {
+        expr: expr.trim().to_string(),
+        expr_type: name_and_print_var(
+            *var,
+            solved.inner_mut(),
+            *module_id,
+            interns,
+            DebugPrint::NOTHING,
+        ),
+    });
+
+    (opt_output, problems)
+}
+
+/// Render the problems of the modules in `sources`, leaving out the ones in the past defs
+fn report_problems(
+    module_src: &str,
+    bytes_before_expr: usize,
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    interns: &Interns,
+    can_problems: &mut MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
+    type_problems: &mut MutMap<ModuleId, Vec<TypeError>>,
+    palette: Palette,
+) -> Problems {
     let mut problems = Problems::default();
 
     let errors = &mut problems.errors;
@@ -165,7 +269,7 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
         }
     }
 
-    (Some(loaded), problems)
+    problems
 }
 
 fn promote_expr_to_module<'a, 'i, I: Iterator<Item = &'i str>>(
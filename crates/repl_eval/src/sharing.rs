@@ -0,0 +1,261 @@
+//! Report how much of a value's heap memory is shared, for the REPL's `:sharing` command.
+//!
+//! Whether updating a value (or a "copy" of it) is cheap depends on the refcounts of the
+//! allocations it is made of: an allocation with one owner is updated in place, a shared one is
+//! copied first. We walk the value by its layout and look at the refcount of every allocation we
+//! reach, counting each allocation once even if the value refers to it more than once.
+use std::fmt;
+
+use roc_collections::MutSet;
+use roc_mono::layout::{Builtin, Discriminant, InLayout, LayoutInterner, LayoutRepr, UnionLayout};
+use roc_target::{PtrWidth, Target};
+
+use crate::ReplAppMemory;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SharingReport {
+    /// Allocations with one owner, which can be updated in place
+    pub unique: usize,
+    /// Allocations with more than one owner, which are copied before they are updated
+    pub shared: usize,
+    /// Constants that are part of the program; they are never freed or updated in place
+    pub constant: usize,
+}
+
+impl fmt::Display for SharingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.unique + self.shared + self.constant;
+
+        if total == 0 {
+            return write!(f, "This value has no heap allocations.");
+        }
+
+        let plural = if total == 1 { "" } else { "s" };
+
+        write!(
+            f,
+            "{total} heap allocation{plural}: {} unique, {} shared, {} constant",
+            self.unique, self.shared, self.constant
+        )
+    }
+}
+
+/// Walk the value of layout `layout` at `addr`
+pub fn sharing_report<'a, M, I>(
+    mem: &M,
+    interner: &I,
+    target: Target,
+    addr: usize,
+    layout: InLayout<'a>,
+) -> SharingReport
+where
+    M: ReplAppMemory,
+    I: LayoutInterner<'a>,
+{
+    let mut walker = Walker {
+        mem,
+        interner,
+        target,
+        visited: MutSet::default(),
+        report: SharingReport::default(),
+    };
+
+    walker.walk(addr, layout);
+
+    walker.report
+}
+
+struct Walker<'m, M, I> {
+    mem: &'m M,
+    interner: &'m I,
+    target: Target,
+    /// The data pointers of the allocations we counted already
+    visited: MutSet<usize>,
+    report: SharingReport,
+}
+
+impl<'a, M: ReplAppMemory, I: LayoutInterner<'a>> Walker<'_, M, I> {
+    fn walk(&mut self, addr: usize, layout: InLayout<'a>) {
+        // a recursive pointer is stored just like the union it points to
+        match self.interner.chase_recursive(layout) {
+            LayoutRepr::Builtin(Builtin::Str) => self.str(addr),
+            LayoutRepr::Builtin(Builtin::List(element)) => self.list(addr, element),
+            LayoutRepr::Builtin(_) => {}
+            LayoutRepr::Struct(fields) => self.fields(addr, fields),
+            LayoutRepr::Union(union_layout) => self.union(addr, union_layout),
+            LayoutRepr::LambdaSet(lambda_set) => {
+                self.walk(addr, lambda_set.runtime_representation())
+            }
+            LayoutRepr::RecursivePointer(_) => unreachable!("we chased the recursive pointer"),
+            // erased closures are not refcounted like other values, and not printed either
+            LayoutRepr::Ptr(_) | LayoutRepr::FunctionPointer(_) | LayoutRepr::Erased(_) => {}
+        }
+    }
+
+    /// Fields are stored one after the other, sorted so that none of them needs padding
+    fn fields(&mut self, addr: usize, fields: &[InLayout<'a>]) {
+        let mut field_addr = addr;
+
+        for field in fields {
+            self.walk(field_addr, *field);
+
+            field_addr += self.interner.stack_size(*field) as usize;
+        }
+    }
+
+    fn str(&mut self, addr: usize) {
+        let ptr_size = self.target.ptr_size();
+        let bytes = self.deref_ptr(addr);
+        let length = self.deref_ptr(addr + ptr_size);
+        let capacity_or_alloc_ptr = self.deref_ptr(addr + 2 * ptr_size);
+
+        // small strings are stored inline
+        if self.high_bit_set(capacity_or_alloc_ptr) {
+            return;
+        }
+
+        // a seamless slice keeps a pointer to the allocation it slices, shifted right by one
+        if self.high_bit_set(length) {
+            self.allocation(capacity_or_alloc_ptr << 1);
+        } else {
+            self.allocation(bytes);
+        }
+    }
+
+    fn list(&mut self, addr: usize, element: InLayout<'a>) {
+        let ptr_size = self.target.ptr_size();
+        let elements = self.deref_ptr(addr);
+        let length = self.deref_ptr(addr + ptr_size);
+        let capacity_or_alloc_ptr = self.deref_ptr(addr + 2 * ptr_size);
+
+        let allocation = if self.high_bit_set(capacity_or_alloc_ptr) {
+            capacity_or_alloc_ptr << 1
+        } else {
+            elements
+        };
+
+        if self.allocation(allocation) {
+            let stride = self.interner.stack_size(element) as usize;
+
+            for index in 0..length {
+                self.walk(elements + index * stride, element);
+            }
+        }
+    }
+
+    fn union(&mut self, addr: usize, union_layout: UnionLayout<'a>) {
+        match union_layout {
+            UnionLayout::NonRecursive(tags) => {
+                let tag_id = self.tag_id_after_data(union_layout, addr);
+
+                self.fields(addr, tags[tag_id]);
+            }
+            UnionLayout::Recursive(tags) => {
+                let (tag_id, data) = self.tag_id_and_data(union_layout, addr);
+
+                if self.allocation(data) {
+                    self.fields(data, tags[tag_id]);
+                }
+            }
+            UnionLayout::NonNullableUnwrapped(fields) => {
+                let data = self.deref_ptr(addr);
+
+                if self.allocation(data) {
+                    self.fields(data, fields);
+                }
+            }
+            UnionLayout::NullableUnwrapped { other_fields, .. } => {
+                let data = self.deref_ptr(addr);
+
+                if self.allocation(data) {
+                    self.fields(data, other_fields);
+                }
+            }
+            UnionLayout::NullableWrapped {
+                nullable_id,
+                other_tags,
+            } => {
+                if self.deref_ptr(addr) == 0 {
+                    return;
+                }
+
+                let (tag_id, data) = self.tag_id_and_data(union_layout, addr);
+
+                // the null tag has no entry in `other_tags`
+                let index = if tag_id > nullable_id as usize {
+                    tag_id - 1
+                } else {
+                    tag_id
+                };
+
+                if self.allocation(data) {
+                    self.fields(data, other_tags[index]);
+                }
+            }
+        }
+    }
+
+    /// The tag id of a recursive union, and the pointer to its data without the tag id bits
+    fn tag_id_and_data(&self, union_layout: UnionLayout<'a>, addr: usize) -> (usize, usize) {
+        let pointer = self.deref_ptr(addr);
+
+        if union_layout.stores_tag_id_in_pointer(self.target) {
+            let (_, mask) = UnionLayout::tag_id_pointer_bits_and_mask(self.target);
+
+            (pointer & mask, pointer & !mask)
+        } else {
+            (self.tag_id_after_data(union_layout, pointer), pointer)
+        }
+    }
+
+    fn tag_id_after_data(&self, union_layout: UnionLayout<'a>, data: usize) -> usize {
+        let offset = union_layout
+            .data_size_without_tag_id(self.interner)
+            .unwrap() as usize;
+
+        match union_layout.discriminant() {
+            Discriminant::U0 => 0,
+            Discriminant::U1 => self.mem.deref_bool(data + offset) as usize,
+            Discriminant::U8 => self.mem.deref_u8(data + offset) as usize,
+            Discriminant::U16 => self.mem.deref_u16(data + offset) as usize,
+        }
+    }
+
+    /// Count the allocation whose data starts at `data`. Returns whether it is the first time we
+    /// see this allocation, i.e. whether its contents still need to be walked.
+    fn allocation(&mut self, data: usize) -> bool {
+        if data == 0 || !self.visited.insert(data) {
+            return false;
+        }
+
+        // the refcount is stored right before the data; see `utils.zig` in the builtins
+        let refcount_addr = data - self.target.ptr_size();
+        let (refcount, refcount_one) = match self.target.ptr_width() {
+            PtrWidth::Bytes8 => (self.mem.deref_i64(refcount_addr), i64::MIN),
+            PtrWidth::Bytes4 => (self.mem.deref_i32(refcount_addr) as i64, i32::MIN as i64),
+        };
+
+        if refcount == 0 {
+            self.report.constant += 1;
+        } else if refcount == refcount_one {
+            self.report.unique += 1;
+        } else {
+            self.report.shared += 1;
+        }
+
+        true
+    }
+
+    fn deref_ptr(&self, addr: usize) -> usize {
+        match self.target.ptr_width() {
+            PtrWidth::Bytes8 => self.mem.deref_u64(addr) as usize,
+            PtrWidth::Bytes4 => self.mem.deref_u32(addr) as usize,
+        }
+    }
+
+    fn high_bit_set(&self, word: usize) -> bool {
+        let bits = self.target.ptr_size() * 8;
+
+        (word >> (bits - 1)) & 1 == 1
+    }
+}
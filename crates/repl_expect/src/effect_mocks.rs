@@ -0,0 +1,62 @@
+//! Mocks for the effects of the platform, so that `roc test` can run effectful expects without it.
+//!
+//! A mock library is a shared library that exports a table of the effects it replaces:
+//!
+//! ```c
+//! struct RocFxMock { const char *name; void *function; };
+//!
+//! const struct RocFxMock roc_fx_mocks[] = {
+//!     { "roc_fx_putLine", mock_put_line },
+//!     { NULL, NULL },
+//! };
+//! ```
+//!
+//! Each mock has the same C signature as the host function it replaces. Effects without a mock
+//! still call the platform's function.
+use std::ffi::{c_void, CStr};
+use std::path::Path;
+
+use roc_gen_llvm::llvm::effect_mocks::{mock_slot_name, RocFxMock, MOCK_TABLE};
+
+pub struct EffectMocks {
+    library: libloading::Library,
+}
+
+impl EffectMocks {
+    pub fn load(path: &Path) -> Result<Self, libloading::Error> {
+        let library = unsafe { libloading::Library::new(path)? };
+
+        // fail early if this is not a mock library
+        unsafe { library.get::<*const RocFxMock>(MOCK_TABLE.as_bytes())? };
+
+        Ok(Self { library })
+    }
+
+    /// Puts the mocks in the mock slots of `lib`, the test build of an app. Returns the names of
+    /// the mocked effects that the app never calls, which usually means a mock has a typo in it.
+    pub fn install(&self, lib: &libloading::Library) -> Vec<String> {
+        let mut unused = Vec::new();
+
+        unsafe {
+            let table = self
+                .library
+                .get::<*const RocFxMock>(MOCK_TABLE.as_bytes())
+                .unwrap();
+            let mut entry: *const RocFxMock = *table;
+
+            while !(*entry).name.is_null() {
+                let RocFxMock { name, function } = *entry;
+                let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+
+                match lib.get::<*mut *const c_void>(mock_slot_name(&name).as_bytes()) {
+                    Ok(slot) => **slot = function,
+                    Err(_) => unused.push(name),
+                }
+
+                entry = entry.add(1);
+            }
+        }
+
+        unused
+    }
+}
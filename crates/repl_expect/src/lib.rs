@@ -15,6 +15,8 @@ use {
 #[cfg(not(windows))]
 mod app;
 #[cfg(not(windows))]
+pub mod effect_mocks;
+#[cfg(not(windows))]
 pub mod run;
 
 #[cfg(not(windows))]
@@ -147,9 +149,15 @@ mod test {
 
         let interns = loaded.interns.clone();
 
-        let (dy_lib, expects_by_module, layout_interner) =
-            expect_mono_module_to_dylib(arena, target, loaded, opt_level, LlvmBackendMode::CliTest)
-                .unwrap();
+        let (dy_lib, expects_by_module, layout_interner) = expect_mono_module_to_dylib(
+            arena,
+            target,
+            loaded,
+            opt_level,
+            LlvmBackendMode::CliTest,
+            None,
+        )
+        .unwrap();
 
         let arena = &bumpalo::Bump::new();
         let interns = arena.alloc(interns);
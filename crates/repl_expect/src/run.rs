@@ -14,7 +14,10 @@ use roc_can::expr::ExpectLookup;
 use roc_collections::{MutMap, MutSet, VecMap};
 use roc_error_macros::internal_error;
 use roc_gen_llvm::{
-    llvm::{build::LlvmBackendMode, externs::add_default_roc_externs},
+    llvm::{
+        build::LlvmBackendMode, externs::add_default_roc_externs, memory::MemoryStrategyKind,
+        sendable::SendableLayouts,
+    },
     run_roc::RocCallResult,
     run_roc_dylib,
 };
@@ -616,6 +619,7 @@ pub fn expect_mono_module_to_dylib<'a>(
     loaded: MonomorphizedModule<'a>,
     opt_level: OptLevel,
     mode: LlvmBackendMode,
+    shuffle_parallel_tasks: Option<u64>,
 ) -> Result<
     (
         libloading::Library,
@@ -657,6 +661,16 @@ pub fn expect_mono_module_to_dylib<'a>(
         mode,
         // important! we don't want any procedures to get the C calling convention
         exposed_to_host: MutSet::default(),
+        helper_cache: None,
+        rc_outlining: Default::default(),
+        memory_strategy: MemoryStrategyKind::default().strategy(),
+        sendable_layouts: SendableLayouts::none(),
+        profiling: false,
+        crash_dumps: false,
+        memory_api: false,
+        shuffle_parallel_tasks,
+        symbol_map: None,
+        basic_types: Default::default(),
     };
 
     // Add roc_alloc, roc_realloc, and roc_dealloc, since the repl has no
@@ -726,11 +740,11 @@ pub fn expect_mono_module_to_dylib<'a>(
         modules_expects.insert(module_id, expect_funs);
     }
 
-    env.dibuilder.finalize();
-
     // Uncomment this to see the module's un-optimized LLVM instruction output:
     // env.module.print_to_stderr();
 
+    roc_gen_llvm::llvm::refcounting::remove_dead_helpers(env.module);
+
     module_pass.run_on(env.module);
 
     // Uncomment this to see the module's optimized LLVM instruction output:
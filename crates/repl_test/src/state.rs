@@ -1,9 +1,9 @@
 use bumpalo::Bump;
 use indoc::indoc;
-use roc_repl_cli::{evaluate, ReplHelper};
-use roc_repl_ui::is_incomplete;
+use roc_repl_cli::{evaluate, evaluate_and_remember, sharing, ReplHelper};
 use roc_repl_ui::repl_state::{ReplAction, ReplState};
-use roc_reporting::report::DEFAULT_PALETTE;
+use roc_repl_ui::{format_output, is_incomplete};
+use roc_reporting::report::{ANSI_STYLE_CODES, DEFAULT_PALETTE};
 use rustyline::Editor;
 use target_lexicon::Triple;
 
@@ -145,6 +145,68 @@ fn standalone_annotation() {
     assert!(matches!(action, ReplAction::Nothing));
 }
 
+#[test]
+fn type_command() {
+    let mut state = ReplState::new();
+
+    complete("x = 5", &mut state, "5 : Num *");
+    type_of(":t x", &mut state, "x : Num *");
+    type_of(
+        ":type Str.concat",
+        &mut state,
+        "Str.concat : Str, Str -> Str",
+    );
+}
+
+#[test]
+fn sharing_command() {
+    let mut state = ReplState::new();
+
+    sharing_of(
+        ":sharing 42",
+        &mut state,
+        "This value has no heap allocations.",
+    );
+    sharing_of(
+        ":sharing List.repeat 1 3",
+        &mut state,
+        "1 heap allocation: 1 unique, 0 shared, 0 constant",
+    );
+
+    // both elements point to the same string, which is counted once
+    sharing_of(
+        ":sharing (\\s -> [s, s]) (Str.repeat \"abc\" 20)",
+        &mut state,
+        "2 heap allocations: 1 unique, 1 shared, 0 constant",
+    );
+}
+
+#[test]
+fn cached_output() {
+    let mut state = ReplState::new();
+
+    complete("x = 5", &mut state, "5 : Num *");
+
+    let arena = Bump::new();
+    let target = Triple::host().into();
+
+    match state.step(&arena, "x + 1", target, DEFAULT_PALETTE) {
+        ReplAction::Eval { opt_mono, problems } => {
+            evaluate_and_remember(&mut state, opt_mono, problems, target);
+        }
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    match state.step(&arena, "x + 1", target, DEFAULT_PALETTE) {
+        ReplAction::Cached(output) => assert_eq!(output.expr, "6"),
+        action => panic!("Unexpected action: {:?}", action),
+    }
+
+    // a new def could change the output of the same input, so it gets evaluated again
+    complete("y = 2", &mut state, "2 : Num *");
+    complete("x + 1", &mut state, "6 : Num *");
+}
+
 /// validate and step the given input, then check the Result vs the output
 /// with ANSI escape codes stripped.
 fn complete(input: &str, state: &mut ReplState, expected_start: &str) {
@@ -205,3 +267,52 @@ fn error(input: &str, state: &mut ReplState, expected_step_result: String) {
         }
     }
 }
+
+/// validate and step the given `:t` input, then check the inferred type vs the output
+/// with ANSI escape codes stripped.
+fn type_of(input: &str, state: &mut ReplState, expected: &str) {
+    assert!(!is_incomplete(input));
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let action = state.step(&arena, input, target, DEFAULT_PALETTE);
+
+    match action {
+        ReplAction::TypeOf {
+            opt_output,
+            problems,
+        } => {
+            let string = format_output(ANSI_STYLE_CODES, opt_output, problems);
+            let escaped =
+                std::string::String::from_utf8(strip_ansi_escapes::strip(string.trim()).unwrap())
+                    .unwrap();
+
+            assert_eq!(expected, escaped);
+        }
+        _ => {
+            panic!("Unexpected action: {:?}", action);
+        }
+    }
+}
+
+/// validate and step the given `:sharing` input, then check the report vs the output
+/// with ANSI escape codes stripped.
+fn sharing_of(input: &str, state: &mut ReplState, expected: &str) {
+    assert!(!is_incomplete(input));
+    let arena = Bump::new();
+    let target = Triple::host().into();
+    let action = state.step(&arena, input, target, DEFAULT_PALETTE);
+
+    match action {
+        ReplAction::Sharing { opt_mono, problems } => {
+            let string = sharing(opt_mono, problems, target);
+            let escaped =
+                std::string::String::from_utf8(strip_ansi_escapes::strip(string.trim()).unwrap())
+                    .unwrap();
+
+            assert_eq!(expected, escaped);
+        }
+        _ => {
+            panic!("Unexpected action: {:?}", action);
+        }
+    }
+}
@@ -1589,6 +1589,7 @@ fn interpolation_with_nested_interpolation() {
                 Enter an expression to evaluate, or a definition (like x = 1) to use later.
 
                   - ctrl-v + ctrl-j makes a newline
+                  - :t <expr> shows the type of an expression without evaluating it
                   - :q quits
                   - :help shows this text again
             "#
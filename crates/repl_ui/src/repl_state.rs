@@ -2,7 +2,7 @@ use std::path::PathBuf;
 use std::{fs, io};
 
 use bumpalo::Bump;
-use roc_collections::MutSet;
+use roc_collections::{MutMap, MutSet};
 use roc_load::MonomorphizedModule;
 use roc_parse::ast::{Defs, Expr, Pattern, StrLiteral, TypeDef, TypeHeader, ValueDef};
 use roc_parse::expr::parse_repl_defs_and_optional_expr;
@@ -10,7 +10,7 @@ use roc_parse::parser::EWhen;
 use roc_parse::parser::{EClosure, EExpr, EPattern};
 use roc_parse::state::State;
 use roc_region::all::Loc;
-use roc_repl_eval::gen::{compile_to_mono, Problems};
+use roc_repl_eval::gen::{compile_to_mono, compile_to_type, Problems, ReplOutput};
 use roc_reporting::report::Palette;
 use roc_target::Target;
 
@@ -23,6 +23,10 @@ enum PastDef {
 pub struct ReplState {
     past_defs: Vec<PastDef>,
     past_def_idents: MutSet<String>,
+    /// The outputs of earlier inputs, keyed by the source of the module they were evaluated in
+    evaluated: MutMap<String, ReplOutput>,
+    /// The key of the input that is being evaluated, if its output can be cached
+    pending_cache_key: Option<String>,
 }
 
 impl Default for ReplState {
@@ -38,6 +42,18 @@ pub enum ReplAction<'a> {
         opt_mono: Option<MonomorphizedModule<'a>>,
         problems: Problems,
     },
+    /// The input was evaluated before, with the same past defs
+    Cached(ReplOutput),
+    /// The type of an expression, inferred without evaluating it
+    TypeOf {
+        opt_output: Option<ReplOutput>,
+        problems: Problems,
+    },
+    /// Evaluate an expression, and report which of its heap allocations are shared
+    Sharing {
+        opt_mono: Option<MonomorphizedModule<'a>>,
+        problems: Problems,
+    },
     Exit,
     Help,
     FileProblem {
@@ -52,6 +68,8 @@ impl ReplState {
         Self {
             past_defs: Default::default(),
             past_def_idents: Default::default(),
+            evaluated: Default::default(),
+            pending_cache_key: None,
         }
     }
 
@@ -62,10 +80,27 @@ impl ReplState {
         target: Target,
         palette: Palette,
     ) -> ReplAction<'a> {
+        self.pending_cache_key = None;
+
         let mut pending_past_def = None;
         let src: &str = match parse_src(arena, line) {
             ParseOutcome::Empty | ParseOutcome::Help => return ReplAction::Help,
             ParseOutcome::Exit => return ReplAction::Exit,
+            ParseOutcome::TypeOf(expr) => {
+                let (opt_output, problems) =
+                    compile_to_type(arena, self.past_def_sources(), expr, target, palette);
+
+                return ReplAction::TypeOf {
+                    opt_output,
+                    problems,
+                };
+            }
+            ParseOutcome::Sharing(expr) => {
+                let (opt_mono, problems) =
+                    compile_to_mono(arena, self.past_def_sources(), expr, target, palette);
+
+                return ReplAction::Sharing { opt_mono, problems };
+            }
             ParseOutcome::Incomplete | ParseOutcome::SyntaxErr => {
                 pending_past_def = None;
 
@@ -260,16 +295,22 @@ impl ReplState {
             }
         };
 
-        let (opt_mono, problems) = compile_to_mono(
-            arena,
-            self.past_defs.iter().map(|past_def| match past_def {
-                PastDef::Def { ident: _, src } => src.as_str(),
-                PastDef::Import(src) => src.as_str(),
-            }),
-            src,
-            target,
-            palette,
-        );
+        let cache_key = self.cache_key(src);
+
+        if let Some(output) = cache_key.as_ref().and_then(|key| self.evaluated.get(key)) {
+            let output = output.clone();
+
+            if let Some((ident, src)) = pending_past_def {
+                self.add_past_def(ident, src);
+            }
+
+            return ReplAction::Cached(output);
+        }
+
+        let (opt_mono, problems) =
+            compile_to_mono(arena, self.past_def_sources(), src, target, palette);
+
+        self.pending_cache_key = cache_key;
 
         if let Some((ident, src)) = pending_past_def {
             self.add_past_def(ident, src);
@@ -278,6 +319,45 @@ impl ReplState {
         ReplAction::Eval { opt_mono, problems }
     }
 
+    /// Remember the output of the input that was just evaluated, so that evaluating it again
+    /// (with the same past defs) doesn't need to compile anything
+    pub fn remember_output(&mut self, opt_output: Option<&ReplOutput>, problems: &Problems) {
+        let key = self.pending_cache_key.take();
+
+        // warnings would be lost when we print a cached output
+        if let (Some(key), Some(output), true) = (key, opt_output, problems.is_empty()) {
+            self.evaluated.insert(key, output.clone());
+        }
+    }
+
+    /// Evaluating an input is pure, so its output only depends on the input and the past defs.
+    /// Imported files can change between inputs though, so we don't cache anything once there
+    /// are imports.
+    fn cache_key(&self, src: &str) -> Option<String> {
+        let mut key = String::new();
+
+        for past_def in self.past_defs.iter() {
+            match past_def {
+                PastDef::Def { ident: _, src } => {
+                    key.push_str(src);
+                    key.push_str("\n\n");
+                }
+                PastDef::Import(_) => return None,
+            }
+        }
+
+        key.push_str(src);
+
+        Some(key)
+    }
+
+    fn past_def_sources(&self) -> impl Iterator<Item = &str> {
+        self.past_defs.iter().map(|past_def| match past_def {
+            PastDef::Def { ident: _, src } => src.as_str(),
+            PastDef::Import(src) => src.as_str(),
+        })
+    }
+
     fn add_past_def(&mut self, ident: String, src: String) {
         let existing_idents = &mut self.past_def_idents;
 
@@ -292,6 +372,10 @@ pub enum ParseOutcome<'a> {
     DefsAndExpr(Defs<'a>, Option<Loc<Expr<'a>>>),
     Incomplete,
     SyntaxErr,
+    /// `:t expr` or `:type expr`
+    TypeOf(&'a str),
+    /// `:sharing expr`
+    Sharing(&'a str),
     Empty,
     Help,
     Exit,
@@ -310,6 +394,14 @@ fn parse_outcome_for_error(e: EExpr<'_>) -> ParseOutcome<'_> {
 }
 
 pub fn parse_src<'a>(arena: &'a Bump, line: &'a str) -> ParseOutcome<'a> {
+    if let Some(expr) = strip_command(line, &[":type", ":t"]) {
+        return ParseOutcome::TypeOf(expr);
+    }
+
+    if let Some(expr) = strip_command(line, &[":sharing"]) {
+        return ParseOutcome::Sharing(expr);
+    }
+
     match line.trim().to_lowercase().as_str() {
         "" => ParseOutcome::Empty,
         ":help" => ParseOutcome::Help,
@@ -334,3 +426,15 @@ pub fn parse_src<'a>(arena: &'a Bump, line: &'a str) -> ParseOutcome<'a> {
         }
     }
 }
+
+/// The expression after a command like `:t`, which can have several names
+fn strip_command<'a>(line: &'a str, names: &[&str]) -> Option<&'a str> {
+    let line = line.trim_start();
+    let rest = names.iter().find_map(|name| line.strip_prefix(name))?;
+
+    // `:t` has to be followed by whitespace, so that `:tx` isn't mistaken for `:t x`
+    match rest.chars().next() {
+        Some(c) if c.is_whitespace() && !rest.trim().is_empty() => Some(rest.trim()),
+        _ => None,
+    }
+}
@@ -8,7 +8,7 @@ use roc_load::MonomorphizedModule;
 use roc_parse::ast::Expr;
 use roc_repl_eval::{
     eval::jit_to_ast,
-    gen::{format_answer, ReplOutput},
+    gen::{format_answer, Problems, ReplOutput},
     ReplApp, ReplAppMemory,
 };
 use roc_repl_ui::{
@@ -213,8 +213,23 @@ pub async fn entrypoint_from_js(src: String) -> String {
                 None => None,
             };
 
+            REPL_STATE.with(|repl_state_cell| {
+                let mut repl_state = repl_state_cell.borrow_mut();
+                repl_state.remember_output(opt_output.as_ref(), &problems);
+            });
+
             format_output(HTML_STYLE_CODES, opt_output, problems)
         }
+        ReplAction::Cached(output) => {
+            format_output(HTML_STYLE_CODES, Some(output), Problems::default())
+        }
+        ReplAction::TypeOf {
+            opt_output,
+            problems,
+        } => format_output(HTML_STYLE_CODES, opt_output, problems),
+        ReplAction::Sharing { .. } => {
+            "The web version of the REPL cannot report sharing... for now!".to_string()
+        }
     }
 }
 
@@ -0,0 +1,24 @@
+app [main!] { pf: platform "platform/main.roc" }
+
+import pf.Stdout
+import pf.File
+import pf.Utc
+
+main! : {} => {}
+main! = \{} ->
+    start = Utc.nowMillis! {}
+
+    Stdout.line! "Roc <3 WASI!"
+
+    when File.writeUtf8! "roc-wasi.txt" "Written by a Roc program running in a WASI runtime.\n" is
+        Ok {} ->
+            when File.readUtf8! "roc-wasi.txt" is
+                Ok contents -> Stdout.line! "Read back: $(contents)"
+                Err (FileReadErr msg) -> Stdout.line! "Could not read the file: $(msg)"
+
+        Err (FileWriteErr msg) ->
+            Stdout.line! "Could not write the file: $(msg)"
+
+    elapsed = Utc.nowMillis! {} - start
+
+    Stdout.line! "Done in $(Num.toStr elapsed) ms"
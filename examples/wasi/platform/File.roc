@@ -0,0 +1,16 @@
+module [readUtf8!, writeUtf8!]
+
+import Host
+
+## Read the contents of the file at `path`. The path is resolved against the directories that
+## the WASI runtime gave the program access to (e.g. with `wasmtime --dir .`).
+readUtf8! : Str => Result Str [FileReadErr Str]
+readUtf8! = \path ->
+    Host.readFile! path
+    |> Result.mapErr FileReadErr
+
+## Write `contents` to the file at `path`, replacing it if it already exists.
+writeUtf8! : Str, Str => Result {} [FileWriteErr Str]
+writeUtf8! = \path, contents ->
+    Host.writeFile! path contents
+    |> Result.mapErr FileWriteErr
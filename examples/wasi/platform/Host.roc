@@ -0,0 +1,11 @@
+hosted Host
+    exposes [stdoutLine!, readFile!, writeFile!, nowMillis!]
+    imports []
+
+stdoutLine! : Str => {}
+
+readFile! : Str => Result Str Str
+
+writeFile! : Str, Str => Result {} Str
+
+nowMillis! : {} => U64
@@ -0,0 +1,7 @@
+module [line!]
+
+import Host
+
+## Write the given string to standard output, followed by a newline.
+line! : Str => {}
+line! = \str -> Host.stdoutLine! str
@@ -0,0 +1,7 @@
+module [nowMillis!]
+
+import Host
+
+## Milliseconds since the UNIX epoch, according to the WASI realtime clock.
+nowMillis! : {} => U64
+nowMillis! = \{} -> Host.nowMillis! {}
@@ -0,0 +1,119 @@
+const std = @import("std");
+const builtin = @import("builtin");
+const str = @import("glue").str;
+const RocStr = str.RocStr;
+
+comptime {
+    if (builtin.target.cpu.arch != .wasm32) {
+        @compileError("This platform is for WebAssembly only. You need to pass `--target wasm32` to the Roc compiler.");
+    }
+}
+
+// Everything below only uses the zig standard library and wasi-libc, which both lower I/O to
+// WASI imports (`fd_write`, `path_open`, `clock_time_get`, ...). The resulting module runs in
+// any WASI runtime, e.g. `wasmtime --dir . main.wasm`, without a JavaScript host.
+
+const Align = 2 * @alignOf(usize);
+extern fn malloc(size: usize) callconv(.C) ?*align(Align) anyopaque;
+extern fn realloc(c_ptr: [*]align(Align) u8, size: usize) callconv(.C) ?*anyopaque;
+extern fn free(c_ptr: [*]align(Align) u8) callconv(.C) void;
+extern fn memset(dst: [*]u8, value: i32, size: usize) callconv(.C) void;
+
+export fn roc_alloc(size: usize, alignment: u32) callconv(.C) ?*anyopaque {
+    _ = alignment;
+
+    return malloc(size);
+}
+
+export fn roc_realloc(c_ptr: *anyopaque, new_size: usize, old_size: usize, alignment: u32) callconv(.C) ?*anyopaque {
+    _ = old_size;
+    _ = alignment;
+
+    return realloc(@as([*]align(Align) u8, @alignCast(@ptrCast(c_ptr))), new_size);
+}
+
+export fn roc_dealloc(c_ptr: *anyopaque, alignment: u32) callconv(.C) void {
+    _ = alignment;
+
+    free(@as([*]align(Align) u8, @alignCast(@ptrCast(c_ptr))));
+}
+
+export fn roc_panic(msg: *RocStr, tag_id: u32) callconv(.C) void {
+    const stderr = std.io.getStdErr().writer();
+    switch (tag_id) {
+        0 => {
+            stderr.print("Roc standard library crashed with message\n\n    {s}\n\nShutting down\n", .{msg.asSlice()}) catch unreachable;
+        },
+        1 => {
+            stderr.print("Application crashed with message\n\n    {s}\n\nShutting down\n", .{msg.asSlice()}) catch unreachable;
+        },
+        else => unreachable,
+    }
+    std.process.exit(1);
+}
+
+export fn roc_dbg(loc: *RocStr, msg: *RocStr, src: *RocStr) callconv(.C) void {
+    const stderr = std.io.getStdErr().writer();
+    stderr.print("[{s}] {s} = {s}\n", .{ loc.asSlice(), src.asSlice(), msg.asSlice() }) catch unreachable;
+}
+
+export fn roc_memset(dst: [*]u8, value: i32, size: usize) callconv(.C) void {
+    return memset(dst, value, size);
+}
+
+extern fn roc__mainForHost_1_exposed_generic([*]u8) void;
+extern fn roc__mainForHost_1_exposed_size() i64;
+
+pub fn main() u8 {
+    const allocator = std.heap.page_allocator;
+
+    // NOTE the return size can be zero, which will segfault. Always allocate at least 8 bytes
+    const size = @max(8, @as(usize, @intCast(roc__mainForHost_1_exposed_size())));
+    const raw_output = allocator.alignedAlloc(u8, @alignOf(u64), size) catch unreachable;
+    const output = @as([*]u8, @ptrCast(raw_output));
+
+    defer {
+        allocator.free(raw_output);
+    }
+
+    roc__mainForHost_1_exposed_generic(output);
+
+    return 0;
+}
+
+// A `Result a Str`; the tags are sorted alphabetically, so `Err` is 0 and `Ok` is 1
+const ResultStr = extern struct {
+    payload: RocStr,
+    tag: u8,
+
+    fn ok(payload: RocStr) ResultStr {
+        return .{ .payload = payload, .tag = 1 };
+    }
+
+    fn err(e: anyerror) ResultStr {
+        return .{ .payload = RocStr.fromSlice(@errorName(e)), .tag = 0 };
+    }
+};
+
+pub export fn roc_fx_stdoutLine(line: *RocStr) void {
+    const stdout = std.io.getStdOut().writer();
+
+    stdout.print("{s}\n", .{line.asSlice()}) catch unreachable;
+}
+
+pub export fn roc_fx_readFile(path: *RocStr) ResultStr {
+    const contents = std.fs.cwd().readFileAlloc(std.heap.page_allocator, path.asSlice(), std.math.maxInt(u32)) catch |e| return ResultStr.err(e);
+    defer std.heap.page_allocator.free(contents);
+
+    return ResultStr.ok(RocStr.fromSlice(contents));
+}
+
+pub export fn roc_fx_writeFile(path: *RocStr, contents: *RocStr) ResultStr {
+    std.fs.cwd().writeFile(path.asSlice(), contents.asSlice()) catch |e| return ResultStr.err(e);
+
+    return ResultStr.ok(RocStr.empty());
+}
+
+pub export fn roc_fx_nowMillis() u64 {
+    return @as(u64, @intCast(std.time.milliTimestamp()));
+}
@@ -0,0 +1,9 @@
+platform "wasi"
+    requires {} { main! : {} => {} }
+    exposes [Stdout, File, Utc]
+    packages {}
+    imports []
+    provides [mainForHost!]
+
+mainForHost! : {} => {}
+mainForHost! = \{} -> main! {}